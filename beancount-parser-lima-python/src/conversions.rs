@@ -332,6 +332,36 @@ impl Converter {
         .into_py(py))
     }
 
+    pub(crate) fn custom(
+        &mut self,
+        py: Python<'_>,
+        date: &Date,
+        metadata: &lima::Metadata,
+        x: &lima::Custom<'_>,
+    ) -> PyResult<Py<PyAny>> {
+        let custom_type = self
+            .string
+            .create_or_reuse(py, x.custom_type().item().as_ref());
+        let values = x
+            .values()
+            .iter()
+            .map(|value| self.meta_value(py, value.item()))
+            .collect::<PyResult<Vec<_>>>()?;
+        let values = PyList::new_bound(py, values).into();
+
+        Ok(Py::new(
+            py,
+            (
+                Custom {
+                    custom_type,
+                    values,
+                },
+                self.directive(py, date, metadata)?,
+            ),
+        )?
+        .into_py(py))
+    }
+
     pub(crate) fn metadata(
         &mut self,
         py: Python<'_>,
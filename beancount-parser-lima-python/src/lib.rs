@@ -89,6 +89,7 @@ fn parse(py: Python<'_>, parser: &lima::BeancountParser) -> PyResult<Py<PyAny>>
                     V::Note(x) => c.note(py, d.date(), d.metadata(), x),
                     V::Event(x) => c.event(py, d.date(), d.metadata(), x),
                     V::Query(x) => c.query(py, d.date(), d.metadata(), x),
+                    V::Custom(x) => c.custom(py, d.date(), d.metadata(), x),
                 })
                 .collect::<PyResult<Vec<Py<PyAny>>>>()?;
 
@@ -149,7 +149,15 @@ pub(crate) struct Query {
     pub(crate) content: Py<PyString>,
 }
 
-// TODO Custom directive, not yet supported by core parser
+/// Beancount custom directive.
+#[derive(Debug)]
+#[pyclass(frozen, extends=Directive)]
+pub(crate) struct Custom {
+    #[pyo3(get)]
+    pub(crate) custom_type: Py<PyString>,
+    #[pyo3(get)]
+    pub(crate) values: Py<PyList>,
+}
 
 #[derive(Clone, Debug)]
 #[pyclass(frozen)]
@@ -324,6 +324,31 @@ fn fmt_query(
     fmt_optional_metadata_inline(metadata, py, f)
 }
 
+#[pymethods]
+impl Custom {
+    fn __str__(self_: PyRef<'_, Self>, py: Python<'_>) -> String {
+        let d = self_.as_ref();
+
+        format!(
+            "{}",
+            Fmt(|f| fmt_custom(self_.borrow(), py, &d.date, &d.metadata, f))
+        )
+    }
+}
+
+fn fmt_custom(
+    x: &Custom,
+    py: Python<'_>,
+    date: &Py<PyDate>,
+    metadata: &Option<Metadata>,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    write!(f, "{} custom \"{}\"", date, &x.custom_type)?;
+    format(f, x.values.bind(py).iter(), plain, SPACE, Some(SPACE))?;
+
+    fmt_optional_metadata_inline(metadata, py, f)
+}
+
 #[pymethods]
 impl Metadata {
     fn __str__(&self, py: Python<'_>) -> String {
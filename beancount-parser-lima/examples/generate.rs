@@ -0,0 +1,43 @@
+// Write a synthetic ledger to stdout, for benchmarking or as shareable reproduction data.
+use std::io;
+
+use beancount_parser_lima::SyntheticLedger;
+
+fn main() -> io::Result<()> {
+    let flags = xflags::parse_or_exit! {
+        /// Seed for the pseudo-random choices, default 0
+        optional --seed seed: u64
+        /// Number of days covered, default 365
+        optional --days days: u32
+        /// Everyday spending transactions per day, default 3
+        optional --transactions-per-day transactions_per_day: u32
+        /// Number of expense accounts, default 12
+        optional --expense-accounts expense_accounts: usize
+        /// Number of payees, default 50
+        optional --payees payees: usize
+        /// Number of commodities, default 3
+        optional --commodities commodities: usize
+    };
+
+    let mut ledger = SyntheticLedger::default();
+    if let Some(seed) = flags.seed {
+        ledger = ledger.seed(seed);
+    }
+    if let Some(days) = flags.days {
+        ledger = ledger.days(days);
+    }
+    if let Some(transactions_per_day) = flags.transactions_per_day {
+        ledger = ledger.transactions_per_day(transactions_per_day);
+    }
+    if let Some(expense_accounts) = flags.expense_accounts {
+        ledger = ledger.expense_accounts(expense_accounts);
+    }
+    if let Some(payees) = flags.payees {
+        ledger = ledger.payees(payees);
+    }
+    if let Some(commodities) = flags.commodities {
+        ledger = ledger.commodities(commodities);
+    }
+
+    ledger.write(io::stdout().lock())
+}
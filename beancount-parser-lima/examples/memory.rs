@@ -0,0 +1,61 @@
+// Measure memory used in parsing a large generated ledger, where most directives have little or no metadata.
+use std::{alloc::System, fmt::Write, mem::size_of};
+
+// for counting allocations
+use stats_alloc::{Region, StatsAlloc, INSTRUMENTED_SYSTEM};
+
+#[global_allocator]
+static GLOBAL: &StatsAlloc<System> = &INSTRUMENTED_SYSTEM;
+
+use beancount_parser_lima::{BeancountParser, BeancountSources, Directive, Metadata, Posting};
+
+fn main() {
+    let flags = xflags::parse_or_exit! {
+        /// Number of transactions to generate, default 100000
+        optional -n, --transactions transactions: usize
+    };
+    let n = flags.transactions.unwrap_or(100_000);
+
+    let mut ledger = String::from("2024-01-01 open Assets:Bank\n2024-01-01 open Expenses:Food\n");
+    for i in 0..n {
+        let date = format!("2024-{:02}-{:02}", i % 12 + 1, i % 28 + 1);
+        writeln!(
+            ledger,
+            "{} * \"Payee {}\" \"transaction {}\"",
+            date,
+            i % 100,
+            i
+        )
+        .unwrap();
+        // one in ten transactions has metadata, and one in a hundred a tag
+        if i % 10 == 0 {
+            writeln!(ledger, "  receipt: \"receipt-{}.pdf\"", i).unwrap();
+        }
+        if i % 100 == 0 {
+            writeln!(ledger, "  #reviewed").unwrap();
+        }
+        writeln!(ledger, "  Assets:Bank  -{}.00 NZD", i % 1000 + 1).unwrap();
+        writeln!(ledger, "  Expenses:Food").unwrap();
+    }
+
+    let sources = BeancountSources::from(ledger);
+    let parser = BeancountParser::new(&sources);
+
+    let reg = Region::new(GLOBAL);
+    let directives = parser.parse().unwrap().directives;
+    let stats = reg.change();
+
+    println!(
+        "size of Metadata {}, Directive {}, Posting {}",
+        size_of::<Metadata>(),
+        size_of::<Directive>(),
+        size_of::<Posting>()
+    );
+    println!(
+        "{} directives: {} allocations, {} bytes allocated, {} bytes still allocated",
+        directives.len(),
+        stats.allocations,
+        stats.bytes_allocated,
+        stats.bytes_allocated as isize - stats.bytes_deallocated as isize
+    );
+}
@@ -0,0 +1,251 @@
+use crate::{
+    interpolation::interpolate, options::Options, recurring::add_months, trial_balance::Units,
+    types::*,
+};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use time::{Date, Duration, Month};
+
+/// The length of calendar buckets for [aggregate].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Interval {
+    /// Weeks starting on Monday, as in ISO 8601.
+    Week,
+    Month,
+    /// Calendar quarters, starting in January, April, July, and October.
+    Quarter,
+    /// Years starting on the given month and day, or on the last day of the month in years without that day,
+    /// such as for February 29.
+    FiscalYear(Month, u8),
+}
+
+impl Interval {
+    /// Calendar years.
+    pub const YEAR: Interval = Interval::FiscalYear(Month::January, 1);
+
+    /// Fiscal years starting as specified by the option `fiscal_year_start`, by default calendar years.
+    pub fn fiscal_year(options: &Options<'_>) -> Self {
+        let (month, day) = options.fiscal_year_start();
+        Interval::FiscalYear(month, day)
+    }
+
+    /// The first day of the bucket containing `date`.
+    pub fn start(&self, date: Date) -> Date {
+        use Interval::*;
+
+        match *self {
+            Week => date - Duration::days(date.weekday().number_days_from_monday().into()),
+            Month => date.replace_day(1).unwrap(),
+            Quarter => {
+                let month = (date.month() as u8 - 1) / 3 * 3 + 1;
+                Date::from_calendar_date(date.year(), month.try_into().unwrap(), 1).unwrap()
+            }
+            FiscalYear(month, day) => fiscal_year_start(date.year(), month, day)
+                .filter(|start| *start <= date)
+                .or_else(|| fiscal_year_start(date.year() - 1, month, day))
+                // the fiscal year started before the first representable date
+                .unwrap_or(Date::MIN),
+        }
+    }
+
+    /// The first day of the bucket following the one which starts on `start`,
+    /// or `None` if that is beyond the representable dates.
+    pub(crate) fn next(&self, start: Date) -> Option<Date> {
+        use Interval::*;
+
+        match *self {
+            Week => start.checked_add(Duration::weeks(1)),
+            Month => add_months(start, 1),
+            Quarter => add_months(start, 3),
+            FiscalYear(month, day) => fiscal_year_start(start.year() + 1, month, day),
+        }
+    }
+}
+
+// the start of the fiscal year in `year`, clamping the day to those in the month,
+// or `None` if the year is beyond the representable dates
+fn fiscal_year_start(year: i32, month: Month, day: u8) -> Option<Date> {
+    let day = day.clamp(1, month.length(year));
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+/// Sum the units posted to each account in calendar buckets of length `interval`, as an [Aggregation].
+///
+/// As for [trial_balance](crate::trial_balance), the units of any posting without an amount are interpolated,
+/// but unlike there, only transactions are counted, so `pad` directives are ignored.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{
+///     aggregate, Account, AccountName, AccountType, BeancountParser, BeancountSources, Interval,
+/// };
+///
+/// let sources = BeancountSources::from(r#"2024-01-02 * "Countdown" "groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// 2024-03-02 * "Countdown" "groceries"
+///   Assets:Bank  -10.00 NZD
+///   Expenses:Groceries
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let groceries = Account::new(
+///     AccountType::Expenses,
+///     [AccountName::try_from("Groceries").unwrap()].into_iter().collect(),
+/// );
+///
+/// let monthly = aggregate(directives.iter(), Interval::Month).unwrap();
+/// let series = monthly
+///     .series(&groceries, &"NZD".try_into().unwrap())
+///     .map(|(date, number)| format!("{} {}", date, number))
+///     .collect::<Vec<_>>();
+/// assert_eq!(series, vec!["2024-01-01 42.50", "2024-02-01 0", "2024-03-01 10.00"]);
+/// ```
+pub fn aggregate<'a, I>(directives: I, interval: Interval) -> Result<Aggregation<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let mut buckets = BTreeMap::<Date, HashMap<&'a Account<'a>, Units<'a>>>::new();
+    let mut errors = Vec::new();
+
+    for directive in directives {
+        if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+            match interpolate(transaction) {
+                Ok(units) => {
+                    let bucket = buckets
+                        .entry(interval.start(*directive.date().item()))
+                        .or_default();
+                    for units in units {
+                        bucket
+                            .entry(units.posting.account().item())
+                            .or_default()
+                            .add(units.currency, units.number);
+                    }
+                }
+                Err(e) => errors.push(e.in_context(directive)),
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // fill in any gaps, so that buckets are contiguous
+    let mut contiguous = Vec::new();
+    if let (Some(first), Some(last)) = (
+        buckets.first_key_value().map(|(start, _)| *start),
+        buckets.last_key_value().map(|(start, _)| *start),
+    ) {
+        let mut start = first;
+        while start <= last {
+            let end = interval.next(start);
+            contiguous.push(Bucket {
+                start,
+                end,
+                accounts: buckets.remove(&start).unwrap_or_default(),
+            });
+            match end {
+                Some(end) => start = end,
+                None => break,
+            }
+        }
+    }
+
+    Ok(Aggregation {
+        interval,
+        buckets: contiguous,
+    })
+}
+
+/// Units posted to each account in contiguous calendar buckets, see [aggregate].
+#[derive(Clone, Debug)]
+pub struct Aggregation<'a> {
+    interval: Interval,
+    buckets: Vec<Bucket<'a>>,
+}
+
+impl<'a> Aggregation<'a> {
+    /// Field accessor.
+    pub fn interval(&self) -> Interval {
+        self.interval
+    }
+
+    /// The buckets in chronological order, from the first containing a transaction to the last,
+    /// including any empty ones in between.
+    pub fn buckets(&self) -> impl ExactSizeIterator<Item = &Bucket<'a>> {
+        self.buckets.iter()
+    }
+
+    /// All accounts with postings in any bucket, in order of name.
+    pub fn accounts(&self) -> Vec<&'a Account<'a>> {
+        let mut accounts = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.accounts.keys().copied())
+            .collect::<Vec<_>>();
+        accounts.sort_by_cached_key(|account| account.to_string());
+        accounts.dedup();
+        accounts
+    }
+
+    /// The start date of every bucket and the number of units of `currency` posted in it to `account`
+    /// and its subaccounts, ready for charting.
+    pub fn series<'s>(
+        &'s self,
+        account: &'s Account<'_>,
+        currency: &'s Currency<'_>,
+    ) -> impl Iterator<Item = (Date, Decimal)> + 's {
+        self.buckets
+            .iter()
+            .map(move |bucket| (bucket.start, bucket.total(account).get(currency)))
+    }
+}
+
+/// Units posted to each account within a single calendar bucket.
+#[derive(Clone, Debug)]
+pub struct Bucket<'a> {
+    start: Date,
+    end: Option<Date>,
+    accounts: HashMap<&'a Account<'a>, Units<'a>>,
+}
+
+impl<'a> Bucket<'a> {
+    /// The first day of the bucket.
+    pub fn start(&self) -> Date {
+        self.start
+    }
+
+    /// The first day after the bucket, or `None` for the last bucket before the end of representable dates.
+    pub fn end(&self) -> Option<Date> {
+        self.end
+    }
+
+    /// The accounts with postings in the bucket, and the units posted to each, in no particular order.
+    pub fn accounts(&self) -> impl Iterator<Item = (&'a Account<'a>, &Units<'a>)> {
+        self.accounts
+            .iter()
+            .map(|(account, units)| (*account, units))
+    }
+
+    /// The units posted to `account` itself, excluding subaccounts.
+    pub fn get(&self, account: &Account<'_>) -> Option<&Units<'a>> {
+        self.accounts
+            .iter()
+            .find_map(|(a, units)| (*a == account).then_some(units))
+    }
+
+    /// The units posted to `account` and all its subaccounts.
+    pub fn total(&self, account: &Account<'_>) -> Units<'a> {
+        let mut total = Units::default();
+        for (a, units) in self.accounts.iter() {
+            if a.account_type() == account.account_type() && a.names().starts_with(account.names())
+            {
+                total.add_all(units);
+            }
+        }
+        total
+    }
+}
+
+mod tests;
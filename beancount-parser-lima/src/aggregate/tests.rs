@@ -0,0 +1,213 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+use test_case::test_case;
+
+fn account(s: &str) -> Account<'_> {
+    let mut names = s.split(':');
+    let account_type = names.next().unwrap().parse().unwrap();
+    let names = names.map(|name| AccountName::try_from(name).unwrap());
+    Account::new(account_type, names.collect())
+}
+
+fn currency(s: &str) -> Currency<'_> {
+    Currency::try_from(s).unwrap()
+}
+
+const LEDGER: &str = r#"
+option "fiscal_year_start" "04-01"
+
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food:Groceries
+2024-01-01 open Expenses:Food:Dining
+2024-01-01 open Expenses:Travel
+
+2024-01-02 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  Expenses:Food:Groceries
+
+2024-01-20 * "Cafe" "lunch"
+  Assets:Bank  -20.00 NZD
+  Expenses:Food:Dining
+
+2024-03-31 * "Air NZ" "flights"
+  Assets:Bank  -300.00 NZD
+  Expenses:Travel  180.00 USD @@ 300.00 NZD
+
+2024-04-01 * "Countdown" "groceries"
+  Assets:Bank  -10.00 NZD
+  Expenses:Food:Groceries
+"#;
+
+#[test_case("2024-01-03", Interval::Week, "2024-01-01")]
+#[test_case("2024-01-07", Interval::Week, "2024-01-01")]
+#[test_case("2024-01-08", Interval::Week, "2024-01-08")]
+#[test_case("2024-02-29", Interval::Month, "2024-02-01")]
+#[test_case("2024-06-30", Interval::Quarter, "2024-04-01")]
+#[test_case("2024-12-31", Interval::Quarter, "2024-10-01")]
+#[test_case("2024-12-31", Interval::YEAR, "2024-01-01")]
+#[test_case("2024-03-31", Interval::FiscalYear(Month::April, 1), "2023-04-01")]
+#[test_case("2024-04-01", Interval::FiscalYear(Month::April, 1), "2024-04-01")]
+#[test_case("2024-02-29", Interval::FiscalYear(Month::February, 29), "2024-02-29")]
+#[test_case("2024-02-28", Interval::FiscalYear(Month::February, 29), "2023-02-28")]
+#[test_case("2023-03-01", Interval::FiscalYear(Month::February, 29), "2023-02-28")]
+#[test_case("2024-01-15", Interval::FiscalYear(Month::January, 0), "2024-01-01")]
+fn bucket_start(d: &str, interval: Interval, expected: &str) {
+    assert_eq!(interval.start(date(d)), date(expected));
+}
+
+#[test_case("2023-02-28", Interval::FiscalYear(Month::February, 29), "2024-02-29")]
+#[test_case("2024-02-29", Interval::FiscalYear(Month::February, 29), "2025-02-28")]
+#[test_case("2024-11-01", Interval::Quarter, "2025-02-01")]
+fn bucket_next(d: &str, interval: Interval, expected: &str) {
+    assert_eq!(interval.next(date(d)), Some(date(expected)));
+}
+
+#[test_case("9999-12-27", Interval::Week)]
+#[test_case("9999-12-01", Interval::Month)]
+#[test_case("9999-10-01", Interval::Quarter)]
+#[test_case("9999-01-01", Interval::YEAR)]
+fn bucket_next_beyond_representable_dates(d: &str, interval: Interval) {
+    assert_eq!(interval.next(date(d)), None);
+}
+
+#[test]
+fn buckets_end_at_representable_dates() {
+    let sources = BeancountSources::from(
+        r#"9999-11-15 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  Expenses:Food
+9999-12-31 * "Countdown" "groceries"
+  Assets:Bank  -10.00 NZD
+  Expenses:Food
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let monthly = aggregate(directives.iter(), Interval::Month).unwrap();
+
+    assert_eq!(
+        monthly
+            .buckets()
+            .map(|bucket| format!("{}..{:?}", bucket.start(), bucket.end()))
+            .collect::<Vec<_>>(),
+        vec!["9999-11-01..Some(9999-12-01)", "9999-12-01..None"]
+    );
+    assert_eq!(
+        monthly
+            .series(&account("Expenses:Food"), &currency("NZD"))
+            .map(|(_, number)| number)
+            .collect::<Vec<_>>(),
+        vec![dec!(42.50), dec!(10.00)]
+    );
+}
+
+#[test]
+fn monthly_buckets_contiguous() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let monthly = aggregate(directives.iter(), Interval::Month).unwrap();
+
+    assert_eq!(
+        monthly
+            .buckets()
+            .map(|bucket| format!("{}..{}", bucket.start(), bucket.end().unwrap()))
+            .collect::<Vec<_>>(),
+        vec![
+            "2024-01-01..2024-02-01",
+            "2024-02-01..2024-03-01",
+            "2024-03-01..2024-04-01",
+            "2024-04-01..2024-05-01"
+        ]
+    );
+    assert_eq!(
+        monthly
+            .series(&account("Expenses:Food"), &currency("NZD"))
+            .map(|(_, number)| number)
+            .collect::<Vec<_>>(),
+        vec![dec!(62.50), dec!(0), dec!(0), dec!(10.00)]
+    );
+    assert_eq!(
+        monthly
+            .series(&account("Assets:Bank"), &currency("NZD"))
+            .map(|(_, number)| number)
+            .collect::<Vec<_>>(),
+        vec![dec!(-62.50), dec!(0), dec!(-300.00), dec!(-10.00)]
+    );
+
+    let january = monthly.buckets().next().unwrap();
+    assert_eq!(january.get(&account("Expenses:Food")), None);
+    assert_eq!(
+        january
+            .get(&account("Expenses:Food:Dining"))
+            .unwrap()
+            .to_string(),
+        "20.00 NZD"
+    );
+    assert_eq!(
+        monthly
+            .accounts()
+            .iter()
+            .map(|account| account.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "Assets:Bank",
+            "Expenses:Food:Dining",
+            "Expenses:Food:Groceries",
+            "Expenses:Travel"
+        ]
+    );
+}
+
+#[test]
+fn fiscal_year_from_options() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let interval = Interval::fiscal_year(&success.options);
+    let yearly = aggregate(success.directives.iter(), interval).unwrap();
+
+    assert_eq!(interval, Interval::FiscalYear(Month::April, 1));
+    assert_eq!(
+        yearly
+            .buckets()
+            .map(|bucket| (
+                bucket.start().to_string(),
+                bucket.total(&account("Expenses")).to_string()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "2023-04-01".to_string(),
+                "62.50 NZD, 180.00 USD".to_string()
+            ),
+            ("2024-04-01".to_string(), "10.00 NZD".to_string())
+        ]
+    );
+}
+
+#[test]
+fn fiscal_year_start_invalid() {
+    let sources = BeancountSources::from("option \"fiscal_year_start\" \"02-29\"\n");
+    let parser = BeancountParser::new(&sources);
+
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn no_transactions() {
+    let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n");
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    assert_eq!(
+        aggregate(directives.iter(), Interval::Week)
+            .unwrap()
+            .buckets()
+            .len(),
+        0
+    );
+}
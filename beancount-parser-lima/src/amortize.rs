@@ -0,0 +1,224 @@
+use crate::{pipeline::Transform, recurring::step, types::*, BudgetInterval};
+use rust_decimal::Decimal;
+use time::Date;
+
+/// A [Transform] which spreads a posting over future periods, such as an annual insurance payment expensed monthly.
+///
+/// A posting is amortized according to its metadata:
+/// - `amortize` is the interval, as for [Budgets](crate::Budgets), such as `"monthly"`
+/// - `periods` is the number of intervals
+/// - `holding` is the account holding the amount not yet amortized,
+///   otherwise that given by [Amortize::holding]
+///
+/// The posting is moved to the holding account, and for each period a transaction is generated,
+/// moving an equal share of the amount from the holding account to the account originally posted to,
+/// the first on the date of the transaction itself.
+/// Shares are rounded to the decimal places of the amount, with any remainder in the last share.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{Amortize, BeancountParser, BeancountSources, Pipeline};
+///
+/// let sources = BeancountSources::from(r#"2024-01-15 * "Insurer" "Annual premium"
+///   Expenses:Insurance  100.00 NZD
+///     amortize: "quarterly"
+///     periods: 3
+///     holding: Assets:Prepaid
+///   Assets:Bank
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let transformed = Pipeline::new().stage(Amortize::default()).run(directives);
+///
+/// assert!(transformed.errors.is_empty());
+/// assert_eq!(
+///     transformed.directives[3].to_string(),
+///     r#"2024-07-15 * "Insurer" "Annual premium"
+///   Expenses:Insurance 33.34 NZD
+///   Assets:Prepaid -33.34 NZD"#
+/// );
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Amortize<'a> {
+    holding: Option<Account<'a>>,
+}
+
+impl<'a> Amortize<'a> {
+    /// The holding account for postings whose metadata does not give one, without which such postings are in error.
+    pub fn holding(mut self, account: Account<'a>) -> Self {
+        self.holding = Some(account);
+        self
+    }
+}
+
+impl<'a> Transform<'a> for Amortize<'a> {
+    fn transform(
+        &mut self,
+        directives: Vec<Spanned<Directive<'a>>>,
+        errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        let mut transformed = Vec::new();
+
+        for mut directive in directives {
+            let mut generated = Vec::new();
+            let mut invalid = Vec::new();
+
+            let Directive { date, variant, .. } = &mut directive.item;
+            if let DirectiveVariant::Transaction(transaction) = variant {
+                for i in 0..transaction.postings.len() {
+                    let posting = &transaction.postings[i];
+                    if posting.metadata().get("amortize").is_none() {
+                        continue;
+                    }
+
+                    match self
+                        .schedule(posting)
+                        .and_then(|(interval, periods, holding)| {
+                            amortization(
+                                transaction,
+                                posting,
+                                *date.item(),
+                                interval,
+                                periods,
+                                &holding,
+                            )
+                            .map(|amortized| (amortized, holding))
+                        }) {
+                        Ok((amortized, holding)) => {
+                            generated.extend(amortized);
+                            transaction.postings[i].item.account.item = holding.item;
+                        }
+                        Err(e) => invalid.push(e),
+                    }
+                }
+            }
+
+            errors.extend(invalid.into_iter().map(|e| e.in_context(&directive)));
+            transformed.push(directive);
+            transformed.append(&mut generated);
+        }
+
+        transformed
+    }
+}
+
+impl<'a> Amortize<'a> {
+    fn schedule(
+        &self,
+        posting: &Spanned<Posting<'a>>,
+    ) -> Result<(BudgetInterval, u32, Spanned<Account<'a>>), Error> {
+        let metadata = posting.metadata();
+        let invalid = |reason: &str, span: Span| Error::new("invalid amortization", reason, span);
+
+        if posting.amount().is_none() || posting.currency().is_none() {
+            return Err(invalid(
+                "amortized posting must have an amount",
+                *posting.span(),
+            ));
+        }
+
+        let interval = metadata.get("amortize").unwrap();
+        let interval = metadata
+            .get_string("amortize")
+            .and_then(|keyword| BudgetInterval::try_from(keyword).ok())
+            .ok_or_else(|| {
+                invalid(
+                    "amortize must be one of daily, weekly, monthly, quarterly, yearly",
+                    *interval.span(),
+                )
+            })?;
+
+        let periods_value = metadata
+            .get_decimal_spanned("periods")
+            .ok_or_else(|| invalid("periods is required", *posting.span()))?;
+        let periods = u32::try_from(*periods_value.item())
+            .ok()
+            .filter(|periods| *periods > 0 && Decimal::from(*periods) == *periods_value.item())
+            .ok_or_else(|| invalid("periods must be a positive integer", *periods_value.span()))?;
+
+        let holding = match metadata.get("holding") {
+            Some(holding) => metadata
+                .get_account_spanned("holding")
+                .map(|account| spanned(account.item.clone(), account.span))
+                .ok_or_else(|| invalid("holding must be an account", *holding.span()))?,
+            None => self
+                .holding
+                .clone()
+                .map(|account| spanned(account, *posting.span()))
+                .ok_or_else(|| invalid("no holding account given", *posting.span()))?,
+        };
+
+        Ok((interval, periods, holding))
+    }
+}
+
+// the transactions moving each share from the holding account, all spanned as the posting,
+// or an error if any falls beyond the representable dates, rather than stranding the remainder
+fn amortization<'a>(
+    transaction: &Transaction<'a>,
+    posting: &Spanned<Posting<'a>>,
+    date: Date,
+    interval: BudgetInterval,
+    periods: u32,
+    holding: &Spanned<Account<'a>>,
+) -> Result<Vec<Spanned<Directive<'a>>>, Error> {
+    let span = *posting.span();
+    let dates = (0..periods)
+        .map(|n| step(date, interval, n))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            Error::new(
+                "invalid amortization",
+                "schedule extends beyond representable dates",
+                span,
+            )
+        })?;
+    let (amount, currency) = (
+        posting.amount.as_ref().unwrap(),
+        posting.currency.as_ref().unwrap(),
+    );
+    let total = amount.item().value();
+    let share = (total / Decimal::from(periods)).round_dp(total.scale());
+
+    Ok((0..periods)
+        .zip(dates)
+        .map(|(n, date)| {
+            let number = if n + 1 == periods {
+                total - share * Decimal::from(periods - 1)
+            } else {
+                share
+            };
+            let leg = |account: &Spanned<Account<'a>>, number: Decimal| {
+                spanned(
+                    Posting {
+                        flag: None,
+                        account: spanned(account.item.clone(), span),
+                        amount: Some(spanned(ExprValue::from(Expr::Value(number)), span)),
+                        currency: Some(*currency),
+                        cost_spec: None,
+                        price_annotation: None,
+                        metadata: Metadata::default(),
+                    },
+                    span,
+                )
+            };
+
+            spanned(
+                Directive {
+                    date: spanned(date, span),
+                    metadata: Metadata::default(),
+                    variant: DirectiveVariant::Transaction(Transaction {
+                        flag: transaction.flag,
+                        payee: transaction.payee,
+                        narration: transaction.narration,
+                        postings: vec![leg(&posting.account, number), leg(holding, -number)],
+                        broken_postings: Vec::new(),
+                    }),
+                },
+                span,
+            )
+        })
+        .collect())
+}
+
+mod tests;
@@ -0,0 +1,137 @@
+#![cfg(test)]
+use super::*;
+use crate::{AccountName, AccountType, BeancountParser, BeancountSources, Pipeline};
+
+const LEDGER: &str = r#"2024-01-31 * "Insurer" "Annual premium"
+  Expenses:Insurance  100.00 NZD
+    amortize: "monthly"
+    periods: 4
+  Assets:Bank
+2024-02-01 * "Council" "Rates"
+  Expenses:Rates  50.00 NZD
+    amortize: "fortnightly"
+    periods: 2
+  Assets:Bank
+2024-02-02 * "Gym" "Membership"
+  Expenses:Gym  60.00 NZD
+    amortize: "weekly"
+    periods: 1.5
+    holding: Assets:Prepaid:Gym
+  Assets:Bank
+"#;
+
+#[test]
+fn amortize_with_default_holding_account() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let prepaid = Account::new(
+        AccountType::Assets,
+        [AccountName::try_from("Prepaid").unwrap()]
+            .into_iter()
+            .collect(),
+    );
+
+    let transformed = Pipeline::new()
+        .stage(Amortize::default().holding(prepaid))
+        .run(directives);
+
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            "amortize must be one of daily, weekly, monthly, quarterly, yearly",
+            "periods must be a positive integer",
+        ]
+    );
+    assert_eq!(
+        transformed
+            .directives
+            .iter()
+            .take(5)
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            r#"2024-01-31 * "Insurer" "Annual premium"
+  Assets:Prepaid 100.00 NZD
+    amortize: "monthly"
+    periods: 4
+  Assets:Bank"#,
+            r#"2024-01-31 * "Insurer" "Annual premium"
+  Expenses:Insurance 25.00 NZD
+  Assets:Prepaid -25.00 NZD"#,
+            r#"2024-02-29 * "Insurer" "Annual premium"
+  Expenses:Insurance 25.00 NZD
+  Assets:Prepaid -25.00 NZD"#,
+            r#"2024-03-31 * "Insurer" "Annual premium"
+  Expenses:Insurance 25.00 NZD
+  Assets:Prepaid -25.00 NZD"#,
+            r#"2024-04-30 * "Insurer" "Annual premium"
+  Expenses:Insurance 25.00 NZD
+  Assets:Prepaid -25.00 NZD"#,
+        ]
+    );
+    assert_eq!(transformed.directives.len(), 7);
+}
+
+#[test]
+fn amortize_without_holding_account() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new().stage(Amortize::default()).run(directives);
+
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            "no holding account given",
+            "amortize must be one of daily, weekly, monthly, quarterly, yearly",
+            "periods must be a positive integer",
+        ]
+    );
+    assert_eq!(transformed.directives.len(), 3);
+}
+
+#[test]
+fn amortize_beyond_representable_dates() {
+    let sources = BeancountSources::from(
+        r#"9999-11-15 * "Insurer" "Annual premium"
+  Expenses:Insurance  100.00 NZD
+    amortize: "monthly"
+    periods: 3
+    holding: Assets:Prepaid
+  Assets:Bank
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new().stage(Amortize::default()).run(directives);
+
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec!["schedule extends beyond representable dates"]
+    );
+    assert_eq!(transformed.directives.len(), 1);
+    assert_eq!(
+        transformed.directives[0].to_string(),
+        r#"9999-11-15 * "Insurer" "Annual premium"
+  Expenses:Insurance 100.00 NZD
+    amortize: "monthly"
+    holding: Assets:Prepaid
+    periods: 3
+  Assets:Bank"#
+    );
+}
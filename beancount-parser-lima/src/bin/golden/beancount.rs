@@ -0,0 +1,2 @@
+// auto-generated from Beancount protobuf schema
+include!(concat!(env!("OUT_DIR"), "/proto/mod.rs"));
@@ -0,0 +1,440 @@
+//! Conversion of parser output to the Beancount protobuf schema, which is the inverse of what the parser tests check.
+//!
+//! Only options which differ from their defaults are included, so as to keep the expected output minimal.
+use super::beancount::{
+    data::{
+        meta::KV, meta_value, Amount, Balance, Close, Commodity, Custom, Directive, Document,
+        Error, Event, Meta, MetaValue, Note, Open, Pad, Posting, Price, Query, Transaction,
+    },
+    date::Date,
+    inter::{CostSpec, PriceSpec},
+    ledger::Ledger,
+    number::Number,
+    options::{
+        options::ProcessingMode, processing_info::Plugin, AccountTypes, Booking, Options,
+        ProcessingInfo,
+    },
+};
+use ::beancount_parser_lima as lima;
+use protobuf::{EnumOrUnknown, MessageField};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+pub(crate) fn ledger(
+    directives: &[lima::Spanned<lima::Directive<'_>>],
+    options: &lima::Options<'_>,
+    plugins: &[lima::Plugin<'_>],
+) -> Ledger {
+    Ledger {
+        directives: directives
+            .iter()
+            .map(|directive| self::directive(directive.item()))
+            .collect(),
+        options: MessageField::some(self::options(options)),
+        info: if plugins.is_empty() {
+            MessageField::none()
+        } else {
+            MessageField::some(ProcessingInfo {
+                plugin: plugins.iter().map(plugin).collect(),
+                ..Default::default()
+            })
+        },
+        ..Default::default()
+    }
+}
+
+/// Only the error messages are checked by the parser tests, so that's all we have.
+pub(crate) fn failed_ledger(errors: &[lima::Error]) -> Ledger {
+    Ledger {
+        errors: errors
+            .iter()
+            .map(|error| Error {
+                message: Some(error.message().to_string()),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn directive(x: &lima::Directive<'_>) -> Directive {
+    use lima::DirectiveVariant as V;
+
+    let metadata = x.metadata();
+    let mut directive = Directive {
+        date: MessageField::some(date(x.date().item())),
+        tags: metadata
+            .tags()
+            .map(|tag| tag.item().as_ref().to_string())
+            .collect(),
+        links: metadata
+            .links()
+            .map(|link| link.item().as_ref().to_string())
+            .collect(),
+        meta: meta(metadata),
+        ..Default::default()
+    };
+    // tags and links are unordered, but we want the output to be reproducible
+    directive.tags.sort();
+    directive.links.sort();
+
+    match x.variant() {
+        V::Transaction(x) => directive.set_transaction(transaction(x)),
+        V::Price(x) => directive.set_price(Price {
+            currency: Some(x.currency().item().as_ref().to_string()),
+            amount: MessageField::some(amount(x.amount().item())),
+            ..Default::default()
+        }),
+        V::Balance(x) => directive.set_balance(Balance {
+            account: Some(x.account().item().to_string()),
+            amount: MessageField::some(amount(x.atol().item().amount().item())),
+            tolerance: x
+                .atol()
+                .item()
+                .tolerance()
+                .map(|tolerance| number(*tolerance.item()))
+                .into(),
+            ..Default::default()
+        }),
+        V::Open(x) => {
+            let mut currencies = x
+                .currencies()
+                .map(|currency| currency.item().as_ref().to_string())
+                .collect::<Vec<_>>();
+            currencies.sort();
+
+            directive.set_open(Open {
+                account: Some(x.account().item().to_string()),
+                currencies,
+                booking: x.booking().map(|b| EnumOrUnknown::new(booking(*b.item()))),
+                ..Default::default()
+            })
+        }
+        V::Close(x) => directive.set_close(Close {
+            account: Some(x.account().item().to_string()),
+            ..Default::default()
+        }),
+        V::Commodity(x) => directive.set_commodity(Commodity {
+            currency: Some(x.currency().item().as_ref().to_string()),
+            ..Default::default()
+        }),
+        V::Pad(x) => directive.set_pad(Pad {
+            account: Some(x.account().item().to_string()),
+            source_account: Some(x.source().item().to_string()),
+            ..Default::default()
+        }),
+        V::Document(x) => directive.set_document(Document {
+            account: Some(x.account().item().to_string()),
+            filename: Some(x.path().item().to_string()),
+            ..Default::default()
+        }),
+        V::Note(x) => directive.set_note(Note {
+            account: Some(x.account().item().to_string()),
+            comment: Some(x.comment().item().to_string()),
+            ..Default::default()
+        }),
+        V::Event(x) => directive.set_event(Event {
+            type_: Some(x.event_type().item().to_string()),
+            description: Some(x.description().item().to_string()),
+            ..Default::default()
+        }),
+        V::Query(x) => directive.set_query(Query {
+            name: Some(x.name().item().to_string()),
+            query_string: Some(x.content().item().to_string()),
+            ..Default::default()
+        }),
+        V::Custom(x) => directive.set_custom(Custom {
+            type_: Some(x.custom_type().item().to_string()),
+            values: x
+                .values()
+                .iter()
+                .map(|value| meta_value(value.item()))
+                .collect(),
+            ..Default::default()
+        }),
+    }
+
+    directive
+}
+
+fn transaction(x: &lima::Transaction<'_>) -> Transaction {
+    Transaction {
+        flag: Some(flag(x.flag().item())),
+        payee: x.payee().map(|payee| payee.item().to_string()),
+        narration: x.narration().map(|narration| narration.item().to_string()),
+        postings: x.postings().map(|p| posting(p.item())).collect(),
+        ..Default::default()
+    }
+}
+
+fn posting(x: &lima::Posting<'_>) -> Posting {
+    let mut posting = Posting {
+        flag: x.flag().map(|f| flag(f.item())),
+        account: Some(x.account().item().to_string()),
+        meta: meta(x.metadata()),
+        ..Default::default()
+    };
+
+    let spec = posting.spec.mut_or_insert_default();
+    let units = spec.units.mut_or_insert_default();
+    units.number = x.amount().map(|expr| number(expr.item().value())).into();
+    units.currency = x
+        .currency()
+        .map(|currency| currency.item().as_ref().to_string());
+    spec.cost = x.cost_spec().map(|cost| cost_spec(cost.item())).into();
+    spec.price = x
+        .price_annotation()
+        .map(|price| price_spec(price.item()))
+        .into();
+
+    posting
+}
+
+fn cost_spec(x: &lima::CostSpec<'_>) -> CostSpec {
+    let mut cost_spec = CostSpec {
+        currency: x
+            .currency()
+            .map(|currency| currency.item().as_ref().to_string()),
+        date: x.date().map(|d| date(d.item())).into(),
+        label: x.label().map(|label| label.item().to_string()),
+        merge_cost: x.merge().then_some(true),
+        ..Default::default()
+    };
+
+    if let Some(per_unit) = x.per_unit() {
+        cost_spec.per_unit.mut_or_insert_default().number =
+            MessageField::some(number(per_unit.item().value()));
+    }
+    if let Some(total) = x.total() {
+        cost_spec.total.mut_or_insert_default().number =
+            MessageField::some(number(total.item().value()));
+    }
+
+    cost_spec
+}
+
+fn price_spec(x: &lima::PriceSpec<'_>) -> PriceSpec {
+    use lima::PriceSpec::*;
+    use lima::ScopedExprValue::*;
+
+    let (currency, value, is_total) = match x {
+        BareCurrency(currency) => (Some(currency), None, false),
+        BareAmount(PerUnit(expr)) => (None, Some(expr.value()), false),
+        BareAmount(Total(expr)) => (None, Some(expr.value()), true),
+        CurrencyAmount(PerUnit(expr), currency) => (Some(currency), Some(expr.value()), false),
+        CurrencyAmount(Total(expr), currency) => (Some(currency), Some(expr.value()), true),
+    };
+
+    PriceSpec {
+        currency: currency.map(|currency| currency.as_ref().to_string()),
+        number: value.map(number).into(),
+        is_total: is_total.then_some(true),
+        ..Default::default()
+    }
+}
+
+/// Tags and links are taken from the directive, so metadata here is only the key/values, in key order.
+fn meta(x: &lima::Metadata<'_>) -> MessageField<Meta> {
+    let mut kv = x
+        .key_values()
+        .map(|(key, value)| KV {
+            key: Some(key.item().as_ref().to_string()),
+            value: MessageField::some(meta_value(value.item())),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    if kv.is_empty() {
+        MessageField::none()
+    } else {
+        kv.sort_by(|kv1, kv2| kv1.key.cmp(&kv2.key));
+
+        MessageField::some(Meta {
+            kv,
+            ..Default::default()
+        })
+    }
+}
+
+fn meta_value(x: &lima::MetaValue<'_>) -> MetaValue {
+    use lima::MetaValue::*;
+    use lima::SimpleValue::*;
+    use meta_value::Value;
+
+    let value = match x {
+        Simple(String(x)) => Some(Value::Text(x.to_string())),
+        Simple(Currency(x)) => Some(Value::Currency(x.as_ref().to_string())),
+        Simple(Account(x)) => Some(Value::Account(x.to_string())),
+        Simple(Tag(x)) => Some(Value::Tag(x.as_ref().to_string())),
+        Simple(Link(x)) => Some(Value::Link(x.as_ref().to_string())),
+        Simple(Date(x)) => Some(Value::Date(date(x))),
+        Simple(Bool(x)) => Some(Value::Boolean(*x)),
+        Simple(lima::SimpleValue::None) => Option::None,
+        Simple(Expr(x)) => Some(Value::Number(number(x.value()))),
+        Amount(x) => Some(Value::Amount(amount(x))),
+    };
+
+    MetaValue {
+        value,
+        ..Default::default()
+    }
+}
+
+fn options(x: &lima::Options<'_>) -> Options {
+    fn non_default<S>(value: S, default: &str) -> Option<String>
+    where
+        S: ToString,
+    {
+        let value = value.to_string();
+        (value != default).then_some(value)
+    }
+
+    let account_type =
+        |account_type, default| non_default(x.account_type_name(account_type).as_ref(), default);
+    let account_types = AccountTypes {
+        assets: account_type(lima::AccountType::Assets, "Assets"),
+        liabilities: account_type(lima::AccountType::Liabilities, "Liabilities"),
+        equity: account_type(lima::AccountType::Equity, "Equity"),
+        income: account_type(lima::AccountType::Income, "Income"),
+        expenses: account_type(lima::AccountType::Expenses, "Expenses"),
+        ..Default::default()
+    };
+
+    let booking_method = booking(x.booking_method());
+    let plugin_processing_mode = match x.plugin_processing_mode() {
+        lima::PluginProcessingMode::Default => ProcessingMode::DEFAULT,
+        lima::PluginProcessingMode::Raw => ProcessingMode::RAW,
+    };
+
+    Options {
+        title: non_default(x.title(), "Beancount"),
+        account_previous_balances: non_default(
+            subaccount(x.account_previous_balances()),
+            "Opening-Balances",
+        ),
+        account_previous_earnings: non_default(
+            subaccount(x.account_previous_earnings()),
+            "Earnings:Previous",
+        ),
+        account_previous_conversions: non_default(
+            subaccount(x.account_previous_conversions()),
+            "Conversions:Previous",
+        ),
+        account_current_earnings: non_default(
+            subaccount(x.account_current_earnings()),
+            "Earnings:Current",
+        ),
+        account_current_conversions: non_default(
+            subaccount(x.account_current_conversions()),
+            "Conversions:Current",
+        ),
+        account_unrealized_gains: non_default(
+            subaccount(x.account_unrealized_gains()),
+            "Earnings:Unrealized",
+        ),
+        account_rounding: x.account_rounding().map(subaccount),
+        conversion_currency: non_default(x.conversion_currency().as_ref(), "NOTHING"),
+        inferred_tolerance_default: x
+            .inferred_tolerance_defaults()
+            .map(|(currency, tolerance)| {
+                (
+                    currency
+                        .map(|currency| currency.as_ref().to_string())
+                        .unwrap_or_else(|| "*".to_string()),
+                    tolerance.to_string(),
+                )
+            })
+            .collect(),
+        inferred_tolerance_multiplier: (x.inferred_tolerance_multiplier() != dec!(0.5))
+            .then(|| x.inferred_tolerance_multiplier().to_string()),
+        infer_tolerance_from_cost: x.infer_tolerance_from_cost().then_some(true),
+        documents: x
+            .documents()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        operating_currency: x
+            .operating_currency()
+            .map(|currency| currency.as_ref().to_string())
+            .collect(),
+        render_commas: x.render_commas().then_some(true),
+        booking_method: (booking_method != Booking::STRICT)
+            .then(|| EnumOrUnknown::new(booking_method)),
+        plugin_processing_mode: (plugin_processing_mode != ProcessingMode::DEFAULT)
+            .then(|| EnumOrUnknown::new(plugin_processing_mode)),
+        account_types: if account_types == AccountTypes::default() {
+            MessageField::none()
+        } else {
+            MessageField::some(account_types)
+        },
+        ..Default::default()
+    }
+}
+
+fn plugin(x: &lima::Plugin<'_>) -> Plugin {
+    Plugin {
+        name: Some(x.module_name().item().to_string()),
+        config: x.config().map(|config| config.item().to_string()),
+        ..Default::default()
+    }
+}
+
+fn amount(x: &lima::Amount<'_>) -> Amount {
+    Amount {
+        number: MessageField::some(number(x.number().item().value())),
+        currency: Some(x.currency().item().as_ref().to_string()),
+        ..Default::default()
+    }
+}
+
+fn number(x: Decimal) -> Number {
+    Number {
+        exact: Some(x.to_string()),
+        ..Default::default()
+    }
+}
+
+fn date(x: &time::Date) -> Date {
+    Date {
+        year: Some(x.year()),
+        month: Some(x.month() as i32),
+        day: Some(x.day() as i32),
+        ..Default::default()
+    }
+}
+
+fn subaccount(x: &lima::Subaccount<'_>) -> String {
+    x.iter()
+        .map(|name| name.as_ref())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn flag(x: &lima::Flag) -> Vec<u8> {
+    use lima::Flag::*;
+
+    let c = match x {
+        Asterisk => '*',
+        Exclamation => '!',
+        Ampersand => '&',
+        Hash => '#',
+        Question => '?',
+        Percent => '%',
+        Letter(letter) => letter.char(),
+    };
+
+    c.to_string().into_bytes()
+}
+
+fn booking(x: lima::Booking) -> Booking {
+    use lima::Booking::*;
+
+    match x {
+        Strict => Booking::STRICT,
+        StrictWithSize => Booking::STRICT_WITH_SIZE,
+        None => Booking::NONE,
+        Average => Booking::AVERAGE,
+        Fifo => Booking::FIFO,
+        Lifo => Booking::LIFO,
+        Hifo => Booking::HIFO,
+    }
+}
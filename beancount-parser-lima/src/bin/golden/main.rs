@@ -0,0 +1,61 @@
+//! Generate the expected output for a parser test case from a Beancount file, that is,
+//! the parse in Protobuf Text Format as found alongside each file in the test-cases directory.
+//!
+//! The output is only as good as the parser which produced it, so review it before committing a new test case.
+//!
+//! ```Shell
+//! cargo run --features golden --bin beancount-golden -- ../test-cases/Example.beancount
+//! ```
+use beancount_parser_lima::{BeancountParser, BeancountSources, ParseError, ParseSuccess};
+use protobuf::text_format::print_to_string_pretty;
+use std::{
+    fs,
+    io::{self, prelude::*},
+    path::PathBuf,
+};
+
+fn main() -> io::Result<()> {
+    let flags = xflags::parse_or_exit! {
+        /// Write to stdout rather than to the .txtpb file alongside the input
+        optional --stdout
+
+        /// Beancount file to parse
+        required path: PathBuf
+    };
+
+    let stderr = &io::stderr();
+    let sources = BeancountSources::try_from(flags.path.as_path())?;
+    let parser = BeancountParser::new(&sources);
+
+    let ledger = match parser.parse() {
+        Ok(ParseSuccess {
+            directives,
+            options,
+            plugins,
+            warnings,
+        }) => {
+            sources.write(stderr, warnings)?;
+            conversions::ledger(&directives, &options, &plugins)
+        }
+        Err(ParseError { errors, warnings }) => {
+            let ledger = conversions::failed_ledger(&errors);
+            sources.write(stderr, errors)?;
+            sources.write(stderr, warnings)?;
+            ledger
+        }
+    };
+
+    let output = print_to_string_pretty(&ledger);
+
+    if flags.stdout {
+        io::stdout().write_all(output.as_bytes())
+    } else {
+        let output_path = flags.path.with_extension("txtpb");
+        fs::write(&output_path, output)?;
+        eprintln!("wrote {}", output_path.display());
+        Ok(())
+    }
+}
+
+mod beancount;
+mod conversions;
@@ -0,0 +1,286 @@
+//! Command line access to the parser, for checking, formatting, and dumping Beancount files.
+//!
+//! ```Shell
+//! cargo run --features cli --bin beancount-lima -- check ledger.beancount
+//! cargo run --features cli --bin beancount-lima -- format ledger.beancount
+//! cargo run --features cli --bin beancount-lima -- context ledger.beancount 123
+//! cargo run --features cli --bin beancount-lima -- dump ledger.beancount
+//! ```
+//!
+//! `check` exits with status 1 if there are any errors, so is suitable for use in scripts and editor integrations.
+//! `dump --proto` is only available with the `golden` feature, which requires the Beancount protobuf schema.
+use beancount_parser_lima::{
+    check_balances, holdings, BeanCheckRenderer, BeancountParser, BeancountSources, Context,
+    DiagnosticRenderer, Directive, ElementType, ErrorOrWarning, ErrorOrWarningKind, JsonRenderer,
+    ParseError, ParseSuccess, PlainRenderer, Spanned, TerminalRenderer,
+};
+use chumsky::span::Span as _;
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, prelude::*},
+    path::Path,
+    process::ExitCode,
+};
+use time::Date;
+
+mod flags {
+    use std::path::PathBuf;
+
+    xflags::xflags! {
+        /// Check, format, or dump a Beancount file, or show the context of a directive.
+        cmd beancount-lima {
+            /// Parse and validate, reporting errors and warnings on stderr.
+            cmd check {
+                /// Diagnostic format, one of terminal, plain, bean-check, or json
+                optional --format format: String
+
+                /// Beancount file to check
+                required path: PathBuf
+            }
+
+            /// Print the directives in canonical form.
+            cmd format {
+                /// Beancount file to format
+                required path: PathBuf
+            }
+
+            /// Print the directive at a line, with the balances of its accounts before and after.
+            cmd context {
+                /// Beancount file containing the directive
+                required path: PathBuf
+
+                /// Line number of the directive, counting from 1
+                required line: usize
+            }
+
+            /// Print the directives as JSON lines, one per directive.
+            cmd dump {
+                /// Print the parse in Protobuf Text Format instead, as for the golden tests
+                optional --proto
+
+                /// Beancount file to dump
+                required path: PathBuf
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<ExitCode> {
+    use flags::BeancountLimaCmd::*;
+
+    let flags = flags::BeancountLima::from_env_or_exit();
+
+    match flags.subcommand {
+        Check(check) => match check.format.as_deref() {
+            None | Some("terminal") => run_check(&check.path, TerminalRenderer),
+            Some("plain") => run_check(&check.path, PlainRenderer),
+            Some("bean-check") => run_check(&check.path, BeanCheckRenderer),
+            Some("json") => run_check(&check.path, JsonRenderer),
+            Some(format) => {
+                eprintln!(
+                    "unknown format {}, expected terminal, plain, bean-check, or json",
+                    format
+                );
+                Ok(ExitCode::from(2))
+            }
+        },
+        Format(format) => run_format(&format.path),
+        Context(context) => run_context(&context.path, context.line),
+        Dump(dump) => run_dump(&dump.path, dump.proto),
+    }
+}
+
+fn run_check<R>(path: &Path, renderer: R) -> io::Result<ExitCode>
+where
+    R: DiagnosticRenderer,
+{
+    let stderr = &io::stderr();
+    let sources = BeancountSources::try_from(path)?;
+    let parser = BeancountParser::new(&sources);
+    let result = parser.parse();
+
+    match result {
+        Ok(ParseSuccess {
+            directives,
+            options,
+            warnings,
+            ..
+        }) => {
+            render(&sources, &renderer, stderr, warnings)?;
+
+            // booking also interpolates, so this validates every transaction, before checking balance assertions
+            match holdings(directives.iter(), &options, Date::MAX)
+                .and_then(|_| check_balances(directives.iter(), &options))
+            {
+                Ok(_) => Ok(ExitCode::SUCCESS),
+                Err(errors) => {
+                    render(&sources, &renderer, stderr, errors)?;
+                    Ok(ExitCode::FAILURE)
+                }
+            }
+        }
+        Err(ParseError { errors, warnings }) => {
+            render(&sources, &renderer, stderr, errors)?;
+            render(&sources, &renderer, stderr, warnings)?;
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn render<R, W, K>(
+    sources: &BeancountSources,
+    renderer: &R,
+    w: W,
+    errors_or_warnings: Vec<ErrorOrWarning<K>>,
+) -> io::Result<()>
+where
+    R: DiagnosticRenderer,
+    W: Write,
+    K: ErrorOrWarningKind,
+{
+    if errors_or_warnings.is_empty() {
+        Ok(())
+    } else {
+        renderer.render(sources, w, errors_or_warnings)
+    }
+}
+
+fn run_format(path: &Path) -> io::Result<ExitCode> {
+    parse_then(path, |_sources, success| {
+        let mut stdout = io::stdout().lock();
+
+        for (i, directive) in success.directives.iter().enumerate() {
+            if i > 0 {
+                writeln!(stdout)?;
+            }
+            writeln!(stdout, "{}", directive.item())?;
+        }
+
+        Ok(ExitCode::SUCCESS)
+    })
+}
+
+fn run_context(path: &Path, line: usize) -> io::Result<ExitCode> {
+    parse_then(path, |sources, success| {
+        match Context::new(sources, &success.directives, &success.options, path, line) {
+            Some(context) => {
+                write!(io::stdout().lock(), "{}", context)?;
+                Ok(ExitCode::SUCCESS)
+            }
+            None => {
+                eprintln!("no directive at {}:{}", path.display(), line);
+                Ok(ExitCode::FAILURE)
+            }
+        }
+    })
+}
+
+fn run_dump(path: &Path, proto: bool) -> io::Result<ExitCode> {
+    if proto && !cfg!(feature = "golden") {
+        eprintln!("dump --proto requires the golden feature");
+        return Ok(ExitCode::from(2));
+    }
+
+    parse_then(path, |sources, success| {
+        let mut stdout = io::stdout().lock();
+
+        if proto {
+            write_proto(&mut stdout, success)?;
+        } else {
+            for directive in success.directives.iter() {
+                writeln!(stdout, "{}", JsonDirective(sources, directive))?;
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    })
+}
+
+#[cfg(feature = "golden")]
+fn write_proto<W>(mut w: W, success: &ParseSuccess) -> io::Result<()>
+where
+    W: Write,
+{
+    let ledger = conversions::ledger(&success.directives, &success.options, &success.plugins);
+    w.write_all(protobuf::text_format::print_to_string_pretty(&ledger).as_bytes())
+}
+
+#[cfg(not(feature = "golden"))]
+fn write_proto<W>(_w: W, _success: &ParseSuccess) -> io::Result<()>
+where
+    W: Write,
+{
+    unreachable!("dump --proto is rejected without the golden feature")
+}
+
+// parse, reporting any errors or warnings on stderr, and if successful, continue with `f` for the exit code
+fn parse_then<F>(path: &Path, f: F) -> io::Result<ExitCode>
+where
+    F: FnOnce(&BeancountSources, &ParseSuccess) -> io::Result<ExitCode>,
+{
+    let stderr = &io::stderr();
+    let sources = BeancountSources::try_from(path)?;
+    let parser = BeancountParser::new(&sources);
+    let result = parser.parse();
+
+    match result {
+        Ok(success) => {
+            sources.write(stderr, success.warnings.clone())?;
+            f(&sources, &success)
+        }
+        Err(ParseError { errors, warnings }) => {
+            sources.write(stderr, errors)?;
+            sources.write(stderr, warnings)?;
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+struct JsonDirective<'a>(&'a BeancountSources, &'a Spanned<Directive<'a>>);
+
+impl Display for JsonDirective<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let JsonDirective(sources, directive) = self;
+        let span = directive.span();
+
+        write!(
+            f,
+            r#"{{"date":"{}","type":"{}","file":{},"start":{},"end":{},"text":{}}}"#,
+            directive.date().item(),
+            directive.element_type(),
+            JsonString(sources.source_name(span.context())),
+            span.start(),
+            span.end(),
+            JsonString(&directive.item().to_string())
+        )
+    }
+}
+
+struct JsonString<'a>(&'a str);
+
+impl Display for JsonString<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use std::fmt::Write;
+
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str(r#"\""#)?,
+                '\\' => f.write_str(r"\\")?,
+                '\n' => f.write_str(r"\n")?,
+                '\r' => f.write_str(r"\r")?,
+                '\t' => f.write_str(r"\t")?,
+                c if c.is_control() => write!(f, r"\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+#[cfg(feature = "golden")]
+#[path = "../golden/beancount.rs"]
+mod beancount;
+#[cfg(feature = "golden")]
+#[path = "../golden/conversions.rs"]
+mod conversions;
@@ -0,0 +1,894 @@
+use crate::{
+    format::{format, plain},
+    interpolation::interpolate,
+    prices::PriceDb,
+    sort::sort_key,
+    types::*,
+    Options,
+};
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display, Formatter},
+    ops::{Add, Mul, Neg, Sub},
+};
+use time::Date;
+
+/// Book all transactions on or before `date`, returning the inventory of every account with any positions.
+///
+/// Postings with a cost specification augment or reduce the lots held at cost, according to the booking method
+/// of the account, as given on its `open` directive, or else by the `booking_method` option.
+/// A merge cost `{*}` merges all lots of the currency at their average cost before reducing, or after augmenting.
+pub(crate) fn book<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    date: Date,
+) -> Result<BTreeMap<&'a Account<'a>, Inventory<'a>>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let (inventories, errors) = book_regardless(directives, options, date);
+
+    if errors.is_empty() {
+        Ok(inventories)
+    } else {
+        Err(errors)
+    }
+}
+
+/// As [book], but returning the inventories despite any errors, in which case the transactions in error are not booked.
+pub(crate) fn book_regardless<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    date: Date,
+) -> (BTreeMap<&'a Account<'a>, Inventory<'a>>, Vec<Error>)
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let (inventories, _, _, errors) = book_all(directives, options, date, &[]);
+    (inventories, errors)
+}
+
+/// As [book_regardless], but returning the inventories as of each of `dates`, which must be in ascending order.
+pub(crate) fn book_snapshots<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    dates: &[Date],
+) -> (Vec<BTreeMap<&'a Account<'a>, Inventory<'a>>>, Vec<Error>)
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let date = dates.last().copied().unwrap_or(Date::MIN);
+    let (_, _, snapshots, errors) = book_all(directives, options, date, dates);
+    (snapshots, errors)
+}
+
+/// Book all transactions on or before `date` as for [holdings](crate::holdings), returning each posting as booked,
+/// with the [Cost] resolved from its cost specification.
+///
+/// A cost specification without a date is for a lot acquired on the date of the transaction.
+/// A posting which reduces several lots is booked as one posting for each lot,
+/// so lots with a label may be reduced by giving just the label.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{booked_postings, BeancountParser, BeancountSources};
+/// use time::Date;
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Broker
+/// 2024-01-01 open Assets:Cash
+/// 2024-01-02 * "Buy"
+///   Assets:Broker  10 HOOL {100 USD, "first"}
+///   Assets:Cash
+/// 2024-02-01 * "Sell"
+///   Assets:Broker  -4 HOOL {"first"} @ 110 USD
+///   Assets:Cash  440 USD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let success = parser.parse().unwrap();
+/// let booked = booked_postings(success.directives.iter(), &success.options, Date::MAX).unwrap();
+///
+/// assert_eq!(booked[2].cost().unwrap().to_string(), r#"{100 USD, 2024-01-02, "first"}"#);
+/// ```
+pub fn booked_postings<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    date: Date,
+) -> Result<Vec<BookedPosting<'a>>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let (_, booked, _, errors) = book_all(directives, options, date, &[]);
+
+    if errors.is_empty() {
+        Ok(booked)
+    } else {
+        Err(errors)
+    }
+}
+
+type Booked<'a> = (
+    BTreeMap<&'a Account<'a>, Inventory<'a>>,
+    Vec<BookedPosting<'a>>,
+    Vec<BTreeMap<&'a Account<'a>, Inventory<'a>>>,
+    Vec<Error>,
+);
+
+// with the inventories also as of each of the ascending `snapshot_dates`
+fn book_all<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    date: Date,
+    snapshot_dates: &[Date],
+) -> Booked<'a>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let mut directives = directives
+        .into_iter()
+        .filter(|directive| *directive.date().item() <= date)
+        .collect::<Vec<_>>();
+    directives.sort_by_key(|directive| sort_key(directive));
+
+    let methods = directives
+        .iter()
+        .filter_map(|directive| match directive.variant() {
+            DirectiveVariant::Open(open) => {
+                Some((open.account().item(), open.booking_method(options)))
+            }
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut inventories = BTreeMap::<&'a Account<'a>, Inventory<'a>>::new();
+    let mut booked_postings = Vec::new();
+    let mut snapshots = Vec::new();
+    let mut errors = Vec::new();
+    let snapshot =
+        |inventories: &BTreeMap<&'a Account<'a>, Inventory<'a>>| -> BTreeMap<_, Inventory<'a>> {
+            inventories
+                .iter()
+                .filter(|(_, inventory)| !inventory.is_empty())
+                .map(|(account, inventory)| (*account, inventory.clone()))
+                .collect()
+        };
+
+    for directive in directives {
+        while snapshot_dates
+            .get(snapshots.len())
+            .is_some_and(|snapshot_date| snapshot_date < directive.date().item())
+        {
+            snapshots.push(snapshot(&inventories));
+        }
+
+        if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+            // for restoring the inventories if any posting fails to book
+            let saved = transaction
+                .postings()
+                .map(|posting| {
+                    let account = posting.account().item();
+                    (account, inventories.get(account).cloned())
+                })
+                .collect::<Vec<_>>();
+
+            let date = *directive.date().item();
+            let booked = interpolate(transaction).and_then(|units| {
+                let mut booked = Vec::new();
+                for units in units {
+                    let account = units.posting.account().item();
+                    // accounts which are not open are booked by default
+                    let method = methods
+                        .get(account)
+                        .copied()
+                        .unwrap_or(options.booking_method());
+                    let lots = inventories.entry(account).or_default().book(
+                        units.posting,
+                        units.currency,
+                        units.number,
+                        date,
+                        method,
+                    )?;
+                    booked.extend(lots.into_iter().map(|(units_booked, cost)| BookedPosting {
+                        posting: units.posting,
+                        date,
+                        units: units_booked,
+                        currency: units.currency,
+                        cost,
+                    }));
+                }
+                Ok(booked)
+            });
+
+            match booked {
+                Ok(mut booked) => booked_postings.append(&mut booked),
+                Err(e) => {
+                    for (account, inventory) in saved {
+                        match inventory {
+                            Some(inventory) => inventories.insert(account, inventory),
+                            None => inventories.remove(account),
+                        };
+                    }
+                    errors.push(e.in_context(directive));
+                }
+            }
+        }
+    }
+
+    while snapshots.len() < snapshot_dates.len() {
+        snapshots.push(snapshot(&inventories));
+    }
+    inventories.retain(|_, inventory| !inventory.is_empty());
+
+    (inventories, booked_postings, snapshots, errors)
+}
+
+/// A posting as booked, see [booked_postings].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct BookedPosting<'a> {
+    posting: &'a Spanned<Posting<'a>>,
+    date: Date,
+    units: Decimal,
+    currency: Currency<'a>,
+    cost: Option<Cost<'a>>,
+}
+
+impl<'a> BookedPosting<'a> {
+    /// The posting as written, which may have been booked as several postings.
+    pub fn posting(&self) -> &'a Spanned<Posting<'a>> {
+        self.posting
+    }
+
+    /// The date of the transaction.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// The units booked, interpolated if not given in the posting.
+    pub fn units(&self) -> Decimal {
+        self.units
+    }
+
+    /// Field accessor.
+    pub fn currency(&self) -> Currency<'a> {
+        self.currency
+    }
+
+    /// The cost of the lot augmented or reduced, if held at cost.
+    pub fn cost(&self) -> Option<&Cost<'a>> {
+        self.cost.as_ref()
+    }
+}
+
+impl<'a> Display for BookedPosting<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {} {}",
+            self.posting.account(),
+            self.units,
+            self.currency
+        )?;
+        if let Some(cost) = &self.cost {
+            write!(f, " {}", cost)?;
+        }
+        Ok(())
+    }
+}
+
+/// The positions held in an account, bucketed by currency and cost, which comprises the cost per unit,
+/// cost currency, acquisition date, and label.
+///
+/// Besides being the result of booking, an inventory may be built up directly, for engines which do their own booking.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{Booking, Cost, Inventory, Position};
+/// use rust_decimal_macros::dec;
+/// use time::{Date, Month};
+///
+/// let hool = "HOOL".try_into().unwrap();
+/// let usd = "USD".try_into().unwrap();
+/// let date = |day| Date::from_calendar_date(2024, Month::January, day).unwrap();
+///
+/// let mut inventory = Inventory::default();
+/// inventory.add(Position::new(dec!(10), hool, Some(Cost::new(dec!(100), usd, date(1), None))));
+/// inventory.add(Position::new(dec!(5), hool, Some(Cost::new(dec!(110), usd, date(2), None))));
+/// inventory.add(Position::new(dec!(-20), usd, None));
+///
+/// let reduced = inventory.reduce(dec!(-12), hool, Booking::Fifo, |_| true).unwrap();
+///
+/// assert_eq!(reduced.len(), 2);
+/// assert_eq!(inventory.to_string(), "3 HOOL {110 USD, 2024-01-02}, -20 USD");
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Inventory<'a> {
+    positions: Vec<Position<'a>>,
+}
+
+impl<'a> Inventory<'a> {
+    /// The positions, those held at cost being in order of acquisition.
+    pub fn positions(&self) -> impl ExactSizeIterator<Item = &Position<'a>> {
+        self.positions.iter()
+    }
+
+    /// Whether there are no positions.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The total number of units of `currency`, whether held at cost or not.
+    pub fn units(&self, currency: &Currency<'_>) -> Decimal {
+        self.positions
+            .iter()
+            .filter(|position| position.currency == *currency)
+            .map(|position| position.units)
+            .sum()
+    }
+
+    /// Add `position`, merging it with any position in the same currency at the same cost,
+    /// and dropping that if the result is zero.
+    ///
+    /// Unlike [Inventory::reduce], this never reduces a lot held at a different cost.
+    pub fn add(&mut self, position: Position<'a>) {
+        match self
+            .positions
+            .iter_mut()
+            .find(|other| other.currency == position.currency && other.cost == position.cost)
+        {
+            Some(other) => other.units += position.units,
+            None => self.positions.push(position),
+        }
+        self.positions.retain(|position| !position.units.is_zero());
+    }
+
+    /// Reduce the lots of `currency` held at cost by `units`, of the opposite sign to the lots,
+    /// choosing among the lots whose cost `matches` according to the booking `method`,
+    /// and returning the positions by which each lot was reduced.
+    ///
+    /// With [Booking::Average] all lots of `currency` are first merged at their average cost.
+    pub fn reduce<F>(
+        &mut self,
+        units: Decimal,
+        currency: Currency<'a>,
+        method: Booking,
+        matches: F,
+    ) -> Result<Vec<Position<'a>>, ReductionError<'a>>
+    where
+        F: FnMut(&Cost<'a>) -> bool,
+    {
+        if method == Booking::Average {
+            self.average(currency);
+        }
+
+        self.reduce_lots(currency, units, method, matches)
+            .map(|reduced| {
+                reduced
+                    .into_iter()
+                    .map(|(units, cost)| Position {
+                        units,
+                        currency,
+                        cost,
+                    })
+                    .collect()
+            })
+    }
+
+    fn book(
+        &mut self,
+        posting: &'a Spanned<Posting<'a>>,
+        currency: Currency<'a>,
+        units: Decimal,
+        date: Date,
+        method: Booking,
+    ) -> Result<Vec<(Decimal, Option<Cost<'a>>)>, Error> {
+        let cost_spec = posting.cost_spec();
+        let merge = cost_spec.filter(|cost_spec| cost_spec.merge());
+
+        // since an empty cost specification is parsed as none at all, any posting may reduce a position held at cost
+        let is_reduction = method != Booking::None
+            && self.positions.iter().any(|position| {
+                position.currency == currency
+                    && position.cost.is_some()
+                    && position.units.is_sign_negative() != units.is_sign_negative()
+            });
+
+        if is_reduction {
+            match merge {
+                Some(merge) => {
+                    if merge.per_unit().is_some()
+                        || merge.total().is_some()
+                        || merge.date().is_some()
+                        || merge.label().is_some()
+                    {
+                        return Err(
+                            merge.error("merge cost may specify only a currency when reducing")
+                        );
+                    }
+                    // reduce the merged lot at its average cost
+                    self.average(currency);
+                    self.reduce_posting(posting, cost_spec, currency, units, Booking::Average)
+                }
+                None => self.reduce_posting(posting, cost_spec, currency, units, method),
+            }
+        } else if let Some(cost_spec) = cost_spec {
+            if merge.is_some() && cost_spec.per_unit().is_none() && cost_spec.total().is_none() {
+                return Err(cost_spec.error("merge cost without a cost may only reduce a position"));
+            }
+            let cost = self.augment(cost_spec, currency, units, date)?;
+            if merge.is_some() || method == Booking::Average {
+                self.average(currency);
+            }
+            Ok(vec![(units, Some(cost))])
+        } else {
+            self.add(Position {
+                units,
+                currency,
+                cost: None,
+            });
+            Ok(vec![(units, None)])
+        }
+    }
+
+    fn augment(
+        &mut self,
+        cost_spec: &'a Spanned<CostSpec<'a>>,
+        currency: Currency<'a>,
+        units: Decimal,
+        date: Date,
+    ) -> Result<Cost<'a>, Error> {
+        let cost_currency = cost_spec
+            .currency()
+            .ok_or_else(|| cost_spec.error("cost currency cannot be inferred"))?;
+        if cost_spec.per_unit().is_none() && cost_spec.total().is_none() {
+            return Err(cost_spec.error("cost cannot be inferred"));
+        }
+
+        let per_unit_from_total = match cost_spec.total() {
+            Some(total) => total
+                .item()
+                .value()
+                .checked_div(units.abs())
+                .ok_or_else(|| cost_spec.error("total cost for zero units"))?,
+            None => Decimal::ZERO,
+        };
+        let per_unit = cost_spec
+            .per_unit()
+            .map(|per_unit| per_unit.item().value())
+            .unwrap_or_default()
+            + per_unit_from_total;
+
+        let cost = Cost {
+            per_unit,
+            currency: *cost_currency.item(),
+            date: cost_spec.date().map_or(date, |date| *date.item()),
+            label: cost_spec.label().map(|label| *label.item()),
+        };
+
+        self.add(Position {
+            units,
+            currency,
+            cost: Some(cost.clone()),
+        });
+        Ok(cost)
+    }
+
+    /// Merge all lots of `currency` held at cost into one per cost currency, at the average cost per unit,
+    /// acquired on the earliest date of any of them.
+    fn average(&mut self, currency: Currency<'a>) {
+        let mut averaged = Vec::<Position<'a>>::new();
+
+        for position in self.positions.drain(..) {
+            let existing = match &position.cost {
+                Some(cost) if position.currency == currency => averaged.iter_mut().find(|other| {
+                    other.currency == currency
+                        && other
+                            .cost
+                            .as_ref()
+                            .is_some_and(|other| other.currency == cost.currency)
+                }),
+                _ => None,
+            };
+
+            match (existing, &position.cost) {
+                (Some(other), Some(cost)) => {
+                    let total = other.units + position.units;
+                    let other_cost = other.cost.as_mut().unwrap();
+                    if !total.is_zero() {
+                        other_cost.per_unit = ((other_cost.per_unit * other.units
+                            + cost.per_unit * position.units)
+                            / total)
+                            .normalize();
+                    }
+                    other_cost.date = other_cost.date.min(cost.date);
+                    other_cost.label = None;
+                    other.units = total;
+                }
+                _ => averaged.push(position),
+            }
+        }
+
+        self.positions = averaged;
+        self.positions.retain(|position| !position.units.is_zero());
+    }
+
+    fn reduce_posting(
+        &mut self,
+        posting: &'a Spanned<Posting<'a>>,
+        cost_spec: Option<&'a Spanned<CostSpec<'a>>>,
+        currency: Currency<'a>,
+        units: Decimal,
+        method: Booking,
+    ) -> Result<Vec<(Decimal, Option<Cost<'a>>)>, Error> {
+        self.reduce_lots(currency, units, method, |cost| {
+            cost_spec.is_none_or(|cost_spec| match method {
+                // the cost of an averaged lot need not be given exactly
+                Booking::Average => cost_spec
+                    .currency()
+                    .is_none_or(|currency| *currency.item() == cost.currency),
+                _ => cost.matches(cost_spec),
+            })
+        })
+        .map_err(|e| posting.error(e.to_string()))
+    }
+
+    fn reduce_lots<F>(
+        &mut self,
+        currency: Currency<'a>,
+        units: Decimal,
+        method: Booking,
+        mut matches_cost: F,
+    ) -> Result<Vec<(Decimal, Option<Cost<'a>>)>, ReductionError<'a>>
+    where
+        F: FnMut(&Cost<'a>) -> bool,
+    {
+        use Booking::*;
+
+        let mut matches = self
+            .positions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, position)| {
+                position
+                    .cost
+                    .as_ref()
+                    .filter(|cost| {
+                        position.currency == currency
+                            && position.units.is_sign_negative() != units.is_sign_negative()
+                            && matches_cost(cost)
+                    })
+                    .map(|cost| (i, cost))
+            })
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() {
+            return Err(ReductionError::NoMatch);
+        }
+
+        let wanted = units.abs();
+        let available = matches
+            .iter()
+            .map(|(i, _)| self.positions[*i].units.abs())
+            .sum::<Decimal>();
+        if wanted > available {
+            return Err(ReductionError::NotEnough(available, currency));
+        }
+
+        match method {
+            Strict | StrictWithSize if matches.len() > 1 && wanted != available => {
+                let exact = matches
+                    .iter()
+                    .find(|(i, _)| self.positions[*i].units.abs() == wanted);
+                match exact {
+                    Some(exact) if method == StrictWithSize => matches = vec![*exact],
+                    _ => return Err(ReductionError::Ambiguous),
+                }
+            }
+            Lifo => matches.sort_by_key(|(_, cost)| std::cmp::Reverse(cost.date)),
+            Hifo => matches.sort_by_key(|(_, cost)| std::cmp::Reverse(cost.per_unit)),
+            // positions are in order of acquisition, and the sort is stable
+            _ => matches.sort_by_key(|(_, cost)| cost.date),
+        }
+
+        let order = matches.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
+        let mut remaining = wanted;
+        let mut reduced = Vec::new();
+        for i in order {
+            let position = &mut self.positions[i];
+            let reduction = remaining.min(position.units.abs());
+            if position.units.is_sign_negative() {
+                position.units += reduction;
+            } else {
+                position.units -= reduction;
+            }
+            remaining -= reduction;
+
+            if !reduction.is_zero() {
+                let signed = if units.is_sign_negative() {
+                    -reduction
+                } else {
+                    reduction
+                };
+                reduced.push((signed, position.cost.clone()));
+            }
+        }
+
+        self.positions.retain(|position| !position.units.is_zero());
+        Ok(reduced)
+    }
+}
+
+/// Equality regardless of the order of positions.
+impl<'a> PartialEq for Inventory<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        // positions are unique by currency and cost, so there are no duplicates to count
+        self.positions.len() == other.positions.len()
+            && self
+                .positions
+                .iter()
+                .all(|position| other.positions.contains(position))
+    }
+}
+
+impl<'a> Eq for Inventory<'a> {}
+
+impl<'a> Display for Inventory<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        format(f, &self.positions, plain, ", ", None)
+    }
+}
+
+/// Error type for [Inventory::reduce].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ReductionError<'a> {
+    /// No lot matches.
+    NoMatch,
+    /// The matching lots hold fewer units than the reduction, only those given.
+    NotEnough(Decimal, Currency<'a>),
+    /// Several lots match, and the booking method cannot choose between them.
+    Ambiguous,
+}
+
+impl<'a> Display for ReductionError<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use ReductionError::*;
+
+        match self {
+            NoMatch => f.write_str("no position matches cost specification"),
+            NotEnough(available, currency) => write!(
+                f,
+                "not enough units to reduce, only {} {} available",
+                available, currency
+            ),
+            Ambiguous => f.write_str("ambiguous match for cost specification"),
+        }
+    }
+}
+
+impl<'a> std::error::Error for ReductionError<'a> {}
+
+/// A number of units of a currency, optionally held at cost.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Position<'a> {
+    units: Decimal,
+    currency: Currency<'a>,
+    cost: Option<Cost<'a>>,
+}
+
+impl<'a> Position<'a> {
+    /// A position of `units` of `currency`, held at `cost` if any.
+    pub fn new(units: Decimal, currency: Currency<'a>, cost: Option<Cost<'a>>) -> Self {
+        Position {
+            units,
+            currency,
+            cost,
+        }
+    }
+
+    /// Field accessor.
+    pub fn units(&self) -> Decimal {
+        self.units
+    }
+
+    /// Field accessor.
+    pub fn currency(&self) -> Currency<'a> {
+        self.currency
+    }
+
+    /// Field accessor.
+    pub fn cost(&self) -> Option<&Cost<'a>> {
+        self.cost.as_ref()
+    }
+
+    /// The value of the position in `target` currency at market price as of `date`, if there is a price for that.
+    pub fn market_value(
+        &self,
+        prices: &PriceDb<'a>,
+        target: Currency<'a>,
+        date: Date,
+    ) -> Option<Decimal> {
+        prices.convert(self.currency, self.units, target, date)
+    }
+
+    /// The total cost of the position in its cost currency, if it is held at cost.
+    pub fn book_value(&self) -> Option<(Decimal, Currency<'a>)> {
+        self.cost
+            .as_ref()
+            .map(|cost| (self.units * cost.per_unit, cost.currency))
+    }
+
+    fn checked<F>(&self, other: &Position<'a>, op: F) -> Result<Position<'a>, ArithmeticError<'a>>
+    where
+        F: FnOnce(Decimal, Decimal) -> Decimal,
+    {
+        if self.currency != other.currency {
+            Err(ArithmeticError::CurrencyMismatch(
+                self.currency,
+                other.currency,
+            ))
+        } else if self.cost != other.cost {
+            Err(ArithmeticError::CostMismatch)
+        } else {
+            Ok(Position {
+                units: op(self.units, other.units),
+                ..self.clone()
+            })
+        }
+    }
+}
+
+/// Addition of positions in the same currency held at the same cost, if any.
+impl<'a, 'b> Add<&'b Position<'a>> for &'b Position<'a> {
+    type Output = Result<Position<'a>, ArithmeticError<'a>>;
+
+    fn add(self, other: &'b Position<'a>) -> Self::Output {
+        self.checked(other, |x, y| x + y)
+    }
+}
+
+impl<'a> Add for Position<'a> {
+    type Output = Result<Position<'a>, ArithmeticError<'a>>;
+
+    fn add(self, other: Position<'a>) -> Self::Output {
+        &self + &other
+    }
+}
+
+/// Subtraction of positions in the same currency held at the same cost, if any.
+impl<'a, 'b> Sub<&'b Position<'a>> for &'b Position<'a> {
+    type Output = Result<Position<'a>, ArithmeticError<'a>>;
+
+    fn sub(self, other: &'b Position<'a>) -> Self::Output {
+        self.checked(other, |x, y| x - y)
+    }
+}
+
+impl<'a> Sub for Position<'a> {
+    type Output = Result<Position<'a>, ArithmeticError<'a>>;
+
+    fn sub(self, other: Position<'a>) -> Self::Output {
+        &self - &other
+    }
+}
+
+impl<'a> Neg for &Position<'a> {
+    type Output = Position<'a>;
+
+    fn neg(self) -> Self::Output {
+        Position {
+            units: -self.units,
+            ..self.clone()
+        }
+    }
+}
+
+impl<'a> Neg for Position<'a> {
+    type Output = Position<'a>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+/// Scaling of the units of a position, leaving the cost per unit unchanged.
+impl<'a> Mul<Decimal> for &Position<'a> {
+    type Output = Position<'a>;
+
+    fn mul(self, factor: Decimal) -> Self::Output {
+        Position {
+            units: self.units * factor,
+            ..self.clone()
+        }
+    }
+}
+
+impl<'a> Mul<Decimal> for Position<'a> {
+    type Output = Position<'a>;
+
+    fn mul(self, factor: Decimal) -> Self::Output {
+        &self * factor
+    }
+}
+
+impl<'a> Display for Position<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.units, self.currency)?;
+        if let Some(cost) = &self.cost {
+            write!(f, " {}", cost)?;
+        }
+        Ok(())
+    }
+}
+
+/// The cost of a lot, determined by booking.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Cost<'a> {
+    per_unit: Decimal,
+    currency: Currency<'a>,
+    date: Date,
+    label: Option<&'a str>,
+}
+
+impl<'a> Cost<'a> {
+    /// The cost of a lot acquired on `date`, optionally with a label.
+    pub fn new(
+        per_unit: Decimal,
+        currency: Currency<'a>,
+        date: Date,
+        label: Option<&'a str>,
+    ) -> Self {
+        Cost {
+            per_unit,
+            currency,
+            date,
+            label,
+        }
+    }
+
+    /// Field accessor.
+    pub fn per_unit(&self) -> Decimal {
+        self.per_unit
+    }
+
+    /// Field accessor.
+    pub fn currency(&self) -> Currency<'a> {
+        self.currency
+    }
+
+    /// The acquisition date.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// Field accessor.
+    pub fn label(&self) -> Option<&'a str> {
+        self.label
+    }
+
+    /// Whether this cost is consistent with every component present in `cost_spec`.
+    fn matches(&self, cost_spec: &CostSpec<'_>) -> bool {
+        cost_spec
+            .per_unit()
+            .is_none_or(|per_unit| per_unit.item().value() == self.per_unit)
+            && cost_spec
+                .currency()
+                .is_none_or(|currency| *currency.item() == self.currency)
+            && cost_spec
+                .date()
+                .is_none_or(|date| *date.item() == self.date)
+            && cost_spec
+                .label()
+                .is_none_or(|label| Some(*label.item()) == self.label)
+    }
+}
+
+impl<'a> Display for Cost<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{{{} {}, {}", self.per_unit, self.currency, self.date)?;
+        if let Some(label) = self.label {
+            write!(f, ", \"{}\"", label)?;
+        }
+        f.write_str("}")
+    }
+}
+
+mod tests;
@@ -0,0 +1,407 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+/// The positions remaining in `Assets:Brokerage` at the end of `reductions`, after buying some lots of HOOL.
+fn book_brokerage(booking: &str, reductions: &str) -> Result<Vec<String>, Vec<String>> {
+    let source = format!(
+        r#"
+2024-01-01 open Assets:Brokerage "{}"
+2024-01-01 open Assets:Bank
+
+2024-01-02 * "Buy"
+  Assets:Brokerage  10 HOOL {{100 USD}}
+  Assets:Bank
+
+2024-02-02 * "Buy"
+  Assets:Brokerage  10 HOOL {{120 USD}}
+  Assets:Bank
+
+2024-03-02 * "Buy"
+  Assets:Brokerage  5 HOOL {{110 USD, "odd lot"}}
+  Assets:Bank
+{}"#,
+        booking, reductions
+    );
+    let sources = BeancountSources::from(source.as_str());
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let date = Date::from_calendar_date(2024, time::Month::December, 31).unwrap();
+
+    let brokerage = Account::new(
+        AccountType::Assets,
+        [AccountName::try_from("Brokerage").unwrap()]
+            .into_iter()
+            .collect(),
+    );
+
+    book(&success.directives, &success.options, date)
+        .map(|inventories| {
+            inventories
+                .get(&brokerage)
+                .map(|inventory| inventory.positions().map(Position::to_string).collect())
+                .unwrap_or_default()
+        })
+        .map_err(|errors| errors.into_iter().map(|e| e.reason.into()).collect())
+}
+
+const SELL_12: &str = r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -12 HOOL {}
+  Assets:Bank
+"#;
+
+#[test]
+fn book_augmentations() {
+    assert_eq!(
+        book_brokerage("STRICT", ""),
+        Ok(vec![
+            "10 HOOL {100 USD, 2024-01-02}".to_string(),
+            "10 HOOL {120 USD, 2024-02-02}".to_string(),
+            "5 HOOL {110 USD, 2024-03-02, \"odd lot\"}".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_fifo() {
+    assert_eq!(
+        book_brokerage("FIFO", SELL_12),
+        Ok(vec![
+            "8 HOOL {120 USD, 2024-02-02}".to_string(),
+            "5 HOOL {110 USD, 2024-03-02, \"odd lot\"}".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_lifo() {
+    assert_eq!(
+        book_brokerage("LIFO", SELL_12),
+        Ok(vec![
+            "10 HOOL {100 USD, 2024-01-02}".to_string(),
+            "3 HOOL {120 USD, 2024-02-02}".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_hifo() {
+    assert_eq!(
+        book_brokerage("HIFO", SELL_12),
+        Ok(vec![
+            "10 HOOL {100 USD, 2024-01-02}".to_string(),
+            "3 HOOL {110 USD, 2024-03-02, \"odd lot\"}".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_strict_by_label() {
+    assert_eq!(
+        book_brokerage(
+            "STRICT",
+            r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -5 HOOL {"odd lot"}
+  Assets:Bank
+"#
+        ),
+        Ok(vec![
+            "10 HOOL {100 USD, 2024-01-02}".to_string(),
+            "10 HOOL {120 USD, 2024-02-02}".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_strict_ambiguous() {
+    assert_eq!(
+        book_brokerage("STRICT", SELL_12),
+        Err(vec!["ambiguous match for cost specification".to_string()])
+    );
+}
+
+#[test]
+fn book_strict_with_size() {
+    assert_eq!(
+        book_brokerage(
+            "STRICT_WITH_SIZE",
+            r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -5 HOOL {}
+  Assets:Bank
+"#
+        ),
+        Ok(vec![
+            "10 HOOL {100 USD, 2024-01-02}".to_string(),
+            "10 HOOL {120 USD, 2024-02-02}".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_not_enough_units() {
+    assert_eq!(
+        book_brokerage(
+            "FIFO",
+            r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -30 HOOL {}
+  Assets:Bank
+"#
+        ),
+        Err(vec![
+            "not enough units to reduce, only 25 HOOL available".to_string()
+        ])
+    );
+}
+
+#[test]
+fn book_average() {
+    assert_eq!(
+        book_brokerage("AVERAGE", ""),
+        Ok(vec!["25 HOOL {110 USD, 2024-01-02}".to_string()])
+    );
+}
+
+#[test]
+fn book_average_reduction() {
+    assert_eq!(
+        book_brokerage(
+            "AVERAGE",
+            r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -15 HOOL {USD}
+  Assets:Bank
+
+2024-05-01 * "Buy"
+  Assets:Brokerage  10 HOOL {131 USD}
+  Assets:Bank
+"#
+        ),
+        Ok(vec!["20 HOOL {120.5 USD, 2024-01-02}".to_string()])
+    );
+}
+
+#[test]
+fn book_merge_cost() {
+    assert_eq!(
+        book_brokerage(
+            "FIFO",
+            r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -5 HOOL {*}
+  Assets:Bank
+"#
+        ),
+        Ok(vec!["20 HOOL {110 USD, 2024-01-02}".to_string()])
+    );
+}
+
+#[test]
+fn book_merge_cost_augmentation() {
+    assert_eq!(
+        book_brokerage(
+            "FIFO",
+            r#"
+2024-04-01 * "Buy"
+  Assets:Brokerage  15 HOOL {*, 130 USD}
+  Assets:Bank
+"#
+        ),
+        Ok(vec!["40 HOOL {117.5 USD, 2024-01-02}".to_string()])
+    );
+}
+
+#[test]
+fn book_merge_cost_invalid() {
+    assert_eq!(
+        book_brokerage(
+            "FIFO",
+            r#"
+2024-04-01 * "Sell"
+  Assets:Brokerage  -5 HOOL {*, 2024-01-02}
+  Assets:Bank
+
+2024-04-02 * "Buy"
+  Assets:Brokerage  5 GOOG {*}
+  Assets:Bank
+"#
+        ),
+        Err(vec![
+            "merge cost may specify only a currency when reducing".to_string(),
+            "merge cost without a cost may only reduce a position".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn book_total_cost_for_zero_units() {
+    assert_eq!(
+        book_brokerage(
+            "FIFO",
+            r#"
+2024-04-01 * "Buy nothing"
+  Assets:Brokerage  0 HOOL {# 100 USD}
+  Assets:Bank
+"#
+        ),
+        Err(vec!["total cost for zero units".to_string()])
+    );
+}
+
+#[test]
+fn booked_postings_resolved_costs() {
+    let sources = BeancountSources::from(
+        r#"
+2024-01-01 open Assets:Brokerage "FIFO"
+2024-01-01 open Assets:Bank
+
+2024-01-02 * "Buy"
+  Assets:Brokerage  10 HOOL {100 USD}
+  Assets:Bank
+
+2024-02-02 * "Buy"
+  Assets:Brokerage  5 HOOL {120 USD, 2024-01-15, "odd lot"}
+  Assets:Bank
+
+2024-04-01 * "Sell"
+  Assets:Brokerage  -12 HOOL {}
+  Assets:Bank  1400 USD
+
+2024-04-02 * "Sell"
+  Assets:Brokerage  -1 HOOL {"odd lot"}
+  Assets:Bank  120 USD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    let booked = booked_postings(&success.directives, &success.options, Date::MAX)
+        .unwrap()
+        .into_iter()
+        .filter(|booked| booked.currency().as_ref() == "HOOL")
+        .map(|booked| booked.to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        booked,
+        vec![
+            // the lot date defaults to the transaction date
+            "Assets:Brokerage  10 HOOL {100 USD, 2024-01-02}",
+            "Assets:Brokerage  5 HOOL {120 USD, 2024-01-15, \"odd lot\"}",
+            // a reduction of several lots is booked against each
+            "Assets:Brokerage  -10 HOOL {100 USD, 2024-01-02}",
+            "Assets:Brokerage  -2 HOOL {120 USD, 2024-01-15, \"odd lot\"}",
+            "Assets:Brokerage  -1 HOOL {120 USD, 2024-01-15, \"odd lot\"}",
+        ]
+    );
+}
+
+#[test]
+fn position_arithmetic() {
+    let usd = Currency::try_from("USD").unwrap();
+    let hool = Currency::try_from("HOOL").unwrap();
+    let cost = |per_unit| Cost {
+        per_unit,
+        currency: usd,
+        date: Date::from_calendar_date(2024, time::Month::January, 2).unwrap(),
+        label: None,
+    };
+    let x = Position {
+        units: Decimal::from(10),
+        currency: hool,
+        cost: Some(cost(Decimal::from(100))),
+    };
+    let y = Position {
+        units: Decimal::from(4),
+        ..x.clone()
+    };
+
+    assert_eq!(
+        (&x - &y).unwrap().to_string(),
+        "6 HOOL {100 USD, 2024-01-02}"
+    );
+    assert_eq!(
+        (-&x * Decimal::from(2)).book_value(),
+        Some((Decimal::from(-2000), usd))
+    );
+
+    let z = Position {
+        cost: Some(cost(Decimal::from(120))),
+        ..y.clone()
+    };
+    assert_eq!(&x + &z, Err(ArithmeticError::CostMismatch));
+
+    let w = Position {
+        units: Decimal::from(5),
+        currency: usd,
+        cost: None,
+    };
+    assert_eq!(x + w, Err(ArithmeticError::CurrencyMismatch(hool, usd)));
+}
+
+#[test]
+fn inventory_add_reduce_and_equality() {
+    let usd = Currency::try_from("USD").unwrap();
+    let hool = Currency::try_from("HOOL").unwrap();
+    let date = |day| Date::from_calendar_date(2024, time::Month::January, day).unwrap();
+    let lot = |units, per_unit, day, label| {
+        Position::new(
+            Decimal::from(units),
+            hool,
+            Some(Cost::new(Decimal::from(per_unit), usd, date(day), label)),
+        )
+    };
+
+    let mut inventory = Inventory::default();
+    inventory.add(lot(10, 100, 1, None));
+    inventory.add(lot(5, 120, 2, Some("odd lot")));
+    inventory.add(lot(2, 100, 1, None));
+    inventory.add(Position::new(Decimal::from(-500), usd, None));
+
+    let mut other = Inventory::default();
+    other.add(Position::new(Decimal::from(-500), usd, None));
+    other.add(lot(5, 120, 2, Some("odd lot")));
+    other.add(lot(12, 100, 1, None));
+    assert_eq!(inventory, other);
+
+    assert_eq!(
+        inventory.reduce(Decimal::from(-3), hool, Booking::Strict, |_| true),
+        Err(ReductionError::Ambiguous)
+    );
+    assert_eq!(
+        inventory.reduce(Decimal::from(-30), hool, Booking::Fifo, |_| true),
+        Err(ReductionError::NotEnough(Decimal::from(17), hool))
+    );
+    assert_eq!(
+        inventory.reduce(Decimal::from(-3), hool, Booking::Fifo, |cost| cost
+            .label()
+            .is_some_and(|label| label == "missing")),
+        Err(ReductionError::NoMatch)
+    );
+
+    let reduced = inventory
+        .reduce(Decimal::from(-3), hool, Booking::Strict, |cost| {
+            cost.label() == Some("odd lot")
+        })
+        .unwrap();
+    assert_eq!(
+        reduced.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        vec!["-3 HOOL {120 USD, 2024-01-02, \"odd lot\"}"]
+    );
+    assert_ne!(inventory, other);
+
+    let reduced = inventory
+        .reduce(Decimal::from(-7), hool, Booking::Average, |_| true)
+        .unwrap();
+    assert_eq!(
+        reduced.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        vec!["-7 HOOL {102.85714285714285714285714286 USD, 2024-01-01}"]
+    );
+    assert_eq!(
+        inventory.to_string(),
+        "7 HOOL {102.85714285714285714285714286 USD, 2024-01-01}, -500 USD"
+    );
+}
@@ -0,0 +1,280 @@
+use crate::{aggregate::Interval, trial_balance::Units, types::*};
+use rust_decimal::Decimal;
+use std::fmt::{self, Display, Formatter};
+use time::{Date, Duration};
+
+/// The budgets declared by Fava-style `custom "budget"` directives, for example:
+///
+/// ```text
+/// 2024-01-01 custom "budget" Expenses:Groceries "monthly" 400.00 NZD
+/// ```
+///
+/// As in Fava, a budget applies to exactly the account given, and from its date until the next budget
+/// for the same account and currency.  Budgets are spread evenly over the days of each interval,
+/// so that a budget for a part of an interval is in proportion to the days covered.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Budgets};
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 custom "budget" Expenses:Groceries "monthly" 310.00 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let budgets = Budgets::new(directives.iter()).unwrap();
+/// let groceries = budgets.iter().next().unwrap().account();
+/// let date = |month, day| Date::from_calendar_date(2024, month, day).unwrap();
+///
+/// assert_eq!(
+///     budgets.budget(groceries, date(Month::January, 1), date(Month::January, 11)).to_string(),
+///     "100.00 NZD"
+/// );
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Budgets<'a> {
+    budgets: Vec<Budget<'a>>,
+}
+
+impl<'a> Budgets<'a> {
+    /// Collect and validate the budgets from `custom "budget"` directives, ignoring all other directives.
+    pub fn new<I>(directives: I) -> Result<Self, Vec<Error>>
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut budgets = Vec::new();
+        let mut errors = Vec::new();
+
+        for directive in directives {
+            if let DirectiveVariant::Custom(custom) = directive.variant() {
+                if *custom.custom_type().item() == "budget" {
+                    match Budget::new(*directive.date().item(), custom) {
+                        Ok(budget) => budgets.push(budget),
+                        Err(e) => errors.push(e.in_context(directive)),
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            // stable, so that of several budgets on the same date, the last in the ledger wins
+            budgets.sort_by_key(|budget| budget.date);
+            Ok(Budgets { budgets })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// All budgets, in date order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Budget<'a>> {
+        self.budgets.iter()
+    }
+
+    /// The total budgeted for `account` from `start` up to but excluding `end`.
+    pub fn budget(&self, account: &Account<'_>, start: Date, end: Date) -> Units<'a> {
+        let mut units = Units::default();
+        let budgets = self
+            .budgets
+            .iter()
+            .filter(|budget| budget.account == account)
+            .collect::<Vec<_>>();
+        let mut currencies = budgets
+            .iter()
+            .map(|budget| *budget.amount.currency().item())
+            .collect::<Vec<_>>();
+        currencies.sort();
+        currencies.dedup();
+
+        for currency in currencies {
+            let budgets = budgets
+                .iter()
+                .filter(|budget| *budget.amount.currency().item() == currency)
+                .collect::<Vec<_>>();
+
+            let mut date = start;
+            while date < end {
+                let after = budgets.partition_point(|budget| budget.date <= date);
+                let Some(current) = after.checked_sub(1).map(|i| budgets[i]) else {
+                    // nothing budgeted before the first budget
+                    date = budgets[0].date;
+                    continue;
+                };
+
+                // each segment is within a single interval and governed by a single budget
+                let (period_start, period_end) = current.interval.period(date);
+                let segment_end = budgets
+                    .get(after)
+                    .map_or(end, |next| next.date.min(end))
+                    .min(period_end.unwrap_or(end));
+                // a period extending beyond the representable dates is truncated after the last of them
+                let period_days = period_end
+                    .map_or((Date::MAX - period_start).whole_days() + 1, |period_end| {
+                        (period_end - period_start).whole_days()
+                    });
+                let number = current.amount.number().value()
+                    * Decimal::from((segment_end - date).whole_days())
+                    / Decimal::from(period_days);
+
+                units.add(currency, number);
+                date = segment_end;
+            }
+        }
+
+        units
+    }
+
+    /// The budget for `account` in each bucket of length `interval` which overlaps `start` up to but excluding `end`,
+    /// where the first and last buckets are clipped to that range, ready for charting against an
+    /// [Aggregation](crate::Aggregation).
+    pub fn expand(
+        &self,
+        account: &Account<'_>,
+        start: Date,
+        end: Date,
+        interval: Interval,
+    ) -> Vec<(Date, Units<'a>)> {
+        let mut expanded = Vec::new();
+        let mut bucket = interval.start(start);
+
+        while bucket < end {
+            let next = interval.next(bucket);
+            expanded.push((
+                bucket,
+                self.budget(
+                    account,
+                    bucket.max(start),
+                    next.map_or(end, |next| next.min(end)),
+                ),
+            ));
+            match next {
+                Some(next) => bucket = next,
+                None => break,
+            }
+        }
+
+        expanded
+    }
+}
+
+/// A single budget, see [Budgets].
+#[derive(Clone, Debug)]
+pub struct Budget<'a> {
+    date: Date,
+    account: &'a Account<'a>,
+    interval: BudgetInterval,
+    amount: &'a Amount<'a>,
+}
+
+impl<'a> Budget<'a> {
+    fn new(date: Date, custom: &'a Custom<'a>) -> Result<Self, Error> {
+        use MetaValue::*;
+        use SimpleValue::*;
+
+        if let [account, interval, amount] = custom.values() {
+            if let (Simple(Account(account)), Simple(String(keyword)), Amount(amount)) =
+                (account.item(), interval.item(), amount.item())
+            {
+                let interval = BudgetInterval::try_from(*keyword).map_err(|_| {
+                    Error::new(
+                        "invalid budget",
+                        "interval must be one of daily, weekly, monthly, quarterly, yearly",
+                        *interval.span(),
+                    )
+                })?;
+
+                return Ok(Budget {
+                    date,
+                    account,
+                    interval,
+                    amount,
+                });
+            }
+        }
+
+        Err(Error::new(
+            "invalid budget",
+            "expected account, interval, and amount",
+            *custom.custom_type().span(),
+        ))
+    }
+
+    /// Field accessor.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// Field accessor.
+    pub fn account(&self) -> &'a Account<'a> {
+        self.account
+    }
+
+    /// Field accessor.
+    pub fn interval(&self) -> BudgetInterval {
+        self.interval
+    }
+
+    /// The amount budgeted for each interval.
+    pub fn amount(&self) -> &'a Amount<'a> {
+        self.amount
+    }
+}
+
+/// The interval of a [Budget], as given by its keyword.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BudgetInterval {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl BudgetInterval {
+    // the first day of the interval containing `date`, and the first day after it if representable
+    fn period(&self, date: Date) -> (Date, Option<Date>) {
+        use BudgetInterval::*;
+
+        let interval = match self {
+            Daily => return (date, date.checked_add(Duration::days(1))),
+            Weekly => Interval::Week,
+            Monthly => Interval::Month,
+            Quarterly => Interval::Quarter,
+            Yearly => Interval::YEAR,
+        };
+        let start = interval.start(date);
+        (start, interval.next(start))
+    }
+}
+
+impl TryFrom<&str> for BudgetInterval {
+    type Error = ();
+
+    fn try_from(keyword: &str) -> Result<Self, Self::Error> {
+        use BudgetInterval::*;
+
+        match keyword {
+            "daily" => Ok(Daily),
+            "weekly" => Ok(Weekly),
+            "monthly" => Ok(Monthly),
+            "quarterly" => Ok(Quarterly),
+            "yearly" => Ok(Yearly),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for BudgetInterval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use BudgetInterval::*;
+
+        f.write_str(match self {
+            Daily => "daily",
+            Weekly => "weekly",
+            Monthly => "monthly",
+            Quarterly => "quarterly",
+            Yearly => "yearly",
+        })
+    }
+}
+
+mod tests;
@@ -0,0 +1,171 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+
+const LEDGER: &str = r#"
+2024-01-01 custom "budget" Expenses:Groceries "monthly" 310.00 NZD
+2024-01-01 custom "budget" Expenses:Groceries "weekly" 10.00 USD
+2024-01-01 custom "budget" Expenses:Travel "yearly" 3660.00 NZD
+2024-02-01 custom "budget" Expenses:Groceries "monthly" 290.00 NZD
+2024-02-01 custom "budget" Expenses:Coffee "daily" 4.50 NZD
+2024-02-01 custom "forecast" Expenses:Coffee "daily"
+"#;
+
+fn account(s: &str) -> Account<'_> {
+    let mut names = s.split(':');
+    let account_type = names.next().unwrap().parse().unwrap();
+    let names = names.map(|name| AccountName::try_from(name).unwrap());
+    Account::new(account_type, names.collect())
+}
+
+#[test]
+fn budgets_parsed() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let budgets = Budgets::new(directives.iter()).unwrap();
+
+    assert_eq!(
+        budgets
+            .iter()
+            .map(|budget| format!(
+                "{} {} {} {}",
+                budget.date(),
+                budget.account(),
+                budget.interval(),
+                budget.amount()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            "2024-01-01 Expenses:Groceries monthly 310.00 NZD",
+            "2024-01-01 Expenses:Groceries weekly 10.00 USD",
+            "2024-01-01 Expenses:Travel yearly 3660.00 NZD",
+            "2024-02-01 Expenses:Groceries monthly 290.00 NZD",
+            "2024-02-01 Expenses:Coffee daily 4.50 NZD",
+        ]
+    );
+}
+
+#[test]
+fn budget_over_range() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let budgets = Budgets::new(directives.iter()).unwrap();
+    let budget = |account_name: &str, start: Date, end: Date| {
+        budgets
+            .budget(&account(account_name), start, end)
+            .to_string()
+    };
+
+    // the January budget for January, then the February budget for 2 days of 29
+    assert_eq!(
+        budget("Expenses:Groceries", date("2024-01-01"), date("2024-02-03")),
+        "330.00 NZD, 47.142857142857142857142857143 USD"
+    );
+    assert_eq!(
+        budget("Expenses:Travel", date("2024-03-01"), date("2024-03-11")),
+        "100.00 NZD"
+    );
+    // nothing budgeted before the budget starts
+    assert_eq!(
+        budget("Expenses:Coffee", date("2024-01-01"), date("2024-02-03")),
+        "9.00 NZD"
+    );
+    // budgets apply to exactly their account
+    assert_eq!(
+        budget("Expenses", date("2024-01-01"), date("2024-03-01")),
+        ""
+    );
+}
+
+#[test]
+fn budget_expanded() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let budgets = Budgets::new(directives.iter()).unwrap();
+
+    assert_eq!(
+        budgets
+            .expand(
+                &account("Expenses:Groceries"),
+                date("2024-01-16"),
+                date("2024-04-01"),
+                Interval::Month
+            )
+            .into_iter()
+            .map(|(start, units)| format!(
+                "{} {}",
+                start,
+                units.get(&Currency::try_from("NZD").unwrap())
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            "2024-01-01 160.00",
+            "2024-02-01 290.00",
+            "2024-03-01 290.00"
+        ]
+    );
+}
+
+#[test]
+fn invalid_budgets() {
+    let sources = BeancountSources::from(
+        r#"2024-01-01 custom "budget" Expenses:Groceries "fortnightly" 310.00 NZD
+2024-01-01 custom "budget" Expenses:Groceries 310.00 NZD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let errors = Budgets::new(directives.iter()).unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .all(|error| error.message() == "invalid budget"));
+    assert!(errors[0].to_string().contains("interval must be one of"));
+    assert!(errors[1]
+        .to_string()
+        .contains("expected account, interval, and amount"));
+}
+
+#[test]
+fn budget_at_representable_dates() {
+    let sources = BeancountSources::from(
+        r#"
+9999-12-01 custom "budget" Expenses:Groceries "monthly" 310.00 NZD
+9999-12-01 custom "budget" Expenses:Coffee "daily" 4.50 NZD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let budgets = Budgets::new(directives.iter()).unwrap();
+    let nzd = Currency::try_from("NZD").unwrap();
+
+    assert_eq!(
+        budgets
+            .budget(
+                &account("Expenses:Coffee"),
+                date("9999-12-30"),
+                date("9999-12-31")
+            )
+            .get(&nzd),
+        dec!(4.50)
+    );
+    assert_eq!(
+        budgets
+            .expand(
+                &account("Expenses:Groceries"),
+                date("9999-11-01"),
+                date("9999-12-31"),
+                Interval::Month
+            )
+            .into_iter()
+            .map(|(start, units)| format!("{} {}", start, units.get(&nzd)))
+            .collect::<Vec<_>>(),
+        vec!["9999-11-01 0", "9999-12-01 300.00"]
+    );
+}
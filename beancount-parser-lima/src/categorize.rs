@@ -0,0 +1,297 @@
+use regex::Regex;
+use rust_decimal::Decimal;
+#[cfg(feature = "toml")]
+use std::fmt::{self, Display, Formatter};
+use time::Date;
+
+/// A transaction as read by an importer, such as from a bank statement, before it is categorized.
+#[derive(Clone, Debug)]
+pub struct ImportedTransaction<'a> {
+    pub date: Date,
+    pub payee: Option<&'a str>,
+    pub narration: Option<&'a str>,
+    /// The account from which the transaction was imported, such as a bank account.
+    pub account: &'a str,
+    /// The amount posted to `account`, so negative for a payment.
+    pub amount: Decimal,
+    pub currency: &'a str,
+}
+
+/// What is known about an imported transaction, for an importer to pre-fill the balancing posting,
+/// and to replace the payee and add tags.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct Categorization {
+    account: Option<String>,
+    payee: Option<String>,
+    tags: Vec<String>,
+}
+
+impl Categorization {
+    /// The account for the balancing posting.
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    /// The payee to use in place of that imported.
+    pub fn payee(&self) -> Option<&str> {
+        self.payee.as_deref()
+    }
+
+    /// Tags to add, without the leading `#`.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+/// Categorization of imported transactions, of which [Rules] is the built-in implementation.
+pub trait Categorizer {
+    /// Categorize `transaction`, or `None` if nothing is known about it.
+    fn categorize(&self, transaction: &ImportedTransaction<'_>) -> Option<Categorization>;
+}
+
+/// Rule-based categorization, where the first rule which matches a transaction determines its categorization.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{Categorizer, ImportedTransaction, Rule, Rules};
+/// use regex::Regex;
+/// use rust_decimal_macros::dec;
+/// use time::{Date, Month};
+///
+/// let rules = Rules::new(vec![Rule::default()
+///     .payee_matching(Regex::new("(?i)^countdown").unwrap())
+///     .to_account("Expenses:Groceries")
+///     .rename_payee("Countdown")]);
+///
+/// let transaction = ImportedTransaction {
+///     date: Date::from_calendar_date(2024, Month::January, 5).unwrap(),
+///     payee: Some("COUNTDOWN NEWMARKET 123"),
+///     narration: None,
+///     account: "Assets:Bank:Current",
+///     amount: dec!(-42.50),
+///     currency: "NZD",
+/// };
+/// let categorization = rules.categorize(&transaction).unwrap();
+///
+/// assert_eq!(categorization.account(), Some("Expenses:Groceries"));
+/// assert_eq!(categorization.payee(), Some("Countdown"));
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+/// A categorization rule, see [Rules].
+///
+/// A rule matches a transaction if all of its conditions hold, so a rule with no conditions matches everything.
+#[derive(Clone, Default, Debug)]
+pub struct Rule {
+    payee: Option<Regex>,
+    min_amount: Option<Decimal>,
+    max_amount: Option<Decimal>,
+    from_account: Option<String>,
+    categorization: Categorization,
+}
+
+impl Rules {
+    /// Create rules, which are tried in the order given.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Rules { rules }
+    }
+
+    /// Read rules from TOML, as an array of tables named `rule`, whose keys are named after the methods of [Rule].
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::Rules;
+    ///
+    /// let rules = Rules::from_toml(r#"
+    /// [[rule]]
+    /// payee_matching = "(?i)^countdown"
+    /// from_account = "Assets:Bank:Current"
+    /// min_amount = -500
+    /// max_amount = "0.00"
+    /// to_account = "Expenses:Groceries"
+    /// rename_payee = "Countdown"
+    /// tags = ["food"]
+    /// "#).unwrap();
+    ///
+    /// assert_eq!(rules.len(), 1);
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> Result<Self, RulesError> {
+        use toml::{Table, Value};
+
+        fn string<'v>(value: &'v Value, key: &str) -> Result<&'v str, RulesError> {
+            value
+                .as_str()
+                .ok_or_else(|| RulesError::new(format!("{} must be a string", key)))
+        }
+
+        fn amount(value: &Value, key: &str) -> Result<Decimal, RulesError> {
+            let amount = match value {
+                Value::Integer(i) => Some(Decimal::from(*i)),
+                Value::String(s) => s.parse::<Decimal>().ok(),
+                _ => None,
+            };
+            amount.ok_or_else(|| {
+                RulesError::new(format!("{} must be an integer or a decimal string", key))
+            })
+        }
+
+        let table = s
+            .parse::<Table>()
+            .map_err(|e| RulesError::new(e.to_string()))?;
+        let rule_tables = match table.get("rule") {
+            Some(Value::Array(rule_tables)) => rule_tables.as_slice(),
+            Some(_) => return Err(RulesError::new("rule must be an array of tables")),
+            None => &[],
+        };
+
+        let mut rules = Vec::new();
+        for (i, rule_table) in rule_tables.iter().enumerate() {
+            let in_rule = |e: RulesError| RulesError::new(format!("rule {}: {}", i + 1, e));
+            let rule_table = rule_table
+                .as_table()
+                .ok_or_else(|| in_rule(RulesError::new("must be a table")))?;
+
+            let mut rule = Rule::default();
+            for (key, value) in rule_table {
+                rule = match key.as_str() {
+                    "payee_matching" => {
+                        let regex = Regex::new(string(value, key).map_err(in_rule)?)
+                            .map_err(|e| in_rule(RulesError::new(e.to_string())))?;
+                        rule.payee_matching(regex)
+                    }
+                    "min_amount" => rule.min_amount(amount(value, key).map_err(in_rule)?),
+                    "max_amount" => rule.max_amount(amount(value, key).map_err(in_rule)?),
+                    "from_account" => rule.from_account(string(value, key).map_err(in_rule)?),
+                    "to_account" => rule.to_account(string(value, key).map_err(in_rule)?),
+                    "rename_payee" => rule.rename_payee(string(value, key).map_err(in_rule)?),
+                    "tags" => {
+                        let tags = value.as_array().ok_or_else(|| {
+                            in_rule(RulesError::new("tags must be an array of strings"))
+                        })?;
+                        tags.iter().try_fold(rule, |rule, tag| {
+                            string(tag, "tag").map(|tag| rule.tag(tag)).map_err(in_rule)
+                        })?
+                    }
+                    _ => return Err(in_rule(RulesError::new(format!("unknown key {}", key)))),
+                };
+            }
+            rules.push(rule);
+        }
+
+        Ok(Rules { rules })
+    }
+
+    /// The number of rules.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Categorizer for Rules {
+    fn categorize(&self, transaction: &ImportedTransaction<'_>) -> Option<Categorization> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(transaction))
+            .map(|rule| rule.categorization.clone())
+    }
+}
+
+impl Rule {
+    /// Match transactions whose payee matches `regex`, or if there is no payee, whose narration does,
+    /// since some banks export the description as the narration.
+    pub fn payee_matching(mut self, regex: Regex) -> Self {
+        self.payee = Some(regex);
+        self
+    }
+
+    /// Match transactions whose amount is at least `min_amount`.
+    pub fn min_amount(mut self, min_amount: Decimal) -> Self {
+        self.min_amount = Some(min_amount);
+        self
+    }
+
+    /// Match transactions whose amount is at most `max_amount`.
+    pub fn max_amount(mut self, max_amount: Decimal) -> Self {
+        self.max_amount = Some(max_amount);
+        self
+    }
+
+    /// Match transactions imported from `account`.
+    pub fn from_account<S: Into<String>>(mut self, account: S) -> Self {
+        self.from_account = Some(account.into());
+        self
+    }
+
+    /// Categorize matching transactions as posting to `account`.
+    pub fn to_account<S: Into<String>>(mut self, account: S) -> Self {
+        self.categorization.account = Some(account.into());
+        self
+    }
+
+    /// Replace the payee of matching transactions with `payee`.
+    pub fn rename_payee<S: Into<String>>(mut self, payee: S) -> Self {
+        self.categorization.payee = Some(payee.into());
+        self
+    }
+
+    /// Tag matching transactions with `tag`, given without the leading `#`.
+    pub fn tag<S: Into<String>>(mut self, tag: S) -> Self {
+        self.categorization.tags.push(tag.into());
+        self
+    }
+
+    fn matches(&self, transaction: &ImportedTransaction<'_>) -> bool {
+        self.payee.as_ref().is_none_or(|regex| {
+            transaction
+                .payee
+                .or(transaction.narration)
+                .is_some_and(|payee| regex.is_match(payee))
+        }) && self
+            .min_amount
+            .is_none_or(|min_amount| transaction.amount >= min_amount)
+            && self
+                .max_amount
+                .is_none_or(|max_amount| transaction.amount <= max_amount)
+            && self
+                .from_account
+                .as_ref()
+                .is_none_or(|account| account == transaction.account)
+    }
+}
+
+/// The error returned when rules cannot be read, see [Rules::from_toml].
+#[cfg(feature = "toml")]
+#[derive(Debug)]
+pub struct RulesError {
+    message: String,
+}
+
+#[cfg(feature = "toml")]
+impl RulesError {
+    fn new<S: Into<String>>(message: S) -> Self {
+        RulesError {
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl Display for RulesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl std::error::Error for RulesError {}
+
+mod tests;
@@ -0,0 +1,112 @@
+#![cfg(test)]
+use super::*;
+use rust_decimal_macros::dec;
+use test_case::test_case;
+use time::Month;
+
+fn transaction<'a>(
+    payee: Option<&'a str>,
+    narration: &'a str,
+    amount: Decimal,
+) -> ImportedTransaction<'a> {
+    ImportedTransaction {
+        date: Date::from_calendar_date(2024, Month::March, 1).unwrap(),
+        payee,
+        narration: Some(narration),
+        account: "Assets:Bank:Current",
+        amount,
+        currency: "NZD",
+    }
+}
+
+fn rules() -> Rules {
+    Rules::new(vec![
+        Rule::default()
+            .payee_matching(Regex::new("(?i)countdown").unwrap())
+            .max_amount(dec!(0))
+            .to_account("Expenses:Groceries")
+            .rename_payee("Countdown")
+            .tag("food"),
+        Rule::default()
+            .min_amount(dec!(1000))
+            .from_account("Assets:Bank:Current")
+            .to_account("Income:Salary"),
+        Rule::default()
+            .from_account("Assets:Bank:Savings")
+            .to_account("Income:Interest"),
+    ])
+}
+
+#[test_case(Some("COUNTDOWN 123"), "EFTPOS", dec!(-20), Some("Expenses:Groceries"); "payee")]
+#[test_case(None, "Countdown Newmarket", dec!(-20), Some("Expenses:Groceries"); "narration without payee")]
+#[test_case(Some("Acme"), "Countdown", dec!(-20), None; "narration ignored with payee")]
+#[test_case(Some("COUNTDOWN 123"), "refund", dec!(20), None; "amount out of range")]
+#[test_case(Some("Employer"), "wages", dec!(2500), Some("Income:Salary"); "amount in range")]
+#[test_case(Some("Employer"), "wages", dec!(999.99), None; "no match")]
+fn categorize_account(
+    payee: Option<&str>,
+    narration: &str,
+    amount: Decimal,
+    expected: Option<&str>,
+) {
+    let categorization = rules().categorize(&transaction(payee, narration, amount));
+
+    assert_eq!(
+        categorization.as_ref().and_then(Categorization::account),
+        expected
+    );
+}
+
+#[test]
+fn categorize_first_match() {
+    let categorization = rules()
+        .categorize(&transaction(Some("COUNTDOWN 123"), "EFTPOS", dec!(-20)))
+        .unwrap();
+
+    assert_eq!(categorization.payee(), Some("Countdown"));
+    assert_eq!(categorization.tags(), &["food".to_string()]);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn rules_from_toml() {
+    let from_toml = Rules::from_toml(
+        r#"
+[[rule]]
+payee_matching = "(?i)countdown"
+max_amount = "0"
+to_account = "Expenses:Groceries"
+rename_payee = "Countdown"
+tags = ["food"]
+
+[[rule]]
+min_amount = 1000
+from_account = "Assets:Bank:Current"
+to_account = "Income:Salary"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(from_toml.len(), 2);
+    assert_eq!(
+        from_toml.categorize(&transaction(Some("COUNTDOWN 123"), "EFTPOS", dec!(-20))),
+        rules().categorize(&transaction(Some("COUNTDOWN 123"), "EFTPOS", dec!(-20)))
+    );
+}
+
+#[cfg(feature = "toml")]
+#[test_case("rule = 1", "rule must be an array of tables")]
+#[test_case("[[rule]]\ncolour = \"red\"", "rule 1: unknown key colour")]
+#[test_case(
+    "[[rule]]\nmin_amount = 1.5",
+    "rule 1: min_amount must be an integer or a decimal string"
+)]
+#[test_case(
+    "[[rule]]\n[[rule]]\npayee_matching = \"(\"",
+    "rule 2: regex parse error"
+)]
+fn rules_from_toml_error(s: &str, expected: &str) {
+    let error = Rules::from_toml(s).unwrap_err().to_string();
+
+    assert!(error.starts_with(expected), "{}", error);
+}
@@ -0,0 +1,170 @@
+use crate::{pipeline::Transform, types::*};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+/// A [Transform] for shared expenses, which mirrors a share of postings tagged for a partner into their clearing account,
+/// so that the clearing account tracks what the partner owes, and sums to zero once they have settled up.
+///
+/// A posting is mirrored if it has the partner's tag itself, or if its transaction has the tag
+/// and it posts to an `Expenses` or `Income` account.
+/// Mirroring appends to the transaction a posting of the share of the amount to the clearing account,
+/// and one of the negated share to the account of the original posting.
+/// The share is a fraction given by the metadata `share` of the posting or else its transaction,
+/// otherwise that given by [Clearing::share], by default one half.
+/// Shares are rounded to the decimal places of the amount.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{
+///     Account, AccountName, AccountType, BeancountParser, BeancountSources, Clearing, Pipeline, Tag,
+/// };
+///
+/// let sources = BeancountSources::from(r#"2024-02-01 * "Restaurant" "Dinner" #alice
+///   Expenses:Dining     80.00 NZD
+///   Liabilities:Card   -80.00 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let alice = Account::new(
+///     AccountType::Assets,
+///     ["Receivable", "Alice"].into_iter().map(|name| AccountName::try_from(name).unwrap()).collect(),
+/// );
+/// let transformed = Pipeline::new()
+///     .stage(Clearing::default().partner(Tag::try_from("alice").unwrap(), alice))
+///     .run(directives);
+///
+/// assert!(transformed.errors.is_empty());
+/// assert_eq!(
+///     transformed.directives[0].to_string(),
+///     r#"2024-02-01 * "Restaurant" "Dinner" #alice
+///   Expenses:Dining 80.00 NZD
+///   Liabilities:Card -80.00 NZD
+///   Assets:Receivable:Alice 40.00 NZD
+///   Expenses:Dining -40.00 NZD"#
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Clearing<'a> {
+    partners: Vec<(Tag<'a>, Account<'a>)>,
+    share: Decimal,
+}
+
+impl<'a> Default for Clearing<'a> {
+    fn default() -> Self {
+        Clearing {
+            partners: Vec::new(),
+            share: dec!(0.5),
+        }
+    }
+}
+
+impl<'a> Clearing<'a> {
+    /// Mirror postings tagged with `tag` into the clearing account `account`.
+    pub fn partner(mut self, tag: Tag<'a>, account: Account<'a>) -> Self {
+        self.partners.push((tag, account));
+        self
+    }
+
+    /// The share mirrored of postings which do not give one in their metadata, default one half.
+    pub fn share(mut self, share: Decimal) -> Self {
+        self.share = share;
+        self
+    }
+}
+
+impl<'a> Transform<'a> for Clearing<'a> {
+    fn transform(
+        &mut self,
+        mut directives: Vec<Spanned<Directive<'a>>>,
+        errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        for directive in directives.iter_mut() {
+            let mut invalid = Vec::new();
+
+            let Directive {
+                metadata, variant, ..
+            } = &mut directive.item;
+            if let DirectiveVariant::Transaction(transaction) = variant {
+                let mut mirrored = Vec::new();
+
+                for posting in transaction.postings.iter() {
+                    for (tag, clearing) in self.partners.iter() {
+                        let has_tag =
+                            |metadata: &Metadata<'a>| metadata.tags().any(|t| t.item() == tag);
+                        let is_tagged = has_tag(&posting.metadata)
+                            || (has_tag(metadata)
+                                && matches!(
+                                    posting.account.account_type(),
+                                    AccountType::Expenses | AccountType::Income
+                                ));
+                        if is_tagged {
+                            match self.mirror(metadata, posting, clearing) {
+                                Ok(mut postings) => mirrored.append(&mut postings),
+                                Err(e) => invalid.push(e),
+                            }
+                        }
+                    }
+                }
+
+                transaction.postings.append(&mut mirrored);
+            }
+
+            errors.extend(invalid.into_iter().map(|e| e.in_context(directive)));
+        }
+
+        directives
+    }
+}
+
+impl<'a> Clearing<'a> {
+    // the postings which mirror `posting` into `clearing`, spanned as the posting
+    fn mirror(
+        &self,
+        metadata: &Metadata<'a>,
+        posting: &Spanned<Posting<'a>>,
+        clearing: &Account<'a>,
+    ) -> Result<Vec<Spanned<Posting<'a>>>, Error> {
+        let span = *posting.span();
+        let (Some(amount), Some(currency)) = (&posting.amount, &posting.currency) else {
+            return Err(Error::new(
+                "invalid clearing",
+                "mirrored posting must have an amount",
+                span,
+            ));
+        };
+
+        let share = [&posting.metadata, metadata]
+            .into_iter()
+            .find_map(|metadata| metadata.get("share").map(|value| (metadata, value)));
+        let share = match share {
+            Some((metadata, value)) => metadata.get_decimal("share").ok_or_else(|| {
+                Error::new("invalid clearing", "share must be a number", *value.span())
+            })?,
+            None => self.share,
+        };
+
+        let number = amount.item().value();
+        let number = (number * share).round_dp(number.scale());
+        let leg = |account: &Account<'a>, number: Decimal| {
+            spanned(
+                Posting {
+                    flag: None,
+                    account: spanned(account.clone(), span),
+                    amount: Some(spanned(ExprValue::from(Expr::Value(number)), span)),
+                    currency: Some(*currency),
+                    cost_spec: None,
+                    price_annotation: None,
+                    metadata: Metadata::default(),
+                },
+                span,
+            )
+        };
+
+        Ok(vec![
+            leg(clearing, number),
+            leg(posting.account.item(), -number),
+        ])
+    }
+}
+
+mod tests;
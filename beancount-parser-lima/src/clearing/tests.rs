@@ -0,0 +1,87 @@
+#![cfg(test)]
+use super::*;
+use crate::{AccountName, BeancountParser, BeancountSources, Pipeline};
+
+const LEDGER: &str = r#"2024-02-01 * "Restaurant" "Dinner" #alice
+  share: 0.25
+  Expenses:Dining     80.00 NZD
+  Liabilities:Card   -80.00 NZD
+2024-02-02 * "Supermarket" "Groceries"
+  Expenses:Groceries  30.00 NZD
+    #bob
+  Expenses:Wine       20.00 NZD
+  Assets:Bank
+2024-02-03 * "Bob" "Settle up" #bob
+  Assets:Bank     15.00 NZD
+  Expenses:Groceries
+2024-02-04 * "Taxi" #alice
+  Expenses:Transport  21.00 NZD
+    share: "half"
+  Assets:Bank        -21.00 NZD
+"#;
+
+fn receivable(name: &str) -> Account<'_> {
+    Account::new(
+        AccountType::Assets,
+        ["Receivable", name]
+            .into_iter()
+            .map(|name| AccountName::try_from(name).unwrap())
+            .collect(),
+    )
+}
+
+#[test]
+fn clearing_mirrors_tagged_postings() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new()
+        .stage(
+            Clearing::default()
+                .partner(Tag::try_from("alice").unwrap(), receivable("Alice"))
+                .partner(Tag::try_from("bob").unwrap(), receivable("Bob")),
+        )
+        .run(directives);
+
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            "mirrored posting must have an amount",
+            "share must be a number"
+        ]
+    );
+    assert_eq!(
+        transformed
+            .directives
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            r#"2024-02-01 * "Restaurant" "Dinner" #alice
+  share: 0.25
+  Expenses:Dining 80.00 NZD
+  Liabilities:Card -80.00 NZD
+  Assets:Receivable:Alice 20.00 NZD
+  Expenses:Dining -20.00 NZD"#,
+            r#"2024-02-02 * "Supermarket" "Groceries"
+  Expenses:Groceries 30.00 NZD
+    #bob
+  Expenses:Wine 20.00 NZD
+  Assets:Bank
+  Assets:Receivable:Bob 15.00 NZD
+  Expenses:Groceries -15.00 NZD"#,
+            r#"2024-02-03 * "Bob" "Settle up" #bob
+  Assets:Bank 15.00 NZD
+  Expenses:Groceries"#,
+            r#"2024-02-04 * "Taxi" #alice
+  Expenses:Transport 21.00 NZD
+    share: "half"
+  Assets:Bank -21.00 NZD"#,
+        ]
+    );
+}
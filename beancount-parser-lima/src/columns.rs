@@ -0,0 +1,324 @@
+use crate::types::*;
+#[cfg(feature = "arrow")]
+use arrow_array::{Array, ArrayRef, Date32Array, Decimal128Array, RecordBatch, StringArray};
+#[cfg(feature = "arrow")]
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use rust_decimal::Decimal;
+#[cfg(feature = "arrow")]
+use std::sync::Arc;
+use time::Date;
+
+/// Postings flattened into columns, one row per posting, for loading directly into columnar data tools,
+/// such as Arrow record batches or data frames, without an intermediate CSV step.
+///
+/// Each posting carries the date, payee, and narration of its transaction, and its own flag if it has one,
+/// otherwise that of the transaction.  Rows are in the order of the directives, and then of the postings.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, PostingColumns};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 * "Countdown" "groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let postings = PostingColumns::new(directives.iter());
+///
+/// assert_eq!(postings.len(), 2);
+/// assert_eq!(postings.payees(), &[Some("Countdown"), Some("Countdown")]);
+/// assert_eq!(postings.numbers()[0].map(|n| n.to_string()), Some("-42.50".to_string()));
+/// assert_eq!(postings.numbers()[1], None);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct PostingColumns<'a> {
+    dates: Vec<Date>,
+    flags: Vec<Flag>,
+    payees: Vec<Option<&'a str>>,
+    narrations: Vec<Option<&'a str>>,
+    accounts: Vec<&'a Account<'a>>,
+    numbers: Vec<Option<Decimal>>,
+    currencies: Vec<Option<Currency<'a>>>,
+}
+
+impl<'a> PostingColumns<'a> {
+    /// Flatten the postings of all transactions, ignoring other directives.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut columns = PostingColumns::default();
+
+        for directive in directives {
+            if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+                for posting in transaction.postings() {
+                    columns.dates.push(*directive.date().item());
+                    columns
+                        .flags
+                        .push(*posting.flag().unwrap_or_else(|| transaction.flag()).item());
+                    columns
+                        .payees
+                        .push(transaction.payee().map(|payee| *payee.item()));
+                    columns
+                        .narrations
+                        .push(transaction.narration().map(|narration| *narration.item()));
+                    columns.accounts.push(posting.account().item());
+                    columns
+                        .numbers
+                        .push(posting.amount().map(|amount| amount.value()));
+                    columns
+                        .currencies
+                        .push(posting.currency().map(|currency| *currency.item()));
+                }
+            }
+        }
+
+        columns
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.dates.len()
+    }
+
+    /// Whether there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty()
+    }
+
+    /// The date of each posting's transaction.
+    pub fn dates(&self) -> &[Date] {
+        &self.dates
+    }
+
+    /// The flag of each posting, or if none, of its transaction.
+    pub fn flags(&self) -> &[Flag] {
+        &self.flags
+    }
+
+    /// The payee of each posting's transaction, if any.
+    pub fn payees(&self) -> &[Option<&'a str>] {
+        &self.payees
+    }
+
+    /// The narration of each posting's transaction, if any.
+    pub fn narrations(&self) -> &[Option<&'a str>] {
+        &self.narrations
+    }
+
+    /// The account of each posting.
+    pub fn accounts(&self) -> &[&'a Account<'a>] {
+        &self.accounts
+    }
+
+    /// The number of units of each posting, or none where this is to be inferred.
+    pub fn numbers(&self) -> &[Option<Decimal>] {
+        &self.numbers
+    }
+
+    /// The currency of each posting, or none where this is to be inferred.
+    pub fn currencies(&self) -> &[Option<Currency<'a>>] {
+        &self.currencies
+    }
+
+    /// Convert to an Arrow record batch, with columns `date`, `flag`, `payee`, `narration`, `account`, `number`, and `currency`.
+    ///
+    /// Numbers are decimals of the largest scale of any of them, which fails only if a number is then too large for Arrow.
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        let numbers = decimal_array(self.numbers.iter().copied())?;
+        let schema = Schema::new(vec![
+            Field::new("date", DataType::Date32, false),
+            Field::new("flag", DataType::Utf8, false),
+            Field::new("payee", DataType::Utf8, true),
+            Field::new("narration", DataType::Utf8, true),
+            Field::new("account", DataType::Utf8, false),
+            Field::new("number", numbers.data_type().clone(), true),
+            Field::new("currency", DataType::Utf8, true),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(date_array(&self.dates)),
+            Arc::new(StringArray::from_iter_values(
+                self.flags.iter().map(|flag| flag.to_string()),
+            )),
+            Arc::new(StringArray::from(self.payees.clone())),
+            Arc::new(StringArray::from(self.narrations.clone())),
+            Arc::new(StringArray::from_iter_values(
+                self.accounts.iter().map(|account| account.to_string()),
+            )),
+            Arc::new(numbers),
+            Arc::new(StringArray::from_iter(self.currencies.iter().map(
+                |currency| {
+                    currency
+                        .as_ref()
+                        .map(|currency| currency.as_ref().to_string())
+                },
+            ))),
+        ];
+
+        RecordBatch::try_new(Arc::new(schema), columns)
+    }
+
+    /// Write as a Parquet file, with the columns of [PostingColumns::to_record_batch].
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W>(&self, w: W) -> Result<(), parquet::errors::ParquetError>
+    where
+        W: std::io::Write + Send,
+    {
+        write_parquet(self.to_record_batch()?, w)
+    }
+}
+
+/// Prices flattened into columns, one row per `price` directive, in the order of the directives.
+/// See [PostingColumns].
+#[derive(Clone, Default, Debug)]
+pub struct PriceColumns<'a> {
+    dates: Vec<Date>,
+    currencies: Vec<Currency<'a>>,
+    numbers: Vec<Decimal>,
+    quote_currencies: Vec<Currency<'a>>,
+}
+
+impl<'a> PriceColumns<'a> {
+    /// Flatten all `price` directives, ignoring other directives.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut columns = PriceColumns::default();
+
+        for directive in directives {
+            if let DirectiveVariant::Price(price) = directive.variant() {
+                columns.dates.push(*directive.date().item());
+                columns.currencies.push(*price.currency().item());
+                columns.numbers.push(price.amount().number().value());
+                columns
+                    .quote_currencies
+                    .push(*price.amount().currency().item());
+            }
+        }
+
+        columns
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.dates.len()
+    }
+
+    /// Whether there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty()
+    }
+
+    /// The date of each price.
+    pub fn dates(&self) -> &[Date] {
+        &self.dates
+    }
+
+    /// The currency being priced.
+    pub fn currencies(&self) -> &[Currency<'a>] {
+        &self.currencies
+    }
+
+    /// The price of one unit, in the quote currency.
+    pub fn numbers(&self) -> &[Decimal] {
+        &self.numbers
+    }
+
+    /// The currency in which each price is quoted.
+    pub fn quote_currencies(&self) -> &[Currency<'a>] {
+        &self.quote_currencies
+    }
+
+    /// Convert to an Arrow record batch, with columns `date`, `currency`, `number`, and `quote_currency`.
+    /// See [PostingColumns::to_record_batch].
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        let numbers = decimal_array(self.numbers.iter().copied().map(Some))?;
+        let schema = Schema::new(vec![
+            Field::new("date", DataType::Date32, false),
+            Field::new("currency", DataType::Utf8, false),
+            Field::new("number", numbers.data_type().clone(), false),
+            Field::new("quote_currency", DataType::Utf8, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(date_array(&self.dates)),
+            Arc::new(StringArray::from_iter_values(
+                self.currencies.iter().map(|currency| currency.as_ref()),
+            )),
+            Arc::new(numbers),
+            Arc::new(StringArray::from_iter_values(
+                self.quote_currencies
+                    .iter()
+                    .map(|currency| currency.as_ref()),
+            )),
+        ];
+
+        RecordBatch::try_new(Arc::new(schema), columns)
+    }
+
+    /// Write as a Parquet file, with the columns of [PriceColumns::to_record_batch].
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W>(&self, w: W) -> Result<(), parquet::errors::ParquetError>
+    where
+        W: std::io::Write + Send,
+    {
+        write_parquet(self.to_record_batch()?, w)
+    }
+}
+
+#[cfg(feature = "arrow")]
+fn date_array(dates: &[Date]) -> Date32Array {
+    let epoch = time::OffsetDateTime::UNIX_EPOCH.date().to_julian_day();
+    Date32Array::from_iter_values(dates.iter().map(|date| date.to_julian_day() - epoch))
+}
+
+// decimals all of the largest scale of any of them, with the maximum precision
+#[cfg(feature = "arrow")]
+fn decimal_array<I>(numbers: I) -> Result<Decimal128Array, ArrowError>
+where
+    I: Iterator<Item = Option<Decimal>> + Clone,
+{
+    const PRECISION: u8 = arrow_schema::DECIMAL128_MAX_PRECISION;
+
+    let scale = numbers
+        .clone()
+        .flatten()
+        .map(|n| n.scale())
+        .max()
+        .unwrap_or(0);
+    let max = 10i128.pow(PRECISION as u32);
+    let values = numbers
+        .map(|n| {
+            n.map(|n| {
+                n.mantissa()
+                    .checked_mul(10i128.pow(scale - n.scale()))
+                    .filter(|value| value.abs() < max)
+                    .ok_or_else(|| {
+                        ArrowError::InvalidArgumentError(format!(
+                            "{} too large for decimal with scale {}",
+                            n, scale
+                        ))
+                    })
+            })
+            .transpose()
+        })
+        .collect::<Result<Decimal128Array, _>>()?;
+
+    values.with_precision_and_scale(PRECISION, scale as i8)
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet<W>(batch: RecordBatch, w: W) -> Result<(), parquet::errors::ParquetError>
+where
+    W: std::io::Write + Send,
+{
+    let mut writer = parquet::arrow::ArrowWriter::try_new(w, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+mod tests;
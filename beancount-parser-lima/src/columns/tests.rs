@@ -0,0 +1,239 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Groceries
+
+2024-01-02 price HOOL 100 USD
+
+2024-01-03 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  ! Expenses:Groceries
+
+2024-01-04 ! "rent"
+  Assets:Bank  -500 NZD
+  Expenses:Rent  500 NZD
+
+2024-01-05 price NZD 0.60 USD
+"#;
+
+#[test]
+fn posting_columns() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let postings = PostingColumns::new(directives.iter());
+
+    assert_eq!(postings.len(), 4);
+    assert_eq!(
+        postings
+            .dates()
+            .iter()
+            .map(|date| date.to_string())
+            .collect::<Vec<_>>(),
+        vec!["2024-01-03", "2024-01-03", "2024-01-04", "2024-01-04"]
+    );
+    assert_eq!(
+        postings.flags(),
+        &[
+            Flag::Asterisk,
+            Flag::Exclamation,
+            Flag::Exclamation,
+            Flag::Exclamation
+        ]
+    );
+    assert_eq!(
+        postings.payees(),
+        &[Some("Countdown"), Some("Countdown"), None, None]
+    );
+    assert_eq!(
+        postings.narrations(),
+        &[
+            Some("groceries"),
+            Some("groceries"),
+            Some("rent"),
+            Some("rent")
+        ]
+    );
+    assert_eq!(
+        postings
+            .accounts()
+            .iter()
+            .map(|account| account.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "Assets:Bank",
+            "Expenses:Groceries",
+            "Assets:Bank",
+            "Expenses:Rent"
+        ]
+    );
+    assert_eq!(
+        postings.numbers(),
+        &[Some(dec!(-42.50)), None, Some(dec!(-500)), Some(dec!(500))]
+    );
+    assert_eq!(
+        postings
+            .currencies()
+            .iter()
+            .map(|currency| currency.map(|currency| currency.to_string()))
+            .collect::<Vec<_>>(),
+        vec![
+            Some("NZD".to_string()),
+            None,
+            Some("NZD".to_string()),
+            Some("NZD".to_string())
+        ]
+    );
+}
+
+#[test]
+fn price_columns() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let prices = PriceColumns::new(directives.iter());
+
+    assert_eq!(prices.len(), 2);
+    assert_eq!(
+        prices
+            .currencies()
+            .iter()
+            .zip(prices.quote_currencies())
+            .map(|(currency, quote)| format!("{}/{}", currency, quote))
+            .collect::<Vec<_>>(),
+        vec!["HOOL/USD", "NZD/USD"]
+    );
+    assert_eq!(prices.numbers(), &[dec!(100), dec!(0.60)]);
+}
+
+#[test]
+fn no_rows() {
+    let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n");
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    assert!(PostingColumns::new(directives.iter()).is_empty());
+    assert!(PriceColumns::new(directives.iter()).is_empty());
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn posting_record_batch() {
+    use arrow_array::{Array, Date32Array, Decimal128Array, StringArray};
+
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let batch = PostingColumns::new(directives.iter())
+        .to_record_batch()
+        .unwrap();
+
+    assert_eq!(batch.num_rows(), 4);
+    assert_eq!(
+        batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "date",
+            "flag",
+            "payee",
+            "narration",
+            "account",
+            "number",
+            "currency"
+        ]
+    );
+
+    let dates = batch
+        .column_by_name("date")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Date32Array>()
+        .unwrap();
+    assert_eq!(dates.value_as_date(0).unwrap().to_string(), "2024-01-03");
+
+    let flags = batch
+        .column_by_name("flag")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(flags.value(1), "!");
+
+    let payees = batch
+        .column_by_name("payee")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(payees.value(0), "Countdown");
+    assert!(payees.is_null(2));
+
+    let numbers = batch
+        .column_by_name("number")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Decimal128Array>()
+        .unwrap();
+    assert_eq!(
+        (0..numbers.len())
+            .map(|i| numbers.is_valid(i).then(|| numbers.value_as_string(i)))
+            .collect::<Vec<_>>(),
+        vec![
+            Some("-42.50".to_string()),
+            None,
+            Some("-500.00".to_string()),
+            Some("500.00".to_string())
+        ]
+    );
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn price_record_batch() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let batch = PriceColumns::new(directives.iter())
+        .to_record_batch()
+        .unwrap();
+
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.num_columns(), 4);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn posting_parquet_round_trip() {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let postings = PostingColumns::new(directives.iter());
+
+    let path = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-postings-{}.parquet",
+        std::process::id()
+    ));
+    postings
+        .write_parquet(std::fs::File::create(&path).unwrap())
+        .unwrap();
+
+    let batches = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+        .unwrap()
+        .build()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(batches, vec![postings.to_record_batch().unwrap()]);
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,695 @@
+use crate::{
+    lexer::{Token, SKIPPED_LINE_LEADERS},
+    path_dir,
+    paths::expand_path,
+    types::*,
+};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    io,
+    path::{Path, PathBuf},
+};
+use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Grammar compatibility mode.
+///
+/// The default mode is this parser's own reading of the Beancount grammar.
+/// Other modes adjust edge-case behaviour to match a reference implementation,
+/// including its bugs.  See the README for the list of divergences.
+#[derive(
+    EnumString, EnumIter, IntoStaticStr, PartialEq, Eq, Default, Clone, Copy, Display, Debug,
+)]
+pub enum CompatMode {
+    #[default]
+    Lima,
+    PythonV2,
+}
+
+impl AsRef<str> for CompatMode {
+    fn as_ref(&self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Beancount syntax version targeted by a ledger.
+///
+/// Constructs which are invalid in the selected version are reported as errors.
+/// Beancount v2 differs from v3 in rejecting currencies beginning with `/`,
+/// and accepting only a limited set of flag letters.
+#[derive(
+    EnumString, EnumIter, IntoStaticStr, PartialEq, Eq, Default, Clone, Copy, Display, Debug,
+)]
+pub enum SyntaxVersion {
+    V2,
+    #[default]
+    V3,
+}
+
+impl AsRef<str> for SyntaxVersion {
+    fn as_ref(&self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Configuration for [BeancountParser](crate::BeancountParser), as distinct from [Options](crate::Options),
+/// which are read from the Beancount sources themselves.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, CompatMode, ParserConfig};
+///
+/// let sources = BeancountSources::from("2024-01-01 open Assets:Bank GBP\n");
+/// let config = ParserConfig::default().compat_mode(CompatMode::PythonV2);
+/// let parser = BeancountParser::with_config(&sources, config);
+///
+/// assert!(parser.parse().is_ok());
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct ParserConfig {
+    pub(crate) compat_mode: CompatMode,
+    pub(crate) syntax_version: Option<SyntaxVersion>,
+    pub(crate) detect_syntax_version: bool,
+    pub(crate) resource_limits: ResourceLimits,
+    pub(crate) normalization: Normalization,
+    pub(crate) max_errors: Option<usize>,
+    pub(crate) collapse_repeated_errors: bool,
+    pub(crate) reported_skipped_lines: Vec<char>,
+    pub(crate) threads: usize,
+    #[cfg(feature = "rayon")]
+    pub(crate) thread_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+    pub(crate) legacy_syntax: bool,
+    // set from the option `allow_pipe_separator`, which must be known before parsing
+    pub(crate) allow_pipe_separator: bool,
+    pub(crate) lenient_numbers: bool,
+    pub(crate) balance_extensions: bool,
+}
+
+impl ParserConfig {
+    /// Select the grammar compatibility mode.
+    pub fn compat_mode(mut self, compat_mode: CompatMode) -> Self {
+        self.compat_mode = compat_mode;
+        self
+    }
+
+    /// Select the syntax version explicitly, rather than defaulting to v3, or detecting it from the ledger.
+    pub fn syntax_version(mut self, syntax_version: SyntaxVersion) -> Self {
+        self.syntax_version = Some(syntax_version);
+        self
+    }
+
+    /// Where no syntax version is selected, detect v2 from the ledger's use of any option which Beancount v3
+    /// no longer accepts, such as `allow_pipe_separator`, rather than defaulting to v3.
+    ///
+    /// This is off by default, since a single legacy option such as `plugin` would change the grammar for the whole ledger.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig, SyntaxVersion};
+    ///
+    /// let sources = BeancountSources::from("option \"allow_pipe_separator\" \"TRUE\"\n");
+    /// let config = ParserConfig::default().detect_syntax_version(true);
+    /// let parser = BeancountParser::with_config(&sources, config);
+    ///
+    /// assert_eq!(parser.syntax_version(), SyntaxVersion::V2);
+    /// ```
+    pub fn detect_syntax_version(mut self, detect_syntax_version: bool) -> Self {
+        self.detect_syntax_version = detect_syntax_version;
+        self
+    }
+
+    /// Limit the resources consumed by parsing.
+    pub fn resource_limits(mut self, resource_limits: ResourceLimits) -> Self {
+        self.resource_limits = resource_limits;
+        self
+    }
+
+    /// Normalize payee and narration.
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Limit the number of errors reported, with any further errors being replaced by a single error giving their number.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = Some(max_errors);
+        self
+    }
+
+    /// Collapse repeated errors, such as the same bad account used on many lines, into the first of them,
+    /// with the message giving the number of occurrences.
+    ///
+    /// Errors are considered repeats if they have the same reason in the same kind of directive, wherever they occur.
+    pub fn collapse_repeated_errors(mut self, collapse_repeated_errors: bool) -> Self {
+        self.collapse_repeated_errors = collapse_repeated_errors;
+        self
+    }
+
+    /// Report as warnings those skipped lines which begin with any of `leaders`, rather than skipping them silently.
+    ///
+    /// As in Beancount, lines beginning with any of `*!&#?%:` are not part of any directive, and are skipped,
+    /// the most common being org-mode headlines, beginning with `*`.
+    /// Other characters in `leaders` have no effect.
+    /// All skipped lines are available from [BeancountParser::skipped_lines](crate::BeancountParser::skipped_lines).
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig};
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n* Banking\n# draft\n");
+    /// let config = ParserConfig::default().report_skipped_lines("#".chars());
+    /// let parser = BeancountParser::with_config(&sources, config);
+    ///
+    /// assert_eq!(parser.skipped_lines().len(), 2);
+    /// assert_eq!(parser.parse().unwrap().warnings.len(), 1);
+    /// ```
+    pub fn report_skipped_lines<I>(mut self, leaders: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.reported_skipped_lines = leaders
+            .into_iter()
+            .filter(|c| SKIPPED_LINE_LEADERS.contains(c))
+            .collect();
+        self
+    }
+
+    /// Lex the source files on up to `threads` threads, since each is lexed independently of the others.
+    ///
+    /// By default, and if `threads` is 0 or 1, all lexing is done on the calling thread,
+    /// which suits embedders needing to constrain CPU use.  Parsing proper is always done on the calling thread.
+    /// The result is the same regardless of the number of threads.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig};
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open Assets:Bank GBP\n");
+    /// let threads = std::thread::available_parallelism().map_or(1, usize::from);
+    /// let parser = BeancountParser::with_config(&sources, ParserConfig::default().threads(threads));
+    ///
+    /// assert!(parser.parse().is_ok());
+    /// ```
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Lex the source files on the given rayon thread pool, such as one shared with the rest of an application,
+    /// in place of the threads given by [ParserConfig::threads].
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig};
+    /// use std::sync::Arc;
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open Assets:Bank GBP\n");
+    /// let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+    /// let parser = BeancountParser::with_config(&sources, ParserConfig::default().thread_pool(pool));
+    ///
+    /// assert!(parser.parse().is_ok());
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn thread_pool(mut self, thread_pool: std::sync::Arc<rayon::ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Accept the legacy syntax of Beancount v1, as found in old ledgers, with a deprecation warning for each use,
+    /// including a fix to the modern syntax, rather than rejecting it.
+    ///
+    /// The legacy constructs are a pipe separating payee and narration, as in `"Payee" | "Narration"`,
+    /// and the `check` directive, now `balance`.
+    /// The pipe alone is also accepted, with the same warning, where the ledger sets the option `allow_pipe_separator`.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig};
+    ///
+    /// let sources = BeancountSources::from(r#"2014-01-01 open Assets:Bank
+    /// 2014-01-02 * "Payee" | "Narration"
+    ///   Assets:Bank  0 GBP
+    /// "#);
+    /// let parser = BeancountParser::with_config(&sources, ParserConfig::default().legacy_syntax(true));
+    /// let warnings = parser.parse().unwrap().warnings;
+    ///
+    /// assert_eq!(warnings[0].fixes()[0].title(), "replace pipe separator with space");
+    /// ```
+    pub fn legacy_syntax(mut self, legacy_syntax: bool) -> Self {
+        self.legacy_syntax = legacy_syntax;
+        self
+    }
+
+    /// Accept numbers in scientific notation or with underscore separators, as in `1e6` and `1_000_000`,
+    /// as found in some generated ledgers, with a warning for each, including a fix to the plain decimal,
+    /// rather than rejecting them.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig};
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 price AAPL 1.5e2 USD\n");
+    /// let parser = BeancountParser::with_config(&sources, ParserConfig::default().lenient_numbers(true));
+    /// let warnings = parser.parse().unwrap().warnings;
+    ///
+    /// assert_eq!(warnings[0].fixes()[0].title(), "replace with 150");
+    /// ```
+    pub fn lenient_numbers(mut self, lenient_numbers: bool) -> Self {
+        self.lenient_numbers = lenient_numbers;
+        self
+    }
+
+    /// Accept the extended balance assertions used by some plugins, restricted to a lot, as in
+    /// `balance Assets:Broker 10 AAPL {150.00 USD}`, or valued at a price, as in `balance Assets:Broker 10 AAPL @ 160.00 USD`,
+    /// rather than rejecting them.  See [Balance::cost_spec](crate::Balance::cost_spec) and
+    /// [Balance::price_annotation](crate::Balance::price_annotation).
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveVariant, ParserConfig};
+    ///
+    /// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Broker
+    /// 2024-01-02 balance Assets:Broker 10 AAPL {150.00 USD}
+    /// "#);
+    /// let parser = BeancountParser::with_config(&sources, ParserConfig::default().balance_extensions(true));
+    /// let directives = parser.parse().unwrap().directives;
+    /// let DirectiveVariant::Balance(balance) = directives[1].variant() else { unreachable!() };
+    ///
+    /// assert_eq!(balance.cost_spec().unwrap().to_string(), "{150.00 USD}");
+    /// ```
+    pub fn balance_extensions(mut self, balance_extensions: bool) -> Self {
+        self.balance_extensions = balance_extensions;
+        self
+    }
+
+    /// Whether the skipped line `line` is to be reported.
+    pub(crate) fn is_reported_skipped_line(&self, line: &str) -> bool {
+        line.starts_with(self.reported_skipped_lines.as_slice())
+    }
+
+    /// Collapse and limit errors as configured, collapsing first so that the limit counts distinct errors.
+    pub(crate) fn reported_errors(&self, errors: Vec<Error>) -> Vec<Error> {
+        let mut errors = if self.collapse_repeated_errors {
+            collapse_repeated(errors)
+        } else {
+            errors
+        };
+
+        if let Some(max_errors) = self.max_errors.filter(|max| errors.len() > *max) {
+            let omitted = errors.split_off(max_errors);
+            errors.push(Error::new(
+                "too many errors",
+                format!("{} further errors not reported", omitted.len()),
+                omitted[0].span,
+            ));
+        }
+
+        errors
+    }
+
+    /// The syntax version, either explicitly selected, implied by the compatibility mode, or else the default.
+    pub(crate) fn selected_syntax_version(&self) -> SyntaxVersion {
+        self.syntax_version.unwrap_or(match self.compat_mode {
+            CompatMode::Lima => SyntaxVersion::default(),
+            CompatMode::PythonV2 => SyntaxVersion::V2,
+        })
+    }
+}
+
+/// Limits on the resources consumed by parsing, as a defence against adversarial input.
+///
+/// By default there are no limits.  Files which exceed a limit are not parsed,
+/// with the violation being reported as an error.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, ParserConfig, ResourceLimits};
+///
+/// let sources = BeancountSources::from("2024-01-01 open Assets:Bank GBP\n");
+/// let limits = ResourceLimits::default().max_file_size(16);
+/// let parser = BeancountParser::with_config(&sources, ParserConfig::default().resource_limits(limits));
+///
+/// assert!(parser.parse().is_err());
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct ResourceLimits {
+    pub(crate) max_file_size: Option<usize>,
+    pub(crate) max_include_depth: Option<usize>,
+    pub(crate) max_directives: Option<usize>,
+    pub(crate) max_expression_depth: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// Limit the size in bytes of each source file.  Note that files are still read before being rejected.
+    pub fn max_file_size(mut self, max_file_size: usize) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self
+    }
+
+    /// Limit the depth of nested includes, where the root file has depth zero.
+    pub fn max_include_depth(mut self, max_include_depth: usize) -> Self {
+        self.max_include_depth = Some(max_include_depth);
+        self
+    }
+
+    /// Limit the total number of directives over all files.
+    pub fn max_directives(mut self, max_directives: usize) -> Self {
+        self.max_directives = Some(max_directives);
+        self
+    }
+
+    /// Limit the nesting of parentheses in arithmetic expressions.
+    pub fn max_expression_depth(mut self, max_expression_depth: usize) -> Self {
+        self.max_expression_depth = Some(max_expression_depth);
+        self
+    }
+
+    /// Check the limits which apply to a whole source file.
+    pub(crate) fn check_source(
+        &self,
+        source_id: SourceId,
+        size: usize,
+        include_depth: usize,
+    ) -> Result<(), Error> {
+        let span = chumsky::span::Span::new(source_id, 0..0);
+
+        if let Some(max_file_size) = self.max_file_size.filter(|max| size > *max) {
+            Err(limit_error(
+                format!("file size {} exceeds limit of {}", size, max_file_size),
+                span,
+            ))
+        } else if let Some(max_include_depth) =
+            self.max_include_depth.filter(|max| include_depth > *max)
+        {
+            Err(limit_error(
+                format!(
+                    "include depth {} exceeds limit of {}",
+                    include_depth, max_include_depth
+                ),
+                span,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check expression nesting ahead of parsing, since the recursive descent would otherwise risk overflowing the stack.
+    pub(crate) fn check_expression_depth(&self, tokens: &[(Token<'_>, Span)]) -> Result<(), Error> {
+        if let Some(max_expression_depth) = self.max_expression_depth {
+            let mut depth = 0usize;
+
+            for (token, span) in tokens {
+                match token {
+                    Token::Lparen => {
+                        depth += 1;
+                        if depth > max_expression_depth {
+                            return Err(limit_error(
+                                format!(
+                                    "expression depth exceeds limit of {}",
+                                    max_expression_depth
+                                ),
+                                *span,
+                            ));
+                        }
+                    }
+                    Token::Rparen => depth = depth.saturating_sub(1),
+                    Token::Eol => depth = 0,
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of further directives which may be parsed, given the number parsed so far, if limited.
+    pub(crate) fn remaining_directives(&self, n_directives: usize) -> Option<usize> {
+        self.max_directives
+            .map(|max| max.saturating_sub(n_directives))
+    }
+}
+
+fn collapse_repeated(errors: Vec<Error>) -> Vec<Error> {
+    let mut collapsed = Vec::<(Error, usize)>::new();
+    let mut first_occurrence = HashMap::<_, usize>::new();
+
+    for error in errors {
+        // messages of parse errors include locations, so only the reason and kind of context are compared
+        let contexts = error
+            .contexts
+            .iter()
+            .map(|(label, _)| label.clone())
+            .collect::<Vec<_>>();
+        match first_occurrence.entry((error.reason.clone(), contexts)) {
+            Entry::Occupied(i) => collapsed[*i.get()].1 += 1,
+            Entry::Vacant(v) => {
+                v.insert(collapsed.len());
+                collapsed.push((error, 1));
+            }
+        }
+    }
+
+    collapsed
+        .into_iter()
+        .map(|(mut error, count)| {
+            if count > 1 {
+                error.message =
+                    format!("{} ({} occurrences)", error.message, count).into_boxed_str();
+            }
+            error
+        })
+        .collect()
+}
+
+/// Normalization of the payee and narration of transactions, so that formatting noise, such as from bank exports,
+/// doesn't confuse downstream deduplication.
+///
+/// By default nothing is normalized.  The spans of normalized strings are unchanged,
+/// so the original text is preserved, and available from [BeancountSources::raw_string](crate::BeancountSources::raw_string).
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveVariant, Normalization, ParserConfig};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 * " ACME   Corp " "Cafe\u0301""#);
+/// let normalization = Normalization::default().trim(true).collapse_spaces(true).unicode_nfc(true);
+/// let parser = BeancountParser::with_config(&sources, ParserConfig::default().normalization(normalization));
+/// let success = parser.parse().unwrap();
+///
+/// let DirectiveVariant::Transaction(transaction) = success.directives[0].variant() else {
+///     panic!("expected transaction");
+/// };
+///
+/// assert_eq!(*transaction.payee().unwrap().item(), "ACME Corp");
+/// assert_eq!(*transaction.narration().unwrap().item(), "Café");
+/// assert_eq!(sources.raw_string(transaction.payee().unwrap()), " ACME   Corp ");
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Normalization {
+    pub(crate) trim: bool,
+    pub(crate) collapse_spaces: bool,
+    pub(crate) unicode_nfc: bool,
+}
+
+impl Normalization {
+    /// Remove leading and trailing whitespace.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Replace each run of whitespace, including tabs and newlines, with a single space.
+    pub fn collapse_spaces(mut self, collapse_spaces: bool) -> Self {
+        self.collapse_spaces = collapse_spaces;
+        self
+    }
+
+    /// Convert to Unicode Normalization Form C, so that accented characters are composed.
+    pub fn unicode_nfc(mut self, unicode_nfc: bool) -> Self {
+        self.unicode_nfc = unicode_nfc;
+        self
+    }
+
+    /// Normalize the payee and narration strings of transaction header lines, which is done on the tokens
+    /// ahead of parsing, since the parsed strings are borrowed from the tokens.
+    pub(crate) fn normalize_transaction_strings(&self, tokens: &mut [(Token<'_>, Span)]) {
+        if !(self.trim || self.collapse_spaces || self.unicode_nfc) {
+            return;
+        }
+
+        for i in 0..tokens.len() {
+            let is_header_start = (i == 0 || tokens[i - 1].0 == Token::Eol)
+                && matches!(tokens[i].0, Token::Date(_))
+                && tokens.get(i + 1).is_some_and(|(tok, _)| {
+                    matches!(
+                        tok,
+                        Token::Txn | Token::DedicatedFlag(_) | Token::Asterisk | Token::Hash
+                    )
+                });
+
+            if is_header_start {
+                for (tok, _) in tokens[i + 2..].iter_mut().take(2) {
+                    match tok {
+                        Token::StringLiteral(s) => {
+                            if let Cow::Owned(normalized) = self.normalize(s) {
+                                s.replace(normalized);
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+    }
+
+    fn normalize<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        let mut normalized = Cow::Borrowed(s);
+
+        if self.unicode_nfc && !is_nfc(&normalized) {
+            normalized = Cow::Owned(normalized.nfc().collect());
+        }
+
+        if self.collapse_spaces
+            && (normalized.contains("  ")
+                || normalized.contains(|c: char| c.is_whitespace() && c != ' '))
+        {
+            let mut collapsed = String::with_capacity(normalized.len());
+            let mut previous_was_whitespace = false;
+            for c in normalized.chars() {
+                if !c.is_whitespace() {
+                    collapsed.push(c);
+                } else if !previous_was_whitespace {
+                    collapsed.push(' ');
+                }
+                previous_was_whitespace = c.is_whitespace();
+            }
+            normalized = Cow::Owned(collapsed);
+        }
+
+        if self.trim && normalized.trim().len() != normalized.len() {
+            normalized = Cow::Owned(normalized.trim().to_string());
+        }
+
+        normalized
+    }
+}
+
+/// How relative include paths are resolved when reading sources, see [BeancountSources::with_include_resolution](crate::BeancountSources::with_include_resolution).
+///
+/// By default, as in Beancount, an include path is relative to the including file.
+/// Alternatively it may be relative to the root file, and in either case a search path of
+/// further folders may be given, which are tried in turn if the file is not found.
+/// An include which is not found anywhere is reported with the list of paths searched.
+///
+/// Expansion of `~` and environment variables in include paths is opt-in, see [IncludeResolution::expand_paths].
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::IncludeResolution;
+///
+/// let resolution = IncludeResolution::default()
+///     .relative_to_root(true)
+///     .search_path(["/usr/share/beancount/common"]);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct IncludeResolution {
+    pub(crate) relative_to_root: bool,
+    pub(crate) search_path: Vec<PathBuf>,
+    pub(crate) expand_paths: bool,
+}
+
+impl IncludeResolution {
+    /// Resolve relative include paths against the folder of the root file, rather than that of the including file.
+    pub fn relative_to_root(mut self, relative_to_root: bool) -> Self {
+        self.relative_to_root = relative_to_root;
+        self
+    }
+
+    /// Folders to search in turn for an include which is not found relative to the base folder.
+    pub fn search_path<I, P>(mut self, folders: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.search_path = folders.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Expand a leading `~` to the home directory, and `${VAR}` to the value of the environment variable `VAR`,
+    /// in include paths.  Anything which can't be expanded is left as written.
+    pub fn expand_paths(mut self, expand_paths: bool) -> Self {
+        self.expand_paths = expand_paths;
+        self
+    }
+
+    /// Resolve `included_path` from the file at `including_path`, returning the first candidate which exists,
+    /// or else the first candidate, along with all candidates in the order searched.
+    pub(crate) fn resolve(
+        &self,
+        root_path: Option<&Path>,
+        including_path: Option<&Path>,
+        included_path: &Path,
+    ) -> (PathBuf, Vec<PathBuf>) {
+        let included_path = if self.expand_paths {
+            expand_path(included_path)
+        } else {
+            Cow::Borrowed(included_path)
+        };
+        let included_path = included_path.as_ref();
+        let base = if self.relative_to_root {
+            root_path
+        } else {
+            including_path
+        };
+        let alongside = match base.and_then(path_dir) {
+            Some(dir) => dir.join(included_path),
+            None => included_path.to_path_buf(),
+        };
+
+        let mut candidates = vec![alongside];
+        if included_path.is_relative() {
+            for folder in self.search_path.iter() {
+                let candidate = folder.join(included_path);
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        let path = candidates
+            .iter()
+            .find(|candidate| candidate.exists())
+            .unwrap_or(&candidates[0])
+            .clone();
+        (path, candidates)
+    }
+
+    /// Replace an error reading a file which was not found with one listing where it was searched for.
+    pub(crate) fn not_found_error(e: io::Error, searched: &[PathBuf]) -> io::Error {
+        if e.kind() == io::ErrorKind::NotFound {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "not found, searched: {}",
+                    searched
+                        .iter()
+                        .map(|path| path.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )
+        } else {
+            e
+        }
+    }
+}
+
+pub(crate) fn limit_error<R>(reason: R, span: Span) -> Error
+where
+    R: Into<String>,
+{
+    Error::new("resource limit exceeded", reason, span)
+}
+
+mod tests;
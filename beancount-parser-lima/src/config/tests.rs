@@ -0,0 +1,623 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources, ParseError};
+use test_case::test_case;
+
+fn parse_with_limits(s: &str, limits: ResourceLimits) -> Result<(), Vec<String>> {
+    let sources = BeancountSources::from(s);
+    let parser =
+        BeancountParser::with_config(&sources, ParserConfig::default().resource_limits(limits));
+
+    parser
+        .parse()
+        .map(|_| ())
+        .map_err(|ParseError { errors, .. }| errors.iter().map(|e| e.reason.to_string()).collect())
+}
+
+#[test_case(ResourceLimits::default(), Ok(()))]
+#[test_case(ResourceLimits::default().max_directives(2), Ok(()))]
+#[test_case(ResourceLimits::default().max_directives(1), Err(vec!["directive count exceeds limit of 1".to_string()]))]
+#[test_case(ResourceLimits::default().max_expression_depth(3), Ok(()))]
+#[test_case(ResourceLimits::default().max_expression_depth(2), Err(vec!["expression depth exceeds limit of 2".to_string()]))]
+#[test_case(ResourceLimits::default().max_file_size(10), Err(vec!["file size 116 exceeds limit of 10".to_string()]))]
+#[test_case(ResourceLimits::default().max_include_depth(0), Ok(()))]
+fn resource_limits(limits: ResourceLimits, expected: Result<(), Vec<String>>) {
+    let s = r#"
+2024-01-01 open Assets:Bank GBP
+2024-01-02 txn "transfer"
+  Assets:Bank  (((1 + 2) * 3)) GBP
+  Assets:Bank  -9 GBP
+"#;
+
+    assert_eq!(parse_with_limits(s, limits), expected);
+}
+
+#[test_case(ParserConfig::default(), vec![
+    "found GBP expected something else",
+    "found GBP expected something else",
+    "found USD expected something else",
+    "found GBP expected something else",
+])]
+#[test_case(ParserConfig::default().collapse_repeated_errors(true), vec![
+    "found GBP expected something else (3 occurrences)",
+    "found USD expected something else",
+])]
+#[test_case(ParserConfig::default().max_errors(2), vec![
+    "found GBP expected something else",
+    "found GBP expected something else",
+    "2 further errors not reported",
+])]
+#[test_case(ParserConfig::default().collapse_repeated_errors(true).max_errors(1), vec![
+    "found GBP expected something else (3 occurrences)",
+    "1 further errors not reported",
+])]
+fn reported_errors(config: ParserConfig, expected: Vec<&str>) {
+    let s = r#"
+2024-01-01 open Assets:Bank
+2024-01-02 balance Assets:Bank GBP
+2024-01-03 balance Assets:Bank GBP
+2024-01-04 balance Assets:Bank USD
+2024-01-05 balance Assets:Bank GBP
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::with_config(&sources, config);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    // the repeat count is in the message, and the number omitted in the reason
+    let reported = errors
+        .iter()
+        .map(|e| match e.message.split_once(" (") {
+            Some((_, count)) if count.ends_with("occurrences)") => {
+                format!("{} ({}", e.reason, count)
+            }
+            _ => e.reason.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(reported, expected);
+}
+
+#[test_case("", vec![])]
+#[test_case("*", vec!["* Banking"])]
+#[test_case("*#", vec!["#+STARTUP: overview", "* Banking"])]
+#[test_case("@", vec![])]
+fn reported_skipped_lines(leaders: &str, expected: Vec<&str>) {
+    let s = r#"; an org-mode ledger
+2024-01-01 open Assets:Bank
+#+STARTUP: overview
+
+* Banking
+2024-01-02 txn "deposit"
+  Assets:Bank  10 GBP
+  Equity:Opening
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::with_config(
+        &sources,
+        ParserConfig::default().report_skipped_lines(leaders.chars()),
+    );
+    let skipped = parser
+        .skipped_lines()
+        .into_iter()
+        .map(|span| &s[span.start..span.end])
+        .collect::<Vec<_>>();
+    let warned = parser
+        .parse()
+        .unwrap()
+        .warnings
+        .into_iter()
+        .map(|w| &s[w.span.start..w.span.end])
+        .collect::<Vec<_>>();
+
+    assert_eq!(skipped, vec!["#+STARTUP: overview", "* Banking"]);
+    assert_eq!(warned, expected);
+}
+
+#[test_case(Normalization::default(), " ACME \t Corp ", " ACME \t Corp ")]
+#[test_case(Normalization::default().trim(true), " ACME \t Corp ", "ACME \t Corp")]
+#[test_case(Normalization::default().collapse_spaces(true), " ACME \t Corp ", " ACME Corp ")]
+#[test_case(Normalization::default().collapse_spaces(true).trim(true), " ACME \t Corp ", "ACME Corp")]
+#[test_case(Normalization::default().unicode_nfc(true), "Cafe\u{301}", "Caf\u{e9}")]
+#[test_case(Normalization::default().trim(true), "ACME", "ACME")]
+fn normalize(normalization: Normalization, s: &str, expected: &str) {
+    assert_eq!(normalization.normalize(s), expected);
+}
+
+#[test]
+fn normalize_only_transaction_strings() {
+    let s = r#"2024-01-01 open Assets:Bank
+2024-01-02 note Assets:Bank " untouched "
+2024-01-03 txn " payee " " narration " #tag
+  Assets:Bank  10 GBP
+  Equity:Opening
+"#;
+    let sources = BeancountSources::from(s);
+    let config = ParserConfig::default().normalization(Normalization::default().trim(true));
+    let parser = BeancountParser::with_config(&sources, config);
+    let directives = parser.parse().unwrap().directives;
+
+    let DirectiveVariant::Note(note) = directives[1].variant() else {
+        panic!("expected note");
+    };
+    let DirectiveVariant::Transaction(transaction) = directives[2].variant() else {
+        panic!("expected transaction");
+    };
+    assert_eq!(*note.comment().item(), " untouched ");
+    assert_eq!(
+        transaction.payee().map(|payee| *payee.item()),
+        Some("payee")
+    );
+    assert_eq!(
+        transaction.narration().map(|narration| *narration.item()),
+        Some("narration")
+    );
+}
+
+const LEGACY: &str = r#"2014-01-01 open Assets:Bank
+2014-01-02 * "Payee" | "Narration"
+  Assets:Bank  0 GBP
+2014-01-03 check Assets:Bank 0 GBP
+"#;
+
+#[test]
+fn legacy_syntax_fixed() {
+    let sources = BeancountSources::from(LEGACY);
+    let parser =
+        BeancountParser::with_config(&sources, ParserConfig::default().legacy_syntax(true));
+    let success = parser.parse().unwrap();
+
+    let DirectiveVariant::Transaction(transaction) = success.directives[1].variant() else {
+        panic!("expected transaction");
+    };
+    assert_eq!(
+        transaction.payee().map(|payee| *payee.item()),
+        Some("Payee")
+    );
+    assert!(matches!(
+        success.directives[2].variant(),
+        DirectiveVariant::Balance(_)
+    ));
+
+    let reasons = success
+        .warnings
+        .iter()
+        .map(|w| w.reason.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        vec![
+            "pipe separator is deprecated",
+            "check directive is deprecated"
+        ]
+    );
+
+    let fixed = sources.apply_fixes(success.warnings.iter().flat_map(|w| w.fixes()));
+    assert_eq!(
+        fixed[0].1,
+        r#"2014-01-01 open Assets:Bank
+2014-01-02 * "Payee" "Narration"
+  Assets:Bank  0 GBP
+2014-01-03 balance Assets:Bank 0 GBP
+"#
+    );
+}
+
+#[test]
+fn legacy_syntax_rejected_by_default() {
+    let sources = BeancountSources::from(LEGACY);
+    let parser = BeancountParser::new(&sources);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    let reasons = errors
+        .iter()
+        .map(|e| e.reason.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        vec![
+            "pipe separator between payee and narration requires legacy syntax",
+            "check directive requires legacy syntax"
+        ]
+    );
+}
+
+#[test]
+fn pipe_separator_allowed_by_option() {
+    let sources = BeancountSources::from(format!(
+        "{}option \"allow_pipe_separator\" \"TRUE\"\n",
+        LEGACY.replace("check", "balance")
+    ));
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    assert!(success.options.allow_pipe_separator());
+    let reasons = success
+        .warnings
+        .iter()
+        .map(|w| w.reason.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(reasons, vec!["pipe separator is deprecated"]);
+}
+
+#[test]
+fn pipe_separator_disallowed_by_option() {
+    let sources = BeancountSources::from(format!(
+        "{}option \"allow_pipe_separator\" \"FALSE\"\n",
+        LEGACY.replace("check", "balance")
+    ));
+    let parser = BeancountParser::new(&sources);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    let reasons = errors
+        .iter()
+        .map(|e| e.reason.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        vec!["pipe separator between payee and narration requires legacy syntax"]
+    );
+}
+
+const LENIENT: &str = r#"2024-01-01 open Assets:Bank
+2024-01-02 txn "generated"
+  Assets:Bank  1_000_000 USD
+  Assets:Bank  -1e6 USD
+"#;
+
+#[test]
+fn lenient_numbers_fixed() {
+    let sources = BeancountSources::from(LENIENT);
+    let parser =
+        BeancountParser::with_config(&sources, ParserConfig::default().lenient_numbers(true));
+    let success = parser.parse().unwrap();
+
+    assert_eq!(success.warnings.len(), 2);
+    let fixed = sources.apply_fixes(success.warnings.iter().flat_map(|w| w.fixes()));
+    assert_eq!(
+        fixed[0].1,
+        r#"2024-01-01 open Assets:Bank
+2024-01-02 txn "generated"
+  Assets:Bank  1000000 USD
+  Assets:Bank  -1000000 USD
+"#
+    );
+}
+
+#[test]
+fn lenient_numbers_rejected_by_default() {
+    let sources = BeancountSources::from(LENIENT);
+    let parser = BeancountParser::new(&sources);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .all(|e| &*e.reason
+            == "scientific notation and underscore separators require lenient numbers"));
+}
+
+const BALANCE_EXTENSIONS: &str = r#"2024-01-01 open Assets:Broker
+2024-01-02 balance Assets:Broker 10 AAPL {150.00 USD, 2024-01-01}
+2024-01-02 balance Assets:Broker 10 AAPL @ 160.00 USD
+"#;
+
+#[test]
+fn balance_extensions_accepted() {
+    let sources = BeancountSources::from(BALANCE_EXTENSIONS);
+    let parser =
+        BeancountParser::with_config(&sources, ParserConfig::default().balance_extensions(true));
+    let directives = parser.parse().unwrap().directives;
+
+    let balances = directives
+        .iter()
+        .filter_map(|directive| match directive.variant() {
+            DirectiveVariant::Balance(balance) => Some(balance),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        balances[0].cost_spec().unwrap().to_string(),
+        "{150.00 USD, 2024-01-01}"
+    );
+    assert!(balances[0].price_annotation().is_none());
+    assert!(balances[1].cost_spec().is_none());
+    assert_eq!(
+        balances[1].price_annotation().unwrap().to_string(),
+        "160.00 USD"
+    );
+    assert_eq!(
+        directives[2].to_string(),
+        "2024-01-02 balance Assets:Broker 10 AAPL @ 160.00 USD"
+    );
+}
+
+#[test]
+fn balance_extensions_rejected_by_default() {
+    let sources = BeancountSources::from(BALANCE_EXTENSIONS);
+    let parser = BeancountParser::new(&sources);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors
+        .iter()
+        .all(|e| &*e.reason == "cost or price in balance assertion requires balance extensions"));
+}
+
+// a fresh ledger directory, with the given files
+fn ledger_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-config-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (path, content) in files {
+        std::fs::write(dir.join(path), content).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn errors_ordered_by_source_then_position() {
+    let dir = ledger_dir(
+        "error-order",
+        &[
+            (
+                "main.beancount",
+                "pushtag #unclosed\ninclude \"b.beancount\"\ninclude \"a.beancount\"\n2024-01-01 open\n",
+            ),
+            ("b.beancount", "2024-01-02 open\n2024-01-03 open\n"),
+            ("a.beancount", "2024-01-04 open\n"),
+        ],
+    );
+
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let error = parser.parse().unwrap_err();
+
+    let located = error
+        .errors
+        .iter()
+        .map(|e| {
+            let name = sources.source_name(e.source_id());
+            let name = &name[name.rfind('/').map_or(0, |i| i + 1)..];
+            format!("{} {}", name, e.span.start)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        located,
+        vec![
+            "main.beancount 0",
+            "main.beancount 77",
+            "b.beancount 15",
+            "b.beancount 31",
+            "a.beancount 15",
+        ]
+    );
+
+    let grouped = error
+        .errors_by_source()
+        .map(|(source_id, errors)| (sources.source_name(source_id).to_string(), errors.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        grouped,
+        vec![
+            (dir.join("main.beancount").to_string_lossy().to_string(), 2),
+            (dir.join("b.beancount").to_string_lossy().to_string(), 2),
+            (dir.join("a.beancount").to_string_lossy().to_string(), 1),
+        ]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn threads_same_result() {
+    let mut files = vec![(
+        "main.beancount".to_string(),
+        (0..8)
+            .map(|i| format!("include \"{}.beancount\"\n", i))
+            .collect::<String>(),
+    )];
+    for i in 0..8 {
+        files.push((
+            format!("{}.beancount", i),
+            format!(
+                "2024-01-{:02} open Assets:Bank{} GBP\n2024-02-{:02} open\n",
+                i + 1,
+                i,
+                i + 1
+            ),
+        ));
+    }
+    let files = files
+        .iter()
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .collect::<Vec<_>>();
+    let dir = ledger_dir("threads", &files);
+
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parse = |threads: usize| {
+        let parser =
+            BeancountParser::with_config(&sources, ParserConfig::default().threads(threads));
+        parser
+            .parse()
+            .unwrap_err()
+            .errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let serial = parse(1);
+    assert_eq!(serial.len(), 8);
+    assert_eq!(parse(3), serial);
+    assert_eq!(parse(16), serial);
+
+    #[cfg(feature = "rayon")]
+    {
+        let pool = std::sync::Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(3)
+                .build()
+                .unwrap(),
+        );
+        let parser =
+            BeancountParser::with_config(&sources, ParserConfig::default().thread_pool(pool));
+        let on_pool = parser
+            .parse()
+            .unwrap_err()
+            .errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(on_pool, serial);
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn include_resolution_relative_to_root() {
+    let dir = ledger_dir(
+        "include-root",
+        &[
+            ("main.beancount", "include \"accounts.beancount\"\n"),
+            ("accounts.beancount", "include \"cash.beancount\"\n"),
+            ("cash.beancount", "2024-01-01 open Assets:Cash\n"),
+        ],
+    );
+    // move the intermediate file into a subfolder, so its include is only found relative to the root
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(
+        dir.join("main.beancount"),
+        "include \"sub/accounts.beancount\"\n",
+    )
+    .unwrap();
+    std::fs::rename(
+        dir.join("accounts.beancount"),
+        dir.join("sub/accounts.beancount"),
+    )
+    .unwrap();
+
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(&*errors[0].message, "can't read file");
+    assert_eq!(
+        errors[0].reason.to_string(),
+        format!(
+            "not found, searched: {}",
+            dir.join("sub/cash.beancount").to_string_lossy()
+        )
+    );
+
+    let sources = BeancountSources::with_include_resolution(
+        dir.join("main.beancount"),
+        IncludeResolution::default().relative_to_root(true),
+    )
+    .unwrap();
+    let parser = BeancountParser::new(&sources);
+    assert_eq!(parser.parse().unwrap().directives.len(), 1);
+}
+
+#[test]
+fn include_resolution_search_path() {
+    let dir = ledger_dir(
+        "include-search",
+        &[(
+            "main.beancount",
+            "include \"common.beancount\"\ninclude \"missing.beancount\"\n",
+        )],
+    );
+    let common = ledger_dir(
+        "include-search-common",
+        &[("common.beancount", "2024-01-01 open Assets:Cash\n")],
+    );
+
+    let sources = BeancountSources::with_include_resolution(
+        dir.join("main.beancount"),
+        IncludeResolution::default().search_path([&common]),
+    )
+    .unwrap();
+    let parser = BeancountParser::new(&sources);
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].reason.to_string(),
+        format!(
+            "not found, searched: {}, {}",
+            dir.join("missing.beancount").to_string_lossy(),
+            common.join("missing.beancount").to_string_lossy()
+        )
+    );
+
+    let options = crate::Options::new(crate::options::ParserOptions::default());
+    let resolver = crate::PathResolver::new(&sources, &options);
+    assert_eq!(
+        resolver
+            .includes()
+            .iter()
+            .map(|include| (include.path().to_path_buf(), include.exists()))
+            .collect::<Vec<_>>(),
+        vec![
+            (common.join("common.beancount"), true),
+            (dir.join("missing.beancount"), false)
+        ]
+    );
+}
+
+#[test]
+fn directive_limit_stops_parsing_without_include_errors() {
+    let dir = ledger_dir(
+        "directive-limit",
+        &[
+            (
+                "main.beancount",
+                "include \"a.beancount\"\ninclude \"b.beancount\"\n",
+            ),
+            (
+                "a.beancount",
+                "2024-01-01 open Assets:Bank\n2024-01-02 open Assets:Cash\n2024-01-03 open Assets:Other\n",
+            ),
+            ("b.beancount", "2024-01-04 open Assets:Savings\n"),
+        ],
+    );
+
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::with_config(
+        &sources,
+        ParserConfig::default().resource_limits(ResourceLimits::default().max_directives(1)),
+    );
+    let ParseError { errors, .. } = parser.parse().unwrap_err();
+
+    let located = errors
+        .iter()
+        .map(|e| {
+            let name = sources.source_name(e.source_id());
+            let name = &name[name.rfind('/').map_or(0, |i| i + 1)..];
+            format!("{} {} {}", name, e.span.start, e.reason)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        located,
+        vec!["a.beancount 28 directive count exceeds limit of 1"]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test_case(ParserConfig::default(), SyntaxVersion::V3)]
+#[test_case(ParserConfig::default().detect_syntax_version(true), SyntaxVersion::V2)]
+#[test_case(ParserConfig::default().detect_syntax_version(true).syntax_version(SyntaxVersion::V3), SyntaxVersion::V3)]
+fn syntax_version_detection(config: ParserConfig, expected: SyntaxVersion) {
+    let sources = BeancountSources::from(
+        "option \"plugin\" \"beancount.plugins.auto\"\n2024-01-01 open Assets:Bank\n",
+    );
+    let parser = BeancountParser::with_config(&sources, config);
+
+    assert_eq!(parser.syntax_version(), expected);
+}
@@ -0,0 +1,190 @@
+use crate::{
+    booking::{book_regardless, Inventory},
+    format::format,
+    index,
+    types::*,
+    BeancountSources, Options,
+};
+use chumsky::span::Span as _;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+/// The directive at a line of a source file, with the inventories of the accounts it refers to before and after it,
+/// as for Beancount's `bean-doctor context`, for investigating balance errors.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Context};
+/// use std::path::Path;
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Expenses:Food
+/// 2024-01-02 * "Shop"
+///   Expenses:Food  10.00 NZD
+///   Assets:Bank
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let success = parser.parse().unwrap();
+/// let context = Context::new(&sources, &success.directives, &success.options, Path::new("inline"), 4).unwrap();
+/// let food = context.accounts().next().unwrap();
+///
+/// assert_eq!(context.line(), 3);
+/// assert!(context.before(food).is_none());
+/// assert_eq!(context.after(food).unwrap().positions().next().unwrap().to_string(), "10.00 NZD");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Context<'a> {
+    file: &'a str,
+    line: usize,
+    directive: &'a Spanned<Directive<'a>>,
+    source: &'a str,
+    accounts: Vec<&'a Account<'a>>,
+    before: BTreeMap<&'a Account<'a>, Inventory<'a>>,
+    after: BTreeMap<&'a Account<'a>, Inventory<'a>>,
+    errors: Vec<Error>,
+}
+
+impl<'a> Context<'a> {
+    /// The context of the directive whose source text includes `line` of `file`, counting from 1,
+    /// where `file` is as given by [BeancountSources::source_name], or `None` if there is no such directive.
+    pub fn new(
+        sources: &'a BeancountSources,
+        directives: &'a [Spanned<Directive<'a>>],
+        options: &Options<'_>,
+        file: &Path,
+        line: usize,
+    ) -> Option<Self> {
+        let (position, directive, (first_line, source)) =
+            directives.iter().enumerate().find_map(|(i, directive)| {
+                let span = directive.span();
+                (Path::new(sources.source_name(span.context())) == file)
+                    .then(|| lines(sources, span))
+                    .filter(|(first_line, source)| {
+                        (*first_line..=first_line + source.matches('\n').count()).contains(&line)
+                    })
+                    .map(|lines| (i, directive, lines))
+            })?;
+
+        // booking is stable with respect to date, so what precedes the directive is everything earlier,
+        // and anything on the same date which precedes it in the ledger
+        let date = *directive.date().item();
+        let preceding = directives.iter().enumerate().filter(|(i, d)| {
+            let d_date = *d.date().item();
+            d_date < date || (d_date == date && *i < position)
+        });
+        let (before, before_errors) =
+            book_regardless(preceding.clone().map(|(_, d)| d), options, date);
+        let (after, mut errors) = book_regardless(
+            preceding.map(|(_, d)| d).chain(Some(directive)),
+            options,
+            date,
+        );
+
+        let mut accounts = index::accounts(directive);
+        let mut seen = Vec::new();
+        accounts.retain(|account| {
+            let first = !seen.contains(account);
+            seen.push(*account);
+            first
+        });
+
+        Some(Context {
+            file: sources.source_name(directive.span().context()),
+            line: first_line,
+            directive,
+            source,
+            accounts,
+            before,
+            after,
+            // the directive is booked last, so its errors follow any others
+            errors: errors.split_off(before_errors.len()),
+        })
+    }
+
+    /// The name of the file containing the directive.
+    pub fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// The line on which the directive starts, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Field accessor.
+    pub fn directive(&self) -> &'a Spanned<Directive<'a>> {
+        self.directive
+    }
+
+    /// The source text of the directive, exactly as written.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// The accounts referred to by the directive, in order of first reference.
+    pub fn accounts(&self) -> impl ExactSizeIterator<Item = &'a Account<'a>> + '_ {
+        self.accounts.iter().copied()
+    }
+
+    /// The inventory of `account` before the directive, if it held any positions.
+    pub fn before(&self, account: &Account<'a>) -> Option<&Inventory<'a>> {
+        self.before.get(account)
+    }
+
+    /// The inventory of `account` after the directive, if it held any positions.
+    pub fn after(&self, account: &Account<'a>) -> Option<&Inventory<'a>> {
+        self.after.get(account)
+    }
+
+    /// Any errors in booking the directive itself, in which case the inventories after are the same as before.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+// the first line of the directive, and its source text without any trailing whitespace
+fn lines<'s>(sources: &'s BeancountSources, span: &Span) -> (usize, &'s str) {
+    let content = sources.source_content(span.context());
+    let first_line = content[..span.start()].matches('\n').count() + 1;
+    (first_line, content[span.start()..span.end()].trim_end())
+}
+
+impl Display for Context<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Location: {}:{}", self.file, self.line)?;
+
+        writeln!(f, "\n; balances before")?;
+        self.fmt_inventories(f, &self.before)?;
+
+        writeln!(f, "\n{}", self.source)?;
+        for error in self.errors.iter() {
+            writeln!(f, "; error: {}: {}", error.message, error.reason)?;
+        }
+
+        writeln!(f, "\n; balances after")?;
+        self.fmt_inventories(f, &self.after)
+    }
+}
+
+impl<'a> Context<'a> {
+    fn fmt_inventories(
+        &self,
+        f: &mut Formatter<'_>,
+        inventories: &BTreeMap<&'a Account<'a>, Inventory<'a>>,
+    ) -> fmt::Result {
+        for account in self.accounts.iter() {
+            write!(f, "{}", account)?;
+            if let Some(inventory) = inventories.get(account) {
+                f.write_str("  ")?;
+                format(f, inventory.positions(), |position| position, ", ", None)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+mod tests;
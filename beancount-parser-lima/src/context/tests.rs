@@ -0,0 +1,112 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+const LEDGER: &str = r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+
+2024-01-02 * "Shop"
+  Expenses:Food  10.00 NZD
+  Assets:Bank
+
+2024-01-03 * "Shop again"
+  Expenses:Food  5.00 NZD
+  Assets:Bank
+"#;
+
+fn context_at<F>(s: &str, line: usize, f: F)
+where
+    F: FnOnce(Option<Context<'_>>),
+{
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    f(Context::new(
+        &sources,
+        &success.directives,
+        &success.options,
+        Path::new("inline"),
+        line,
+    ))
+}
+
+#[test]
+fn balances_before_and_after() {
+    context_at(LEDGER, 10, |context| {
+        let context = context.unwrap();
+        assert_eq!(context.line(), 8);
+        assert!(context.source().starts_with("2024-01-03 * \"Shop again\""));
+        assert!(context.source().ends_with("Assets:Bank"));
+
+        let accounts = context
+            .accounts()
+            .map(|account| account.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(accounts, vec!["Expenses:Food", "Assets:Bank"]);
+
+        let bank = context.accounts().nth(1).unwrap();
+        assert_eq!(
+            context
+                .before(bank)
+                .unwrap()
+                .units(&Currency::try_from("NZD").unwrap()),
+            "-10.00".parse().unwrap()
+        );
+        assert_eq!(
+            context
+                .after(bank)
+                .unwrap()
+                .units(&Currency::try_from("NZD").unwrap()),
+            "-15.00".parse().unwrap()
+        );
+        assert!(context.errors().is_empty());
+    });
+}
+
+#[test]
+fn display() {
+    context_at(LEDGER, 4, |context| {
+        assert_eq!(
+            context.unwrap().to_string(),
+            r#"Location: inline:4
+
+; balances before
+Expenses:Food
+Assets:Bank
+
+2024-01-02 * "Shop"
+  Expenses:Food  10.00 NZD
+  Assets:Bank
+
+; balances after
+Expenses:Food  10.00 NZD
+Assets:Bank  -10.00 NZD
+"#
+        );
+    });
+}
+
+#[test]
+fn error_in_directive() {
+    context_at(
+        r#"2024-01-01 open Assets:Bank
+2024-01-02 * "Shop"
+  Assets:Bank  10.00 NZD
+  Assets:Bank
+  Assets:Bank
+"#,
+        2,
+        |context| {
+            let context = context.unwrap();
+            assert_eq!(context.errors().len(), 1);
+            assert!(context.after(context.accounts().next().unwrap()).is_none());
+        },
+    );
+}
+
+#[test]
+fn no_directive() {
+    context_at(LEDGER, 3, |context| assert!(context.is_none()));
+    context_at(LEDGER, 100, |context| assert!(context.is_none()));
+}
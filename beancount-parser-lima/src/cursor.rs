@@ -0,0 +1,193 @@
+use crate::types::*;
+use chumsky::span::Span as _;
+
+/// An arena over directives and their postings and metadata, in which each node knows its parent and siblings,
+/// complementing the top-down structure of [Directive].
+///
+/// Navigation is by [Cursor], starting either from the top-level directives, or from the node at a given span,
+/// such as the location of a code action in an editor.
+#[derive(Clone, Debug)]
+pub struct SyntaxTree<'a> {
+    nodes: Vec<NodeData<'a>>,
+    roots: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct NodeData<'a> {
+    node: Node<'a>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // position among the children of the parent, or among the roots
+    position: usize,
+}
+
+impl<'a> SyntaxTree<'a> {
+    /// Build the tree for `directives`, which become its roots in the given order.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut tree = SyntaxTree {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        };
+
+        for directive in directives {
+            let d = tree.push(Node::Directive(directive), None);
+            tree.push_metadata(directive.metadata(), d);
+
+            if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+                for posting in transaction.postings() {
+                    let p = tree.push(Node::Posting(posting), Some(d));
+                    tree.push_metadata(posting.metadata(), p);
+                }
+            }
+        }
+
+        tree
+    }
+
+    fn push(&mut self, node: Node<'a>, parent: Option<usize>) -> usize {
+        let i = self.nodes.len();
+        let siblings = match parent {
+            Some(parent) => &mut self.nodes[parent].children,
+            None => &mut self.roots,
+        };
+        let position = siblings.len();
+        siblings.push(i);
+
+        self.nodes.push(NodeData {
+            node,
+            parent,
+            children: Vec::new(),
+            position,
+        });
+        i
+    }
+
+    fn push_metadata(&mut self, metadata: &'a Metadata<'a>, parent: usize) {
+        for (key, value) in metadata.key_values() {
+            self.push(Node::KeyValue(key, value), Some(parent));
+        }
+    }
+
+    /// Cursors at each of the directives.
+    pub fn roots(&self) -> impl ExactSizeIterator<Item = Cursor<'_, 'a>> {
+        self.roots.iter().map(|&index| Cursor { tree: self, index })
+    }
+
+    /// A cursor at the innermost node whose span contains `span`, if any.
+    pub fn at(&self, span: &Span) -> Option<Cursor<'_, 'a>> {
+        let contains = |index: &&usize| {
+            let outer = self.nodes[**index].node.span();
+            outer.context() == span.context()
+                && outer.start() <= span.start()
+                && span.end() <= outer.end()
+        };
+
+        let mut index = *self.roots.iter().find(contains)?;
+        while let Some(child) = self.nodes[index].children.iter().find(contains) {
+            index = *child;
+        }
+        Some(Cursor { tree: self, index })
+    }
+}
+
+/// A node in a [SyntaxTree].
+#[derive(Copy, Clone, Debug)]
+pub enum Node<'a> {
+    Directive(&'a Spanned<Directive<'a>>),
+    Posting(&'a Spanned<Posting<'a>>),
+    KeyValue(&'a Spanned<Key<'a>>, &'a Spanned<MetaValue<'a>>),
+}
+
+impl<'a> Node<'a> {
+    /// The span of the whole node, which for a key/value runs from the key to the end of the value.
+    pub fn span(&self) -> Span {
+        use Node::*;
+
+        match self {
+            Directive(directive) => *directive.span(),
+            Posting(posting) => *posting.span(),
+            KeyValue(key, value) => {
+                Span::new(key.span().context(), key.span().start()..value.span().end())
+            }
+        }
+    }
+}
+
+/// A position in a [SyntaxTree], from which to navigate to related nodes.
+#[derive(Copy, Clone, Debug)]
+pub struct Cursor<'t, 'a> {
+    tree: &'t SyntaxTree<'a>,
+    index: usize,
+}
+
+impl<'t, 'a> Cursor<'t, 'a> {
+    /// The node at this cursor.
+    pub fn node(&self) -> Node<'a> {
+        self.data().node
+    }
+
+    /// The enclosing node, which is `None` for a directive.
+    pub fn parent(&self) -> Option<Cursor<'t, 'a>> {
+        self.data().parent.map(|index| self.at(index))
+    }
+
+    /// The nodes enclosing this one, innermost first.
+    pub fn ancestors(&self) -> impl Iterator<Item = Cursor<'t, 'a>> {
+        std::iter::successors(self.parent(), Cursor::parent)
+    }
+
+    /// The directive containing this node, or the node itself if it is a directive.
+    pub fn directive(&self) -> &'a Spanned<Directive<'a>> {
+        match std::iter::once(*self)
+            .chain(self.ancestors())
+            .last()
+            .map(|root| root.node())
+        {
+            Some(Node::Directive(directive)) => directive,
+            _ => unreachable!("root node is always a directive"),
+        }
+    }
+
+    /// The immediately enclosed nodes, which for a transaction are its metadata key/values followed by its postings.
+    pub fn children(&self) -> impl ExactSizeIterator<Item = Cursor<'t, 'a>> + '_ {
+        self.data().children.iter().map(|&index| self.at(index))
+    }
+
+    /// The following node with the same parent, if any.
+    pub fn next_sibling(&self) -> Option<Cursor<'t, 'a>> {
+        self.siblings()
+            .get(self.data().position + 1)
+            .map(|&index| self.at(index))
+    }
+
+    /// The preceding node with the same parent, if any.
+    pub fn prev_sibling(&self) -> Option<Cursor<'t, 'a>> {
+        self.data()
+            .position
+            .checked_sub(1)
+            .map(|position| self.at(self.siblings()[position]))
+    }
+
+    fn data(&self) -> &'t NodeData<'a> {
+        &self.tree.nodes[self.index]
+    }
+
+    fn siblings(&self) -> &'t [usize] {
+        match self.data().parent {
+            Some(parent) => &self.tree.nodes[parent].children,
+            None => &self.tree.roots,
+        }
+    }
+
+    fn at(&self, index: usize) -> Cursor<'t, 'a> {
+        Cursor {
+            tree: self.tree,
+            index,
+        }
+    }
+}
+
+mod tests;
@@ -0,0 +1,72 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank
+
+2024-01-02 * "Coffee"
+  receipt: "1234"
+  Assets:Bank  -3.50 GBP
+    category: "treat"
+  Expenses:Coffee
+"#;
+
+fn kind(cursor: &Cursor<'_, '_>) -> String {
+    match cursor.node() {
+        Node::Directive(directive) => directive.element_type().to_string(),
+        Node::Posting(posting) => format!("posting {}", posting.account().item()),
+        Node::KeyValue(key, _) => format!("key {}", key.item().as_ref()),
+    }
+}
+
+#[test]
+fn cursor_navigation() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let tree = SyntaxTree::new(&directives);
+
+    let transaction = tree.roots().nth(1).unwrap();
+    assert_eq!(
+        transaction.children().map(|c| kind(&c)).collect::<Vec<_>>(),
+        vec![
+            "key receipt",
+            "posting Assets:Bank",
+            "posting Expenses:Coffee"
+        ]
+    );
+    assert_eq!(kind(&transaction.prev_sibling().unwrap()), "open");
+    assert!(transaction.next_sibling().is_none());
+
+    let category = transaction
+        .children()
+        .nth(1)
+        .unwrap()
+        .children()
+        .next()
+        .unwrap();
+    assert_eq!(kind(&category), "key category");
+    assert_eq!(
+        category.ancestors().map(|c| kind(&c)).collect::<Vec<_>>(),
+        vec!["posting Assets:Bank", "transaction"]
+    );
+    assert_eq!(category.directive().date().item().to_string(), "2024-01-02");
+}
+
+#[test]
+fn cursor_at_span() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let tree = SyntaxTree::new(&directives);
+
+    let posting = match directives[1].variant() {
+        DirectiveVariant::Transaction(transaction) => transaction.postings().nth(1).unwrap(),
+        _ => unreachable!(),
+    };
+    let cursor = tree.at(posting.account().span()).unwrap();
+    assert_eq!(kind(&cursor), "posting Expenses:Coffee");
+    assert_eq!(kind(&cursor.prev_sibling().unwrap()), "posting Assets:Bank");
+    assert_eq!(kind(&cursor.parent().unwrap()), "transaction");
+}
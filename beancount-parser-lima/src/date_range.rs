@@ -0,0 +1,203 @@
+use crate::{recurring::add_months, Options};
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::{Bound, RangeBounds},
+    str::FromStr,
+};
+use time::{Date, Duration, Month, Weekday};
+
+/// A range of dates, from its first day up to but excluding its end, as given by a period expression.
+///
+/// Being a [RangeBounds], it may be used to restrict a [LedgerView](crate::LedgerView) to the period.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::DateRange;
+/// use time::Month;
+///
+/// let q3: DateRange = "2023-Q3".parse().unwrap();
+/// assert_eq!(q3.to_string(), "2023-07-01 to 2023-10-01");
+///
+/// let fiscal = DateRange::parse("FY2024-Q1", (Month::April, 1)).unwrap();
+/// assert_eq!(fiscal.to_string(), "2023-04-01 to 2023-07-01");
+/// ```
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct DateRange {
+    begin: Date,
+    end: Date,
+}
+
+impl DateRange {
+    /// Parse a period expression, with fiscal years starting on the given month and day.
+    ///
+    /// A period is one of:
+    /// - a year, `2023`
+    /// - a quarter, `2023-Q3`
+    /// - a month, `2023-07`
+    /// - an ISO 8601 week, starting on Monday, `2023-W27`
+    /// - a day, `2023-07-14`
+    /// - a fiscal year, `FY2024`, being the one which ends in 2024
+    /// - a fiscal quarter, `FY2024-Q1`
+    ///
+    /// or two periods separated by `to`, such as `2023-Q3 to 2024-01`, for the range from the first to the end of the second.
+    ///
+    /// Fiscal quarters starting on a day which does not exist in every month start on the last day of shorter months.
+    pub fn parse(expr: &str, fiscal_year_start: (Month, u8)) -> Result<Self, DateRangeError> {
+        let range = match expr.split_once(" to ") {
+            Some((first, last)) => {
+                let (first, last) = (
+                    parse_period(first.trim(), fiscal_year_start),
+                    parse_period(last.trim(), fiscal_year_start),
+                );
+                first
+                    .zip(last)
+                    .filter(|(first, last)| first.begin < last.end)
+                    .map(|(first, last)| DateRange {
+                        begin: first.begin,
+                        end: last.end,
+                    })
+            }
+            None => parse_period(expr.trim(), fiscal_year_start),
+        };
+
+        range.ok_or_else(|| DateRangeError::new(format!("invalid period {}", expr)))
+    }
+
+    /// Parse a period expression, with fiscal years starting as specified by the option `fiscal_year_start`.
+    pub fn parse_with_options(expr: &str, options: &Options<'_>) -> Result<Self, DateRangeError> {
+        Self::parse(expr, options.fiscal_year_start())
+    }
+
+    /// The first day.
+    pub fn begin(&self) -> Date {
+        self.begin
+    }
+
+    /// The day after the last day.
+    pub fn end(&self) -> Date {
+        self.end
+    }
+
+    /// Whether `date` is in the range.
+    pub fn contains(&self, date: Date) -> bool {
+        self.begin <= date && date < self.end
+    }
+}
+
+/// Parse a period expression with fiscal years being calendar years, see [DateRange::parse].
+impl FromStr for DateRange {
+    type Err = DateRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DateRange::parse(s, (Month::January, 1))
+    }
+}
+
+impl RangeBounds<Date> for DateRange {
+    fn start_bound(&self) -> Bound<&Date> {
+        Bound::Included(&self.begin)
+    }
+
+    fn end_bound(&self) -> Bound<&Date> {
+        Bound::Excluded(&self.end)
+    }
+}
+
+impl Display for DateRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} to {}", self.begin, self.end)
+    }
+}
+
+// a single period, without `to`
+fn parse_period(expr: &str, fiscal_year_start: (Month, u8)) -> Option<DateRange> {
+    let range = |begin: Date, end: Option<Date>| end.map(|end| DateRange { begin, end });
+
+    if let Some(fiscal) = expr.strip_prefix("FY") {
+        let (year, quarter) = match fiscal.split_once("-Q") {
+            Some((year, quarter)) => (year, Some(number::<u8>(quarter, 1)?)),
+            None => (fiscal, None),
+        };
+        let year = number::<i32>(year, 4)?;
+        let (month, day) = fiscal_year_start;
+        // the fiscal year ends in the given year
+        let begin_year = if (month, day) == (Month::January, 1) {
+            year
+        } else {
+            year - 1
+        };
+        let year_begin = Date::from_calendar_date(begin_year, month, day).ok()?;
+
+        return match quarter {
+            Some(quarter @ 1..=4) => range(
+                add_months(year_begin, (quarter as u32 - 1) * 3)?,
+                add_months(year_begin, quarter as u32 * 3),
+            ),
+            Some(_) => None,
+            None => range(year_begin, add_months(year_begin, 12)),
+        };
+    }
+
+    let mut parts = expr.splitn(3, '-');
+    let year = number::<i32>(parts.next()?, 4)?;
+    let year_begin = Date::from_calendar_date(year, Month::January, 1).ok()?;
+
+    match (parts.next(), parts.next()) {
+        (None, _) => range(year_begin, add_months(year_begin, 12)),
+        (Some(quarter), None) if quarter.starts_with('Q') => {
+            match number::<u8>(&quarter[1..], 1)? {
+                quarter @ 1..=4 => {
+                    let begin = add_months(year_begin, (quarter as u32 - 1) * 3)?;
+                    range(begin, add_months(begin, 3))
+                }
+                _ => None,
+            }
+        }
+        (Some(week), None) if week.starts_with('W') => {
+            let week = number::<u8>(&week[1..], 2)?;
+            let begin = Date::from_iso_week_date(year, week, Weekday::Monday).ok()?;
+            range(begin, begin.checked_add(Duration::weeks(1)))
+        }
+        (Some(month), None) => {
+            let month = Month::try_from(number::<u8>(month, 2)?).ok()?;
+            let begin = Date::from_calendar_date(year, month, 1).ok()?;
+            range(begin, add_months(begin, 1))
+        }
+        (Some(month), Some(day)) => {
+            let month = Month::try_from(number::<u8>(month, 2)?).ok()?;
+            let begin = Date::from_calendar_date(year, month, number::<u8>(day, 2)?).ok()?;
+            range(begin, begin.next_day())
+        }
+    }
+}
+
+// a number of exactly `digits` decimal digits
+fn number<T: FromStr>(s: &str, digits: usize) -> Option<T> {
+    (s.len() == digits && s.chars().all(|c| c.is_ascii_digit()))
+        .then(|| s.parse().ok())
+        .flatten()
+}
+
+/// The error returned when a period expression is invalid, see [DateRange::parse].
+#[derive(Debug)]
+pub struct DateRangeError {
+    message: String,
+}
+
+impl DateRangeError {
+    fn new<S: Into<String>>(message: S) -> Self {
+        DateRangeError {
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for DateRangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DateRangeError {}
+
+mod tests;
@@ -0,0 +1,83 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources, LedgerView};
+
+#[test]
+fn period_expressions() {
+    let parse = |expr: &str, fiscal_year_start| {
+        DateRange::parse(expr, fiscal_year_start).map(|range| range.to_string())
+    };
+    let calendar = (Month::January, 1);
+    let april = (Month::April, 1);
+
+    for (expr, fiscal_year_start, expected) in [
+        ("2023", calendar, "2023-01-01 to 2024-01-01"),
+        ("2023-Q4", calendar, "2023-10-01 to 2024-01-01"),
+        ("2024-02", calendar, "2024-02-01 to 2024-03-01"),
+        ("2024-W01", calendar, "2024-01-01 to 2024-01-08"),
+        ("2024-02-29", calendar, "2024-02-29 to 2024-03-01"),
+        ("FY2024", calendar, "2024-01-01 to 2025-01-01"),
+        ("FY2024", april, "2023-04-01 to 2024-04-01"),
+        ("FY2024-Q4", april, "2024-01-01 to 2024-04-01"),
+        (
+            "FY2024-Q2",
+            (Month::January, 31),
+            "2023-04-30 to 2023-07-31",
+        ),
+        ("2023-Q3 to 2024-01", calendar, "2023-07-01 to 2024-02-01"),
+    ] {
+        assert_eq!(
+            parse(expr, fiscal_year_start).as_deref().ok(),
+            Some(expected),
+            "{}",
+            expr
+        );
+    }
+
+    for expr in [
+        "",
+        "23",
+        "2023-Q5",
+        "2023-13",
+        "2023-W54",
+        "2023-02-29",
+        "FY2024-Q0",
+        "2024 to 2023",
+        "2023-7",
+    ] {
+        assert_eq!(
+            parse(expr, calendar).map_err(|e| e.to_string()),
+            Err(format!("invalid period {}", expr))
+        );
+    }
+}
+
+#[test]
+fn date_range_restricts_view() {
+    let s = r#"2023-06-30 * "Before"
+  Assets:Bank  -1.00 NZD
+  Expenses:Food
+2023-07-01 * "First"
+  Assets:Bank  -1.00 NZD
+  Expenses:Food
+2023-09-30 * "Last"
+  Assets:Bank  -1.00 NZD
+  Expenses:Food
+2023-10-01 * "After"
+  Assets:Bank  -1.00 NZD
+  Expenses:Food
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    let q3 = DateRange::parse_with_options("2023-Q3", &success.options).unwrap();
+    let view = LedgerView::new(&success.directives).dates(q3);
+
+    assert_eq!(
+        view.iter()
+            .map(|directive| directive.date().to_string())
+            .collect::<Vec<_>>(),
+        vec!["2023-07-01", "2023-09-30"]
+    );
+}
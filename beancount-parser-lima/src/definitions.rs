@@ -0,0 +1,76 @@
+use crate::types::*;
+use std::collections::{hash_map::Entry, HashMap};
+
+/// Where accounts, currencies, and links are defined, for jump-to-definition in editors.
+///
+/// An account is defined by its `open` directive, and a currency by its `commodity` directive.
+/// Links have no definition as such, so the first usage stands in for one.
+#[derive(Clone, Default, Debug)]
+pub struct Definitions<'a> {
+    accounts: HashMap<&'a Account<'a>, Span>,
+    currencies: HashMap<Currency<'a>, Span>,
+    links: HashMap<Link<'a>, Span>,
+}
+
+impl<'a> Definitions<'a> {
+    /// Find the definitions among `directives`, where for anything defined more than once the first in order is used.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut definitions = Definitions::default();
+
+        for directive in directives {
+            match directive.variant() {
+                DirectiveVariant::Open(open) => {
+                    first(&mut definitions.accounts, open.account().item(), directive)
+                }
+                DirectiveVariant::Commodity(commodity) => first(
+                    &mut definitions.currencies,
+                    *commodity.currency().item(),
+                    directive,
+                ),
+                _ => (),
+            }
+
+            for link in directive.metadata().links() {
+                first(&mut definitions.links, *link.item(), link);
+            }
+            if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+                for posting in transaction.postings() {
+                    for link in posting.metadata().links() {
+                        first(&mut definitions.links, *link.item(), link);
+                    }
+                }
+            }
+        }
+
+        definitions
+    }
+
+    /// The span of the `open` directive for `account`, if any.
+    pub fn definition_of_account(&self, account: &Account<'a>) -> Option<Span> {
+        self.accounts.get(account).copied()
+    }
+
+    /// The span of the `commodity` directive for `currency`, if any.
+    pub fn definition_of_currency(&self, currency: &Currency<'a>) -> Option<Span> {
+        self.currencies.get(currency).copied()
+    }
+
+    /// The span of the first usage of `link`, if any.
+    pub fn definition_of_link(&self, link: &Link<'a>) -> Option<Span> {
+        self.links.get(link).copied()
+    }
+}
+
+fn first<K, T>(definitions: &mut HashMap<K, Span>, key: K, element: &Spanned<T>)
+where
+    K: Eq + std::hash::Hash,
+{
+    if let Entry::Vacant(entry) = definitions.entry(key) {
+        entry.insert(*element.span());
+    }
+}
+
+mod tests;
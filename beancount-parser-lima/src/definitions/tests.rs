@@ -0,0 +1,49 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+use chumsky::span::Span as _;
+
+const LEDGER: &str = r#"2024-01-01 commodity GBP
+2024-01-01 open Assets:Bank
+2024-01-02 * "Coffee" ^receipts
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-03 * "Tea" ^receipts
+  Assets:Bank  -2.50 GBP
+  Expenses:Tea
+"#;
+
+fn text(span: Span) -> &'static str {
+    LEDGER[span.start()..span.end()].trim_end()
+}
+
+#[test]
+fn definitions() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let definitions = Definitions::new(&directives);
+
+    let bank = match directives[1].variant() {
+        DirectiveVariant::Open(open) => open.account().item(),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        definitions.definition_of_account(bank).map(text),
+        Some("2024-01-01 open Assets:Bank")
+    );
+
+    let gbp = Currency::try_from("GBP").unwrap();
+    assert_eq!(
+        definitions.definition_of_currency(&gbp).map(text),
+        Some("2024-01-01 commodity GBP")
+    );
+    assert!(definitions
+        .definition_of_currency(&Currency::try_from("USD").unwrap())
+        .is_none());
+
+    let receipts = Link::try_from("receipts").unwrap();
+    let span = definitions.definition_of_link(&receipts).unwrap();
+    assert_eq!(text(span), "^receipts");
+    assert!(span.start() < LEDGER.find("Tea").unwrap());
+}
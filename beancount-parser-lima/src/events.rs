@@ -0,0 +1,127 @@
+use crate::types::*;
+use std::{collections::BTreeMap, ops::Range};
+use time::Date;
+
+/// Timelines of the values of `event` directives, one for each event type,
+/// as used by Beancount reports for tracking things such as location or employer over time.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Events};
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 event "location" "Wellington"
+/// 2024-03-01 event "location" "Tokyo"
+/// 2024-03-11 event "location" "Wellington"
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let events = Events::new(directives.iter());
+/// let location = events.get("location").unwrap();
+/// let date = |month, day| Date::from_calendar_date(2024, month, day).unwrap();
+///
+/// assert_eq!(location.value_at(date(Month::March, 5)), Some("Tokyo"));
+/// assert_eq!(location.days(date(Month::December, 31))["Tokyo"], 10);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Events<'a> {
+    timelines: BTreeMap<&'a str, Timeline<'a>>,
+}
+
+impl<'a> Events<'a> {
+    /// Collect the timelines of all `event` directives, ignoring other directives.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut events = Events::default();
+
+        for directive in directives {
+            if let DirectiveVariant::Event(event) = directive.variant() {
+                events
+                    .timelines
+                    .entry(*event.event_type().item())
+                    .or_default()
+                    .changes
+                    .push((*directive.date().item(), *event.description().item()));
+            }
+        }
+
+        // stable, so that of several events on the same date, the last in the ledger wins
+        for timeline in events.timelines.values_mut() {
+            timeline.changes.sort_by_key(|(date, _)| *date);
+        }
+
+        events
+    }
+
+    /// The timeline for `event_type`, if there are any events of that type.
+    pub fn get(&self, event_type: &str) -> Option<&Timeline<'a>> {
+        self.timelines.get(event_type)
+    }
+
+    /// The event types and their timelines, in order of event type.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &Timeline<'a>)> {
+        self.timelines
+            .iter()
+            .map(|(event_type, timeline)| (*event_type, timeline))
+    }
+}
+
+/// The values of a single event type over time, see [Events].
+#[derive(Clone, Default, Debug)]
+pub struct Timeline<'a> {
+    changes: Vec<(Date, &'a str)>,
+}
+
+impl<'a> Timeline<'a> {
+    /// The dates on which an event occurred and the value it set, in date order.
+    pub fn changes(&self) -> impl ExactSizeIterator<Item = (Date, &'a str)> + '_ {
+        self.changes.iter().copied()
+    }
+
+    /// The value in effect on `date`, which is that of the latest event on or before it, if any.
+    pub fn value_at(&self, date: Date) -> Option<&'a str> {
+        let after = self.changes.partition_point(|(d, _)| *d <= date);
+        after.checked_sub(1).map(|i| self.changes[i].1)
+    }
+
+    /// The contiguous date ranges over which each value was in effect, up to but excluding `until`,
+    /// in date order, with the ranges of consecutive events with the same value merged.
+    pub fn intervals(&self, until: Date) -> Vec<(&'a str, Range<Date>)> {
+        let mut intervals = Vec::<(&'a str, Range<Date>)>::new();
+
+        for (i, (start, value)) in self.changes.iter().enumerate() {
+            let end = self
+                .changes
+                .get(i + 1)
+                .map_or(until, |(next, _)| *next)
+                .min(until);
+            if *start >= end {
+                continue;
+            }
+
+            match intervals.last_mut() {
+                Some((last_value, last_range))
+                    if last_value == value && last_range.end == *start =>
+                {
+                    last_range.end = end;
+                }
+                _ => intervals.push((*value, *start..end)),
+            }
+        }
+
+        intervals
+    }
+
+    /// The total number of days on which each value was in effect, up to but excluding `until`.
+    pub fn days(&self, until: Date) -> BTreeMap<&'a str, i64> {
+        let mut days = BTreeMap::new();
+        for (value, range) in self.intervals(until) {
+            *days.entry(value).or_default() += (range.end - range.start).whole_days();
+        }
+        days
+    }
+}
+
+mod tests;
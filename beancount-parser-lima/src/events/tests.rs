@@ -0,0 +1,78 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+use time::Month;
+
+const LEDGER: &str = r#"
+2024-01-01 event "location" "Wellington"
+2024-01-01 event "employer" "Acme"
+2024-03-01 event "location" "Tokyo"
+2024-03-11 event "location" "Wellington"
+2024-02-01 event "location" "Wellington"
+2024-06-01 event "location" "Auckland"
+2024-06-01 event "location" "Christchurch"
+"#;
+
+#[test]
+fn value_at() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let events = Events::new(directives.iter());
+    let location = events.get("location").unwrap();
+
+    assert_eq!(
+        events
+            .iter()
+            .map(|(event_type, timeline)| (event_type, timeline.changes().len()))
+            .collect::<Vec<_>>(),
+        vec![("employer", 1), ("location", 6)]
+    );
+    assert!(events.get("weather").is_none());
+    assert_eq!(
+        location.value_at(Date::from_calendar_date(2023, Month::December, 31).unwrap()),
+        None
+    );
+    assert_eq!(location.value_at(date("2024-01-01")), Some("Wellington"));
+    assert_eq!(location.value_at(date("2024-02-29")), Some("Wellington"));
+    assert_eq!(location.value_at(date("2024-03-01")), Some("Tokyo"));
+    assert_eq!(location.value_at(date("2024-03-10")), Some("Tokyo"));
+    assert_eq!(location.value_at(date("2024-03-11")), Some("Wellington"));
+    // the later of events on the same date wins
+    assert_eq!(location.value_at(date("2024-06-01")), Some("Christchurch"));
+}
+
+#[test]
+fn intervals_and_days() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let events = Events::new(directives.iter());
+    let location = events.get("location").unwrap();
+
+    assert_eq!(
+        location.intervals(date("2024-07-01")),
+        vec![
+            ("Wellington", date("2024-01-01")..date("2024-03-01")),
+            ("Tokyo", date("2024-03-01")..date("2024-03-11")),
+            ("Wellington", date("2024-03-11")..date("2024-06-01")),
+            ("Christchurch", date("2024-06-01")..date("2024-07-01")),
+        ]
+    );
+    assert_eq!(
+        location.intervals(date("2024-03-05")),
+        vec![
+            ("Wellington", date("2024-01-01")..date("2024-03-01")),
+            ("Tokyo", date("2024-03-01")..date("2024-03-05")),
+        ]
+    );
+    assert_eq!(
+        location
+            .days(date("2024-07-01"))
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec![("Christchurch", 30), ("Tokyo", 10), ("Wellington", 60 + 82)]
+    );
+    assert!(location.days(date("2024-01-01")).is_empty());
+}
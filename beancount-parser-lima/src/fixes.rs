@@ -0,0 +1,152 @@
+use crate::{types::*, BeancountSources};
+use chumsky::span::Span as _;
+use std::path::Path;
+
+/// A machine-applicable fix for an error or warning, as edits to the sources,
+/// for editors to offer as a code action, or to be applied by [BeancountSources::apply_fixes].
+///
+/// # Examples
+/// An application which checks that accounts are opened might offer to open one at its first use:
+/// ```ignore
+/// let fix = Fix::new(format!("add open directive for {}", account))
+///     .insert_before(*first_use.span(), format!("{} open {}\n", first_use.date(), account));
+/// errors.push(posting.error("account not open").with_fix(fix));
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Fix {
+    title: String,
+    edits: Vec<TextEdit>,
+}
+
+/// Replacement of the text at a span, where an empty span is an insertion, see [Fix].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TextEdit {
+    span: Span,
+    replacement: String,
+}
+
+impl Fix {
+    /// A fix with no edits as yet, described by `title`, such as "insert missing currency".
+    pub fn new<T: Into<String>>(title: T) -> Self {
+        Fix {
+            title: title.into(),
+            edits: Vec::new(),
+        }
+    }
+
+    /// Add an edit to insert `text` immediately before `span`.
+    pub fn insert_before<T: Into<String>>(self, span: Span, text: T) -> Self {
+        let at = Span::new(span.context(), span.start()..span.start());
+        self.replace(at, text)
+    }
+
+    /// Add an edit to insert `text` immediately after `span`.
+    pub fn insert_after<T: Into<String>>(self, span: Span, text: T) -> Self {
+        let at = Span::new(span.context(), span.end()..span.end());
+        self.replace(at, text)
+    }
+
+    /// Add an edit to replace the text at `span` with `text`.
+    pub fn replace<T: Into<String>>(mut self, span: Span, text: T) -> Self {
+        self.edits.push(TextEdit::new(span, text));
+        self
+    }
+
+    /// A short description of the fix, suitable for a menu.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The edits comprising the fix, in the order added.
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+}
+
+impl TextEdit {
+    pub(crate) fn new<T: Into<String>>(span: Span, replacement: T) -> Self {
+        TextEdit {
+            span,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// The span whose text is replaced.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The text replacing that at the span.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+impl BeancountSources {
+    /// Apply the edits of all `fixes`, returning the fixed content of each source which was changed,
+    /// with its path, or `None` for inline content.
+    ///
+    /// Where edits overlap, only the first in order of position is applied, so the result is always well-formed,
+    /// though possibly with some fixes incomplete.  Insertions at the same position are applied in the order given.
+    pub fn apply_fixes<'f, I>(&self, fixes: I) -> Vec<(Option<&Path>, String)>
+    where
+        I: IntoIterator<Item = &'f Fix>,
+    {
+        self.apply_edits(fixes.into_iter().flat_map(|fix| fix.edits.iter()))
+            .into_iter()
+            .map(|(_, path, fixed)| (path, fixed))
+            .collect()
+    }
+
+    // apply edits as described for `apply_fixes`, returning the changed sources
+    pub(crate) fn apply_edits<'e, I>(&self, edits: I) -> Vec<(SourceId, Option<&Path>, String)>
+    where
+        I: IntoIterator<Item = &'e TextEdit>,
+    {
+        self.applicable_edits(edits)
+            .into_iter()
+            .map(|(source_id, path, content, edits)| {
+                let mut fixed = String::with_capacity(content.len());
+                let mut copied_to = 0;
+                for edit in edits {
+                    fixed.push_str(&content[copied_to..edit.span.start()]);
+                    fixed.push_str(&edit.replacement);
+                    copied_to = edit.span.end();
+                }
+                fixed.push_str(&content[copied_to..]);
+                (source_id, path, fixed)
+            })
+            .collect()
+    }
+
+    // the edits which apply to each changed source, with its content, in order of position and not overlapping
+    pub(crate) fn applicable_edits<'e, I>(
+        &self,
+        edits: I,
+    ) -> Vec<(SourceId, Option<&Path>, &str, Vec<&'e TextEdit>)>
+    where
+        I: IntoIterator<Item = &'e TextEdit>,
+    {
+        let mut edits = edits.into_iter().collect::<Vec<_>>();
+        // stable sort preserves the order of insertions at the same position
+        edits.sort_by_key(|edit| edit.span.start());
+
+        self.content_iter()
+            .filter_map(|(source_id, path, content)| {
+                let mut applicable = Vec::new();
+                let mut copied_to = 0;
+
+                for edit in edits.iter().filter(|edit| edit.span.context() == source_id) {
+                    if edit.span.start() >= copied_to {
+                        applicable.push(*edit);
+                        copied_to = edit.span.end();
+                    }
+                }
+
+                (!applicable.is_empty()).then_some((source_id, path, content, applicable))
+            })
+            .collect()
+    }
+}
+
+mod tests;
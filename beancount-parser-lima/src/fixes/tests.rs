@@ -0,0 +1,65 @@
+#![cfg(test)]
+use super::*;
+use crate::{trial_balance::trial_balance, BeancountParser};
+use time::{Date, Month};
+
+fn at(start: usize, end: usize) -> Span {
+    Span::new(SourceId::default(), start..end)
+}
+
+#[test]
+fn apply_edits_in_order_of_position() {
+    let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n");
+    let open = Fix::new("open").replace(at(11, 15), "close");
+    let tag = Fix::new("tag")
+        .insert_after(at(0, 27), " #a")
+        .insert_after(at(0, 27), " #b");
+    let overlapping = Fix::new("overlapping").replace(at(13, 20), "X");
+
+    assert_eq!(
+        sources.apply_fixes([&tag, &overlapping, &open]),
+        vec![(None, "2024-01-01 close Assets:Bank #a #b\n".to_string())]
+    );
+    assert!(sources.apply_fixes([]).is_empty());
+}
+
+#[test]
+fn insert_missing_currency() {
+    let sources = BeancountSources::from(
+        "2024-01-01 * \"Lunch\"\n  Assets:Bank  -10 USD\n  Expenses:Food  10\n",
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let date = Date::from_calendar_date(2024, Month::January, 2).unwrap();
+    let errors = trial_balance(&directives, date).unwrap_err();
+
+    let fixes = errors[0].fixes();
+    assert_eq!(fixes.len(), 1);
+    assert_eq!(fixes[0].title(), "insert missing currency USD");
+    assert_eq!(
+        sources.apply_fixes(fixes),
+        vec![(
+            None,
+            "2024-01-01 * \"Lunch\"\n  Assets:Bank  -10 USD\n  Expenses:Food  10 USD\n".to_string()
+        )]
+    );
+}
+
+#[test]
+fn insert_closing_quote() {
+    let sources =
+        BeancountSources::from("2024-01-01 * \"Lunch  \n  Assets:Bank  -10 USD\n  Expenses:Food\n");
+    let parser = BeancountParser::new(&sources);
+    let errors = parser.parse().unwrap_err().errors;
+
+    let fixes = errors[0].fixes();
+    assert_eq!(fixes.len(), 1);
+    assert_eq!(fixes[0].title(), "insert closing quote");
+    assert_eq!(
+        sources.apply_fixes(fixes),
+        vec![(
+            None,
+            "2024-01-01 * \"Lunch\"  \n  Assets:Bank  -10 USD\n  Expenses:Food\n".to_string()
+        )]
+    );
+}
@@ -0,0 +1,205 @@
+use crate::types::*;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+use time::Date;
+
+/// The pending items of a ledger, being transactions and postings flagged with `!` or a flag letter,
+/// for a "todo" view of what remains to be reconciled.
+///
+/// A flagged transaction is pending for each account to which it posts, and a flagged posting for its own account.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{Age, BeancountParser, BeancountSources, Flagged};
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Expenses:Food
+/// 2024-03-02 ! "Supermarket" "check receipt"
+///   Expenses:Food  85.20 NZD
+///   Assets:Bank
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let flagged = Flagged::new(directives.iter());
+/// let today = Date::from_calendar_date(2024, Month::March, 20).unwrap();
+///
+/// let by_account = flagged.by_account(today);
+/// let (account, ages) = by_account.iter().next().unwrap();
+/// assert_eq!(account.to_string(), "Assets:Bank");
+/// assert_eq!(ages[&Age::Month].len(), 1);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Flagged<'a> {
+    items: Vec<FlaggedItem<'a>>,
+}
+
+impl<'a> Flagged<'a> {
+    /// Collect the pending items from `directives`, of which only transactions are relevant, in date order.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut items = Vec::new();
+
+        for directive in directives {
+            if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+                let date = *directive.date().item();
+
+                if is_pending(transaction.flag().item()) {
+                    items.push(FlaggedItem {
+                        date,
+                        flag: *transaction.flag().item(),
+                        directive,
+                        transaction,
+                        posting: None,
+                    });
+                }
+
+                for posting in transaction.postings() {
+                    if let Some(flag) = posting.flag().filter(|flag| is_pending(flag.item())) {
+                        items.push(FlaggedItem {
+                            date,
+                            flag: *flag.item(),
+                            directive,
+                            transaction,
+                            posting: Some(posting),
+                        });
+                    }
+                }
+            }
+        }
+
+        // stable, so items on the same date remain in ledger order
+        items.sort_by_key(|item| item.date);
+
+        Flagged { items }
+    }
+
+    /// All pending items, in date order.
+    pub fn items(&self) -> impl ExactSizeIterator<Item = &FlaggedItem<'a>> {
+        self.items.iter()
+    }
+
+    /// The pending items of each account, by age as of `today`, in date order.
+    pub fn by_account(
+        &self,
+        today: Date,
+    ) -> BTreeMap<&'a Account<'a>, BTreeMap<Age, Vec<&FlaggedItem<'a>>>> {
+        let mut by_account = BTreeMap::<_, BTreeMap<_, Vec<_>>>::new();
+
+        for item in self.items.iter() {
+            let mut accounts = item.accounts();
+            // a transaction may post to an account more than once
+            accounts.sort();
+            accounts.dedup();
+            for account in accounts {
+                by_account
+                    .entry(account)
+                    .or_default()
+                    .entry(item.age(today))
+                    .or_default()
+                    .push(item);
+            }
+        }
+
+        by_account
+    }
+}
+
+// the asterisk is complete, and the other symbols are not conventionally used for pending items
+fn is_pending(flag: &Flag) -> bool {
+    matches!(flag, Flag::Exclamation | Flag::Letter(_))
+}
+
+/// A pending transaction or posting, see [Flagged].
+#[derive(Clone, Debug)]
+pub struct FlaggedItem<'a> {
+    date: Date,
+    flag: Flag,
+    directive: &'a Spanned<Directive<'a>>,
+    transaction: &'a Transaction<'a>,
+    posting: Option<&'a Spanned<Posting<'a>>>,
+}
+
+impl<'a> FlaggedItem<'a> {
+    /// Field accessor.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// Field accessor.
+    pub fn flag(&self) -> Flag {
+        self.flag
+    }
+
+    /// The transaction directive, whether it is the transaction or one of its postings which is flagged.
+    pub fn directive(&self) -> &'a Spanned<Directive<'a>> {
+        self.directive
+    }
+
+    /// Field accessor.
+    pub fn transaction(&self) -> &'a Transaction<'a> {
+        self.transaction
+    }
+
+    /// The flagged posting, or `None` if it is the transaction which is flagged.
+    pub fn posting(&self) -> Option<&'a Spanned<Posting<'a>>> {
+        self.posting
+    }
+
+    /// The number of days since the item's date, as of `today`.
+    pub fn days(&self, today: Date) -> i64 {
+        (today - self.date).whole_days()
+    }
+
+    /// How long the item has been pending, as of `today`.
+    pub fn age(&self, today: Date) -> Age {
+        match self.days(today) {
+            ..=7 => Age::Week,
+            8..=31 => Age::Month,
+            32..=92 => Age::Quarter,
+            _ => Age::Older,
+        }
+    }
+
+    fn accounts(&self) -> Vec<&'a Account<'a>> {
+        match self.posting {
+            Some(posting) => vec![posting.account().item()],
+            None => self
+                .transaction
+                .postings()
+                .map(|posting| posting.account().item())
+                .collect(),
+        }
+    }
+}
+
+impl Display for FlaggedItem<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.date, self.flag)?;
+        if let Some(posting) = self.posting {
+            write!(f, " {}", posting.account())?;
+        }
+        if let Some(payee) = self.transaction.payee() {
+            write!(f, " \"{}\"", payee)?;
+        }
+        if let Some(narration) = self.transaction.narration() {
+            write!(f, " \"{}\"", narration)?;
+        }
+        Ok(())
+    }
+}
+
+/// How long an item has been pending, as up to a week, a month, a quarter, or longer, see [FlaggedItem::age].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Age {
+    Week,
+    Month,
+    Quarter,
+    Older,
+}
+
+mod tests;
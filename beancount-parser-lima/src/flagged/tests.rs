@@ -0,0 +1,91 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Travel
+
+2024-01-05 ! "Airline" "refund due"
+  Expenses:Travel  -300.00 NZD
+  Assets:Bank
+
+2024-03-01 * "Supermarket"
+  ! Expenses:Food  85.20 NZD
+  Assets:Bank
+
+2024-03-10 'P "Supermarket"
+  Expenses:Food  20.00 NZD
+  Assets:Bank
+
+2024-03-12 * "Cafe"
+  Expenses:Food  5.00 NZD
+  Assets:Bank
+"#;
+
+#[test]
+fn flagged_items() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let flagged = Flagged::new(directives.iter());
+
+    assert_eq!(
+        flagged
+            .items()
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            r#"2024-01-05 ! "Airline" "refund due""#,
+            r#"2024-03-01 ! Expenses:Food "Supermarket""#,
+            r#"2024-03-10 'P "Supermarket""#,
+        ]
+    );
+}
+
+#[test]
+fn flagged_by_account_and_age() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let flagged = Flagged::new(directives.iter());
+
+    let by_account = flagged
+        .by_account(date("2024-03-15"))
+        .into_iter()
+        .map(|(account, ages)| {
+            (
+                account.to_string(),
+                ages.into_iter()
+                    .map(|(age, items)| (age, items.iter().map(|item| item.date()).collect()))
+                    .collect::<Vec<(Age, Vec<Date>)>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        by_account,
+        vec![
+            (
+                "Assets:Bank".to_string(),
+                vec![
+                    (Age::Week, vec![date("2024-03-10")]),
+                    (Age::Quarter, vec![date("2024-01-05")]),
+                ]
+            ),
+            (
+                "Expenses:Food".to_string(),
+                vec![
+                    (Age::Week, vec![date("2024-03-10")]),
+                    (Age::Month, vec![date("2024-03-01")]),
+                ]
+            ),
+            (
+                "Expenses:Travel".to_string(),
+                vec![(Age::Quarter, vec![date("2024-01-05")])]
+            ),
+        ]
+    );
+}
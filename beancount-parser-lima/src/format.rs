@@ -1,5 +1,8 @@
 use lazy_format::lazy_format;
-use std::fmt::{self, Display, Formatter};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+};
 
 pub fn format<C, T, M, D>(
     f: &mut Formatter<'_>,
@@ -67,6 +70,17 @@ where
     lazy_format!("\"{s}\"")
 }
 
+/// Format as a string literal, in double quotes with any double quotes or backslashes escaped.
+pub fn string_literal<S>(s: S) -> impl Display
+where
+    S: Display,
+{
+    lazy_format!(
+        "\"{}\"",
+        s.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
 /// Format key/value.
 pub fn key_value<K, V>(kv: (K, V)) -> impl Display
 where
@@ -76,6 +90,32 @@ where
     lazy_format!("{}: {}", kv.0, kv.1)
 }
 
+/// Collect the items of an unordered container in order, so that formatting is deterministic.
+pub fn sorted_by<C, T, F>(container: C, compare: F) -> Vec<T>
+where
+    C: IntoIterator<Item = T>,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut items = container.into_iter().collect::<Vec<_>>();
+    items.sort_by(compare);
+    items
+}
+
+/// Escape text by replacing each character for which `escaped` gives a replacement.
+pub(crate) fn escape<F>(text: &str, escaped: F) -> String
+where
+    F: Fn(char) -> Option<&'static str>,
+{
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match escaped(c) {
+            Some(replacement) => result.push_str(replacement),
+            None => result.push(c),
+        }
+    }
+    result
+}
+
 fn pad_if(condition: bool) -> &'static str {
     if condition {
         " "
@@ -88,3 +128,4 @@ pub const SPACE: &str = " ";
 pub const NEWLINE: &str = "\n";
 pub const INDENT: &str = "  ";
 pub const NEWLINE_INDENT: &str = "\n  ";
+pub const NEWLINE_INDENT_INDENT: &str = "\n    ";
@@ -0,0 +1,88 @@
+use crate::{
+    booking::{book, Inventory, Position},
+    prices::PriceDb,
+    trial_balance::Units,
+    types::*,
+    Options,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use time::Date;
+
+/// Book all transactions on or before `date`, and return the remaining positions in each account,
+/// including the individual lots held at cost.
+pub fn holdings<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    date: Date,
+) -> Result<Holdings<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    book(directives, options, date).map(|inventories| Holdings {
+        date,
+        accounts: inventories
+            .into_iter()
+            .map(|(account, inventory)| (account.clone(), inventory))
+            .collect(),
+    })
+}
+
+/// The positions held in each account as of a date, see [holdings].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Holdings<'a> {
+    date: Date,
+    accounts: BTreeMap<Account<'a>, Inventory<'a>>,
+}
+
+impl<'a> Holdings<'a> {
+    /// Field accessor.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// The accounts with any positions, in order of account.
+    pub fn accounts(&self) -> impl Iterator<Item = (&Account<'a>, &Inventory<'a>)> {
+        self.accounts.iter()
+    }
+
+    /// The inventory for the given account, if it has any positions.
+    pub fn get(&self, account: &Account<'a>) -> Option<&Inventory<'a>> {
+        self.accounts.get(account)
+    }
+
+    /// All positions, with the account in which each is held.
+    pub fn positions(&self) -> impl Iterator<Item = (&Account<'a>, &Position<'a>)> {
+        self.accounts
+            .iter()
+            .flat_map(|(account, inventory)| inventory.positions().map(move |p| (account, p)))
+    }
+
+    /// Only those holdings in accounts whose full name matches `account_regex`.
+    pub fn filter(&self, account_regex: &Regex) -> Holdings<'a> {
+        Holdings {
+            date: self.date,
+            accounts: self
+                .accounts
+                .iter()
+                .filter(|(account, _)| account_regex.is_match(&account.to_string()))
+                .map(|(account, inventory)| (account.clone(), inventory.clone()))
+                .collect(),
+        }
+    }
+
+    /// The total value of all positions in `target` currency at market price as of the date of the holdings,
+    /// leaving unchanged any units for which there is no price.
+    pub fn market_value(&self, prices: &PriceDb<'a>, target: Currency<'a>) -> Units<'a> {
+        let mut total = Units::default();
+        for (_, position) in self.positions() {
+            match position.market_value(prices, target, self.date) {
+                Some(value) => total.add(target, value),
+                None => total.add(position.currency(), position.units()),
+            }
+        }
+        total
+    }
+}
+
+mod tests;
@@ -0,0 +1,82 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Brokerage:Hooli
+2024-01-01 open Assets:Brokerage:Cash
+2024-01-01 open Assets:Pension
+2024-01-01 open Income:Salary
+
+2024-01-02 * "Buy"
+  Assets:Brokerage:Hooli  10 HOOL {100 USD}
+  Assets:Brokerage:Cash  -1000 USD
+
+2024-01-03 * "Contribution"
+  Assets:Pension  4 HOOL {101 USD}
+  Income:Salary  -404 USD
+
+2024-06-01 price HOOL 150 USD
+"#;
+
+fn check_holdings<F>(source: &str, check: F)
+where
+    F: FnOnce(Holdings<'_>, PriceDb<'_>),
+{
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let date = Date::from_calendar_date(2024, time::Month::June, 30).unwrap();
+
+    check(
+        holdings(&success.directives, &success.options, date).unwrap(),
+        PriceDb::new(&success.directives),
+    );
+}
+
+fn accounts(holdings: &Holdings<'_>) -> Vec<String> {
+    holdings
+        .accounts()
+        .map(|(account, _)| account.to_string())
+        .collect()
+}
+
+#[test]
+fn holdings_lots() {
+    check_holdings(LEDGER, |holdings, _| {
+        assert_eq!(
+            accounts(&holdings),
+            vec![
+                "Assets:Brokerage:Cash",
+                "Assets:Brokerage:Hooli",
+                "Assets:Pension",
+                "Income:Salary"
+            ]
+        );
+
+        let (_, lot) = holdings
+            .positions()
+            .find(|(account, _)| account.to_string() == "Assets:Pension")
+            .unwrap();
+        let cost = lot.cost().unwrap();
+        assert_eq!(lot.units(), dec!(4));
+        assert_eq!(cost.per_unit(), dec!(101));
+        assert_eq!(cost.date().to_string(), "2024-01-03");
+        assert_eq!(cost.label(), None);
+    });
+}
+
+#[test]
+fn holdings_filtered_and_valued() {
+    check_holdings(LEDGER, |holdings, prices| {
+        let brokerage = holdings.filter(&Regex::new("^Assets:Brokerage:").unwrap());
+        assert_eq!(
+            accounts(&brokerage),
+            vec!["Assets:Brokerage:Cash", "Assets:Brokerage:Hooli"]
+        );
+
+        let usd = Currency::try_from("USD").unwrap();
+        assert_eq!(brokerage.market_value(&prices, usd).to_string(), "500 USD");
+    });
+}
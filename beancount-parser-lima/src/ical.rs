@@ -0,0 +1,240 @@
+use crate::{format, types::*};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+use time::Date;
+
+/// An exporter of ledger milestones as an iCalendar feed, as specified by RFC 5545,
+/// so that they can be seen alongside everything else in a calendar application.
+///
+/// Each `event` directive becomes an all-day calendar event.  Optionally, balance assertions on or after a given date
+/// are included, along with the next assertion expected for each account whose assertions recur regularly.
+/// The UID of each event is derived from its date and what it is for, such as the account of a balance assertion,
+/// so that calendar subscriptions are not disturbed as the ledger grows.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, ICalExport};
+///
+/// let sources = BeancountSources::from(r#"2024-03-01 event "location" "Tokyo"
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let ical = ICalExport::new(directives.iter()).generate();
+///
+/// assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+/// assert!(ical.contains("DTSTART;VALUE=DATE:20240301\r\nSUMMARY:location: Tokyo\r\n"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ICalExport<'a> {
+    directives: Vec<&'a Spanned<Directive<'a>>>,
+    name: Option<String>,
+    balances_from: Option<Date>,
+}
+
+impl<'a> ICalExport<'a> {
+    /// Export from `directives`, of which only `event` and `balance` directives are relevant.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        ICalExport {
+            directives: directives.into_iter().collect(),
+            name: None,
+            balances_from: None,
+        }
+    }
+
+    /// The name of the calendar, as shown by calendar applications.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Include balance assertions dated on or after `from`, and the projected next assertion for each account
+    /// whose last three assertions were equally spaced, whether by days or by calendar months, if that is on or after `from`.
+    pub fn balances(mut self, from: Date) -> Self {
+        self.balances_from = Some(from);
+        self
+    }
+
+    /// Generate the iCalendar feed.
+    pub fn generate(&self) -> String {
+        let mut ical = String::new();
+
+        write_line(&mut ical, "BEGIN:VCALENDAR");
+        write_line(&mut ical, "VERSION:2.0");
+        write_line(&mut ical, "PRODID:-//beancount-parser-lima//ICalExport//EN");
+        if let Some(name) = &self.name {
+            write_line(&mut ical, &format!("X-WR-CALNAME:{}", escape(name)));
+        }
+
+        let mut events = Vec::new();
+        for directive in self.directives.iter() {
+            let date = *directive.date().item();
+
+            if let DirectiveVariant::Event(event) = directive.variant() {
+                events.push((
+                    date,
+                    format!("event-{}", event.event_type().item()),
+                    format!(
+                        "{}: {}",
+                        event.event_type().item(),
+                        event.description().item()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(from) = self.balances_from {
+            events.extend(self.balance_dates(from));
+        }
+
+        // UIDs are derived from content rather than position, so that they are stable as the ledger grows,
+        // with an occurrence count distinguishing events with the same key on the same date
+        let mut occurrences = HashMap::<(Date, &str), usize>::new();
+        for (date, key, summary) in events.iter() {
+            let occurrence = occurrences.entry((*date, key)).or_default();
+            write_event(&mut ical, key, *occurrence, *date, summary);
+            *occurrence += 1;
+        }
+
+        write_line(&mut ical, "END:VCALENDAR");
+        ical
+    }
+
+    /// Generate the iCalendar feed, writing it to `w`.
+    pub fn write<W>(&self, mut w: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        w.write_all(self.generate().as_bytes())
+    }
+
+    // actual balance assertions from `from`, then those projected for each account, in order of account,
+    // each with the key of its UID and its summary
+    fn balance_dates(&self, from: Date) -> Vec<(Date, String, String)> {
+        let mut dates = Vec::new();
+        let mut by_account = Vec::<(String, Vec<Date>)>::new();
+
+        for directive in self.directives.iter() {
+            if let DirectiveVariant::Balance(balance) = directive.variant() {
+                let date = *directive.date().item();
+                let account = balance.account().item().to_string();
+
+                if date >= from {
+                    let amount = balance.atol().amount();
+                    dates.push((
+                        date,
+                        format!("balance-{}-{}", account, amount.currency().item()),
+                        format!("balance {} {}", account, amount),
+                    ));
+                }
+
+                match by_account.iter_mut().find(|(a, _)| *a == account) {
+                    Some((_, account_dates)) => account_dates.push(date),
+                    None => by_account.push((account, vec![date])),
+                }
+            }
+        }
+
+        by_account.sort();
+        for (account, mut account_dates) in by_account {
+            account_dates.sort();
+            account_dates.dedup();
+
+            if let [.., first, second, last] = account_dates.as_slice() {
+                if let Some(next) = next_in_sequence(*first, *second, *last) {
+                    if next >= from {
+                        dates.push((
+                            next,
+                            format!("balance-due-{}", account),
+                            format!("balance due {}", account),
+                        ));
+                    }
+                }
+            }
+        }
+
+        dates
+    }
+}
+
+// the date following three equally spaced dates, either by days, or by months on the same day of the month
+fn next_in_sequence(first: Date, second: Date, last: Date) -> Option<Date> {
+    let months = |from: Date, to: Date| {
+        (from.day() == to.day())
+            .then_some((to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32)
+    };
+
+    if second - first == last - second {
+        last.checked_add(last - second)
+    } else {
+        let interval = months(second, last).filter(|n| *n > 0)?;
+        (months(first, second)? == interval)
+            .then(|| {
+                let month = last.month() as i32 - 1 + interval;
+                Date::from_calendar_date(
+                    last.year() + month / 12,
+                    ((month % 12 + 1) as u8).try_into().unwrap(),
+                    last.day(),
+                )
+                .ok()
+            })
+            .flatten()
+    }
+}
+
+fn write_event(ical: &mut String, key: &str, occurrence: usize, date: Date, summary: &str) {
+    let date = format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    );
+
+    write_line(ical, "BEGIN:VEVENT");
+    write_line(
+        ical,
+        &format!(
+            "UID:{}-{}.{}@beancount-parser-lima",
+            date,
+            escape(key),
+            occurrence
+        ),
+    );
+    // a fixed stamp keeps the feed reproducible
+    write_line(ical, &format!("DTSTAMP:{}T000000Z", date));
+    write_line(ical, &format!("DTSTART;VALUE=DATE:{}", date));
+    write_line(ical, &format!("SUMMARY:{}", escape(summary)));
+    write_line(ical, "END:VEVENT");
+}
+
+// lines are folded at 75 octets, without splitting any character, and terminated by CRLF
+fn write_line(ical: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ical.push_str("\r\n ");
+            // the leading space counts towards the width
+            width = 1;
+        }
+        ical.push(c);
+        width += c.len_utf8();
+    }
+    ical.push_str("\r\n");
+}
+
+fn escape(text: &str) -> String {
+    format::escape(text, |c| match c {
+        '\\' => Some("\\\\"),
+        ';' => Some("\\;"),
+        ',' => Some("\\,"),
+        '\n' => Some("\\n"),
+        '\r' => Some(""),
+        _ => None,
+    })
+}
+
+mod tests;
@@ -0,0 +1,158 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Cash
+
+2024-01-01 event "location" "Wellington, NZ"
+2024-03-01 event "location" "Tokyo"
+
+2024-01-01 balance Assets:Bank  0 NZD
+2024-02-01 balance Assets:Bank  0 NZD
+2024-03-01 balance Assets:Bank  0 NZD
+2024-02-15 balance Assets:Cash  0 NZD
+2024-03-01 balance Assets:Cash  0 NZD
+"#;
+
+// the start date and summary of each event in the feed
+fn events(ical: &str) -> Vec<String> {
+    let lines = ical.split("\r\n").collect::<Vec<_>>();
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            line.strip_prefix("DTSTART;VALUE=DATE:").map(|start| {
+                format!(
+                    "{} {}",
+                    start,
+                    lines[i + 1].strip_prefix("SUMMARY:").unwrap()
+                )
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn events_only() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let ical = ICalExport::new(directives.iter()).name("Ledger").generate();
+
+    assert!(ical.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ical.ends_with("END:VCALENDAR\r\n"));
+    assert!(ical.contains("\r\nX-WR-CALNAME:Ledger\r\n"));
+    assert_eq!(ical.matches("BEGIN:VEVENT").count(), 2);
+    assert_eq!(
+        events(&ical),
+        vec![
+            r"20240101 location: Wellington\, NZ",
+            "20240301 location: Tokyo"
+        ]
+    );
+}
+
+#[test]
+fn with_balances() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let ical = ICalExport::new(directives.iter())
+        .balances(date("2024-02-10"))
+        .generate();
+
+    // Assets:Cash has only two assertions, so is not projected
+    assert_eq!(
+        events(&ical),
+        vec![
+            r"20240101 location: Wellington\, NZ",
+            "20240301 location: Tokyo",
+            "20240215 balance Assets:Cash 0 NZD",
+            "20240301 balance Assets:Bank 0 NZD",
+            "20240301 balance Assets:Cash 0 NZD",
+            "20240401 balance due Assets:Bank",
+        ]
+    );
+}
+
+#[test]
+fn sequences() {
+    let sequence = |dates: [&str; 3]| {
+        let [first, second, last] = dates.map(date);
+        next_in_sequence(first, second, last)
+    };
+
+    assert_eq!(
+        sequence(["2024-01-01", "2024-01-08", "2024-01-15"]),
+        Some(date("2024-01-22"))
+    );
+    assert_eq!(
+        sequence(["2024-06-30", "2024-09-30", "2024-12-30"]),
+        Some(date("2025-03-30"))
+    );
+    assert_eq!(sequence(["2024-01-01", "2024-02-01", "2024-04-01"]), None);
+    assert_eq!(
+        sequence(["2024-01-31", "2024-03-31", "2024-05-31"]),
+        Some(date("2024-07-31"))
+    );
+    assert_eq!(sequence(["9999-12-25", "9999-12-28", "9999-12-31"]), None);
+    assert_eq!(sequence(["9999-08-15", "9999-10-15", "9999-12-15"]), None);
+}
+
+// the UID of each event in the feed
+fn uids(ical: &str) -> Vec<&str> {
+    ical.split("\r\n")
+        .filter_map(|line| line.strip_prefix("UID:"))
+        .collect()
+}
+
+#[test]
+fn uids_stable_as_ledger_grows() {
+    let generate = |ledger: &str| {
+        let sources = BeancountSources::from(ledger);
+        let parser = BeancountParser::new(&sources);
+        let directives = parser.parse().unwrap().directives;
+        ICalExport::new(directives.iter())
+            .balances(date("2024-01-01"))
+            .generate()
+    };
+    let before = generate(LEDGER);
+    let after = generate(&format!(
+        "2023-12-01 event \"location\" \"Auckland\"\n{}2024-03-01 event \"location\" \"Kyoto\"\n",
+        LEDGER
+    ));
+
+    assert_eq!(
+        uids(&before),
+        vec![
+            "20240101-event-location.0@beancount-parser-lima",
+            "20240301-event-location.0@beancount-parser-lima",
+            "20240101-balance-Assets:Bank-NZD.0@beancount-parser-lima",
+            "20240201-balance-Assets:Bank-NZD.0@beancount-parser-lima",
+            "20240215-balance-Assets:Cash-NZD.0@beancount-parser-lima",
+            "20240301-balance-Assets:Bank-NZD.0@beancount-parser-lima",
+            "20240301-balance-Assets:Cash-NZD.0@beancount-parser-lima",
+            "20240401-balance-due-Assets:Bank.0@beancount-parser-lima",
+        ]
+    );
+    assert!(uids(&before).iter().all(|uid| uids(&after).contains(uid)));
+    assert!(uids(&after).contains(&"20240301-event-location.1@beancount-parser-lima"));
+}
+
+#[test]
+fn long_lines_folded() {
+    let description = "é".repeat(50);
+    let source = format!("2024-01-01 event \"travel\" \"{}\"\n", description);
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let ical = ICalExport::new(directives.iter()).generate();
+
+    assert!(ical.split("\r\n").all(|line| line.len() <= 75));
+    assert!(ical
+        .replace("\r\n ", "")
+        .contains(&format!("SUMMARY:travel: {}\r\n", description)));
+}
@@ -0,0 +1,108 @@
+use crate::{types::*, BeancountSources};
+use chumsky::span::Span as _;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// An identity for a directive which survives re-parsing, for correlating directives between parses,
+/// for example by editor features and caches.
+///
+/// The identity comprises the name of the source containing the directive, its [Directive::content_hash],
+/// and a disambiguator counting earlier directives in the same source with the same content.
+/// Since the content hash is of the formatted directive, edits to whitespace or comments don't change the identity,
+/// nor do edits to other directives, except those which make them identical to this one.
+///
+/// As with the content hash, identities are not stable across releases, so should not be persisted.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Debug)]
+pub struct DirectiveId {
+    source: String,
+    content_hash: u64,
+    occurrence: usize,
+}
+
+impl DirectiveId {
+    /// The name of the source containing the directive, as given by [BeancountSources::source_name].
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The content hash of the directive.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// The number of earlier directives in the same source with the same content.
+    pub fn occurrence(&self) -> usize {
+        self.occurrence
+    }
+}
+
+impl Display for DirectiveId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}#{:016x}.{}",
+            self.source, self.content_hash, self.occurrence
+        )
+    }
+}
+
+/// The [DirectiveId] of each of a list of directives.
+#[derive(Clone, Default, Debug)]
+pub struct DirectiveIds {
+    ids: Vec<DirectiveId>,
+    indices: HashMap<DirectiveId, usize>,
+}
+
+impl DirectiveIds {
+    /// Identify each of `directives`, as parsed from `sources`.
+    ///
+    /// Directives with the same content in the same source are disambiguated in the order given,
+    /// which for parsed directives is source order.
+    pub fn new<'a, I>(sources: &BeancountSources, directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut occurrences = HashMap::<(&str, u64), usize>::new();
+        let ids = directives
+            .into_iter()
+            .map(|directive| {
+                let source = sources.source_name(directive.span().context());
+                let content_hash = directive.content_hash();
+                let occurrence = occurrences.entry((source, content_hash)).or_default();
+                let id = DirectiveId {
+                    source: source.to_string(),
+                    content_hash,
+                    occurrence: *occurrence,
+                };
+                *occurrence += 1;
+                id
+            })
+            .collect::<Vec<_>>();
+        let indices = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect();
+
+        DirectiveIds { ids, indices }
+    }
+
+    /// The identities, in the same order as the directives.
+    pub fn ids(&self) -> &[DirectiveId] {
+        &self.ids
+    }
+
+    /// The identity of the directive at `index`.
+    pub fn get(&self, index: usize) -> Option<&DirectiveId> {
+        self.ids.get(index)
+    }
+
+    /// The index of the directive with identity `id`, if any.
+    pub fn index_of(&self, id: &DirectiveId) -> Option<usize> {
+        self.indices.get(id).copied()
+    }
+}
+
+mod tests;
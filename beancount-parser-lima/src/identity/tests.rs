@@ -0,0 +1,62 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+const LEDGER: &str = r#"2024-01-01 open Assets:Bank NZD
+2024-01-01 open Expenses:Coffee
+
+2024-01-02 * "Cafe" "coffee"
+  Expenses:Coffee  5.00 NZD
+  Assets:Bank
+
+2024-01-02 * "Cafe" "coffee"
+  Expenses:Coffee  5.00 NZD
+  Assets:Bank
+"#;
+
+// the same, but with different whitespace and comments, and an extra directive
+const EDITED_LEDGER: &str = r#"2024-01-01 open   Assets:Bank NZD
+2024-01-01 open Expenses:Coffee
+2024-01-01 open Expenses:Tea
+
+; morning
+2024-01-02 * "Cafe"    "coffee"
+  Expenses:Coffee      5.00 NZD
+  Assets:Bank
+
+2024-01-02 * "Cafe" "coffee"
+  Expenses:Coffee  5.00 NZD
+  Assets:Bank
+"#;
+
+#[test]
+fn directive_ids_survive_whitespace_edits() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let ids = DirectiveIds::new(&sources, &directives);
+
+    let edited_sources = BeancountSources::from(EDITED_LEDGER);
+    let edited_parser = BeancountParser::new(&edited_sources);
+    let edited_directives = edited_parser.parse().unwrap().directives;
+    let edited_ids = DirectiveIds::new(&edited_sources, &edited_directives);
+
+    assert_eq!(ids.ids().len(), 4);
+    assert_eq!(ids.ids()[2].content_hash(), ids.ids()[3].content_hash());
+    assert_eq!(
+        ids.ids()
+            .iter()
+            .map(|id| id.occurrence())
+            .collect::<Vec<_>>(),
+        vec![0, 0, 0, 1]
+    );
+
+    assert_eq!(
+        ids.ids()
+            .iter()
+            .map(|id| edited_ids.index_of(id))
+            .collect::<Vec<_>>(),
+        vec![Some(0), Some(1), Some(3), Some(4)]
+    );
+    assert_eq!(edited_ids.get(2).unwrap().source(), "inline");
+}
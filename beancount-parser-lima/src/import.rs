@@ -0,0 +1,172 @@
+use crate::{merge::ExactDuplicates, types::*};
+use std::collections::BTreeMap;
+use time::{Date, Duration};
+
+/// Support for tools which import directives into a ledger, such as from bank statements,
+/// see also [Categorizer](crate::Categorizer).
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Importer};
+///
+/// let existing = BeancountSources::from(r#"
+/// 2024-01-05 * "Countdown" "groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// "#);
+/// let imported = BeancountSources::from(r#"
+/// 2024-01-06 * "COUNTDOWN NEWMARKET"
+///   Assets:Bank  -42.50 NZD
+/// 2024-01-07 * "Z Energy"
+///   Assets:Bank  -80.00 NZD
+/// "#);
+/// let existing_parser = BeancountParser::new(&existing);
+/// let imported_parser = BeancountParser::new(&imported);
+/// let existing = existing_parser.parse().unwrap().directives;
+/// let imported = imported_parser.parse().unwrap().directives;
+///
+/// let candidates = Importer::default().dedupe(imported, &existing);
+///
+/// assert!(!candidates[0].is_new());
+/// assert!(candidates[1].is_new());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Importer {
+    date_window: Duration,
+}
+
+/// An imported directive, with the existing one of which it is a duplicate, if any, see [Importer::dedupe].
+#[derive(Clone, Debug)]
+pub struct ImportCandidate<'a> {
+    pub directive: Spanned<Directive<'a>>,
+    pub duplicate: Option<Duplicate>,
+}
+
+/// How an imported directive duplicates an existing one, whose span is given.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Duplicate {
+    /// Identical in content, as determined by [Directive::content_hash] and then equality.
+    Exact(Span),
+    /// A transaction within the date window with a posting of the same amount to the same account,
+    /// as when the bank's description differs from that recorded in the ledger.
+    Fuzzy(Span),
+}
+
+impl Default for Importer {
+    fn default() -> Self {
+        Importer {
+            date_window: Duration::days(3),
+        }
+    }
+}
+
+impl Importer {
+    /// Match transactions whose dates differ by at most `days`, by default 3, allowing for clearing delays.
+    pub fn date_window(mut self, days: u16) -> Self {
+        self.date_window = Duration::days(days.into());
+        self
+    }
+
+    /// Mark those of `new_directives` which are already present in `existing_ledger`, returning them in the same order.
+    ///
+    /// Each existing directive is the duplicate of at most one new one, so that repeated transactions,
+    /// such as two coffees on the same day, are only considered duplicates if both are present.
+    /// Exact duplicates are found first, and otherwise the fuzzy match closest in date.
+    pub fn dedupe<'a, 'e, 'b, I, J>(
+        &self,
+        new_directives: I,
+        existing_ledger: J,
+    ) -> Vec<ImportCandidate<'a>>
+    where
+        I: IntoIterator<Item = Spanned<Directive<'a>>>,
+        J: IntoIterator<Item = &'e Spanned<Directive<'b>>>,
+        'b: 'e,
+    {
+        let existing = existing_ledger.into_iter().collect::<Vec<_>>();
+        let mut exact = ExactDuplicates::new(existing.iter().copied());
+        let mut transactions_by_date = BTreeMap::<Date, Vec<usize>>::new();
+        for (i, directive) in existing.iter().enumerate() {
+            if matches!(directive.variant(), DirectiveVariant::Transaction(_)) {
+                transactions_by_date
+                    .entry(*directive.date().item())
+                    .or_default()
+                    .push(i);
+            }
+        }
+
+        let new_directives = new_directives.into_iter().collect::<Vec<_>>();
+
+        // exact duplicates take precedence over fuzzy ones, wherever they occur
+        let mut duplicates = new_directives
+            .iter()
+            .map(|directive| {
+                exact
+                    .find(&existing, directive)
+                    .map(|i| Duplicate::Exact(existing[i].span))
+            })
+            .collect::<Vec<_>>();
+
+        for (directive, duplicate) in new_directives.iter().zip(duplicates.iter_mut()) {
+            if duplicate.is_none() {
+                let date = *directive.date().item();
+                let earliest = date.saturating_sub(self.date_window);
+                let latest = date.saturating_add(self.date_window);
+
+                let closest = transactions_by_date
+                    .range(earliest..=latest)
+                    .flat_map(|(existing_date, candidates)| {
+                        candidates.iter().map(move |&i| (*existing_date, i))
+                    })
+                    .filter(|&(_, i)| !exact.matched[i] && share_posting(directive, existing[i]))
+                    .min_by_key(|&(existing_date, _)| (existing_date - date).abs());
+
+                if let Some((_, i)) = closest {
+                    exact.matched[i] = true;
+                    *duplicate = Some(Duplicate::Fuzzy(existing[i].span));
+                }
+            }
+        }
+
+        new_directives
+            .into_iter()
+            .zip(duplicates)
+            .map(|(directive, duplicate)| ImportCandidate {
+                directive,
+                duplicate,
+            })
+            .collect()
+    }
+}
+
+impl<'a> ImportCandidate<'a> {
+    /// Whether the directive is genuinely new, that is, not a duplicate.
+    pub fn is_new(&self) -> bool {
+        self.duplicate.is_none()
+    }
+}
+
+// whether both are transactions with a posting of the same amount to the same account
+fn share_posting(directive: &Directive<'_>, other: &Directive<'_>) -> bool {
+    match (directive.variant(), other.variant()) {
+        (DirectiveVariant::Transaction(transaction), DirectiveVariant::Transaction(other)) => {
+            transaction.postings().any(|posting| {
+                posting_amount(posting).is_some_and(|amount| {
+                    other.postings().any(|other_posting| {
+                        other_posting.account().item() == posting.account().item()
+                            && posting_amount(other_posting) == Some(amount)
+                    })
+                })
+            })
+        }
+        _ => false,
+    }
+}
+
+fn posting_amount<'p>(posting: &'p Posting<'_>) -> Option<(rust_decimal::Decimal, &'p str)> {
+    posting
+        .amount()
+        .zip(posting.currency())
+        .map(|(amount, currency)| (amount.value(), currency.item().as_ref()))
+}
+
+mod tests;
@@ -0,0 +1,95 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+use chumsky::span::Span as _;
+use test_case::test_case;
+
+const EXISTING: &str = r#"
+2024-01-01 open Assets:Bank
+
+2024-01-05 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  Expenses:Groceries
+
+2024-01-10 * "Cafe" "coffee"
+  Assets:Bank  -5.00 NZD
+  Expenses:Coffee
+"#;
+
+// the candidates, each described as `new`, `exact`, or `fuzzy` with the line of the existing duplicate
+fn dedupe(importer: Importer, imported: &str) -> Vec<String> {
+    let existing_sources = BeancountSources::from(EXISTING);
+    let imported_sources = BeancountSources::from(imported);
+    let existing_parser = BeancountParser::new(&existing_sources);
+    let imported_parser = BeancountParser::new(&imported_sources);
+    let existing = existing_parser.parse().unwrap().directives;
+    let imported = imported_parser.parse().unwrap().directives;
+
+    let line = |span: Span| EXISTING[..span.start()].lines().count() + 1;
+    importer
+        .dedupe(imported, &existing)
+        .into_iter()
+        .map(|candidate| match candidate.duplicate {
+            None => "new".to_string(),
+            Some(Duplicate::Exact(span)) => format!("exact {}", line(span)),
+            Some(Duplicate::Fuzzy(span)) => format!("fuzzy {}", line(span)),
+        })
+        .collect()
+}
+
+#[test_case(r#"
+2024-01-05 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  Expenses:Groceries
+"#, &["exact 4"]; "exact")]
+#[test_case(r#"
+2024-01-01 open Assets:Bank
+"#, &["exact 2"]; "exact non-transaction")]
+#[test_case(r#"
+2024-01-07 * "COUNTDOWN NEWMARKET"
+  Assets:Bank  -42.50 NZD
+"#, &["fuzzy 4"]; "fuzzy within window")]
+#[test_case(r#"
+2024-01-09 * "COUNTDOWN NEWMARKET"
+  Assets:Bank  -42.50 NZD
+"#, &["new"]; "fuzzy outside window")]
+#[test_case(r#"
+2024-01-05 * "COUNTDOWN NEWMARKET"
+  Assets:Bank  -42.51 NZD
+"#, &["new"]; "different amount")]
+#[test_case(r#"
+2024-01-05 * "COUNTDOWN NEWMARKET"
+  Assets:Savings  -42.50 NZD
+"#, &["new"]; "different account")]
+#[test_case(r#"
+2024-01-10 * "CAFE"
+  Assets:Bank  -5.00 NZD
+2024-01-10 * "CAFE"
+  Assets:Bank  -5.00 NZD
+"#, &["fuzzy 8", "new"]; "repeated transaction matched once")]
+#[test_case(r#"
+2024-01-09 * "CAFE"
+  Assets:Bank  -5.00 NZD
+2024-01-10 * "Cafe" "coffee"
+  Assets:Bank  -5.00 NZD
+  Expenses:Coffee
+"#, &["new", "exact 8"]; "exact takes precedence")]
+fn dedupe_candidates(imported: &str, expected: &[&str]) {
+    assert_eq!(dedupe(Importer::default(), imported), expected);
+}
+
+#[test]
+fn dedupe_date_window() {
+    let imported = r#"
+2024-01-09 * "COUNTDOWN NEWMARKET"
+  Assets:Bank  -42.50 NZD
+"#;
+    assert_eq!(
+        dedupe(Importer::default().date_window(4), imported),
+        vec!["fuzzy 4"]
+    );
+    assert_eq!(
+        dedupe(Importer::default().date_window(0), imported),
+        vec!["new"]
+    );
+}
@@ -0,0 +1,406 @@
+use crate::{format, types::*, writeback::write_atomically, BeancountSources};
+use chumsky::span::Span as _;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    ops::{Bound, Range, RangeBounds},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use time::{Date, Month};
+
+/// An index of directives by account, date, and payee, giving the location of each in its source file,
+/// which may be saved to disk and loaded without re-parsing, for fast cold-start queries on huge ledgers.
+///
+/// Only directives from files are indexed, since those from inline content cannot be read back.
+///
+/// # Examples
+/// ```ignore
+/// let index = match LedgerIndex::load(&index_path) {
+///     Ok(index) if index.is_current() => index,
+///     _ => {
+///         let sources = BeancountSources::try_from(ledger_path)?;
+///         let parser = BeancountParser::new(&sources);
+///         let index = LedgerIndex::new(&sources, &parser.parse()?.directives);
+///         index.save(&index_path)?;
+///         index
+///     }
+/// };
+///
+/// for entry in index.of_account("Assets:Bank") {
+///     println!("{}", index.read(entry)?);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LedgerIndex {
+    files: Vec<IndexedFile>,
+    entries: Vec<IndexEntry>, // in date order
+    accounts: BTreeMap<String, Vec<usize>>,
+    payees: BTreeMap<String, Vec<usize>>,
+}
+
+/// The location and indexed fields of a single directive, see [LedgerIndex].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct IndexEntry {
+    file: usize,
+    range: Range<usize>,
+    date: Date,
+    payee: Option<String>,
+    accounts: Vec<String>,
+}
+
+// a file as it was when indexed, for detecting a stale index
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct IndexedFile {
+    path: PathBuf,
+    len: u64,
+    modified: u128, // nanoseconds since the epoch
+}
+
+// first line of the index file, to be changed whenever the format changes
+const HEADER: &str = "beancount-parser-lima index 1";
+
+impl LedgerIndex {
+    /// Index `directives`, which were parsed from `sources`.
+    pub fn new<'d, 'a: 'd, I>(sources: &BeancountSources, directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'d Spanned<Directive<'a>>>,
+    {
+        let mut files = Vec::new();
+        let mut file_by_source_id = HashMap::new();
+        for (source_id, path, content) in sources.content_iter() {
+            if let Some(path) = path {
+                file_by_source_id.insert(Into::<usize>::into(source_id), files.len());
+                files.push(IndexedFile {
+                    path: path.to_path_buf(),
+                    len: content.len() as u64,
+                    modified: modified(path).unwrap_or_default(),
+                });
+            }
+        }
+
+        let mut entries = directives
+            .into_iter()
+            .filter_map(|directive| {
+                let span = directive.span();
+                file_by_source_id
+                    .get(&span.context().into())
+                    .map(|&file| IndexEntry {
+                        file,
+                        range: span.start()..span.end(),
+                        date: *directive.date().item(),
+                        payee: payee(directive).map(str::to_string),
+                        accounts: accounts(directive)
+                            .into_iter()
+                            .map(|account| account.to_string())
+                            .collect(),
+                    })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| (entry.date, entry.file, entry.range.start));
+
+        Self::from_parts(files, entries)
+    }
+
+    fn from_parts(files: Vec<IndexedFile>, entries: Vec<IndexEntry>) -> Self {
+        let mut accounts = BTreeMap::<String, Vec<usize>>::new();
+        let mut payees = BTreeMap::<String, Vec<usize>>::new();
+        for (i, entry) in entries.iter().enumerate() {
+            for account in entry.accounts.iter() {
+                let indices = accounts.entry(account.clone()).or_default();
+                // an account may appear more than once in a transaction
+                if indices.last() != Some(&i) {
+                    indices.push(i);
+                }
+            }
+            if let Some(payee) = &entry.payee {
+                payees.entry(payee.clone()).or_default().push(i);
+            }
+        }
+
+        LedgerIndex {
+            files,
+            entries,
+            accounts,
+            payees,
+        }
+    }
+
+    /// Load an index saved by [LedgerIndex::save].
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines().enumerate();
+        let invalid = |i: usize| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid index at line {}", i + 1),
+            )
+        };
+
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an index, or from an incompatible version",
+            ));
+        }
+
+        let mut files = Vec::new();
+        let mut entries = Vec::new();
+        for (i, line) in lines {
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("file") => {
+                    let file = (|| {
+                        Some(IndexedFile {
+                            len: fields.next()?.parse().ok()?,
+                            modified: fields.next()?.parse().ok()?,
+                            path: PathBuf::from(unescape(fields.next()?)?),
+                        })
+                    })()
+                    .ok_or_else(|| invalid(i))?;
+                    files.push(file);
+                }
+                Some("entry") => {
+                    let entry = (|| {
+                        let file = fields.next()?.parse().ok().filter(|&f| f < files.len())?;
+                        let start = fields.next()?.parse().ok()?;
+                        let end = fields.next()?.parse().ok()?;
+                        let date = parse_date(fields.next()?)?;
+                        let payee = match fields.next()? {
+                            "" => None,
+                            payee => Some(unescape(payee)?),
+                        };
+                        Some(IndexEntry {
+                            file,
+                            range: start..end,
+                            date,
+                            payee,
+                            accounts: fields.map(str::to_string).collect(),
+                        })
+                    })()
+                    .ok_or_else(|| invalid(i))?;
+                    entries.push(entry);
+                }
+                _ => return Err(invalid(i)),
+            }
+        }
+
+        Ok(Self::from_parts(files, entries))
+    }
+
+    /// Save the index to `path`, atomically replacing any existing file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut content = format!("{}\n", HEADER);
+        for file in self.files.iter() {
+            writeln!(
+                content,
+                "file\t{}\t{}\t{}",
+                file.len,
+                file.modified,
+                escape(&file.path.to_string_lossy())
+            )
+            .unwrap();
+        }
+        for entry in self.entries.iter() {
+            write!(
+                content,
+                "entry\t{}\t{}\t{}\t{}\t{}",
+                entry.file,
+                entry.range.start,
+                entry.range.end,
+                entry.date,
+                entry.payee.as_deref().map(escape).unwrap_or_default()
+            )
+            .unwrap();
+            for account in entry.accounts.iter() {
+                write!(content, "\t{}", account).unwrap();
+            }
+            content.push('\n');
+        }
+
+        write_atomically(path.as_ref(), &content)
+    }
+
+    /// Whether every indexed file is unchanged since the index was created, as far as can be told
+    /// from its length and modification time.
+    pub fn is_current(&self) -> bool {
+        self.files.iter().all(|file| {
+            fs::metadata(&file.path).is_ok_and(|metadata| metadata.len() == file.len)
+                && modified(&file.path).is_some_and(|modified| modified == file.modified)
+        })
+    }
+
+    /// The number of directives indexed.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no directives are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries, in date order.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// All accounts referred to by indexed directives, in order.
+    pub fn accounts(&self) -> impl Iterator<Item = &str> {
+        self.accounts.keys().map(String::as_str)
+    }
+
+    /// All payees of indexed transactions, in order.
+    pub fn payees(&self) -> impl Iterator<Item = &str> {
+        self.payees.keys().map(String::as_str)
+    }
+
+    /// Entries for directives referring to `account`, in date order.
+    pub fn of_account(&self, account: &str) -> impl Iterator<Item = &IndexEntry> {
+        self.of(self.accounts.get(account))
+    }
+
+    /// Entries for transactions with `payee`, in date order.
+    pub fn of_payee(&self, payee: &str) -> impl Iterator<Item = &IndexEntry> {
+        self.of(self.payees.get(payee))
+    }
+
+    fn of<'i>(&'i self, indices: Option<&'i Vec<usize>>) -> impl Iterator<Item = &'i IndexEntry> {
+        indices.into_iter().flatten().map(|&i| &self.entries[i])
+    }
+
+    /// Entries for directives dated within `dates`.
+    pub fn in_dates<R: RangeBounds<Date>>(&self, dates: R) -> &[IndexEntry] {
+        let start = match dates.start_bound() {
+            Bound::Included(date) => self.entries.partition_point(|entry| entry.date < *date),
+            Bound::Excluded(date) => self.entries.partition_point(|entry| entry.date <= *date),
+            Bound::Unbounded => 0,
+        };
+        let end = match dates.end_bound() {
+            Bound::Included(date) => self.entries.partition_point(|entry| entry.date <= *date),
+            Bound::Excluded(date) => self.entries.partition_point(|entry| entry.date < *date),
+            Bound::Unbounded => self.entries.len(),
+        };
+        &self.entries[start..end.max(start)]
+    }
+
+    /// The path of the file containing the directive of `entry`.
+    pub fn path(&self, entry: &IndexEntry) -> &Path {
+        &self.files[entry.file].path
+    }
+
+    /// Read the source text of the directive of `entry`, without reading the rest of its file.
+    pub fn read(&self, entry: &IndexEntry) -> io::Result<String> {
+        let mut file = File::open(self.path(entry))?;
+        file.seek(SeekFrom::Start(entry.range.start as u64))?;
+        let mut text = vec![0; entry.range.len()];
+        file.read_exact(&mut text)?;
+        String::from_utf8(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl IndexEntry {
+    /// The date of the directive.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// The byte range of the directive within its file, see [LedgerIndex::path].
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The payee, for a transaction which has one.
+    pub fn payee(&self) -> Option<&str> {
+        self.payee.as_deref()
+    }
+
+    /// The accounts to which the directive refers, in order of appearance, and possibly with repeats.
+    pub fn accounts(&self) -> impl Iterator<Item = &str> {
+        self.accounts.iter().map(String::as_str)
+    }
+}
+
+fn payee<'d>(directive: &'d Directive<'_>) -> Option<&'d str> {
+    match directive.variant() {
+        DirectiveVariant::Transaction(transaction) => {
+            transaction.payee().map(|payee| *payee.item())
+        }
+        _ => None,
+    }
+}
+
+// the accounts referenced by a directive
+pub(crate) fn accounts<'d>(directive: &'d Directive<'_>) -> Vec<&'d Account<'d>> {
+    use DirectiveVariant::*;
+
+    match directive.variant() {
+        Transaction(transaction) => transaction
+            .postings()
+            .map(|posting| posting.account().item())
+            .collect(),
+        Balance(balance) => vec![balance.account().item()],
+        Open(open) => vec![open.account().item()],
+        Close(close) => vec![close.account().item()],
+        Pad(pad) => vec![pad.account().item(), pad.source().item()],
+        Document(document) => vec![document.account().item()],
+        Note(note) => vec![note.account().item()],
+        Custom(custom) => custom
+            .values()
+            .iter()
+            .filter_map(|value| match value.item() {
+                MetaValue::Simple(SimpleValue::Account(account)) => Some(account),
+                _ => None,
+            })
+            .collect(),
+        Price(_) | Commodity(_) | Event(_) | Query(_) => Vec::new(),
+    }
+}
+
+fn modified(path: &Path) -> Option<u128> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_nanos())
+}
+
+fn parse_date(s: &str) -> Option<Date> {
+    let mut parts = s.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = Month::try_from(parts.next()?.parse::<u8>().ok()?).ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+// fields are separated by tabs and entries by newlines, so these must be escaped
+fn escape(s: &str) -> String {
+    format::escape(s, |c| match c {
+        '\\' => Some("\\\\"),
+        '\t' => Some("\\t"),
+        '\n' => Some("\\n"),
+        _ => None,
+    })
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.push(match chars.next()? {
+                '\\' => '\\',
+                't' => '\t',
+                'n' => '\n',
+                _ => return None,
+            });
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Some(unescaped)
+}
+
+mod tests;
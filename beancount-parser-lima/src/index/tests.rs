@@ -0,0 +1,156 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::BeancountParser;
+
+const MAIN: &str = r#"include "2024.beancount"
+2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Groceries
+"#;
+
+const YEAR: &str = r#"2024-03-01 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  Expenses:Groceries
+
+2024-02-01 * "Tab	and \\ backslash" "coffee"
+  Assets:Bank  -5.00 NZD
+  Expenses:Groceries
+
+2024-02-15 balance Assets:Bank  -5.00 NZD
+"#;
+
+// a fresh ledger directory, with the main file and one it includes
+fn ledger_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-index-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.beancount"), MAIN).unwrap();
+    fs::write(dir.join("2024.beancount"), YEAR).unwrap();
+    dir
+}
+
+fn index_ledger(dir: &Path) -> LedgerIndex {
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    LedgerIndex::new(&sources, &directives)
+}
+
+fn dates<'i, I>(entries: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'i IndexEntry>,
+{
+    entries
+        .into_iter()
+        .map(|entry| entry.date().to_string())
+        .collect()
+}
+
+#[test]
+fn index_queries() {
+    let dir = ledger_dir("queries");
+    let index = index_ledger(&dir);
+
+    assert_eq!(index.len(), 5);
+    assert_eq!(
+        dates(index.entries()),
+        vec![
+            "2024-01-01",
+            "2024-01-01",
+            "2024-02-01",
+            "2024-02-15",
+            "2024-03-01"
+        ]
+    );
+    assert_eq!(
+        index.accounts().collect::<Vec<_>>(),
+        vec!["Assets:Bank", "Expenses:Groceries"]
+    );
+    assert_eq!(
+        index.payees().collect::<Vec<_>>(),
+        vec!["Countdown", "Tab\tand \\ backslash"]
+    );
+    assert_eq!(
+        dates(index.of_account("Assets:Bank")),
+        vec!["2024-01-01", "2024-02-01", "2024-02-15", "2024-03-01"]
+    );
+    assert_eq!(dates(index.of_account("Assets:Cash")), Vec::<String>::new());
+    assert_eq!(dates(index.of_payee("Countdown")), vec!["2024-03-01"]);
+    assert_eq!(
+        dates(index.in_dates(date("2024-02-01")..date("2024-03-01"))),
+        vec!["2024-02-01", "2024-02-15"]
+    );
+    assert_eq!(
+        dates(index.in_dates(date("2024-02-02")..=date("2024-03-01"))),
+        vec!["2024-02-15", "2024-03-01"]
+    );
+    assert_eq!(
+        dates(index.in_dates(..date("2024-01-01"))),
+        Vec::<String>::new()
+    );
+
+    let countdown = index.of_payee("Countdown").next().unwrap();
+    assert_eq!(index.path(countdown), dir.join("2024.beancount"));
+    assert_eq!(
+        index.read(countdown).unwrap(),
+        "2024-03-01 * \"Countdown\" \"groceries\"\n  Assets:Bank  -42.50 NZD\n  Expenses:Groceries\n\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn index_save_and_load() {
+    let dir = ledger_dir("save");
+    let index = index_ledger(&dir);
+    let index_path = dir.join("ledger.index");
+
+    index.save(&index_path).unwrap();
+    let loaded = LedgerIndex::load(&index_path).unwrap();
+
+    assert_eq!(loaded.files, index.files);
+    assert_eq!(loaded.entries, index.entries);
+    assert_eq!(loaded.accounts, index.accounts);
+    assert_eq!(loaded.payees, index.payees);
+    assert!(loaded.is_current());
+
+    fs::write(dir.join("2024.beancount"), "").unwrap();
+    assert!(!loaded.is_current());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn index_load_invalid() {
+    let dir = ledger_dir("invalid");
+    let index_path = dir.join("ledger.index");
+
+    fs::write(&index_path, "something else\n").unwrap();
+    let error = LedgerIndex::load(&index_path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    fs::write(
+        &index_path,
+        format!("{}\nentry\t0\t0\t1\t2024-01-01\t\n", HEADER),
+    )
+    .unwrap();
+    let error = LedgerIndex::load(&index_path).unwrap_err();
+    assert_eq!(error.to_string(), "invalid index at line 2");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn index_excludes_inline_content() {
+    let sources = BeancountSources::from(YEAR);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let index = LedgerIndex::new(&sources, &directives);
+
+    assert!(index.is_empty());
+    assert!(index.is_current());
+}
@@ -0,0 +1,280 @@
+use crate::{types::*, Fix, Options};
+use rust_decimal::Decimal;
+
+/// The units of a posting, with the posting from which they came.
+///
+/// A posting with no amount may give rise to several of these, one for each currency required to balance the transaction.
+#[derive(Clone, Debug)]
+pub(crate) struct PostingUnits<'a> {
+    pub(crate) posting: &'a Spanned<Posting<'a>>,
+    pub(crate) currency: Currency<'a>,
+    pub(crate) number: Decimal,
+}
+
+/// Determine the units of each posting of a transaction, interpolating those of the posting without an amount, if any,
+/// so that the transaction balances.
+pub(crate) fn interpolate<'a>(
+    transaction: &'a Transaction<'a>,
+) -> Result<Vec<PostingUnits<'a>>, Error> {
+    let mut units = Vec::new();
+    let mut residual = Vec::<(Currency<'a>, Decimal)>::new();
+    let mut elided = None;
+
+    for posting in transaction.postings() {
+        match (posting.amount(), posting.currency()) {
+            (Some(amount), Some(currency)) => {
+                let number = amount.item().value();
+                let (weight_currency, weight) = weight(posting, *currency.item(), number);
+                add(&mut residual, weight_currency, weight);
+                units.push(PostingUnits {
+                    posting,
+                    currency: *currency.item(),
+                    number,
+                });
+            }
+            (Some(amount), None) => {
+                let error = posting.error("currency cannot be inferred");
+                return Err(match only_currency(transaction) {
+                    Some(currency) => error.with_fix(
+                        Fix::new(format!("insert missing currency {}", currency))
+                            .insert_after(*amount.span(), format!(" {}", currency)),
+                    ),
+                    None => error,
+                });
+            }
+            (None, currency) => {
+                if elided.is_some() {
+                    return Err(posting.error("multiple postings without amount"));
+                }
+                elided = Some((posting, currency.map(|currency| *currency.item())));
+            }
+        }
+    }
+
+    if let Some((posting, currency)) = elided {
+        for (residual_currency, residual_number) in residual {
+            if !residual_number.is_zero()
+                && currency.is_none_or(|currency| currency == residual_currency)
+            {
+                units.push(PostingUnits {
+                    posting,
+                    currency: residual_currency,
+                    number: -residual_number,
+                });
+            }
+        }
+    }
+
+    Ok(units)
+}
+
+/// A posting of a transaction after interpolation, see [interpolated_postings].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct InterpolatedPosting<'a> {
+    posting: Option<&'a Spanned<Posting<'a>>>,
+    account: Account<'a>,
+    currency: Currency<'a>,
+    number: Decimal,
+}
+
+impl<'a> InterpolatedPosting<'a> {
+    /// The posting as written, or `None` for a posting generated to the rounding account.
+    pub fn posting(&self) -> Option<&'a Spanned<Posting<'a>>> {
+        self.posting
+    }
+
+    /// Field accessor.
+    pub fn account(&self) -> &Account<'a> {
+        &self.account
+    }
+
+    /// Field accessor.
+    pub fn currency(&self) -> Currency<'a> {
+        self.currency
+    }
+
+    /// The units, interpolated if not given in the posting.
+    pub fn number(&self) -> Decimal {
+        self.number
+    }
+
+    /// Whether the posting was generated to absorb the residual, rather than written in the transaction.
+    pub fn is_generated(&self) -> bool {
+        self.posting.is_none()
+    }
+}
+
+/// Interpolate the postings of a transaction, so that it balances, as Beancount does.
+///
+/// A posting without an amount has its units interpolated, one for each currency required to balance the transaction.
+///
+/// If the `account_rounding` option is given, interpolated units are rounded to the precision of the amounts
+/// given in that currency, from which the tolerance is inferred, and any residual within tolerance, whether from that rounding or from the amounts as written,
+/// is absorbed by a posting to that subaccount of `Equity`, which [is generated](InterpolatedPosting::is_generated).
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{interpolated_postings, BeancountParser, BeancountSources, DirectiveVariant};
+///
+/// let sources = BeancountSources::from(r#"option "account_rounding" "Rounding"
+/// 2024-01-01 * "Split three ways"
+///   Assets:Bank     -10.00 NZD
+///   Expenses:Food    3.333 NZD
+///   Expenses:Drink
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let result = parser.parse().unwrap();
+/// let DirectiveVariant::Transaction(transaction) = result.directives[0].variant() else {
+///     panic!("expected transaction");
+/// };
+/// let postings = interpolated_postings(transaction, &result.options).unwrap();
+///
+/// assert_eq!(postings[2].number().to_string(), "6.67");
+/// assert_eq!(postings[3].account().to_string(), "Equity:Rounding");
+/// assert_eq!(postings[3].number().to_string(), "-0.003");
+/// assert!(postings[3].is_generated());
+/// ```
+pub fn interpolated_postings<'a>(
+    transaction: &'a Transaction<'a>,
+    options: &Options<'a>,
+) -> Result<Vec<InterpolatedPosting<'a>>, Error> {
+    let mut postings = interpolate(transaction)?
+        .into_iter()
+        .map(|units| InterpolatedPosting {
+            posting: Some(units.posting),
+            account: units.posting.account().item().clone(),
+            currency: units.currency,
+            number: units.number,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(rounding) = options.account_rounding() {
+        let mut residual = Vec::new();
+
+        for interpolated in postings.iter_mut() {
+            let posting = interpolated.posting.unwrap();
+            if posting.amount().is_none() {
+                if let Some(scale) = precision(transaction, interpolated.currency) {
+                    interpolated.number = interpolated.number.round_dp(scale);
+                }
+            }
+            let (weight_currency, weight) =
+                weight(posting, interpolated.currency, interpolated.number);
+            add(&mut residual, weight_currency, weight);
+        }
+
+        for (currency, number) in residual {
+            if !number.is_zero() && number.abs() <= tolerance(transaction, options, currency) {
+                postings.push(InterpolatedPosting {
+                    posting: None,
+                    account: Account::new(AccountType::Equity, rounding.clone()),
+                    currency,
+                    number: -number,
+                });
+            }
+        }
+    }
+
+    Ok(postings)
+}
+
+/// The tolerance within which a transaction balances in `currency`, being the `inferred_tolerance_default`
+/// option for the currency if any, or else inferred from the precision of the amounts in that currency, as in Beancount.
+pub(crate) fn tolerance(
+    transaction: &Transaction<'_>,
+    options: &Options<'_>,
+    currency: Currency<'_>,
+) -> Decimal {
+    options
+        .inferred_tolerance_default(&currency)
+        .or_else(|| {
+            precision(transaction, currency)
+                .map(|scale| Decimal::new(1, scale) * options.inferred_tolerance_multiplier())
+        })
+        .unwrap_or_default()
+}
+
+// the fewest decimal places of any amount given in the currency with a fractional part, as Beancount infers tolerance
+fn precision(transaction: &Transaction<'_>, currency: Currency<'_>) -> Option<u32> {
+    transaction
+        .postings()
+        .filter_map(|posting| match (posting.amount(), posting.currency()) {
+            (Some(amount), Some(posting_currency)) if *posting_currency.item() == currency => {
+                Some(amount.item().value().scale())
+            }
+            _ => None,
+        })
+        .filter(|scale| *scale > 0)
+        .min()
+}
+
+// the one currency of all postings with a currency, if there is only one
+fn only_currency<'a>(transaction: &'a Transaction<'a>) -> Option<Currency<'a>> {
+    let mut currencies = transaction
+        .postings()
+        .filter_map(|posting| posting.currency().map(|currency| *currency.item()));
+    let currency = currencies.next()?;
+    currencies
+        .all(|other| other == currency)
+        .then_some(currency)
+}
+
+/// The weight of a posting is what it contributes to the balance of its transaction,
+/// which is the cost if any, otherwise the price if any, otherwise simply the units.
+pub(crate) fn weight<'a>(
+    posting: &'a Posting<'a>,
+    currency: Currency<'a>,
+    number: Decimal,
+) -> (Currency<'a>, Decimal) {
+    use PriceSpec::*;
+    use ScopedExprValue::*;
+
+    let signed = |total: Decimal| {
+        if number.is_sign_negative() {
+            -total
+        } else {
+            total
+        }
+    };
+
+    if let Some(cost_spec) = posting.cost_spec().map(|cost_spec| cost_spec.item()) {
+        if let (Some(cost_currency), true) = (
+            cost_spec.currency(),
+            cost_spec.per_unit().is_some() || cost_spec.total().is_some(),
+        ) {
+            let per_unit = cost_spec
+                .per_unit()
+                .map(|per_unit| per_unit.item().value() * number)
+                .unwrap_or_default();
+            let total = cost_spec
+                .total()
+                .map(|total| signed(total.item().value()))
+                .unwrap_or_default();
+
+            return (*cost_currency.item(), per_unit + total);
+        }
+    }
+
+    match posting.price_annotation().map(|price| price.item()) {
+        Some(CurrencyAmount(PerUnit(price), price_currency)) => {
+            (*price_currency, price.value() * number)
+        }
+        Some(CurrencyAmount(Total(price), price_currency)) => {
+            (*price_currency, signed(price.value()))
+        }
+        _ => (currency, number),
+    }
+}
+
+pub(crate) fn add<'a>(
+    residual: &mut Vec<(Currency<'a>, Decimal)>,
+    currency: Currency<'a>,
+    number: Decimal,
+) {
+    match residual.iter_mut().find(|(c, _)| *c == currency) {
+        Some((_, total)) => *total += number,
+        None => residual.push((currency, number)),
+    }
+}
+
+mod tests;
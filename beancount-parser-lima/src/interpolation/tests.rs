@@ -0,0 +1,82 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+fn interpolated(s: &str) -> Vec<(String, String, bool)> {
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let result = parser.parse().unwrap();
+
+    result
+        .directives
+        .iter()
+        .filter_map(|directive| match directive.variant() {
+            DirectiveVariant::Transaction(transaction) => Some(transaction),
+            _ => None,
+        })
+        .flat_map(|transaction| interpolated_postings(transaction, &result.options).unwrap())
+        .map(|posting| {
+            (
+                posting.account().to_string(),
+                format!("{} {}", posting.number(), posting.currency()),
+                posting.is_generated(),
+            )
+        })
+        .collect()
+}
+
+fn owned(expected: &[(&str, &str, bool)]) -> Vec<(String, String, bool)> {
+    expected
+        .iter()
+        .map(|(account, amount, generated)| (account.to_string(), amount.to_string(), *generated))
+        .collect()
+}
+
+const TRANSACTIONS: &str = r#"2024-01-01 * "Rounded interpolation"
+  Assets:Bank     -10.00 NZD
+  Expenses:Food     2 USD @ 1.3333 NZD
+  Expenses:Other
+2024-01-02 * "Residual within tolerance"
+  Assets:Bank     -10.00 NZD
+  Expenses:Food    10.003 NZD
+2024-01-03 * "Residual beyond tolerance"
+  Assets:Bank     -10.00 NZD
+  Expenses:Food    10.10 NZD
+"#;
+
+#[test]
+fn residual_to_rounding_account() {
+    assert_eq!(
+        interpolated(&format!(
+            "option \"account_rounding\" \"Rounding\"\n{}",
+            TRANSACTIONS
+        )),
+        owned(&[
+            ("Assets:Bank", "-10.00 NZD", false),
+            ("Expenses:Food", "2 USD", false),
+            ("Expenses:Other", "7.33 NZD", false),
+            ("Equity:Rounding", "0.0034 NZD", true),
+            ("Assets:Bank", "-10.00 NZD", false),
+            ("Expenses:Food", "10.003 NZD", false),
+            ("Equity:Rounding", "-0.003 NZD", true),
+            ("Assets:Bank", "-10.00 NZD", false),
+            ("Expenses:Food", "10.10 NZD", false),
+        ])
+    );
+}
+
+#[test]
+fn no_rounding_account_by_default() {
+    assert_eq!(
+        interpolated(TRANSACTIONS),
+        owned(&[
+            ("Assets:Bank", "-10.00 NZD", false),
+            ("Expenses:Food", "2 USD", false),
+            ("Expenses:Other", "7.3334 NZD", false),
+            ("Assets:Bank", "-10.00 NZD", false),
+            ("Expenses:Food", "10.003 NZD", false),
+            ("Assets:Bank", "-10.00 NZD", false),
+            ("Expenses:Food", "10.10 NZD", false),
+        ])
+    );
+}
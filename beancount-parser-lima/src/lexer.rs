@@ -1,12 +1,13 @@
-use super::types::*;
+use super::{config::CompatMode, types::*};
 use logos::Logos;
 use rust_decimal::Decimal;
 use std::{
-    borrow::Cow,
     error::Error,
     fmt::{self, Debug, Display, Formatter},
-    ops::Range,
+    io::{self, BufRead},
+    ops::{Deref, Range},
     str::FromStr,
+    sync::OnceLock,
 };
 use time::{Date, Month, Time};
 use unescaper::unescape;
@@ -14,11 +15,13 @@ use unescaper::unescape;
 // when adjusting any of these regexes, be sure to check whether `RecoveryToken` needs the same
 #[derive(Logos, Clone, Debug, PartialEq, Eq)]
 #[logos(error = LexerError, skip r"[ \t]+")]
+#[logos(extras = CompatMode)]
 // ANOMALY: to be ignored, we have to require a flag occurs at the beginning of a line, otherwise
 // indented tag or flagged postings are consumed by this rule.
 //
 // It ought to be possible to ignore an indented line where colon is the first non-whitespace character,
 // but if we try to do that here we hit a limitation in Logos which means we lose indent not followed by colon.
+// The leading characters must match `SKIPPED_LINE_LEADERS`.
 #[logos(subpattern ignored_whole_line= r"([*!&#?%:].*\n)")] // rolled into end-of-line handling below
 #[logos(subpattern comment_to_eol= r"(;.*)")] // rolled into end-of-line handling below
 #[logos(subpattern currency = r"[A-Z][A-Z0-9'\._-]*|/[0-9'\._-]*[A-Z][A-Z0-9'\._-]*")]
@@ -28,6 +31,7 @@ use unescaper::unescape;
 #[logos(subpattern account_name = r"[\p{Lu}\p{Lo}\p{N}][\p{L}\p{N}\-]*")]
 #[logos(subpattern string_literal = r#""([^\\"]|\\.)*""#)]
 #[logos(subpattern number = r"\d+(,\d{3})*(\.\d+)?")]
+#[logos(subpattern lenient_number = r"\d+(_\d+)*(\.\d+(_\d+)*)?e[+-]?\d+|\d+(_\d+)+(\.\d+(_\d+)*)?|\d+\.\d+(_\d+)+")]
 #[logos(subpattern tag_or_link_identifier = r"[A-Za-z0-9\-_/.]+")]
 #[logos(subpattern key = r"[a-z][a-zA-Z0-9\-_]+")]
 pub enum Token<'a> {
@@ -124,7 +128,7 @@ pub enum Token<'a> {
     #[token("include")]
     Include,
 
-    #[regex(r"(?&date)", |lex| parse_date(lex.slice()))]
+    #[regex(r"(?&date)", |lex| parse_date(lex.slice()).and_then(|date| validate_date(date, lex.extras)))]
     Date(Date),
 
     #[regex(r"(?&time)", |lex| parse_time(lex.slice()))]
@@ -135,13 +139,18 @@ pub enum Token<'a> {
 
     #[regex(r"(?&string_literal)", |lex| {
         let len = lex.slice().len();
-        unescape_string_literal(&lex.slice()[1..len-1])
+        EscapedStr::new(&lex.slice()[1..len-1], lex.extras)
     })]
-    StringLiteral(Cow<'a, str>),
+    StringLiteral(EscapedStr<'a>),
 
     #[regex(r"(?&number)", |lex| parse_number(lex.slice()))]
     Number(Decimal),
 
+    // a number in scientific notation or with underscore separators, as found in some generated ledgers,
+    // which is only accepted by the parser if so configured
+    #[regex(r"(?&lenient_number)", |lex| parse_lenient_number(lex.slice()))]
+    LenientNumber(Decimal),
+
     #[regex(r"#(?&tag_or_link_identifier)", |lex| &lex.slice()[1..])]
     Tag(&'a str),
 
@@ -165,6 +174,10 @@ pub enum Token<'a> {
     // which fails to capture an indented first line, but that isn't a thing in Beancount anyway
     Indent,
 
+    // a string literal missing its closing quote, up to the end of the line on which it starts,
+    // see `UnterminatedStringDetector`
+    UnterminatedString(&'a str),
+
     // errors are returned as an error token
     Error(LexerError),
 }
@@ -234,9 +247,9 @@ impl<'a> Display for Token<'a> {
             Time(x) => write!(f, "{}", x),
             Account(x) => write!(f, "{}", x),
 
-            // TODO escapes
-            StringLiteral(x) => write!(f, "\"{}\"", x),
+            StringLiteral(x) => write!(f, "\"{}\"", x.raw()),
             Number(x) => write!(f, "{}", x),
+            LenientNumber(x) => write!(f, "{}", x),
             Tag(x) => write!(f, "{}", x),
             Link(x) => write!(f, "{}", x),
             Key(x) => write!(f, "{}", x),
@@ -245,6 +258,8 @@ impl<'a> Display for Token<'a> {
             Eol => write!(f, "\\n"),
             Indent => write!(f, "{}", INDENT),
 
+            UnterminatedString(x) => write!(f, "\"{}", x),
+
             Error(e) => write!(f, "ERROR {}", e),
         }
     }
@@ -259,6 +274,7 @@ type SpannedToken<'a> = (Token<'a>, Span);
 // when adjusting any of these regexes, be sure to make the same change in `Token`
 #[derive(Logos, Clone, Debug, PartialEq, Eq)]
 #[logos(error = LexerError)]
+#[logos(extras = CompatMode)]
 #[logos(subpattern currency = r"[A-Z][A-Z0-9'\._-]*|/[0-9'\._-]*[A-Z][A-Z0-9'\._-]*")]
 #[logos(subpattern date = r"\d{4}[\-/]\d{2}[\-/]\d{2}")]
 #[logos(subpattern number = r"\d+(,\d{3})*(\.\d+)?")]
@@ -270,7 +286,7 @@ enum RecoveryToken {
     #[token(":")]
     Colon,
 
-    #[regex(r"(?&date)", |lex| parse_date(lex.slice()))]
+    #[regex(r"(?&date)", |lex| parse_date(lex.slice()).and_then(|date| validate_date(date, lex.extras)))]
     Date(Date),
 
     #[regex(r"(?&number)", |lex| parse_number(lex.slice()))]
@@ -296,27 +312,189 @@ impl<'a> From<RecoveryToken> for Token<'a> {
 ///
 /// Lexing errors are returned as `Error` tokens.
 pub fn lex(s: &str) -> impl Iterator<Item = RangedToken> {
+    lex_with_compat_mode(s, CompatMode::default())
+}
+
+/// Lex the input as for [lex], with edge-case behaviour according to `compat_mode`.
+pub fn lex_with_compat_mode(
+    s: &str,
+    compat_mode: CompatMode,
+) -> impl Iterator<Item = RangedToken<'_>> {
     let end_of_input = s.len()..s.len();
-    lex_with_final_eol(s, Some(end_of_input))
+    lex_with_final_eol(s, Some(end_of_input), compat_mode)
+}
+
+/// Lex input read from `reader` as for [lex_with_compat_mode], but in chunks of at least `chunk_size` bytes,
+/// calling `f` with the tokens of each chunk in turn, so that the whole input is never in memory at once.
+///
+/// Chunks are split only before a line which looks like the start of an entry, such as a date,
+/// since no token spans such a line, as for unterminated strings.  The tokens and their spans,
+/// which are relative to the whole input, are therefore exactly those from lexing the whole input at once.
+/// Input before such a line is spilled over into the next chunk, so a chunk may be larger than `chunk_size`,
+/// by as much as the largest entry.
+///
+/// Only lexing is bounded in memory like this, since [BeancountParser](crate::BeancountParser) still reads
+/// each of its sources in full.
+pub fn lex_chunks<R, F>(
+    mut reader: R,
+    chunk_size: usize,
+    compat_mode: CompatMode,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(Vec<RangedToken<'_>>),
+{
+    let mut chunk = String::new();
+    let mut offset = 0;
+    let mut line = String::new();
+    let mut any_eol = false;
+
+    loop {
+        line.clear();
+        let at_end = reader.read_line(&mut line)? == 0;
+
+        if at_end || (chunk.len() >= chunk_size && looks_like_entry_start(&line)) {
+            if !chunk.is_empty() {
+                // only the final chunk may be missing its final Eol, which as for the whole input
+                // is forced only if there has been no Eol at all
+                let final_eol = (at_end && !any_eol).then_some(chunk.len()..chunk.len());
+                let tokens = lex_with_final_eol(&chunk, final_eol, compat_mode)
+                    .map(|(tok, span)| (tok, span.start + offset..span.end + offset))
+                    .collect::<Vec<_>>();
+                any_eol = any_eol || tokens.iter().any(|(tok, _)| *tok == Token::Eol);
+                f(tokens);
+            }
+            if at_end {
+                return Ok(());
+            }
+            offset += chunk.len();
+            chunk.clear();
+        }
+
+        chunk.push_str(&line);
+    }
 }
 
 /// Lex the input discarding empty lines.
 #[cfg(test)]
 pub fn bare_lex(s: &str) -> impl Iterator<Item = RangedToken> {
-    lex_with_final_eol(s, None)
+    lex_with_final_eol(s, None, CompatMode::default())
 }
 
 fn lex_with_final_eol(
     s: &str,
     final_eol: Option<Range<usize>>,
+    compat_mode: CompatMode,
 ) -> impl Iterator<Item = RangedToken> {
-    Token::lexer(s)
-        .spanned()
-        .attempt_recovery(s)
+    UnterminatedStringDetector::new(s, compat_mode)
+        .attempt_recovery(s, compat_mode)
         .keyword_then_colon_to_key()
         .handle_eol_indent(final_eol)
 }
 
+/// The characters which at the start of a line cause the whole line to be skipped,
+/// such as `*` for org-mode headlines.
+pub(crate) const SKIPPED_LINE_LEADERS: [char; 7] = ['*', '!', '&', '#', '?', '%', ':'];
+
+// Ranges of the whole lines skipped within the text of an `Eol` token, excluding their newlines.
+pub(crate) fn skipped_lines_in_eol(eol: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    // the first line is the end of a line with content, so is never skipped, and the last may be indent
+    let first_line_end = eol.find('\n').map_or(eol.len(), |i| i + 1);
+
+    eol[first_line_end..]
+        .split_inclusive('\n')
+        .scan(first_line_end, |start, line| {
+            let range = *start..*start + line.len();
+            *start = range.end;
+            Some((range, line))
+        })
+        .filter(|(_, line)| line.ends_with('\n') && line.starts_with(SKIPPED_LINE_LEADERS))
+        .map(|(range, _)| range.start..range.end - 1)
+}
+
+// A missing closing quote results in a string literal which runs on to the opening quote of the next string,
+// and so on to the end of the file, since every subsequent string is then inside out.
+//
+// Beancount strings may legitimately span lines, so we detect this heuristically, as a string literal
+// with a later line which looks like the start of an entry, or an opening quote which is never closed.
+// The string is then lexed as `UnterminatedString` to the end of its first line, and lexing resumes from there.
+struct UnterminatedStringDetector<'a> {
+    lexer: logos::SpannedIter<'a, Token<'a>>,
+    offset: usize,
+    source: &'a str,
+    compat_mode: CompatMode,
+}
+
+impl<'a> UnterminatedStringDetector<'a> {
+    fn new(source: &'a str, compat_mode: CompatMode) -> Self {
+        UnterminatedStringDetector {
+            lexer: Token::lexer_with_extras(source, compat_mode).spanned(),
+            offset: 0,
+            source,
+            compat_mode,
+        }
+    }
+
+    fn is_unterminated(&self, tok: &Result<Token<'a>, LexerError>, span: &Range<usize>) -> bool {
+        let text = &self.source[span.clone()];
+
+        match tok {
+            Ok(Token::StringLiteral(_)) => text.lines().skip(1).any(looks_like_entry_start),
+            // an invalid escape sequence is a different error, for a string which is terminated
+            Err(_) => text.starts_with('"') && !(text.len() > 1 && text.ends_with('"')),
+            _ => false,
+        }
+    }
+
+    fn resume_from(&mut self, offset: usize) {
+        self.lexer = Token::lexer_with_extras(&self.source[offset..], self.compat_mode).spanned();
+        self.offset = offset;
+    }
+}
+
+impl<'a> Iterator for UnterminatedStringDetector<'a> {
+    type Item = RangedTokenOrError<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (tok, span) = self.lexer.next()?;
+        let span = span.start + self.offset..span.end + self.offset;
+
+        if self.is_unterminated(&tok, &span) {
+            let end = self.source[span.start..]
+                .find('\n')
+                .map_or(self.source.len(), |eol| span.start + eol);
+            self.resume_from(end);
+
+            Some((
+                Ok(Token::UnterminatedString(&self.source[span.start + 1..end])),
+                span.start..end,
+            ))
+        } else {
+            Some((tok, span))
+        }
+    }
+}
+
+// whether a line begins with a date or a pragma keyword
+fn looks_like_entry_start(line: &str) -> bool {
+    const KEYWORDS: [&str; 7] = [
+        "pushtag", "poptag", "pushmeta", "popmeta", "option", "plugin", "include",
+    ];
+    let followed_by_space = |rest: &str| rest.starts_with([' ', '\t']);
+
+    let date = line.get(..10).is_some_and(|date| {
+        date.bytes().enumerate().all(|(i, b)| match i {
+            4 | 7 => b == b'-' || b == b'/',
+            _ => b.is_ascii_digit(),
+        })
+    }) && followed_by_space(&line[10..]);
+
+    date || KEYWORDS
+        .iter()
+        .any(|keyword| line.strip_prefix(keyword).is_some_and(followed_by_space))
+}
+
 // This is a work-around for Logos issue #315.
 // Logos starts matching text like '/1.24' as a currency, and when it fails, it doesn't retry as slash followed by number.
 //
@@ -331,17 +509,19 @@ struct RecoveryAttempter<'a, I> {
     primary_iter: I,
     recovery: Option<(logos::SpannedIter<'a, RecoveryToken>, usize)>,
     source: &'a str,
+    compat_mode: CompatMode,
 }
 
 impl<'a, I> RecoveryAttempter<'a, I>
 where
     I: Iterator<Item = RangedTokenOrError<'a>>,
 {
-    fn new(iter: I, source: &'a str) -> Self {
+    fn new(iter: I, source: &'a str, compat_mode: CompatMode) -> Self {
         RecoveryAttempter {
             primary_iter: iter,
             recovery: None,
             source,
+            compat_mode,
         }
     }
 
@@ -349,7 +529,7 @@ where
         let failed_source = &self.source[failed_span.start..failed_span.end];
 
         self.recovery = Some((
-            RecoveryToken::lexer(failed_source).spanned(),
+            RecoveryToken::lexer_with_extras(failed_source, self.compat_mode).spanned(),
             failed_span.start,
         ));
     }
@@ -400,8 +580,12 @@ where
 }
 
 trait RecoveryAttempterIteratorAdaptor<'a>: Iterator<Item = RangedTokenOrError<'a>> + Sized {
-    fn attempt_recovery(self, source: &'a str) -> RecoveryAttempter<'a, Self> {
-        RecoveryAttempter::new(self, source)
+    fn attempt_recovery(
+        self,
+        source: &'a str,
+        compat_mode: CompatMode,
+    ) -> RecoveryAttempter<'a, Self> {
+        RecoveryAttempter::new(self, source, compat_mode)
     }
 }
 
@@ -666,6 +850,15 @@ fn parse_date(s: &str) -> Result<Date, LexerError> {
     Date::from_calendar_date(year, month, day).or(Err(LexerError::new("date out of range")))
 }
 
+// Python's datetime, and therefore Beancount v2, has no year zero
+fn validate_date(date: Date, compat_mode: CompatMode) -> Result<Date, LexerError> {
+    if compat_mode == CompatMode::PythonV2 && date.year() < 1 {
+        Err(LexerError::new("year out of range"))
+    } else {
+        Ok(date)
+    }
+}
+
 fn parse_time(s: &str) -> Result<Time, LexerError> {
     let mut time = s.split(':');
     let hour = time.by_ref().next().unwrap().parse::<u8>().unwrap();
@@ -679,25 +872,193 @@ fn parse_time(s: &str) -> Result<Time, LexerError> {
     Time::from_hms(hour, min, sec).or(Err(LexerError::new("time out of range")))
 }
 
+/// The content of a string literal, borrowed from the source as written, so that lexing allocates nothing for it,
+/// with any escapes resolved only on first access.
+#[derive(Clone, Debug)]
+pub struct EscapedStr<'a> {
+    raw: &'a str,
+    escaped: bool,
+    compat_mode: CompatMode,
+    unescaped: OnceLock<Box<str>>,
+}
+
+impl<'a> EscapedStr<'a> {
+    fn new(raw: &'a str, compat_mode: CompatMode) -> Result<Self, LexerError> {
+        let escaped = raw.contains('\\');
+
+        // only an invalid escape allocates, for the error message
+        if escaped && compat_mode == CompatMode::Lima && !has_valid_escapes(raw) {
+            return Err(unescape(raw).map_or_else(
+                |e| LexerError::new(e.to_string()),
+                |_| LexerError::new("invalid escape"),
+            ));
+        }
+
+        Ok(EscapedStr {
+            raw,
+            escaped,
+            compat_mode,
+            unescaped: OnceLock::new(),
+        })
+    }
+
+    /// The string as written in the source, without its quotes.
+    pub fn raw(&self) -> &'a str {
+        self.raw
+    }
+
+    /// The string with escapes resolved, which allocates only if there are any, and only on first access.
+    pub fn as_str(&self) -> &str {
+        match self.unescaped.get() {
+            Some(unescaped) => unescaped,
+            None if self.escaped => self
+                .unescaped
+                .get_or_init(|| unescape_string_literal(self.raw, self.compat_mode).into()),
+            None => self.raw,
+        }
+    }
+
+    /// Replace the resolved string, as for normalization.
+    pub(crate) fn replace(&mut self, s: String) {
+        self.unescaped = OnceLock::from(s.into_boxed_str());
+    }
+}
+
+impl<'a> Deref for EscapedStr<'a> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> PartialEq for EscapedStr<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<'a> Eq for EscapedStr<'a> {}
+
 // Unescape string literal using the inverse of std::ascii::escape_default
 // https://doc.rust-lang.org/std/ascii/fn.escape_default.html
-fn unescape_string_literal(s: &str) -> Result<Cow<str>, LexerError> {
-    if s.contains('\\') {
-        unescape(s)
-            .map(Cow::Owned)
-            .map_err(|e| LexerError::new(e.to_string()))
-    } else {
-        Ok(Cow::Borrowed(s))
+//
+// In Lima mode the escapes have already been checked when lexed, by `has_valid_escapes`,
+// which must agree with `unescape`.
+fn unescape_string_literal(s: &str, compat_mode: CompatMode) -> String {
+    match compat_mode {
+        CompatMode::Lima => {
+            let unescaped = unescape(s);
+            debug_assert!(
+                unescaped.is_ok(),
+                "escapes accepted when lexed but rejected by unescape in {:?}",
+                s
+            );
+            unescaped.unwrap_or_else(|_| unescape_leniently(s))
+        }
+        CompatMode::PythonV2 => unescape_leniently(s),
+    }
+}
+
+// Whether `unescape` accepts all the escapes in `s`, following exactly its rules, but without allocating.
+fn has_valid_escapes(s: &str) -> bool {
+    let mut rest = s;
+
+    while let Some(i) = rest.find('\\') {
+        let mut chars = rest[i + 1..].chars();
+        rest = match chars.next() {
+            Some('b' | 'f' | 'n' | 'r' | 't' | 'v' | '\'' | '"' | '\\' | '/') => chars.as_str(),
+
+            Some('u') => {
+                let unicode = chars.as_str();
+                let (digits, after) = if let Some(braced) = unicode.strip_prefix('{') {
+                    braced
+                        .split_once('}')
+                        .unwrap_or((braced, &braced[braced.len()..]))
+                } else {
+                    match split_chars(unicode, 4) {
+                        Some(split) => split,
+                        None => return false,
+                    }
+                };
+                if !u32::from_str_radix(digits, 16).is_ok_and(|u| char::from_u32(u).is_some()) {
+                    return false;
+                }
+                after
+            }
+
+            Some('x') => match split_chars(chars.as_str(), 2) {
+                Some((digits, after)) if u8::from_str_radix(digits, 16).is_ok() => after,
+                _ => return false,
+            },
+
+            Some(c @ '0'..='7') => {
+                // up to three octal digits, with at most 0o377
+                let max_following = if c <= '3' { 2 } else { 1 };
+                let after = chars.as_str();
+                let following = after
+                    .chars()
+                    .take(max_following)
+                    .take_while(|c| c.is_digit(8))
+                    .count();
+                &after[following..]
+            }
+
+            _ => return false,
+        };
+    }
+
+    true
+}
+
+// split after the first `n` chars, if there are that many
+fn split_chars(s: &str, n: usize) -> Option<(&str, &str)> {
+    match s.char_indices().nth(n) {
+        Some((i, _)) => Some(s.split_at(i)),
+        None => (s.chars().count() == n).then_some((s, &s[s.len()..])),
     }
 }
 
+// Python Beancount v2 never rejects an escape, but takes any character other than n or t literally
+fn unescape_leniently(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some(escaped) => unescaped.push(escaped),
+                None => unescaped.push(c),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped
+}
+
 fn parse_number(s: &str) -> Result<Decimal, LexerError> {
-    let result = if s.contains(',') {
+    // thousands separators are removed in a buffer on the stack, unless the number is absurdly long
+    const MAX_STACK_LEN: usize = 64;
+
+    let result = if !s.contains(',') {
+        FromStr::from_str(s)
+    } else if s.len() <= MAX_STACK_LEN {
+        let mut buf = [0u8; MAX_STACK_LEN];
+        let mut len = 0;
+        for b in s.bytes().filter(|b| *b != b',') {
+            buf[len] = b;
+            len += 1;
+        }
+        // a number is all ASCII digits, commas, and a point
+        FromStr::from_str(std::str::from_utf8(&buf[..len]).unwrap_or_default())
+    } else {
         let mut without_commas = s.to_string();
         without_commas.retain(|c| c != ',');
         FromStr::from_str(&without_commas)
-    } else {
-        FromStr::from_str(s)
     };
 
     result.map_err(|e: <rust_decimal::Decimal as std::str::FromStr>::Err| {
@@ -705,6 +1066,18 @@ fn parse_number(s: &str) -> Result<Decimal, LexerError> {
     })
 }
 
+fn parse_lenient_number(s: &str) -> Result<Decimal, LexerError> {
+    let mut without_underscores = s.to_string();
+    without_underscores.retain(|c| c != '_');
+
+    if without_underscores.contains('e') {
+        Decimal::from_scientific(&without_underscores)
+    } else {
+        FromStr::from_str(&without_underscores)
+    }
+    .map_err(|e| LexerError::new(e.to_string()))
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct LexerError {
     message: String,
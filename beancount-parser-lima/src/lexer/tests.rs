@@ -1,10 +1,14 @@
 #![cfg(test)]
 use crate::bare_lex;
 
-use super::{lex, LexerError, Token, Token::*};
+use super::{
+    has_valid_escapes, lex, lex_chunks, lex_with_compat_mode, EscapedStr, LexerError, Token,
+    Token::*,
+};
+use crate::{test_support, CompatMode};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use std::borrow::Cow;
+use test_case::test_case;
 use time::format_description::well_known::Iso8601;
 
 macro_rules! number {
@@ -14,7 +18,7 @@ macro_rules! number {
 }
 
 fn date(s: &str) -> Token {
-    Date(time::Date::parse(s, &Iso8601::DEFAULT).unwrap())
+    Date(test_support::date(s))
 }
 
 fn time(s: &str) -> Token {
@@ -22,7 +26,7 @@ fn time(s: &str) -> Token {
 }
 
 fn string_literal(s: &str) -> Token {
-    StringLiteral(Cow::Borrowed(s))
+    StringLiteral(EscapedStr::new(s, CompatMode::Lima).unwrap())
 }
 
 fn unrecognized() -> Token<'static> {
@@ -263,6 +267,32 @@ fn number_ok() {
     );
 }
 
+#[test]
+fn number_lenient() {
+    lex_and_check(
+        r#"
+1e6 USD
+1.5e-3 USD
+1_000_000 USD
+1_000.000_5 USD
+"#,
+        vec![
+            LenientNumber(dec!(1000000)),
+            Currency("USD"),
+            Eol,
+            LenientNumber(dec!(0.0015)),
+            Currency("USD"),
+            Eol,
+            LenientNumber(dec!(1000000)),
+            Currency("USD"),
+            Eol,
+            LenientNumber(dec!(1000.0005)),
+            Currency("USD"),
+            Eol,
+        ],
+    );
+}
+
 #[test]
 fn number_space() {
     lex_and_check(
@@ -518,6 +548,102 @@ fn string_escaped() {
     );
 }
 
+#[test]
+fn string_escaped_unicode() {
+    lex_and_check(
+        r#"
+"Caf\u00e9 \u{1F600} \x41\101"
+"#,
+        vec![string_literal("Café \u{1F600} AA"), Eol],
+    );
+}
+
+#[test]
+fn string_multi_line() {
+    lex_and_check(
+        r#"
+"SELECT account
+  WHERE \"Expenses\" ~ account"
+"#,
+        vec![
+            string_literal("SELECT account\n  WHERE \"Expenses\" ~ account"),
+            Eol,
+        ],
+    );
+}
+
+#[test]
+fn string_unescaped_on_demand() {
+    let actual = lex(r#""Tab\there" "plain""#)
+        .map(|(tok, _span)| tok)
+        .collect::<Vec<_>>();
+
+    match actual.as_slice() {
+        [StringLiteral(escaped), StringLiteral(plain), Eol] => {
+            assert_eq!(escaped.raw(), r"Tab\there");
+            assert!(escaped.unescaped.get().is_none());
+            assert_eq!(escaped.as_str(), "Tab\there");
+            assert!(escaped.unescaped.get().is_some());
+            assert_eq!(actual[0].to_string(), r#""Tab\there""#);
+
+            assert_eq!(plain.as_str(), "plain");
+            assert!(std::ptr::eq(plain.as_str(), plain.raw()));
+        }
+        _ => panic!("unexpected tokens {:?}", actual),
+    }
+}
+
+#[test_case(r#"plain"#)]
+#[test_case(r#"\b\f\n\r\t\v\'\"\\\/"#)]
+#[test_case(r#"\u00e9 \u{1F600}"#)]
+#[test_case(r#"\u{}"#)]
+#[test_case(r#"\u{D800}"#)]
+#[test_case(r#"\u{110000}"#)]
+#[test_case(r#"\u{+41}"#)]
+#[test_case(r#"\u{41"#)]
+#[test_case(r#"\u00e"#)]
+#[test_case(r#"\u+041"#)]
+#[test_case(r#"\uD800"#)]
+#[test_case(r#"\x41"#)]
+#[test_case(r#"\x4"#)]
+#[test_case(r#"\xg1"#)]
+#[test_case(r#"\xé1"#)]
+#[test_case(r#"\101\0\3777\47"#)]
+#[test_case(r#"\8"#)]
+#[test_case(r#"\q"#)]
+#[test_case(r#"trailing\"#)]
+fn valid_escapes_as_unescaper(s: &str) {
+    assert_eq!(has_valid_escapes(s), unescaper::unescape(s).is_ok());
+}
+
+#[test]
+fn string_escaped_python_v2() {
+    let s = r#""The Great \"Juju\" \q\r\t\n"
+"#;
+    let actual = lex_with_compat_mode(s, CompatMode::PythonV2)
+        .map(|(tok, _span)| tok)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actual,
+        vec![string_literal("The Great \"Juju\" qr\t\n"), Eol]
+    );
+}
+
+#[test]
+fn date_year_zero_python_v2() {
+    let s = "0000-01-01\n";
+    let lima = lex_with_compat_mode(s, CompatMode::Lima)
+        .map(|(tok, _span)| tok)
+        .collect::<Vec<_>>();
+    let python_v2 = lex_with_compat_mode(s, CompatMode::PythonV2)
+        .map(|(tok, _span)| tok)
+        .collect::<Vec<_>>();
+
+    assert_eq!(lima, vec![date("0000-01-01"), Eol]);
+    assert_eq!(python_v2, vec![error("year out of range"), Eol]);
+}
+
 #[test]
 fn string_newline() {
     let s = format!(r#""The Great\nJuju"{}"#, "\n");
@@ -606,6 +732,23 @@ fn invalid_commas_in_fractional() {
     );
 }
 
+#[test]
+fn long_number_with_commas() {
+    lex_and_check(
+        r#"
+    1,234,567,890,123,456,789,012,345,678.123456789012345678901234567890
+"#,
+        vec![
+            Number(
+                "1234567890123456789012345678.123456789012345678901234567890"
+                    .parse()
+                    .unwrap(),
+            ),
+            Eol,
+        ],
+    );
+}
+
 #[test]
 fn ignored_lines_comment() {
     lex_and_check(
@@ -1005,3 +1148,113 @@ fn keywords_as_key() {
         ],
     );
 }
+
+#[test]
+fn unterminated_string_before_entry() {
+    lex_and_check(
+        r#"
+2024-01-01 * "Coffee
+  Expenses:Coffee
+2024-01-02 * "Tea"
+"#,
+        vec![
+            date("2024-01-01"),
+            Asterisk,
+            UnterminatedString("Coffee"),
+            Eol,
+            Indent,
+            Account("Expenses:Coffee"),
+            Eol,
+            date("2024-01-02"),
+            Asterisk,
+            string_literal("Tea"),
+            Eol,
+        ],
+    );
+}
+
+#[test]
+fn unterminated_string_at_end_of_file() {
+    lex_and_check(
+        r#"
+2024-01-01 * "Coffee
+  Expenses:Coffee
+"#,
+        vec![
+            date("2024-01-01"),
+            Asterisk,
+            UnterminatedString("Coffee"),
+            Eol,
+            Indent,
+            Account("Expenses:Coffee"),
+            Eol,
+        ],
+    );
+}
+
+#[test]
+fn multi_line_string_is_terminated() {
+    lex_and_check(
+        r#"
+2024-01-01 note Assets:Bank "first line
+second line"
+"#,
+        vec![
+            date("2024-01-01"),
+            Note,
+            Account("Assets:Bank"),
+            string_literal("first line\nsecond line"),
+            Eol,
+        ],
+    );
+}
+
+fn check_chunked_as_whole(s: &str, chunk_size: usize) {
+    let mut chunks = 0;
+    let mut actual = Vec::new();
+    lex_chunks(s.as_bytes(), chunk_size, CompatMode::default(), |tokens| {
+        chunks += 1;
+        actual.extend(
+            tokens
+                .into_iter()
+                .map(|(tok, span)| (format!("{:?}", tok), span)),
+        );
+    })
+    .unwrap();
+    let expected = lex(s)
+        .map(|(tok, span)| (format!("{:?}", tok), span))
+        .collect::<Vec<_>>();
+
+    assert_eq!(actual, expected);
+    if chunk_size < s.len() / 2 {
+        assert!(chunks > 1);
+    }
+}
+
+#[test_case(1; "line at a time")]
+#[test_case(64; "small")]
+#[test_case(1 << 20; "whole")]
+fn chunked_as_whole(chunk_size: usize) {
+    check_chunked_as_whole(
+        include_str!("../../examples/data/full.beancount"),
+        chunk_size,
+    );
+}
+
+#[test_case(1; "line at a time")]
+#[test_case(40; "small")]
+fn chunked_edge_cases_as_whole(chunk_size: usize) {
+    check_chunked_as_whole(
+        r#"
+; leading comment
+
+2024-01-01 * "unterminated
+  Expenses:Coffee
+2024-01-02 note Assets:Bank "first line
+second line"
+* org-mode heading
+
+2024-01-03 note Assets:Bank "no final newline""#,
+        chunk_size,
+    );
+}
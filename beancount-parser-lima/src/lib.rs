@@ -82,20 +82,23 @@
 //!}
 //!```
 
-use ariadne::{Color, Label, Report};
 use chumsky::prelude::{Input, Parser};
-use lazy_format::lazy_format;
-use lexer::{lex, Token};
+use config::limit_error;
+use lexer::{lex_chunks, lex_with_compat_mode, skipped_lines_in_eol, Token};
+use options::PYTHON_V2_ONLY_OPTIONS;
 use parsers::{file, includes, ParserState};
 use sort::SortIteratorAdaptor;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     ffi::OsStr,
-    fmt::{self, Formatter},
+    fmt::{self, Display, Formatter},
     fs::File,
     io::{self, Read, Write},
     iter::once,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Instant,
 };
 pub use types::*;
 
@@ -119,7 +122,10 @@ pub struct BeancountSources {
     root_source_id: SourceId,
     root_content: String,
     included_content: HashMap<PathBuf, IncludedSource>,
-    source_id_strings: Vec<String>, // indexed by SourceId
+    source_id_strings: Vec<String>,   // indexed by SourceId
+    include_depths: Vec<usize>,       // indexed by SourceId
+    includers: Vec<Option<SourceId>>, // indexed by SourceId
+    include_resolution: IncludeResolution,
 }
 
 enum IncludedSource {
@@ -129,12 +135,12 @@ enum IncludedSource {
 }
 
 // get all includes, discarding errors
-fn get_includes(content: &str, source_id: SourceId) -> Vec<String> {
+fn get_includes(content: &str, source_id: SourceId) -> Vec<Spanned<String>> {
     fn get_includes_for_tokens(
         tokens: Vec<(Token, Span)>,
         source_id: SourceId,
         end_of_input: Span,
-    ) -> Vec<String> {
+    ) -> Vec<Spanned<String>> {
         let spanned_tokens = tokens.spanned(end_of_input).with_context(source_id);
 
         // ignore any errors in parsing, we'll pick them up in the next pass
@@ -159,31 +165,66 @@ fn path_dir(p: &Path) -> Option<&Path> {
     })
 }
 
-// get included path relative to including path
-fn resolve_included_path(including_path: Option<&PathBuf>, included_path: &Path) -> PathBuf {
-    match including_path.and_then(|p| path_dir(p.as_ref())) {
-        Some(p) => p.join(included_path),
-        None => included_path.to_path_buf(),
+impl BeancountSources {
+    /// Read the sources from `root_path` and its includes, resolving relative include paths as specified.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use beancount_parser_lima::{BeancountSources, IncludeResolution};
+    ///
+    /// let resolution = IncludeResolution::default().search_path(["/usr/share/beancount/common"]);
+    /// let sources = BeancountSources::with_include_resolution("main.beancount", resolution).unwrap();
+    /// ```
+    pub fn with_include_resolution<P>(
+        root_path: P,
+        include_resolution: IncludeResolution,
+    ) -> io::Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        Self::try_read_with_includes(root_path.into(), include_resolution)
     }
-}
 
-impl BeancountSources {
-    fn try_read_with_includes(root_path: PathBuf) -> io::Result<Self> {
+    fn try_read_with_includes(
+        root_path: PathBuf,
+        include_resolution: IncludeResolution,
+    ) -> io::Result<Self> {
         let root_content = read(&root_path)?;
-        Ok(Self::read_with_includes(Some(root_path), root_content))
+        Ok(Self::read_with_includes(
+            Some(root_path),
+            root_content,
+            include_resolution,
+        ))
     }
 
-    fn read_with_includes(root_path: Option<PathBuf>, root_content: String) -> Self {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(root_path = ?root_path)))]
+    fn read_with_includes(
+        root_path: Option<PathBuf>,
+        root_content: String,
+        include_resolution: IncludeResolution,
+    ) -> Self {
         let root_source_id = SourceId::default();
         let root_source_id_string = root_path
             .as_ref()
             .map(|p| p.to_string_lossy().into())
             .unwrap_or("inline".to_string());
         let mut source_id_strings = Vec::from([root_source_id_string]);
+        let mut include_depths = Vec::from([0]);
+        let mut includers = Vec::from([None]);
 
         let mut pending_paths = get_includes(&root_content, root_source_id)
             .into_iter()
-            .map(|included_path| resolve_included_path(root_path.as_ref(), included_path.as_ref()))
+            .map(|included_path| {
+                (
+                    include_resolution.resolve(
+                        root_path.as_deref(),
+                        root_path.as_deref(),
+                        included_path.item().as_ref(),
+                    ),
+                    1,
+                    root_source_id,
+                )
+            })
             .collect::<VecDeque<_>>();
 
         let mut included_content: HashMap<PathBuf, IncludedSource> = HashMap::new();
@@ -193,7 +234,9 @@ impl BeancountSources {
             HashSet::from([root_path.as_ref().and_then(|p| p.canonicalize().ok())]);
 
         while !pending_paths.is_empty() {
-            let path = pending_paths.pop_front().unwrap();
+            let ((path, searched), include_depth, includer) = pending_paths.pop_front().unwrap();
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("include", path = ?path).entered();
             let canonical_path = path.canonicalize().ok();
 
             if canonical_paths.contains(&canonical_path) {
@@ -206,10 +249,13 @@ impl BeancountSources {
 
                 let source_id = SourceId::from(source_id_strings.len());
                 source_id_strings.push(path.to_string_lossy().into());
+                include_depths.push(include_depth);
+                includers.push(Some(includer));
 
-                let included_source = read(&path).map_or_else(IncludedSource::IoError, |c| {
-                    IncludedSource::Content(source_id, c)
-                });
+                let included_source = read(&path).map_or_else(
+                    |e| IncludedSource::IoError(IncludeResolution::not_found_error(e, &searched)),
+                    |c| IncludedSource::Content(source_id, c),
+                );
 
                 // stabilisation of VacantEntry::insert_entry() would enable us to avoid cloning the path here
                 // and doing an immediate lookup
@@ -220,7 +266,15 @@ impl BeancountSources {
                     let mut includes = get_includes(content, source_id)
                         .into_iter()
                         .map(|included_path| {
-                            resolve_included_path(Some(&path), included_path.as_ref())
+                            (
+                                include_resolution.resolve(
+                                    root_path.as_deref(),
+                                    Some(&path),
+                                    included_path.item().as_ref(),
+                                ),
+                                include_depth + 1,
+                                source_id,
+                            )
                         })
                         .collect::<VecDeque<_>>();
                     pending_paths.append(&mut includes);
@@ -234,51 +288,47 @@ impl BeancountSources {
             root_content,
             included_content,
             source_id_strings,
+            include_depths,
+            includers,
+            include_resolution,
         }
     }
 
+    /// Write errors or warnings in rich terminal format.  For other formats see [DiagnosticRenderer].
     pub fn write<W, K>(&self, w: W, errors_or_warnings: Vec<ErrorOrWarning<K>>) -> io::Result<()>
     where
         W: Write + Copy,
         K: ErrorOrWarningKind,
     {
-        for error_or_warning in errors_or_warnings.into_iter() {
-            use chumsky::span::Span;
-
-            let src_id = self.span_source_id_string(&error_or_warning.span);
-            let color = error_or_warning.color();
-            let report_kind = error_or_warning.report_kind();
-
-            Report::build(report_kind, src_id.to_string(), error_or_warning.span.start)
-                .with_message(error_or_warning.message)
-                .with_labels(Some(
-                    Label::new((
-                        src_id.to_string(),
-                        error_or_warning.span.start()..error_or_warning.span.end(),
-                    ))
-                    .with_message(error_or_warning.reason)
-                    .with_color(color),
-                ))
-                .with_labels(error_or_warning.contexts.into_iter().map(|(label, span)| {
-                    Label::new((
-                        self.span_source_id_string(&span).to_string(),
-                        span.start()..span.end(),
-                    ))
-                    .with_message(lazy_format!("in this {}", label))
-                    .with_color(Color::Yellow)
-                }))
-                .with_labels(error_or_warning.related.into_iter().map(|(label, span)| {
-                    Label::new((
-                        self.span_source_id_string(&span).to_string(),
-                        span.start()..span.end(),
-                    ))
-                    .with_message(lazy_format!("{}", label))
-                    .with_color(Color::Yellow)
-                }))
-                .finish()
-                .write(ariadne::sources(self.sources()), w)?;
-        }
-        Ok(())
+        TerminalRenderer.render(self, w, errors_or_warnings)
+    }
+
+    /// The raw text of a string literal as written in the sources, without its quotes and with any escapes intact,
+    /// for a `string` such as a narration, whose value is the unescaped form.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveVariant};
+    ///
+    /// let sources = BeancountSources::from(r#"2024-01-01 note Assets:Bank "Caf\u{e9} \"Olé\"""#);
+    /// let parser = BeancountParser::new(&sources);
+    /// let success = parser.parse().unwrap();
+    ///
+    /// let DirectiveVariant::Note(note) = success.directives[0].variant() else {
+    ///     panic!("expected note");
+    /// };
+    ///
+    /// assert_eq!(*note.comment().item(), r#"Café "Olé""#);
+    /// assert_eq!(sources.raw_string(note.comment()), r#"Caf\u{e9} \"Olé\""#);
+    /// ```
+    pub fn raw_string(&self, string: &Spanned<&str>) -> &str {
+        use chumsky::span::Span;
+
+        let span = string.span();
+        let raw = &self.source_content(span.context())[span.start()..span.end()];
+        raw.strip_prefix('"')
+            .and_then(|raw| raw.strip_suffix('"'))
+            .unwrap_or(raw)
     }
 
     fn span_source_id_string(&self, span: &Span) -> &str {
@@ -287,10 +337,44 @@ impl BeancountSources {
         self.source_id_string(span.context())
     }
 
+    fn source_content(&self, source_id: SourceId) -> &str {
+        self.content_iter()
+            .find_map(|(id, _path, content)| (id == source_id).then_some(content))
+            .unwrap_or_default()
+    }
+
+    fn include_depth(&self, source_id: SourceId) -> usize {
+        self.include_depths[Into::<usize>::into(source_id)]
+    }
+
+    /// The name of a source, which is its path, or `inline` for content not read from a file,
+    /// for example as a heading for errors grouped by [ParseError::errors_by_source].
+    pub fn source_name(&self, source_id: SourceId) -> &str {
+        self.source_id_string(source_id)
+    }
+
     fn source_id_string(&self, source_id: SourceId) -> &str {
         self.source_id_strings[Into::<usize>::into(source_id)].as_str()
     }
 
+    fn source_path(&self, source_id: SourceId) -> Option<&Path> {
+        self.content_iter()
+            .find_map(|(id, path, _content)| (id == source_id).then_some(path))
+            .flatten()
+    }
+
+    // the source which included this one, if any
+    fn includer(&self, source_id: SourceId) -> Option<SourceId> {
+        self.includers
+            .get(Into::<usize>::into(source_id))
+            .copied()
+            .flatten()
+    }
+
+    fn contains(&self, source_id: SourceId) -> bool {
+        Into::<usize>::into(source_id) < self.source_id_strings.len()
+    }
+
     fn sources(&self) -> Vec<(String, &str)> {
         once((
             self.source_id_string(self.root_source_id).to_string(),
@@ -319,17 +403,24 @@ impl BeancountSources {
             self.root_path.as_deref(),
             self.root_content.as_str(),
         ))
-        .chain(
-            self.included_content
-                .iter()
-                .filter_map(|(pathbuf, included_source)| {
-                    if let IncludedSource::Content(source_id, content) = included_source {
-                        Some((*source_id, Some(pathbuf.as_path()), content.as_str()))
-                    } else {
-                        None
-                    }
-                }),
-        )
+        .chain(self.included_content_in_order())
+    }
+
+    // included content in order of `SourceId`, which is the order of inclusion, so that parsing is deterministic
+    fn included_content_in_order(&self) -> Vec<(SourceId, Option<&Path>, &str)> {
+        let mut included = self
+            .included_content
+            .iter()
+            .filter_map(|(pathbuf, included_source)| {
+                if let IncludedSource::Content(source_id, content) = included_source {
+                    Some((*source_id, Some(pathbuf.as_path()), content.as_str()))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        included.sort_by_key(|(source_id, _, _)| Into::<usize>::into(*source_id));
+        included
     }
 
     fn error_path_iter(&self) -> impl Iterator<Item = (Option<&Path>, &io::Error)> {
@@ -349,7 +440,7 @@ impl TryFrom<PathBuf> for BeancountSources {
     type Error = io::Error;
 
     fn try_from(source_path: PathBuf) -> io::Result<Self> {
-        Self::try_read_with_includes(source_path)
+        Self::try_read_with_includes(source_path, IncludeResolution::default())
     }
 }
 
@@ -357,19 +448,19 @@ impl TryFrom<&Path> for BeancountSources {
     type Error = io::Error;
 
     fn try_from(source_path: &Path) -> io::Result<Self> {
-        Self::try_read_with_includes(source_path.to_owned())
+        Self::try_read_with_includes(source_path.to_owned(), IncludeResolution::default())
     }
 }
 
 impl From<String> for BeancountSources {
     fn from(source_string: String) -> Self {
-        Self::read_with_includes(None, source_string)
+        Self::read_with_includes(None, source_string, IncludeResolution::default())
     }
 }
 
 impl From<&str> for BeancountSources {
     fn from(source_string: &str) -> Self {
-        Self::read_with_includes(None, source_string.to_owned())
+        Self::read_with_includes(None, source_string.to_owned(), IncludeResolution::default())
     }
 }
 
@@ -395,7 +486,38 @@ impl std::fmt::Debug for BeancountSources {
 }
 
 pub fn lex_with_source(source_id: SourceId, s: &str) -> Vec<(Token, Span)> {
-    lex(s)
+    lex_with_source_and_compat_mode(source_id, s, CompatMode::default())
+}
+
+/// Lex input from `reader` in chunks of at least `chunk_size` bytes, so that memory use is bounded
+/// even for huge generated ledgers, calling `f` with the tokens of each chunk in turn.
+///
+/// The tokens and spans are exactly those of [lex_with_source] on the whole input.
+/// This is for tools which need only the tokens, since [BeancountParser] parses [BeancountSources] read in full.
+pub fn lex_reader_with_source<R, F>(
+    source_id: SourceId,
+    reader: R,
+    chunk_size: usize,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: io::BufRead,
+    F: FnMut(Vec<(Token<'_>, Span)>),
+{
+    lex_chunks(reader, chunk_size, CompatMode::default(), |tokens| {
+        f(tokens
+            .into_iter()
+            .map(|(tok, span)| (tok, chumsky::span::Span::new(source_id, span)))
+            .collect())
+    })
+}
+
+fn lex_with_source_and_compat_mode(
+    source_id: SourceId,
+    s: &str,
+    compat_mode: CompatMode,
+) -> Vec<(Token<'_>, Span)> {
+    lex_with_compat_mode(s, compat_mode)
         .map(|(tok, span)| (tok, chumsky::span::Span::new(source_id, span)))
         .collect::<Vec<_>>()
 }
@@ -414,6 +536,34 @@ where
 
 type SpannedToken<'t> = (Token<'t>, Span);
 
+/// Detect a ledger targeting Beancount v2 from its use of options which v3 no longer accepts.
+fn detect_syntax_version(tokenized_sources: &[Vec<SpannedToken<'_>>]) -> Option<SyntaxVersion> {
+    tokenized_sources
+        .iter()
+        .flat_map(|tokens| tokens.windows(2))
+        .any(|window| match window {
+            [(Token::Option, _), (Token::StringLiteral(name), _)] => {
+                PYTHON_V2_ONLY_OPTIONS.contains(&name.as_str())
+            }
+            _ => false,
+        })
+        .then_some(SyntaxVersion::V2)
+}
+
+/// Whether any source sets the option `allow_pipe_separator`, which affects the parsing of every transaction,
+/// wherever in the ledger the option is given.
+fn allows_pipe_separator(tokenized_sources: &[Vec<SpannedToken<'_>>]) -> bool {
+    tokenized_sources
+        .iter()
+        .flat_map(|tokens| tokens.windows(3))
+        .any(|window| match window {
+            [(Token::Option, _), (Token::StringLiteral(name), _), (Token::StringLiteral(value), _)] => {
+                name.as_str() == "allow_pipe_separator" && value.as_str().eq_ignore_ascii_case("true")
+            }
+            _ => false,
+        })
+}
+
 /// The Beancount parser itself, which tokenizes and parses the source files
 /// contained in `BeancountSources`.
 ///
@@ -457,8 +607,11 @@ type SpannedToken<'t> = (Token<'t>, Span);
 /// ````
 pub struct BeancountParser<'s, 't> {
     sources: &'s BeancountSources,
+    config: ParserConfig,
     // indexed by source_id as per sources
     tokenized_sources: Vec<Vec<SpannedToken<'t>>>,
+    // violations found before parsing
+    limit_errors: Vec<Error>,
 }
 
 // We seem to need to actual input type in places, ugh!
@@ -478,13 +631,50 @@ pub struct ParseSuccess<'t> {
     pub warnings: Vec<Warning>,
 }
 
+/// The value returned when parsing is cancelled, see [BeancountParser::parse_cancellable].
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("parse cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
 /// The value returned when parsing fails.
+///
+/// The errors are in a stable order, by source in order of inclusion, and then by position.
 #[derive(Debug)]
 pub struct ParseError {
     pub errors: Vec<Error>,
     pub warnings: Vec<Warning>,
 }
 
+impl ParseError {
+    /// The errors grouped by source, in the same order as `errors`.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources};
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open\n2024-01-02 open\n");
+    /// let parser = BeancountParser::new(&sources);
+    /// let error = parser.parse().unwrap_err();
+    ///
+    /// for (source_id, errors) in error.errors_by_source() {
+    ///     assert_eq!(sources.source_name(source_id), "inline");
+    ///     assert_eq!(errors.len(), 2);
+    /// }
+    /// ```
+    pub fn errors_by_source(&self) -> impl Iterator<Item = (SourceId, &[Error])> {
+        self.errors
+            .chunk_by(|e1, e2| e1.source_id() == e2.source_id())
+            .map(|errors| (errors[0].source_id(), errors))
+    }
+}
+
 // result of parse_declarations
 type ParseDeclarationsResult<'s, 't> = (
     HashMap<Option<&'s Path>, Vec<Spanned<Declaration<'t>>>>,
@@ -499,46 +689,254 @@ where
 {
     /// Create a `BeancountParser` from `BeancountSources` read from all input files.
     pub fn new(sources: &'s BeancountSources) -> Self {
+        Self::with_config(sources, ParserConfig::default())
+    }
+
+    /// Create a `BeancountParser` with non-default `ParserConfig`.
+    pub fn with_config(sources: &'s BeancountSources, config: ParserConfig) -> Self {
         let mut tokenized_sources = Vec::new();
+        let mut limit_errors = Vec::new();
+        let limits = &config.resource_limits;
+
+        let contents = sources.content_iter().collect::<Vec<_>>();
+        let lexed = map_on_threads(&contents, &config, |(source_id, _path, content)| {
+            let source_id = *source_id;
+
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::info_span!("lex", source = sources.source_id_string(source_id)).entered();
+
+            // sources which exceed limits are not parsed
+            limits
+                .check_source(source_id, content.len(), sources.include_depth(source_id))
+                .map(|()| lex_with_source_and_compat_mode(source_id, content, config.compat_mode))
+                .and_then(|tokens| limits.check_expression_depth(&tokens).map(|()| tokens))
+                .map(|mut tokens| {
+                    config
+                        .normalization
+                        .normalize_transaction_strings(&mut tokens);
+                    tokens
+                })
+        });
+
+        for tokens in lexed {
+            tokenized_sources.push(tokens.unwrap_or_else(|e| {
+                limit_errors.push(e);
+                Vec::new()
+            }));
+        }
 
-        for (source_id, _path, content) in sources.content_iter() {
-            tokenized_sources.push(lex_with_source(source_id, content));
+        let mut config = config;
+        if config.detect_syntax_version
+            && config.syntax_version.is_none()
+            && config.compat_mode == CompatMode::Lima
+        {
+            config.syntax_version = detect_syntax_version(&tokenized_sources);
         }
+        config.allow_pipe_separator = allows_pipe_separator(&tokenized_sources);
 
         BeancountParser {
             sources,
+            config,
             tokenized_sources,
+            limit_errors,
         }
     }
 
+    /// The Beancount syntax version targeted by the sources, whether selected by `ParserConfig` or detected.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, SyntaxVersion};
+    ///
+    /// let sources = BeancountSources::from("option \"allow_pipe_separator\" \"TRUE\"\n");
+    /// let parser = BeancountParser::new(&sources);
+    ///
+    /// // detection is not enabled, see `ParserConfig::detect_syntax_version`
+    /// assert_eq!(parser.syntax_version(), SyntaxVersion::V3);
+    /// ```
+    pub fn syntax_version(&self) -> SyntaxVersion {
+        self.config.selected_syntax_version()
+    }
+
+    /// The spans of all lines which are skipped as not being part of any directive, such as org-mode headlines,
+    /// in order within each source, so that formatters may preserve them.  See [ParserConfig::report_skipped_lines].
+    pub fn skipped_lines(&self) -> Vec<Span> {
+        self.sources
+            .content_iter()
+            .flat_map(|(source_id, _, content)| self.skipped_lines_in_source(source_id, content))
+            .collect()
+    }
+
+    fn skipped_lines_in_source(
+        &self,
+        source_id: SourceId,
+        content: &'s str,
+    ) -> impl Iterator<Item = Span> + '_ {
+        let i_source: usize = source_id.into();
+
+        self.tokenized_sources[i_source]
+            .iter()
+            .filter(|(tok, _)| *tok == Token::Eol)
+            .flat_map(move |(_, span)| {
+                skipped_lines_in_eol(&content[span.start..span.end]).map(move |line| {
+                    chumsky::span::Span::new(
+                        source_id,
+                        span.start + line.start..span.start + line.end,
+                    )
+                })
+            })
+    }
+
     /// Parse the sources, returning date-sorted directives and options, or errors, along with warnings in both cases.
     pub fn parse(&'t self) -> Result<ParseSuccess<'t>, ParseError>
     where
         's: 't,
     {
-        let (parsed_sources, options, mut errors, warnings) = self.parse_declarations();
+        match self.parse_unless_cancelled(None, None) {
+            Ok(result) => result,
+            Err(Cancelled) => unreachable!("parse cancelled without cancellation flag"),
+        }
+    }
+
+    /// Parse the sources as for [parse](Self::parse), but giving up with `Cancelled` as soon as possible after `cancelled` is set,
+    /// which is checked between files and between directives.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, Cancelled};
+    /// use std::sync::atomic::AtomicBool;
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open Assets:Bank GBP\n");
+    /// let parser = BeancountParser::new(&sources);
+    /// let cancelled = AtomicBool::new(true);
+    ///
+    /// assert!(matches!(parser.parse_cancellable(&cancelled), Err(Cancelled)));
+    /// ```
+    pub fn parse_cancellable(
+        &'t self,
+        cancelled: &AtomicBool,
+    ) -> Result<Result<ParseSuccess<'t>, ParseError>, Cancelled>
+    where
+        's: 't,
+    {
+        self.parse_unless_cancelled(Some(cancelled), None)
+    }
+
+    /// Parse the sources as for [parse](Self::parse), also passing each error and warning to `sink` as soon as it is discovered,
+    /// which is after each file is parsed, and for errors in pragmas, after all files are parsed.
+    /// Errors are therefore passed in order of discovery, rather than the order in which they are returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, DiagnosticSink, Error, Warning};
+    ///
+    /// #[derive(Default)]
+    /// struct Counter(usize);
+    ///
+    /// impl DiagnosticSink for Counter {
+    ///     fn error(&mut self, _error: &Error) {
+    ///         self.0 += 1;
+    ///     }
+    ///
+    ///     fn warning(&mut self, _warning: &Warning) {}
+    /// }
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open\n2024-01-02 open\n");
+    /// let parser = BeancountParser::new(&sources);
+    /// let mut counter = Counter::default();
+    ///
+    /// assert!(parser.parse_reporting(&mut counter).is_err());
+    /// assert_eq!(counter.0, 2);
+    /// ```
+    pub fn parse_reporting<S>(&'t self, sink: &mut S) -> Result<ParseSuccess<'t>, ParseError>
+    where
+        's: 't,
+        S: DiagnosticSink,
+    {
+        match self.parse_unless_cancelled(None, Some(sink)) {
+            Ok(result) => result,
+            Err(Cancelled) => unreachable!("parse cancelled without cancellation flag"),
+        }
+    }
+
+    fn parse_unless_cancelled(
+        &'t self,
+        cancelled: Option<&AtomicBool>,
+        mut sink: Option<&mut dyn DiagnosticSink>,
+    ) -> Result<Result<ParseSuccess<'t>, ParseError>, Cancelled>
+    where
+        's: 't,
+    {
+        let is_cancelled = || cancelled.is_some_and(|cancelled| cancelled.load(Ordering::Relaxed));
+
+        report(&mut sink, &self.limit_errors, &[]);
+        let (parsed_sources, options, mut declaration_errors, warnings) =
+            self.parse_declarations(is_cancelled, &mut sink)?;
+        let mut errors = self.limit_errors.clone();
+        errors.append(&mut declaration_errors);
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("post_process").entered();
         let error_paths = self.sources.error_path_iter().collect::<HashMap<_, _>>();
-        let mut p = PragmaProcessor::new(self.root_path(), parsed_sources, error_paths, options);
+        let mut p = PragmaProcessor::new(
+            self.root_path(),
+            &self.sources.include_resolution,
+            parsed_sources,
+            error_paths,
+            options,
+        );
 
         let directives = p
             .by_ref()
+            .take_while(|_| !is_cancelled())
             .sort(|d| *d.item().date().item())
             .collect::<Vec<_>>();
+        if is_cancelled() {
+            return Err(Cancelled);
+        }
         let (options, plugins, mut pragma_errors) = p.result();
+        report(&mut sink, &pragma_errors, &[]);
         errors.append(&mut pragma_errors);
+        sort_by_position(&mut errors);
+        let errors = self.config.reported_errors(errors);
 
         if errors.is_empty() {
-            Ok(ParseSuccess {
+            Ok(Ok(ParseSuccess {
                 directives,
                 options,
                 plugins,
                 warnings,
-            })
+            }))
         } else {
-            Err(ParseError { errors, warnings })
+            Ok(Err(ParseError { errors, warnings }))
         }
     }
 
+    /// Parse the sources as for [parse](Self::parse), also returning statistics about the parse.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources};
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open Assets:Bank GBP\n");
+    /// let parser = BeancountParser::new(&sources);
+    /// let (result, stats) = parser.parse_with_stats();
+    ///
+    /// assert!(result.is_ok());
+    /// assert_eq!(stats.directives().get("open"), Some(&1));
+    /// ```
+    pub fn parse_with_stats(&'t self) -> (Result<ParseSuccess<'t>, ParseError>, ParseStats)
+    where
+        's: 't,
+    {
+        let start = Instant::now();
+        let result = self.parse();
+        let stats = ParseStats::new(self.sources, &result, start.elapsed());
+
+        (result, stats)
+    }
+
     fn root_path(&'t self) -> Option<&'s Path>
     where
         's: 't,
@@ -548,39 +946,170 @@ where
 
     /// Parse the sources, returning declarations and any errors.
     /// The declarations are indexed by SourceId
-    fn parse_declarations(&'t self) -> ParseDeclarationsResult<'s, 't>
+    fn parse_declarations<F>(
+        &'t self,
+        is_cancelled: F,
+        sink: &mut Option<&mut dyn DiagnosticSink>,
+    ) -> Result<ParseDeclarationsResult<'s, 't>, Cancelled>
     where
         's: 't,
+        F: Fn() -> bool,
     {
         let mut all_outputs = HashMap::new();
         let mut all_errors = Vec::new();
-        let mut parser_state = ParserState::default();
+        let mut n_directives = 0;
+        let mut limit_reached = false;
+        let mut parser_state = ParserState {
+            config: self.config.clone(),
+            ..Default::default()
+        };
 
         for (source_id, source_path, content) in self.sources.content_iter() {
+            if is_cancelled() {
+                return Err(Cancelled);
+            }
+
+            // sources beyond the directive limit are not parsed, but are still includable, without further errors
+            if limit_reached {
+                all_outputs.insert(source_path, Vec::new());
+                continue;
+            }
+
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::info_span!("parse", source = self.sources.source_id_string(source_id))
+                    .entered();
             let i_source: usize = source_id.into();
             let tokens = &self.tokenized_sources[i_source];
 
+            // only parse as far as the first directive beyond the limit, so the work for a huge file is bounded
+            let excess_directive = directive_starts(tokens)
+                .nth(
+                    self.config
+                        .resource_limits
+                        .remaining_directives(n_directives)
+                        .unwrap_or(usize::MAX),
+                )
+                .map(|i| (i, tokens[i].1));
+            let (tokens, end_of_input) = match excess_directive {
+                Some((i, span)) => (
+                    &tokens[..i],
+                    chumsky::span::Span::new(source_id, span.start..span.start),
+                ),
+                None => (&tokens[..], end_of_input(source_id, content)),
+            };
+
             // type assertion here is to ensure we keep these in step
-            let spanned_tokens: ConcreteInput = tokens
-                .spanned(end_of_input(source_id, content))
-                .with_context(source_id);
+            let spanned_tokens: ConcreteInput =
+                tokens.spanned(end_of_input).with_context(source_id);
 
+            let (n_errors_before, n_warnings_before) =
+                (all_errors.len(), parser_state.warnings.len());
             let (output, errors) = file(source_path)
                 .parse_with_state(spanned_tokens, &mut parser_state)
                 .into_output_errors();
 
-            all_outputs.insert(source_path, output.unwrap_or(Vec::new()));
-            all_errors.extend(errors);
+            let output = output.unwrap_or(Vec::new());
+            n_directives += output
+                .iter()
+                .filter(|declaration| matches!(declaration.item(), Declaration::Directive(_)))
+                .count();
+            all_outputs.insert(source_path, output);
+            all_errors.extend(errors.into_iter().map(Error::from));
+            parser_state.warnings.extend(
+                self.skipped_lines_in_source(source_id, content)
+                    .filter(|span| {
+                        self.config
+                            .is_reported_skipped_line(&content[span.start..span.end])
+                    })
+                    .map(|span| Warning::new("skipped line", "not part of any directive", span)),
+            );
+
+            if let Some((_, span)) = excess_directive {
+                let max_directives = self.config.resource_limits.max_directives.unwrap_or(0);
+                all_errors.push(limit_error(
+                    format!("directive count exceeds limit of {}", max_directives),
+                    span,
+                ));
+                limit_reached = true;
+            }
+
+            report(
+                sink,
+                &all_errors[n_errors_before..],
+                &parser_state.warnings[n_warnings_before..],
+            );
         }
 
-        let ParserState { options, warnings } = parser_state;
+        let ParserState {
+            options, warnings, ..
+        } = parser_state;
 
-        (
-            all_outputs,
-            Options::new(options),
-            all_errors.into_iter().map(Error::from).collect(),
-            warnings,
-        )
+        Ok((all_outputs, Options::new(options), all_errors, warnings))
+    }
+}
+
+// map `f` over `items` on the configured thread pool, or else on up to `threads` scoped threads,
+// with the results in the order of `items`
+fn map_on_threads<T, U, F>(items: &[T], config: &ParserConfig, f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    #[cfg(feature = "rayon")]
+    if let Some(thread_pool) = config.thread_pool.as_ref() {
+        use rayon::prelude::*;
+
+        return thread_pool.install(|| items.par_iter().map(&f).collect());
+    }
+
+    let threads = config.threads;
+    if threads <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..threads.min(items.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= items.len() {
+                            return results;
+                        }
+                        results.push((i, f(&items[i])));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// a stable order for errors, regardless of the order in which they were discovered
+fn sort_by_position(errors: &mut [Error]) {
+    errors.sort_by_key(|error| (Into::<usize>::into(error.source_id()), error.span.start));
+}
+
+// pass newly discovered errors and warnings to the sink, if any
+fn report(sink: &mut Option<&mut dyn DiagnosticSink>, errors: &[Error], warnings: &[Warning]) {
+    if let Some(sink) = sink {
+        errors.iter().for_each(|error| sink.error(error));
+        warnings.iter().for_each(|warning| sink.warning(warning));
     }
 }
 
@@ -589,6 +1118,8 @@ where
 /// When the iterator is exhausted, any errors should be collected by the caller.
 #[derive(Debug)]
 struct PragmaProcessor<'s, 't> {
+    root_path: Option<PathBuf>,
+    include_resolution: &'s IncludeResolution,
     current_path: Option<PathBuf>,
     current_declarations: VecDeque<Spanned<Declaration<'t>>>,
     stacked: VecDeque<(Option<PathBuf>, VecDeque<Spanned<Declaration<'t>>>)>,
@@ -610,6 +1141,7 @@ where
 {
     fn new(
         root_path: Option<&Path>,
+        include_resolution: &'s IncludeResolution,
         parsed_sources: HashMap<Option<&Path>, Vec<Spanned<Declaration<'t>>>>,
         error_paths: HashMap<Option<&Path>, &'s io::Error>,
         options: Options<'t>,
@@ -629,6 +1161,8 @@ where
         let current_declarations = remaining.remove(&current_path).unwrap();
 
         PragmaProcessor {
+            root_path: root_path.map(|p| p.to_path_buf()),
+            include_resolution,
             current_path,
             current_declarations,
             stacked: VecDeque::new(),
@@ -764,10 +1298,15 @@ where
                             }
                             Include(relpath) => {
                                 let (path, span) = (
-                                    Some(resolve_included_path(
-                                        self.current_path.as_ref(),
-                                        AsRef::<Path>::as_ref(*relpath.item()),
-                                    )),
+                                    Some(
+                                        self.include_resolution
+                                            .resolve(
+                                                self.root_path.as_deref(),
+                                                self.current_path.as_deref(),
+                                                AsRef::<Path>::as_ref(*relpath.item()),
+                                            )
+                                            .0,
+                                    ),
                                     *relpath.span(),
                                 );
                                 let canonical_path =
@@ -847,16 +1386,136 @@ where
     }
 }
 
+// indices of the tokens which start a line with a date, being those which start a directive
+fn directive_starts<'a>(tokens: &'a [SpannedToken<'_>]) -> impl Iterator<Item = usize> + 'a {
+    tokens.iter().enumerate().filter_map(|(i, (token, _))| {
+        (matches!(token, Token::Date(_)) && (i == 0 || tokens[i - 1].0 == Token::Eol)).then_some(i)
+    })
+}
+
 fn end_of_input(source_id: SourceId, s: &str) -> Span {
     chumsky::span::Span::new(source_id, s.len()..s.len())
 }
 
+pub use aggregate::{aggregate, Aggregation, Bucket, Interval};
+mod aggregate;
+pub use amortize::Amortize;
+mod amortize;
+pub use booking::{booked_postings, BookedPosting, Cost, Inventory, Position, ReductionError};
+mod booking;
+pub use budget::{Budget, BudgetInterval, Budgets};
+mod budget;
+#[cfg(feature = "toml")]
+pub use categorize::RulesError;
+pub use categorize::{Categorization, Categorizer, ImportedTransaction, Rule, Rules};
+mod categorize;
+pub use clearing::Clearing;
+mod clearing;
+pub use columns::{PostingColumns, PriceColumns};
+mod columns;
+pub use config::{
+    CompatMode, IncludeResolution, Normalization, ParserConfig, ResourceLimits, SyntaxVersion,
+};
+mod config;
+pub use context::Context;
+mod context;
+pub use cursor::{Cursor, Node, SyntaxTree};
+mod cursor;
+pub use date_range::{DateRange, DateRangeError};
+mod date_range;
+pub use definitions::Definitions;
+mod definitions;
+pub use events::{Events, Timeline};
+mod events;
+pub use fixes::{Fix, TextEdit};
+mod fixes;
+pub use flagged::{Age, Flagged, FlaggedItem};
+mod flagged;
 #[cfg(test)]
 pub use lexer::bare_lex;
 mod format;
+pub use holdings::{holdings, Holdings};
+mod holdings;
+pub use ical::ICalExport;
+mod ical;
+pub use identity::{DirectiveId, DirectiveIds};
+mod identity;
+pub use import::{Duplicate, ImportCandidate, Importer};
+mod import;
+pub use index::{IndexEntry, LedgerIndex};
+mod index;
+pub use interpolation::{interpolated_postings, InterpolatedPosting};
+mod interpolation;
 mod lexer;
+pub use lints::{
+    duplicate_balance_assertions, nonzero_closes, unopened_accounts, CommodityLints, PayeeLints,
+    PostingLints,
+};
+mod lints;
+pub use merge::{merge, MergeConflict, Merged};
+mod merge;
 pub use options::Options;
 mod options;
+pub use origin::DirectiveSource;
+mod origin;
 mod parsers;
+pub use paths::{DiscoveredDocument, DiscoveredDocuments, PathResolver, ResolvedPath};
+mod paths;
+pub use payees::{PayeeSummaries, PayeeSummary};
+mod payees;
+pub use pipeline::{Pipeline, Transform, Transformed};
+mod pipeline;
+pub use price_requests::{PriceRequest, PriceRequests, PriceSource};
+mod price_requests;
+pub use prices::PriceDb;
+mod prices;
+pub use qif::QifExport;
+mod qif;
+pub use recurring::{recurrences, Recurring};
+mod recurring;
+pub use references::{Reference, References};
+mod references;
+pub use register::{register, Register, RegisterRow};
+mod register;
+pub use render::{
+    BeanCheckRenderer, DiagnosticRenderer, DiagnosticSink, HtmlRenderer, JsonLinesWriter,
+    JsonRenderer, PlainRenderer, TerminalRenderer,
+};
+mod render;
+#[cfg(feature = "schemars")]
+pub use schema::{diagnostics_schema, directives_schema};
+#[cfg(feature = "schemars")]
+mod schema;
+pub use session::EditSession;
+mod session;
+pub use sort::sort_directives;
 mod sort;
+pub use split::{split_by_period, Period, PeriodFile, SplitLedger};
+mod split;
+pub use statements::{statements, Statements};
+mod statements;
+pub use stats::{FileStats, LedgerStats, ParseStats};
+mod stats;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+pub use store::LedgerStore;
+mod store;
+pub use synthetic::SyntheticLedger;
+mod synthetic;
+pub use tag_link_index::TagLinkIndex;
+mod tag_link_index;
+pub use trial_balance::{check_balances, trial_balance, AccountTotals, TrialBalance, Units};
+#[cfg(test)]
+mod test_support;
+mod trial_balance;
 pub mod types;
+pub use unrealized::{unrealized_gains, UnrealizedGain};
+mod unrealized;
+pub use valuation::{valuations, Valuations};
+mod valuation;
+pub use view::{LedgerView, LedgerViewIter};
+mod view;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub use writeback::WriteBack;
+mod writeback;
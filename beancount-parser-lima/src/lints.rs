@@ -0,0 +1,730 @@
+use crate::{
+    booking::book_regardless,
+    format::string_literal,
+    interpolation::{add, tolerance, weight},
+    types::*,
+    Fix, Options, Reference, References,
+};
+use chumsky::span::Span as _;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use time::Date;
+
+/// Find balance assertions for the same account, currency, and date as an earlier one,
+/// warning of those which conflict with it, and those which are redundant, asserting the same amount again.
+///
+/// Each warning is on the later assertion, and related to the earlier one.
+/// Redundant assertions have a fix which removes them.
+/// Assertions restricted to lots with a cost are compared only with those for the same cost.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{duplicate_balance_assertions, BeancountParser, BeancountSources};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-02-01 balance Assets:Bank 100.00 NZD
+/// 2024-02-01 balance Assets:Bank 100.00 NZD
+/// 2024-02-01 balance Assets:Bank 90.00 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let warnings = duplicate_balance_assertions(&directives);
+///
+/// assert_eq!(
+///     warnings.iter().map(|w| w.message()).collect::<Vec<_>>(),
+///     vec!["redundant balance assertion", "conflicting balance assertions"]
+/// );
+/// ```
+pub fn duplicate_balance_assertions<'a, I>(directives: I) -> Vec<Warning>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let mut first_assertions =
+        HashMap::<(Date, &Account, Currency, Option<String>), &Spanned<Directive>>::new();
+    let mut warnings = Vec::new();
+
+    for directive in directives {
+        let DirectiveVariant::Balance(balance) = directive.variant() else {
+            continue;
+        };
+        let amount = balance.atol().amount();
+        let key = (
+            *directive.date().item(),
+            balance.account().item(),
+            *amount.currency().item(),
+            balance
+                .cost_spec()
+                .map(|cost_spec| cost_spec.item().to_string()),
+        );
+
+        match first_assertions.get(&key) {
+            None => {
+                first_assertions.insert(key, directive);
+            }
+            Some(first) => {
+                let DirectiveVariant::Balance(first_balance) = first.variant() else {
+                    unreachable!("only balance assertions are recorded");
+                };
+                let first_amount = first_balance.atol().amount();
+                let (expected, first_expected) =
+                    (amount.number().value(), first_amount.number().value());
+
+                let warning = if expected == first_expected {
+                    Warning::new(
+                        "redundant balance assertion",
+                        format!("same amount {} {} already asserted", expected, key.2),
+                        *directive.span(),
+                    )
+                    .related_to(*first)
+                    .with_fix(
+                        Fix::new("remove redundant balance assertion")
+                            .replace(*directive.span(), ""),
+                    )
+                } else {
+                    Warning::new(
+                        "conflicting balance assertions",
+                        format!(
+                            "asserts {} {} but {} {} already asserted",
+                            expected, key.2, first_expected, key.2
+                        ),
+                        *balance.atol().span(),
+                    )
+                    .in_context(directive)
+                    .related_to(*first)
+                };
+                warnings.push(warning);
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Lints on the amounts of postings, each of which is enabled separately, none by default.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, PostingLints};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Expenses:Fees
+/// 2024-02-01 * "Fee"
+///   Assets:Bank    -0.00 NZD
+///   Expenses:Fees   0.00 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let result = parser.parse().unwrap();
+/// let lints = PostingLints::default().zero_amounts(true);
+/// let warnings = lints.check(&result.directives, &result.options);
+///
+/// assert_eq!(warnings.len(), 2);
+/// assert_eq!(warnings[0].message(), "zero amount");
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PostingLints {
+    zero_amounts: bool,
+    tiny_residuals: bool,
+    excess_precision: bool,
+}
+
+impl PostingLints {
+    /// Warn of postings whose amount is exactly zero.
+    pub fn zero_amounts(mut self, zero_amounts: bool) -> Self {
+        self.zero_amounts = zero_amounts;
+        self
+    }
+
+    /// Warn of transactions with every amount given, whose residual is not zero,
+    /// but small enough to be within tolerance, so that the imprecision would pass silently.
+    ///
+    /// The tolerance for each currency is as for [interpolated_postings](crate::interpolated_postings).
+    pub fn tiny_residuals(mut self, tiny_residuals: bool) -> Self {
+        self.tiny_residuals = tiny_residuals;
+        self
+    }
+
+    /// Warn of posting amounts with more decimal places than the display precision of their currency,
+    /// which is the number of decimal places most commonly used for that currency across the directives.
+    pub fn excess_precision(mut self, excess_precision: bool) -> Self {
+        self.excess_precision = excess_precision;
+        self
+    }
+
+    /// Check the postings of all transactions, returning warnings in directive order.
+    pub fn check<'a, I>(&self, directives: I, options: &Options<'_>) -> Vec<Warning>
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let transactions = directives
+            .into_iter()
+            .filter_map(|directive| match directive.variant() {
+                DirectiveVariant::Transaction(transaction) => Some((directive, transaction)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let display_precisions = if self.excess_precision {
+            display_precisions(transactions.iter().map(|(_, transaction)| *transaction))
+        } else {
+            HashMap::new()
+        };
+        let mut warnings = Vec::new();
+
+        for (directive, transaction) in transactions {
+            for posting in transaction.postings() {
+                let (Some(amount), Some(currency)) = (posting.amount(), posting.currency()) else {
+                    continue;
+                };
+                let number = amount.item().value();
+
+                if self.zero_amounts && number.is_zero() {
+                    warnings.push(
+                        Warning::new(
+                            "zero amount",
+                            format!("posting to {} is zero", posting.account().item()),
+                            *amount.span(),
+                        )
+                        .in_context(directive),
+                    );
+                }
+
+                if let Some(precision) = display_precisions.get(currency.item()) {
+                    if number.scale() > *precision {
+                        warnings.push(
+                            Warning::new(
+                                "excess precision",
+                                format!(
+                                    "{} has {} decimal places but {} is displayed with {}",
+                                    number,
+                                    number.scale(),
+                                    currency.item(),
+                                    precision
+                                ),
+                                *amount.span(),
+                            )
+                            .in_context(directive),
+                        );
+                    }
+                }
+            }
+
+            if self.tiny_residuals {
+                if let Some(warning) = tiny_residual(directive, transaction, options) {
+                    warnings.push(warning);
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+// the most common number of decimal places for each currency, preferring the greater on a tie
+fn display_precisions<'a>(
+    transactions: impl Iterator<Item = &'a Transaction<'a>>,
+) -> HashMap<Currency<'a>, u32> {
+    let mut counts = HashMap::<Currency<'a>, HashMap<u32, usize>>::new();
+    for posting in transactions.flat_map(|transaction| transaction.postings()) {
+        if let (Some(amount), Some(currency)) = (posting.amount(), posting.currency()) {
+            *counts
+                .entry(*currency.item())
+                .or_default()
+                .entry(amount.item().value().scale())
+                .or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter_map(|(currency, scales)| {
+            scales
+                .into_iter()
+                .max_by_key(|(scale, count)| (*count, *scale))
+                .map(|(scale, _)| (currency, scale))
+        })
+        .collect()
+}
+
+/// Find accounts which are used without being opened, warning of the first use of each,
+/// with a fix which adds an `open` directive dated at that first use, immediately before it,
+/// constrained to the currencies held in the account by its postings and balance assertions.
+///
+/// This helps bootstrap a strict ledger from one which relied on accounts being opened implicitly.
+/// Warnings are in order of first use.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{unopened_accounts, BeancountParser, BeancountSources};
+///
+/// let s = r#"2024-01-01 open Assets:Bank
+/// 2024-02-01 * "Groceries"
+///   Assets:Bank    -10.00 NZD
+///   Expenses:Food
+/// "#;
+/// let sources = BeancountSources::from(s);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let warnings = unopened_accounts(&directives);
+///
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].fixes()[0].title(), "add open directive for Expenses:Food");
+/// assert_eq!(
+///     sources.apply_fixes(warnings[0].fixes())[0].1,
+///     s.replace("2024-02-01", "2024-02-01 open Expenses:Food\n2024-02-01")
+/// );
+/// ```
+pub fn unopened_accounts<'a, I>(directives: I) -> Vec<Warning>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    use DirectiveVariant::*;
+
+    let mut opened = HashSet::<&Account>::new();
+    let mut first_uses = HashMap::<&Account, FirstUse>::new();
+
+    for (order, directive) in directives.into_iter().enumerate() {
+        let mut used = |account: &'a Spanned<Account<'a>>, currency: Option<&Currency<'a>>| {
+            let first_use = first_uses
+                .entry(account.item())
+                .or_insert_with(|| FirstUse {
+                    directive,
+                    account: *account.span(),
+                    order,
+                    currencies: BTreeSet::new(),
+                });
+            if (directive.date().item(), order)
+                < (first_use.directive.date().item(), first_use.order)
+            {
+                first_use.directive = directive;
+                first_use.account = *account.span();
+                first_use.order = order;
+            }
+            if let Some(currency) = currency {
+                first_use.currencies.insert(currency.to_string());
+            }
+        };
+
+        match directive.variant() {
+            Transaction(transaction) => {
+                for posting in transaction.postings() {
+                    used(
+                        posting.account(),
+                        posting.currency().map(|currency| currency.item()),
+                    );
+                }
+            }
+            Balance(balance) => used(
+                balance.account(),
+                Some(balance.atol().amount().currency().item()),
+            ),
+            Open(open) => {
+                opened.insert(open.account().item());
+            }
+            Close(close) => used(close.account(), None),
+            Pad(pad) => {
+                used(pad.account(), None);
+                used(pad.source(), None);
+            }
+            Document(document) => used(document.account(), None),
+            Note(note) => used(note.account(), None),
+            Price(_) | Commodity(_) | Event(_) | Query(_) | Custom(_) => (),
+        }
+    }
+
+    let mut unopened = first_uses
+        .into_iter()
+        .filter(|(account, _)| !opened.contains(account))
+        .collect::<Vec<_>>();
+    // accounts first used in the same directive are in order of their use there
+    unopened.sort_by_key(|(_, first_use)| {
+        (
+            *first_use.directive.date().item(),
+            first_use.order,
+            first_use.account.start,
+        )
+    });
+
+    unopened
+        .into_iter()
+        .map(|(account, first_use)| {
+            let date = first_use.directive.date().item();
+            let open = if first_use.currencies.is_empty() {
+                format!("{} open {}\n", date, account)
+            } else {
+                let currencies = first_use.currencies.into_iter().collect::<Vec<_>>();
+                format!("{} open {} {}\n", date, account, currencies.join(","))
+            };
+
+            Warning::new(
+                "account not open",
+                format!("{} is used without an open directive", account),
+                first_use.account,
+            )
+            .in_context(first_use.directive)
+            .with_fix(
+                Fix::new(format!("add open directive for {}", account))
+                    .insert_before(*first_use.directive.span(), open),
+            )
+        })
+        .collect()
+}
+
+struct FirstUse<'a> {
+    directive: &'a Spanned<Directive<'a>>,
+    account: Span,
+    order: usize,
+    currencies: BTreeSet<String>,
+}
+
+/// Find `close` directives for accounts which still hold positions at the close date,
+/// as booked by [booked_postings](crate::booked_postings), including any transactions on that date.
+///
+/// Each warning lists the outstanding positions.
+/// Transactions which fail to book are ignored, since errors in booking are reported by that.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{nonzero_closes, BeancountParser, BeancountSources};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Equity:Opening
+/// 2024-01-02 * "Opening balance"
+///   Assets:Bank     100.00 NZD
+///   Equity:Opening
+/// 2024-06-30 close Assets:Bank
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let result = parser.parse().unwrap();
+/// let warnings = nonzero_closes(&result.directives, &result.options);
+///
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].message(), "closing account with outstanding balance");
+/// ```
+pub fn nonzero_closes<'a, I>(directives: I, options: &Options<'_>) -> Vec<Warning>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let directives = directives.into_iter().collect::<Vec<_>>();
+    let closes = directives
+        .iter()
+        .filter_map(|directive| match directive.variant() {
+            DirectiveVariant::Close(close) => Some((*directive, close)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // book once for each date on which any account is closed
+    let mut inventories_by_date = HashMap::new();
+    let mut warnings = Vec::new();
+
+    for (directive, close) in closes {
+        let date = *directive.date().item();
+        let inventories = inventories_by_date
+            .entry(date)
+            .or_insert_with(|| book_regardless(directives.iter().copied(), options, date).0);
+
+        if let Some(inventory) = inventories.get(close.account().item()) {
+            let positions = inventory
+                .positions()
+                .map(|position| position.to_string())
+                .collect::<Vec<_>>();
+            warnings.push(
+                Warning::new(
+                    "closing account with outstanding balance",
+                    format!(
+                        "{} holds {} at close",
+                        close.account().item(),
+                        positions.join(", ")
+                    ),
+                    *close.account().span(),
+                )
+                .in_context(directive),
+            );
+        }
+    }
+
+    warnings
+}
+
+/// Lints on the declaration of currencies by `commodity` directives, each of which is enabled separately, none by default.
+///
+/// These are for ledgers following the convention that every currency is declared.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, CommodityLints};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 commodity NZD
+/// 2024-01-01 commodity USD
+/// 2024-01-01 open Assets:Bank NZD
+/// 2024-02-01 balance Assets:Bank 0.00 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let lints = CommodityLints::default().unused(true);
+/// let warnings = lints.check(&directives);
+///
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].message(), "unused commodity");
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CommodityLints {
+    undeclared: bool,
+    unused: bool,
+}
+
+impl CommodityLints {
+    /// Warn of the first use of each currency which has no `commodity` directive,
+    /// with a fix which adds one dated at that first use, immediately before it.
+    pub fn undeclared(mut self, undeclared: bool) -> Self {
+        self.undeclared = undeclared;
+        self
+    }
+
+    /// Warn of `commodity` directives for currencies which are not otherwise referenced,
+    /// with a fix which removes the directive.
+    pub fn unused(mut self, unused: bool) -> Self {
+        self.unused = unused;
+        self
+    }
+
+    /// Check all references to currencies, returning warnings of undeclared currencies in order of first use,
+    /// followed by those of unused commodities in directive order.
+    pub fn check<'a, I>(&self, directives: I) -> Vec<Warning>
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let directives = directives.into_iter().collect::<Vec<_>>();
+        let references = References::new(directives.iter().copied());
+        let is_commodity = |reference: &Reference<'_>| {
+            matches!(
+                reference.directive().variant(),
+                DirectiveVariant::Commodity(_)
+            )
+        };
+        let mut warnings = Vec::new();
+
+        if self.undeclared {
+            let mut first_uses = references
+                .currencies()
+                .filter(|currency| !references.of_currency(currency).iter().any(is_commodity))
+                .filter_map(|currency| {
+                    references
+                        .of_currency(&currency)
+                        .iter()
+                        .min_by_key(|reference| *reference.directive().date().item())
+                        .map(|reference| (currency, *reference))
+                })
+                .collect::<Vec<_>>();
+            first_uses.sort_by_key(|(_, reference)| {
+                (*reference.directive().date().item(), reference.span().start)
+            });
+
+            warnings.extend(first_uses.into_iter().map(|(currency, reference)| {
+                let directive = reference.directive();
+                Warning::new(
+                    "undeclared commodity",
+                    format!("{} is used without a commodity directive", currency),
+                    reference.span(),
+                )
+                .in_context(directive)
+                .with_fix(
+                    Fix::new(format!("add commodity directive for {}", currency)).insert_before(
+                        *directive.span(),
+                        format!("{} commodity {}\n", directive.date().item(), currency),
+                    ),
+                )
+            }));
+        }
+
+        if self.unused {
+            for directive in directives {
+                let DirectiveVariant::Commodity(commodity) = directive.variant() else {
+                    continue;
+                };
+                let currency = commodity.currency().item();
+                if references.of_currency(currency).iter().all(is_commodity) {
+                    warnings.push(
+                        Warning::new(
+                            "unused commodity",
+                            format!("{} is declared but never used", currency),
+                            *commodity.currency().span(),
+                        )
+                        .in_context(directive)
+                        .with_fix(
+                            Fix::new(format!("remove commodity directive for {}", currency))
+                                .replace(*directive.span(), ""),
+                        ),
+                    );
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Lints for transactions whose payee and narration look to have been confused,
+/// enabled by giving payees to recognise, either explicitly or by inference.
+///
+/// A single string is the narration, so a transaction with just a payee written is flagged,
+/// with a fix which adds an empty narration.
+/// Conversely, a transaction with an empty narration whose payee is not recognised is flagged,
+/// with a fix which makes the payee the narration.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, PayeeLints};
+///
+/// let s = r#"2024-02-01 * "Countdown"
+///   Assets:Bank    -10.00 NZD
+///   Expenses:Food
+/// "#;
+/// let sources = BeancountSources::from(s);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let lints = PayeeLints::default().known_payees(["Countdown"]);
+/// let warnings = lints.check(&directives);
+///
+/// assert_eq!(warnings[0].message(), "narration looks like a payee");
+/// assert_eq!(
+///     sources.apply_fixes(warnings[0].fixes())[0].1,
+///     s.replace(r#""Countdown""#, r#""Countdown" """#)
+/// );
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct PayeeLints {
+    known_payees: HashSet<String>,
+    infer_payees: bool,
+}
+
+impl PayeeLints {
+    /// Recognise `payees` as payees, in addition to any already known.
+    pub fn known_payees<I, S>(mut self, payees: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.known_payees
+            .extend(payees.into_iter().map(|payee| payee.into()));
+        self
+    }
+
+    /// Recognise as payees those of transactions which have both a payee and a non-empty narration.
+    pub fn infer_payees(mut self, infer_payees: bool) -> Self {
+        self.infer_payees = infer_payees;
+        self
+    }
+
+    /// Check the payee and narration of all transactions, returning warnings in directive order.
+    pub fn check<'a, I>(&self, directives: I) -> Vec<Warning>
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        // with no payees to recognise, every payee would be suspect
+        if self.known_payees.is_empty() && !self.infer_payees {
+            return Vec::new();
+        }
+
+        let transactions = directives
+            .into_iter()
+            .filter_map(|directive| match directive.variant() {
+                DirectiveVariant::Transaction(transaction) => Some((directive, transaction)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut known_payees = self
+            .known_payees
+            .iter()
+            .map(String::as_str)
+            .collect::<HashSet<_>>();
+        if self.infer_payees {
+            known_payees.extend(transactions.iter().filter_map(|(_, transaction)| {
+                transaction
+                    .narration()
+                    .filter(|narration| !narration.item().is_empty())
+                    .and(transaction.payee())
+                    .map(|payee| *payee.item())
+            }));
+        }
+        let mut warnings = Vec::new();
+
+        for (directive, transaction) in transactions {
+            match (transaction.payee(), transaction.narration()) {
+                (None, Some(narration)) if known_payees.contains(narration.item()) => {
+                    warnings.push(
+                        Warning::new(
+                            "narration looks like a payee",
+                            format!("{} is a payee but is written as the narration", narration),
+                            *narration.span(),
+                        )
+                        .in_context(directive)
+                        .with_fix(
+                            Fix::new("add empty narration after payee").replace(
+                                *narration.span(),
+                                format!("{} \"\"", string_literal(narration.item())),
+                            ),
+                        ),
+                    );
+                }
+                (Some(payee), Some(narration))
+                    if narration.item().is_empty() && !known_payees.contains(payee.item()) =>
+                {
+                    let span = Span::new(
+                        payee.span().context(),
+                        payee.span().start()..narration.span().end(),
+                    );
+                    warnings.push(
+                        Warning::new(
+                            "payee looks like a narration",
+                            format!("{} is not a payee and the narration is empty", payee),
+                            *payee.span(),
+                        )
+                        .in_context(directive)
+                        .with_fix(
+                            Fix::new("make payee the narration")
+                                .replace(span, string_literal(payee.item()).to_string()),
+                        ),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        warnings
+    }
+}
+
+fn tiny_residual(
+    directive: &Spanned<Directive<'_>>,
+    transaction: &Transaction<'_>,
+    options: &Options<'_>,
+) -> Option<Warning> {
+    let mut residual = Vec::new();
+
+    for posting in transaction.postings() {
+        // with an amount elided the residual is absorbed by interpolation
+        let (amount, currency) = (posting.amount()?, posting.currency()?);
+        let (weight_currency, weight) = weight(posting, *currency.item(), amount.item().value());
+        add(&mut residual, weight_currency, weight);
+    }
+
+    let tiny = residual
+        .into_iter()
+        .filter(|(currency, number)| {
+            !number.is_zero() && number.abs() <= tolerance(transaction, options, *currency)
+        })
+        .map(|(currency, number)| format!("{} {}", number, currency))
+        .collect::<Vec<_>>();
+
+    (!tiny.is_empty()).then(|| {
+        Warning::new(
+            "residual within tolerance",
+            format!(
+                "transaction does not balance exactly, residual {}",
+                tiny.join(", ")
+            ),
+            *directive.span(),
+        )
+    })
+}
+
+mod tests;
@@ -0,0 +1,317 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+#[test]
+fn duplicate_balance_assertions_by_account_currency_date() {
+    let s = r#"2024-01-01 open Assets:Bank
+2024-02-01 balance Assets:Bank 100.00 NZD
+2024-02-01 balance Assets:Bank 100 NZD
+2024-02-01 balance Assets:Bank 90.00 NZD
+2024-02-01 balance Assets:Bank 100.00 USD
+2024-02-01 balance Assets:Cash 100.00 NZD
+2024-02-02 balance Assets:Bank 100.00 NZD
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let warnings = duplicate_balance_assertions(&directives);
+    let line = |span: Span| s[..span.start].matches('\n').count() + 1;
+
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (
+                w.message(),
+                &*w.reason,
+                line(w.span),
+                w.related
+                    .iter()
+                    .map(|(_, span)| line(*span))
+                    .collect::<Vec<_>>()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "redundant balance assertion",
+                "same amount 100 NZD already asserted",
+                3,
+                vec![2]
+            ),
+            (
+                "conflicting balance assertions",
+                "asserts 90.00 NZD but 100.00 NZD already asserted",
+                4,
+                vec![2]
+            ),
+        ]
+    );
+
+    let fixed = sources.apply_fixes(warnings[0].fixes());
+    assert_eq!(
+        fixed[0].1,
+        s.replace("2024-02-01 balance Assets:Bank 100 NZD\n", "")
+    );
+}
+
+#[test]
+fn posting_lints_each_enabled_separately() {
+    let s = r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-02-01 * "Exact"
+  Assets:Bank    -10.00 NZD
+  Expenses:Food   10.00 NZD
+2024-02-02 * "Zero"
+  Assets:Bank      0.00 NZD
+  Expenses:Food
+2024-02-03 * "Tiny residual"
+  Assets:Bank    -10.00 NZD
+  Expenses:Food   10.004 NZD
+2024-02-04 * "Large residual is not tiny"
+  Assets:Bank    -10.00 NZD
+  Expenses:Food   11.00 NZD
+2024-02-05 * "Elided"
+  Assets:Bank    -3.333 NZD
+  Expenses:Food
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let result = parser.parse().unwrap();
+    let line = |span: Span| s[..span.start].matches('\n').count() + 1;
+    let check = |lints: PostingLints| {
+        lints
+            .check(&result.directives, &result.options)
+            .into_iter()
+            .map(|w| (w.message().to_string(), w.reason.to_string(), line(w.span)))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(check(PostingLints::default()).is_empty());
+
+    assert_eq!(
+        check(PostingLints::default().zero_amounts(true)),
+        vec![(
+            "zero amount".to_string(),
+            "posting to Assets:Bank is zero".to_string(),
+            7
+        )]
+    );
+
+    assert_eq!(
+        check(PostingLints::default().tiny_residuals(true)),
+        vec![(
+            "residual within tolerance".to_string(),
+            "transaction does not balance exactly, residual 0.004 NZD".to_string(),
+            9
+        )]
+    );
+
+    assert_eq!(
+        check(PostingLints::default().excess_precision(true)),
+        vec![
+            (
+                "excess precision".to_string(),
+                "10.004 has 3 decimal places but NZD is displayed with 2".to_string(),
+                11
+            ),
+            (
+                "excess precision".to_string(),
+                "-3.333 has 3 decimal places but NZD is displayed with 2".to_string(),
+                16
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unopened_accounts_opened_at_first_use_with_currencies() {
+    let s = r#"2024-01-01 open Assets:Bank
+2024-02-01 * "Groceries"
+  Assets:Bank    -10.00 NZD
+  Expenses:Food   10.00 NZD
+2024-02-02 balance Assets:Cash 5.00 USD
+2024-01-15 * "Earlier lunch"
+  Assets:Cash    -4.00 NZD
+  Expenses:Food
+2024-02-03 note Liabilities:Card "No currency known"
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let warnings = unopened_accounts(&directives);
+    let line = |span: Span| s[..span.start].matches('\n').count() + 1;
+
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (&*w.reason, line(w.span)))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Assets:Cash is used without an open directive", 7),
+            ("Expenses:Food is used without an open directive", 8),
+            ("Liabilities:Card is used without an open directive", 9),
+        ]
+    );
+
+    let opens = warnings
+        .iter()
+        .map(|w| w.fixes()[0].edits()[0].replacement())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        opens,
+        vec![
+            "2024-01-15 open Assets:Cash NZD,USD\n",
+            "2024-01-15 open Expenses:Food NZD\n",
+            "2024-02-03 open Liabilities:Card\n",
+        ]
+    );
+}
+
+#[test]
+fn nonzero_closes_at_close_date() {
+    let s = r#"2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Broker
+2024-01-01 open Assets:Cash
+2024-01-01 open Equity:Opening
+2024-01-02 * "Opening balance"
+  Assets:Bank     100.00 NZD
+  Assets:Cash      20.00 NZD
+  Equity:Opening
+2024-01-03 * "Buy"
+  Assets:Broker    10 HOOL {5.00 NZD}
+  Assets:Bank     -50.00 NZD
+2024-06-30 * "Withdraw"
+  Assets:Cash     -20.00 NZD
+  Assets:Bank      20.00 NZD
+2024-06-30 close Assets:Cash
+2024-06-30 close Assets:Broker
+2024-07-01 * "Sell"
+  Assets:Broker   -10 HOOL {5.00 NZD}
+  Assets:Bank      50.00 NZD
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let result = parser.parse().unwrap();
+
+    let warnings = nonzero_closes(&result.directives, &result.options);
+    let line = |span: Span| s[..span.start].matches('\n').count() + 1;
+
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (&*w.reason, line(w.span)))
+            .collect::<Vec<_>>(),
+        vec![(
+            "Assets:Broker holds 10 HOOL {5.00 NZD, 2024-01-03} at close",
+            16
+        )]
+    );
+}
+
+#[test]
+fn commodity_lints_each_enabled_separately() {
+    let s = r#"2024-01-01 commodity NZD
+2024-01-01 commodity GBP
+2024-01-01 open Assets:Bank NZD
+2024-01-01 open Assets:Broker
+2024-02-01 * "Buy"
+  Assets:Broker  10 HOOL {1.50 USD}
+  Assets:Bank   -20.00 NZD @ 0.75 USD
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let line = |span: Span| s[..span.start].matches('\n').count() + 1;
+    let check = |lints: CommodityLints| {
+        lints
+            .check(&directives)
+            .into_iter()
+            .map(|w| (w.reason.to_string(), line(w.span)))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(check(CommodityLints::default()).is_empty());
+
+    assert_eq!(
+        check(CommodityLints::default().undeclared(true)),
+        vec![
+            ("HOOL is used without a commodity directive".to_string(), 6),
+            ("USD is used without a commodity directive".to_string(), 6),
+        ]
+    );
+
+    let warnings = CommodityLints::default().unused(true).check(&directives);
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (w.reason.to_string(), line(w.span)))
+            .collect::<Vec<_>>(),
+        vec![("GBP is declared but never used".to_string(), 2)]
+    );
+    assert_eq!(
+        sources.apply_fixes(warnings[0].fixes())[0].1,
+        s.replace("2024-01-01 commodity GBP\n", "")
+    );
+}
+
+#[test]
+fn payee_lints_known_and_inferred() {
+    let s = r#"2024-02-01 * "Countdown" "Groceries"
+  Assets:Bank    -10.00 NZD
+  Expenses:Food
+2024-02-02 * "Countdown"
+  Assets:Bank    -10.00 NZD
+  Expenses:Food
+2024-02-03 * "Z Energy"
+  Assets:Bank    -10.00 NZD
+  Expenses:Fuel
+2024-02-04 * "Lunch with friends" ""
+  Assets:Bank    -10.00 NZD
+  Expenses:Food
+2024-02-05 * "Z Energy" ""
+  Assets:Bank    -10.00 NZD
+  Expenses:Fuel
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let line = |span: Span| s[..span.start].matches('\n').count() + 1;
+    let check = |lints: PayeeLints| {
+        lints
+            .check(&directives)
+            .into_iter()
+            .map(|w| (w.message().to_string(), line(w.span)))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(check(PayeeLints::default()).is_empty());
+
+    assert_eq!(
+        check(PayeeLints::default().infer_payees(true)),
+        vec![
+            ("narration looks like a payee".to_string(), 4),
+            ("payee looks like a narration".to_string(), 10),
+            ("payee looks like a narration".to_string(), 13),
+        ]
+    );
+
+    let warnings = PayeeLints::default()
+        .known_payees(["Z Energy"])
+        .check(&directives);
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (w.message(), line(w.span)))
+            .collect::<Vec<_>>(),
+        vec![
+            ("narration looks like a payee", 7),
+            ("payee looks like a narration", 10),
+        ]
+    );
+    assert_eq!(
+        sources.apply_fixes(warnings[1].fixes())[0].1,
+        s.replace(r#""Lunch with friends" """#, r#""Lunch with friends""#)
+    );
+}
@@ -0,0 +1,206 @@
+use crate::{sort::SortIteratorAdaptor, types::*};
+use std::{borrow::Borrow, collections::HashMap};
+
+/// Merge `incoming` directives into `existing` ones, for example a freshly imported file into the main ledger.
+///
+/// Incoming directives which are duplicates of existing ones, as determined by [Directive::content_hash]
+/// and then equality, are dropped, each existing directive accounting for at most one incoming duplicate,
+/// so that repeats such as two identical purchases on the same day are kept where there are more of them incoming.
+/// Incoming directives which are about the same thing as one already merged but differ from it,
+/// such as a second `open` for the same account, or a balance assertion for the same account,
+/// date, and currency with a different amount, are retained but reported as conflicts for human review.
+/// Since transactions may legitimately repeat, an incoming transaction conflicts only with an existing one,
+/// again each existing transaction accounting for at most one conflict.
+///
+/// The merged directives are in chronological order, with existing before incoming on any date.
+pub fn merge<'a, I, J>(existing: I, incoming: J) -> Merged<'a>
+where
+    I: IntoIterator<Item = Spanned<Directive<'a>>>,
+    J: IntoIterator<Item = Spanned<Directive<'a>>>,
+{
+    let mut directives = existing.into_iter().collect::<Vec<_>>();
+    let mut exact = ExactDuplicates::new(directives.iter());
+    let mut by_subject = HashMap::<String, Vec<usize>>::new();
+    for (i, directive) in directives.iter().enumerate() {
+        if let Some(subject) = subject(directive) {
+            by_subject.entry(subject).or_default().push(i);
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    let mut conflicting = Vec::new();
+
+    for directive in incoming {
+        if exact.find(&directives, &directive).is_some() {
+            duplicates.push(directive);
+        } else {
+            let i = directives.len();
+            if let Some(subject) = subject(&directive) {
+                let candidates = by_subject.entry(subject).or_default();
+                if matches!(directive.variant(), DirectiveVariant::Transaction(_)) {
+                    if let Some(j) = candidates.iter().copied().find(|&j| !exact.matched[j]) {
+                        exact.matched[j] = true;
+                        conflicting.push((j, i));
+                    }
+                } else {
+                    match candidates.first() {
+                        Some(&j) => conflicting.push((j, i)),
+                        None => candidates.push(i),
+                    }
+                }
+            }
+            directives.push(directive);
+        }
+    }
+
+    let conflicts = conflicting
+        .into_iter()
+        .map(|(existing, incoming)| MergeConflict {
+            existing: directives[existing].clone(),
+            incoming: directives[incoming].clone(),
+        })
+        .collect();
+
+    Merged {
+        directives: directives.into_iter().sort(|d| *d.date().item()).collect(),
+        duplicates,
+        conflicts,
+    }
+}
+
+/// The result of [merge].
+#[derive(Clone, Debug)]
+pub struct Merged<'a> {
+    /// The merged directives in chronological order, without duplicates.
+    pub directives: Vec<Spanned<Directive<'a>>>,
+    /// Incoming directives which were dropped as duplicates.
+    pub duplicates: Vec<Spanned<Directive<'a>>>,
+    /// Conflicting pairs of directives, both of which are included in `directives`.
+    pub conflicts: Vec<MergeConflict<'a>>,
+}
+
+/// An incoming directive which conflicts with one merged before it.
+#[derive(Clone, Debug)]
+pub struct MergeConflict<'a> {
+    pub existing: Spanned<Directive<'a>>,
+    pub incoming: Spanned<Directive<'a>>,
+}
+
+impl<'a> MergeConflict<'a> {
+    /// A warning for the incoming directive, related to the one it conflicts with.
+    ///
+    /// This may only be written with [BeancountSources](crate::BeancountSources) containing both directives.
+    pub fn warning(&self) -> Warning {
+        self.incoming
+            .warning(format!(
+                "conflicts with existing {}",
+                self.existing.element_type()
+            ))
+            .related_to(&self.existing)
+    }
+}
+
+/// What a directive is about, where there should be only one directive about that,
+/// or `None` where there may be many.
+/// Exact duplicates of existing directives, as determined by [Directive::content_hash] and then equality,
+/// each existing directive accounting for at most one duplicate.
+pub(crate) struct ExactDuplicates {
+    by_hash: HashMap<u64, Vec<usize>>,
+    /// Whether each existing directive has already been matched, whether as a duplicate or otherwise by the caller.
+    pub(crate) matched: Vec<bool>,
+}
+
+impl ExactDuplicates {
+    pub(crate) fn new<'e, 'b, I>(existing: I) -> Self
+    where
+        I: IntoIterator<Item = &'e Spanned<Directive<'b>>>,
+        'b: 'e,
+    {
+        let mut by_hash = HashMap::<u64, Vec<usize>>::new();
+        let mut matched = Vec::new();
+        for (i, directive) in existing.into_iter().enumerate() {
+            by_hash.entry(directive.content_hash()).or_default().push(i);
+            matched.push(false);
+        }
+
+        ExactDuplicates { by_hash, matched }
+    }
+
+    /// The index of an unmatched existing directive equal to `directive`, which is then matched,
+    /// where `existing` are those from which this was created.
+    pub(crate) fn find<'b, E>(
+        &mut self,
+        existing: &[E],
+        directive: &Spanned<Directive<'_>>,
+    ) -> Option<usize>
+    where
+        E: Borrow<Spanned<Directive<'b>>>,
+    {
+        let matched = &mut self.matched;
+        let i = self
+            .by_hash
+            .get(&directive.content_hash())?
+            .iter()
+            .copied()
+            .find(|&i| !matched[i] && *existing[i].borrow() == *directive)?;
+        matched[i] = true;
+        Some(i)
+    }
+}
+
+fn subject(directive: &Directive) -> Option<String> {
+    use DirectiveVariant::*;
+
+    let date = directive.date().item();
+
+    match directive.variant() {
+        Transaction(transaction) => {
+            let mut postings = transaction
+                .postings()
+                .map(|posting| {
+                    format!(
+                        "{} {} {}",
+                        posting.account().item(),
+                        posting
+                            .amount()
+                            .map(|amount| amount.item().value().to_string())
+                            .unwrap_or_default(),
+                        posting
+                            .currency()
+                            .map(|currency| currency.item().to_string())
+                            .unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<_>>();
+            postings.sort();
+
+            Some(format!("{} transaction {}", date, postings.join(", ")))
+        }
+        Price(price) => Some(format!(
+            "{} price {} {}",
+            date,
+            price.currency().item(),
+            price.amount().currency().item()
+        )),
+        Balance(balance) => Some(format!(
+            "{} balance {} {}",
+            date,
+            balance.account().item(),
+            balance.atol().amount().currency().item()
+        )),
+        Open(open) => Some(format!("open {}", open.account().item())),
+        Close(close) => Some(format!("close {}", close.account().item())),
+        Commodity(commodity) => Some(format!("commodity {}", commodity.currency().item())),
+        Pad(pad) => Some(format!("{} pad {}", date, pad.account().item())),
+        Document(document) => Some(format!(
+            "document {} {}",
+            document.account().item(),
+            document.path().item()
+        )),
+        Event(event) => Some(format!("{} event {}", date, event.event_type().item())),
+        Query(query) => Some(format!("query {}", query.name().item())),
+        Note(_) | Custom(_) => None,
+    }
+}
+
+mod tests;
@@ -0,0 +1,184 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+fn check_merge<F>(existing: &str, incoming: &str, check: F)
+where
+    F: FnOnce(Merged<'_>),
+{
+    let existing_sources = BeancountSources::from(existing);
+    let existing_parser = BeancountParser::new(&existing_sources);
+    let incoming_sources = BeancountSources::from(incoming);
+    let incoming_parser = BeancountParser::new(&incoming_sources);
+
+    check(merge(
+        existing_parser.parse().unwrap().directives,
+        incoming_parser.parse().unwrap().directives,
+    ));
+}
+
+fn dates(merged: &Merged<'_>) -> Vec<String> {
+    merged
+        .directives
+        .iter()
+        .map(|d| d.date().item().to_string())
+        .collect()
+}
+
+#[test]
+fn merge_chronological() {
+    check_merge(
+        r#"
+2024-01-01 open Assets:Bank
+2024-03-01 note Assets:Bank "existing"
+"#,
+        r#"
+2024-02-01 note Assets:Bank "incoming"
+2024-03-01 note Assets:Bank "incoming"
+"#,
+        |merged| {
+            assert_eq!(
+                dates(&merged),
+                vec!["2024-01-01", "2024-02-01", "2024-03-01", "2024-03-01"]
+            );
+            assert_eq!(
+                merged.directives[2].to_string(),
+                r#"2024-03-01 note Assets:Bank "existing""#
+            );
+            assert!(merged.duplicates.is_empty());
+            assert!(merged.conflicts.is_empty());
+        },
+    );
+}
+
+#[test]
+fn merge_drops_duplicates() {
+    check_merge(
+        r#"
+2024-01-01 open Assets:Bank
+2024-01-02 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+"#,
+        r#"
+2024-01-02 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-03 * "Tea"
+  Assets:Bank  -2.00 GBP
+  Expenses:Tea
+"#,
+        |merged| {
+            assert_eq!(
+                dates(&merged),
+                vec!["2024-01-01", "2024-01-02", "2024-01-03"]
+            );
+            assert_eq!(merged.duplicates.len(), 1);
+            assert!(merged.conflicts.is_empty());
+        },
+    );
+}
+
+#[test]
+fn merge_reports_conflicts() {
+    check_merge(
+        r#"
+2024-01-01 open Assets:Bank GBP
+2024-02-01 balance Assets:Bank 100.00 GBP
+"#,
+        r#"
+2024-01-05 open Assets:Bank
+2024-02-01 balance Assets:Bank 120.00 GBP
+2024-02-01 balance Assets:Bank 50.00 USD
+"#,
+        |merged| {
+            assert_eq!(merged.directives.len(), 5);
+            assert!(merged.duplicates.is_empty());
+            assert_eq!(
+                merged
+                    .conflicts
+                    .iter()
+                    .map(|conflict| (conflict.existing.to_string(), conflict.incoming.to_string()))
+                    .collect::<Vec<_>>(),
+                vec![
+                    (
+                        "2024-01-01 open Assets:Bank GBP".to_string(),
+                        "2024-01-05 open Assets:Bank".to_string()
+                    ),
+                    (
+                        "2024-02-01 balance Assets:Bank 100.00 GBP".to_string(),
+                        "2024-02-01 balance Assets:Bank 120.00 GBP".to_string()
+                    ),
+                ]
+            );
+        },
+    );
+}
+
+#[test]
+fn merge_keeps_repeated_transactions() {
+    check_merge(
+        r#"
+2024-01-01 open Assets:Bank
+2024-01-02 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+"#,
+        r#"
+2024-01-02 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-02 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-03 * "Tea"
+  Assets:Bank  -2.00 GBP
+  Expenses:Tea
+2024-01-03 * "Tea"
+  Assets:Bank  -2.00 GBP
+  Expenses:Tea
+"#,
+        |merged| {
+            assert_eq!(
+                dates(&merged),
+                vec![
+                    "2024-01-01",
+                    "2024-01-02",
+                    "2024-01-02",
+                    "2024-01-03",
+                    "2024-01-03"
+                ]
+            );
+            assert_eq!(merged.duplicates.len(), 1);
+            assert!(merged.conflicts.is_empty());
+        },
+    );
+}
+
+#[test]
+fn merge_transaction_conflicts_only_with_existing() {
+    check_merge(
+        r#"
+2024-01-02 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+"#,
+        r#"
+2024-01-02 * "Cafe Nero"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-02 * "Cafe Nero"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+"#,
+        |merged| {
+            assert_eq!(merged.directives.len(), 3);
+            assert!(merged.duplicates.is_empty());
+            assert_eq!(merged.conflicts.len(), 1);
+            assert_eq!(
+                merged.conflicts[0].existing.to_string().lines().next(),
+                Some(r#"2024-01-02 * "Coffee""#)
+            );
+        },
+    );
+}
@@ -9,6 +9,7 @@ use std::{
     hash::Hash,
 };
 use strum::IntoEnumIterator;
+use time::{Date, Month};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub(crate) struct BeancountOption<'a> {
@@ -35,8 +36,10 @@ pub(crate) enum BeancountOptionVariant<'a> {
     OperatingCurrency(Currency<'a>),
     RenderCommas(bool),
     LongStringMaxlines(usize),
+    FiscalYearStart(Month, u8),
     BookingMethod(Booking),
     PluginProcessingMode(PluginProcessingMode),
+    AllowPipeSeparator(bool),
     Assimilated,
 }
 
@@ -143,12 +146,18 @@ impl<'a> BeancountOption<'a> {
                 .map(LongStringMaxlines)
                 .map_err(|e| BadValueErrorKind::ParseIntError(e).wrap()),
 
+            "fiscal_year_start" => {
+                parse_fiscal_year_start(value.item).map(|(month, day)| FiscalYearStart(month, day))
+            }
+
             "booking_method" => parse_booking(value.item).map(BookingMethod),
 
             "plugin_processing_mode" => {
                 parse_plugin_processing_mode(value.item).map(PluginProcessingMode)
             }
 
+            "allow_pipe_separator" => parse_bool(value.item).map(AllowPipeSeparator),
+
             _ => Err(UnknownOption),
         }
         .map(|variant| BeancountOption {
@@ -199,10 +208,37 @@ fn parse_inferred_tolerance_default(
     }
 }
 
+// month and day, as MM-DD, which must exist in every year, so excluding 02-29
+fn parse_fiscal_year_start(value: &str) -> Result<(Month, u8), BeancountOptionError> {
+    value
+        .split_once('-')
+        .filter(|(month, day)| month.len() == 2 && day.len() == 2)
+        .and_then(|(month, day)| {
+            let month = Month::try_from(month.parse::<u8>().ok()?).ok()?;
+            let day = day.parse::<u8>().ok()?;
+            Date::from_calendar_date(2023, month, day)
+                .ok()
+                .map(|_| (month, day))
+        })
+        .ok_or(BadValueErrorKind::MonthDay.wrap())
+}
+
 fn parse_booking(value: &str) -> Result<Booking, BeancountOptionError> {
     Booking::try_from(value).map_err(|e| BadValueErrorKind::Booking(e).wrap())
 }
 
+/// Options accepted by Beancount v2 but no longer by v3, whose presence marks a ledger as targeting v2.
+pub(crate) const PYTHON_V2_ONLY_OPTIONS: [&str; 8] = [
+    "allow_pipe_separator",
+    "allow_deprecated_none_for_tags_and_links",
+    "default_tolerance",
+    "experiment_explicit_tolerances",
+    "insert_pythonpath",
+    "plugin",
+    "tolerance",
+    "use_legacy_fixed_tolerances",
+];
+
 fn parse_plugin_processing_mode(value: &str) -> Result<PluginProcessingMode, BeancountOptionError> {
     PluginProcessingMode::try_from(value)
         .map_err(|e| BadValueErrorKind::PluginProcessingMode(e).wrap())
@@ -261,6 +297,7 @@ enum BadValueErrorKind {
     PluginProcessingMode(strum::ParseError),
     Decimal(rust_decimal::Error),
     Bool,
+    MonthDay,
     MissingColon,
     TooManyColons,
     ParseIntError(std::num::ParseIntError),
@@ -291,6 +328,7 @@ impl Display for BadValueErrorKind {
             ),
             Decimal(e) => write!(f, "{}", e),
             Bool => f.write_str("must be true or false or case-insensitive equivalent"),
+            MonthDay => f.write_str("must be a month and day as MM-DD, excluding 02-29"),
             MissingColon => f.write_str("missing colon"),
             TooManyColons => f.write_str("too many colons"),
             ParseIntError(e) => write!(f, "{}", e),
@@ -384,8 +422,10 @@ pub struct Options<'a> {
     documents: HashMap<PathBuf, Source>,
     operating_currency: HashMap<Currency<'a>, Source>,
     render_commas: OptionallySourced<bool>,
+    fiscal_year_start: OptionallySourced<(Month, u8)>,
     booking_method: OptionallySourced<Booking>,
     plugin_processing_mode: OptionallySourced<PluginProcessingMode>,
+    allow_pipe_separator: OptionallySourced<bool>,
     parser_options: ParserOptions<'a>,
 }
 
@@ -411,8 +451,10 @@ impl<'a> Options<'a> {
             documents: HashMap::new(),
             operating_currency: HashMap::new(),
             render_commas: unsourced(false),
+            fiscal_year_start: unsourced((Month::January, 1)),
             booking_method: unsourced(Booking::Strict),
             plugin_processing_mode: unsourced(PluginProcessingMode::Default),
+            allow_pipe_separator: unsourced(false),
             parser_options,
         }
     }
@@ -482,12 +524,20 @@ impl<'a> Options<'a> {
             // already assimilated into ParserOptions
             LongStringMaxlines(_) => Ok(()),
 
+            FiscalYearStart(month, day) => {
+                Self::update(&mut self.fiscal_year_start, (month, day), source)
+            }
+
             BookingMethod(value) => Self::update(&mut self.booking_method, value, source),
 
             PluginProcessingMode(value) => {
                 Self::update(&mut self.plugin_processing_mode, value, source)
             }
 
+            AllowPipeSeparator(value) => {
+                Self::update(&mut self.allow_pipe_separator, value, source)
+            }
+
             // this value contains nothing
             Assimilated => Ok(()),
         }
@@ -583,31 +633,31 @@ impl<'a> Options<'a> {
         self.title.item
     }
 
-    pub fn account_previous_balances(&self) -> &Subaccount {
+    pub fn account_previous_balances(&self) -> &Subaccount<'a> {
         &self.account_previous_balances.item
     }
 
-    pub fn account_previous_earnings(&self) -> &Subaccount {
+    pub fn account_previous_earnings(&self) -> &Subaccount<'a> {
         &self.account_previous_earnings.item
     }
 
-    pub fn account_previous_conversions(&self) -> &Subaccount {
+    pub fn account_previous_conversions(&self) -> &Subaccount<'a> {
         &self.account_previous_conversions.item
     }
 
-    pub fn account_current_earnings(&self) -> &Subaccount {
+    pub fn account_current_earnings(&self) -> &Subaccount<'a> {
         &self.account_current_earnings.item
     }
 
-    pub fn account_current_conversions(&self) -> &Subaccount {
+    pub fn account_current_conversions(&self) -> &Subaccount<'a> {
         &self.account_current_conversions.item
     }
 
-    pub fn account_unrealized_gains(&self) -> &Subaccount {
+    pub fn account_unrealized_gains(&self) -> &Subaccount<'a> {
         &self.account_unrealized_gains.item
     }
 
-    pub fn account_rounding(&self) -> Option<&Subaccount> {
+    pub fn account_rounding(&self) -> Option<&Subaccount<'a>> {
         self.account_rounding.as_ref().map(|x| &x.item)
     }
 
@@ -655,6 +705,20 @@ impl<'a> Options<'a> {
         self.render_commas.item
     }
 
+    /// Whether a pipe may separate payee and narration, as in Beancount v2, by default false.
+    ///
+    /// Since this affects parsing, it applies throughout the ledger, wherever the option is given.
+    pub fn allow_pipe_separator(&self) -> bool {
+        self.allow_pipe_separator.item
+    }
+
+    /// The month and day on which each fiscal year starts, by default January 1st.
+    ///
+    /// This is a Lima extension, not an option recognised by Beancount.
+    pub fn fiscal_year_start(&self) -> (Month, u8) {
+        self.fiscal_year_start.item
+    }
+
     pub fn booking_method(&self) -> Booking {
         self.booking_method.item
     }
@@ -0,0 +1,91 @@
+use crate::{types::*, BeancountSources};
+use chumsky::span::Span as _;
+use std::path::Path;
+
+/// Where a directive came from, for attribution by tools, see [Spanned::source].
+#[derive(Clone, Debug)]
+pub struct DirectiveSource<'s> {
+    name: &'s str,
+    path: Option<&'s Path>,
+    include_chain: Vec<&'s str>,
+    synthesized: bool,
+}
+
+impl<'s> DirectiveSource<'s> {
+    /// The name of the originating source, as given by [BeancountSources::source_name].
+    pub fn name(&self) -> &'s str {
+        self.name
+    }
+
+    /// The path of the originating file, or `None` for inline content or a synthesized directive.
+    pub fn path(&self) -> Option<&'s Path> {
+        self.path
+    }
+
+    /// Whether the directive came from a file included from elsewhere, rather than the root source.
+    pub fn is_included(&self) -> bool {
+        !self.include_chain.is_empty()
+    }
+
+    /// The names of the sources through which the originating source was included,
+    /// starting with the root source, and ending with the one which directly included it.
+    ///
+    /// This is empty for directives in the root source.
+    pub fn include_chain(&self) -> &[&'s str] {
+        &self.include_chain
+    }
+
+    /// Whether the directive was created by a transform rather than parsed,
+    /// as determined by its span not lying within any of the sources.
+    pub fn is_synthesized(&self) -> bool {
+        self.synthesized
+    }
+}
+
+impl<'a> Spanned<Directive<'a>> {
+    /// Where the directive came from, among `sources`, which must be those it was parsed from.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources};
+    ///
+    /// let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n");
+    /// let parser = BeancountParser::new(&sources);
+    /// let directives = parser.parse().unwrap().directives;
+    /// let source = directives[0].source(&sources);
+    ///
+    /// assert_eq!(source.name(), "inline");
+    /// assert_eq!(source.path(), None);
+    /// assert!(!source.is_included());
+    /// assert!(!source.is_synthesized());
+    /// ```
+    pub fn source<'s>(&self, sources: &'s BeancountSources) -> DirectiveSource<'s> {
+        let source_id = self.span.context();
+
+        if !sources.contains(source_id) {
+            return DirectiveSource {
+                name: "synthesized",
+                path: None,
+                include_chain: Vec::new(),
+                synthesized: true,
+            };
+        }
+
+        let mut include_chain = Vec::new();
+        let mut includer = sources.includer(source_id);
+        while let Some(including_source_id) = includer {
+            include_chain.push(sources.source_name(including_source_id));
+            includer = sources.includer(including_source_id);
+        }
+        include_chain.reverse();
+
+        DirectiveSource {
+            name: sources.source_name(source_id),
+            path: sources.source_path(source_id),
+            include_chain,
+            synthesized: false,
+        }
+    }
+}
+
+mod tests;
@@ -0,0 +1,71 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+#[test]
+fn directive_source_through_includes() {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-origin-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("accounts")).unwrap();
+    for (path, content) in [
+        (
+            "main.beancount",
+            "include \"accounts/bank.beancount\"\n2024-01-01 open Assets:Cash\n",
+        ),
+        (
+            "accounts/bank.beancount",
+            "include \"fees.beancount\"\n2024-01-01 open Assets:Bank\n",
+        ),
+        ("accounts/fees.beancount", "2024-01-01 open Expenses:Fees\n"),
+    ] {
+        std::fs::write(dir.join(path), content).unwrap();
+    }
+
+    let main = dir.join("main.beancount");
+    let bank = dir.join("accounts/bank.beancount");
+    let fees = dir.join("accounts/fees.beancount");
+    let sources = BeancountSources::try_from(main.as_path()).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let open = |account: &str| {
+        directives
+            .iter()
+            .find(|d| match d.variant() {
+                DirectiveVariant::Open(open) => open.account().item().to_string() == account,
+                _ => false,
+            })
+            .unwrap()
+    };
+    let source_of = |account: &str| open(account).source(&sources);
+
+    let cash = source_of("Assets:Cash");
+    assert_eq!(cash.path(), Some(main.as_path()));
+    assert!(!cash.is_included());
+    assert!(!cash.is_synthesized());
+
+    let bank_source = source_of("Assets:Bank");
+    assert_eq!(bank_source.path(), Some(bank.as_path()));
+    assert_eq!(
+        bank_source.include_chain(),
+        &[main.to_string_lossy().as_ref()]
+    );
+
+    let fees_source = source_of("Expenses:Fees");
+    assert_eq!(fees_source.path(), Some(fees.as_path()));
+    assert_eq!(
+        fees_source.include_chain(),
+        &[
+            main.to_string_lossy().as_ref(),
+            bank.to_string_lossy().as_ref()
+        ]
+    );
+
+    // a directive from an included file is not within a single inline source
+    let unrelated = BeancountSources::from("");
+    assert!(open("Expenses:Fees").source(&unrelated).is_synthesized());
+    assert!(!open("Assets:Cash").source(&unrelated).is_synthesized());
+}
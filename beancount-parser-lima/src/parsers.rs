@@ -1,35 +1,34 @@
 use crate::{
+    config::{CompatMode, ParserConfig, SyntaxVersion},
     lexer::Token,
     options::{BeancountOption, BeancountOptionError, ParserOptions},
     types::*,
-    ConcreteInput,
+    ConcreteInput, Fix,
 };
 use chumsky::{input::BorrowInput, label::LabelError, prelude::*};
 use either::Either;
 use rust_decimal::Decimal;
 use std::{
-    collections::{hash_map, HashMap, HashSet},
+    collections::{HashMap, HashSet},
     iter::once,
     ops::Deref,
     path::Path,
 };
+use strum::IntoEnumIterator;
 use time::Date;
 
 /// Matches all the includes in the file, ignoring everything else.
-pub(crate) fn includes<'src, I>() -> impl Parser<'src, I, Vec<String>, Extra<'src>>
+pub(crate) fn includes<'src, I>() -> impl Parser<'src, I, Vec<Spanned<String>>, Extra<'src>>
 where
     I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
 {
-    (just(Token::Include).ignore_then(string()).map(Some))
-        .or(any_ref().map(|_| None))
-        .repeated()
-        .collect::<Vec<_>>()
-        .map(|includes| {
-            includes
-                .into_iter()
-                .filter_map(|s| s.as_ref().map(|s| s.to_string()))
-                .collect::<Vec<_>>()
-        })
+    (just(Token::Include)
+        .ignore_then(string().map_with(|path, e| spanned(path.to_string(), e.span())))
+        .map(Some))
+    .or(any_ref().map(|_| None))
+    .repeated()
+    .collect::<Vec<_>>()
+    .map(|includes| includes.into_iter().flatten().collect::<Vec<_>>())
 }
 
 /// Matches the whole file.
@@ -39,7 +38,12 @@ pub(crate) fn file<'src, I>(
 where
     I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
 {
-    declaration(source_path).repeated().collect::<Vec<_>>()
+    declaration(source_path)
+        .map(Some)
+        .recover_with(via_parser(broken_entry().to(None)))
+        .repeated()
+        .collect::<Vec<_>>()
+        .map(|declarations| declarations.into_iter().flatten().collect::<Vec<_>>())
 }
 
 /// Matches a [Declaration], and returns with Span.
@@ -51,9 +55,41 @@ where
 {
     use Declaration::*;
 
-    choice((directive().map(Directive), pragma(source_path).map(Pragma)))
-        .map_with(spanned_extra)
-        .recover_with(skip_then_retry_until(any_ref().ignored(), end()))
+    choice((directive().map(Directive), pragma(source_path).map(Pragma))).map_with(spanned_extra)
+}
+
+/// Matches whatever remains of a broken entry, for recovery from a failed [Declaration].
+///
+/// This is at least one token, and then everything up to and including the end of the line before the next entry,
+/// which is a non-indented line beginning with a date or a pragma keyword.  So each broken entry results in a single error,
+/// without swallowing any of the entries which follow it.
+fn broken_entry<'src, I>() -> impl Parser<'src, I, (), Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    use Token::*;
+
+    let is_entry_start = |token: &Token| {
+        matches!(
+            token,
+            Date(_) | Pushtag | Poptag | Pushmeta | Popmeta | Option | Plugin | Include
+        )
+    };
+    let within_entry = choice((
+        any_ref().filter(|token| **token != Eol).ignored(),
+        just(Eol)
+            .then(
+                any_ref()
+                    .filter(move |token| !is_entry_start(token))
+                    .rewind(),
+            )
+            .ignored(),
+    ));
+
+    any_ref()
+        .then(within_entry.repeated())
+        .then(just(Eol).or_not())
+        .ignored()
 }
 
 /// Matches a [Directive].
@@ -74,7 +110,7 @@ where
             note(),
             event(),
             query(),
-            // TODO custom
+            custom(),
         ))
         .labelled("directive")
         .as_context(),
@@ -175,6 +211,10 @@ where
         metadata(),
         posting()
             .map_with(spanned_extra)
+            .map(Ok)
+            .recover_with(via_parser(
+                broken_posting().map_with(|_, e| Err(spanned(BrokenPosting, e.span()))),
+            ))
             .repeated()
             .collect::<Vec<_>>(),
     ))
@@ -184,6 +224,11 @@ where
          emitter| {
             metadata.merge_tags(&tags, emitter);
             metadata.merge_links(&links, emitter);
+            let mut broken_postings = Vec::new();
+            let postings = postings
+                .into_iter()
+                .filter_map(|posting| posting.map_err(|b| broken_postings.push(b)).ok())
+                .collect();
 
             Directive {
                 date,
@@ -193,6 +238,7 @@ where
                     payee,
                     narration,
                     postings,
+                    broken_postings,
                 }),
             }
         },
@@ -220,18 +266,67 @@ where
         // payee and narration get special handling in case one is omitted
         group((
             string().map_with(spanned_extra).or_not(),
+            legacy_pipe_separator().or_not(),
             string().map_with(spanned_extra).or_not(),
         ))
-        .map(|(s1, s2)| match (s1, s2) {
-            // a single string is narration
-            (Some(s1), None) => (None, Some(s1)),
-            (s1, s2) => (s1, s2),
+        .validate(|(s1, pipe, s2), e, emitter| {
+            if let Some(pipe) = pipe {
+                let parser_state: &mut ParserState = e.state();
+
+                match (&s1, &s2) {
+                    (Some(s1), Some(s2))
+                        if parser_state.config.legacy_syntax
+                            || parser_state.config.allow_pipe_separator =>
+                    {
+                        let span =
+                            Span::new(s1.span().context(), s1.span().end()..s2.span().start());
+                        parser_state.warnings.push(
+                            Warning::new("deprecated syntax", "pipe separator is deprecated", pipe)
+                                .with_fix(
+                                    Fix::new("replace pipe separator with space")
+                                        .replace(span, " "),
+                                ),
+                        );
+                    }
+                    (Some(_), Some(_)) => (),
+                    _ => emitter.emit(Rich::custom(
+                        pipe,
+                        "pipe separator must be between payee and narration",
+                    )),
+                }
+            }
+
+            match (s1, s2) {
+                // a single string is narration
+                (Some(s1), None) => (None, Some(s1)),
+                (s1, s2) => (s1, s2),
+            }
         }),
         tags_links(),
     ))
     .then_ignore(just(Token::Eol))
 }
 
+/// Matches the Beancount v1 pipe separating payee and narration, only with [ParserConfig::legacy_syntax]
+/// or the option `allow_pipe_separator`.
+fn legacy_pipe_separator<'src, I>() -> impl Parser<'src, I, Span, Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    just(Token::Pipe).validate(|_, e, emitter| {
+        let span = e.span();
+        let parser_state: &mut ParserState = e.state();
+
+        if !(parser_state.config.legacy_syntax || parser_state.config.allow_pipe_separator) {
+            emitter.emit(Rich::custom(
+                span,
+                "pipe separator between payee and narration requires legacy syntax",
+            ));
+        }
+        span
+    })
+}
+
 /// Matches a price directive, including metadata, over several lines.
 pub(crate) fn price<'src, I>() -> impl Parser<'src, I, Directive<'src>, Extra<'src>>
 where
@@ -268,21 +363,29 @@ where
 {
     group((
         date().map_with(spanned_extra),
-        just(Token::Balance),
+        just(Token::Balance).ignored().or(legacy_check()),
         account().map_with(spanned_extra),
         amount_with_tolerance().map_with(spanned_extra),
+        balance_extensions(),
         tags_links(),
     ))
     .then_ignore(just(Token::Eol))
     .then(metadata())
     .validate(
-        |((date, _, account, atol, (tags, links)), mut metadata), _span, emitter| {
+        |((date, _, account, atol, (cost_spec, price_annotation), (tags, links)), mut metadata),
+         _span,
+         emitter| {
             metadata.merge_tags(&tags, emitter);
             metadata.merge_links(&links, emitter);
             Directive {
                 date,
                 metadata,
-                variant: DirectiveVariant::Balance(Balance { account, atol }),
+                variant: DirectiveVariant::Balance(Balance {
+                    account,
+                    atol,
+                    cost_spec,
+                    price_annotation,
+                }),
             }
         },
     )
@@ -290,6 +393,65 @@ where
     .as_context()
 }
 
+/// Matches the optional cost and price of an extended balance assertion, only with [ParserConfig::balance_extensions].
+fn balance_extensions<'src, I>() -> impl Parser<'src, I, BalanceExtensions<'src>, Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    group((
+        cost_spec().or_not().map_with(|cost_spec, e| {
+            cost_spec
+                .flatten()
+                .map(|cost_spec| Box::new(spanned(cost_spec, e.span())))
+        }),
+        price_annotation().or_not().map_with(|price_spec, e| {
+            price_spec
+                .flatten()
+                .map(|price_spec| Box::new(spanned(price_spec, e.span())))
+        }),
+    ))
+    .validate(|(cost_spec, price_annotation), e, emitter| {
+        let parser_state: &mut ParserState = e.state();
+
+        if (cost_spec.is_some() || price_annotation.is_some())
+            && !parser_state.config.balance_extensions
+        {
+            emitter.emit(Rich::custom(
+                e.span(),
+                "cost or price in balance assertion requires balance extensions",
+            ));
+        }
+
+        (cost_spec, price_annotation)
+    })
+}
+
+type BalanceExtensions<'a> = (
+    Option<Box<Spanned<CostSpec<'a>>>>,
+    Option<Box<Spanned<PriceSpec<'a>>>>,
+);
+
+/// Matches the Beancount v1 `check` keyword for `balance`, only with [ParserConfig::legacy_syntax].
+fn legacy_check<'src, I>() -> impl Parser<'src, I, (), Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    // not being a keyword, check is lexed as a key
+    just(Token::Key("check")).validate(|_, e, emitter| {
+        let span = e.span();
+        let parser_state: &mut ParserState = e.state();
+
+        if parser_state.config.legacy_syntax {
+            parser_state.warnings.push(
+                Warning::new("deprecated syntax", "check directive is deprecated", span)
+                    .with_fix(Fix::new("replace check with balance").replace(span, "balance")),
+            );
+        } else {
+            emitter.emit(Rich::custom(span, "check directive requires legacy syntax"));
+        }
+    })
+}
+
 /// Matches a open, including metadata, over several lines.
 pub(crate) fn open<'src, I>() -> impl Parser<'src, I, Directive<'src>, Extra<'src>>
 where
@@ -334,7 +496,10 @@ where
         just(Token::Open),
         account().map_with(spanned_extra),
         currency_list(),
-        booking().map_with(spanned_extra).or_not(),
+        booking()
+            .map_with(|booking, e| booking.map(|booking| spanned(booking, e.span())))
+            .or_not()
+            .map(Option::flatten),
         tags_links(),
     ))
     .then_ignore(just(Token::Eol))
@@ -414,12 +579,34 @@ where
     })
 }
 
-/// Matches a [Booking].
-fn booking<'src, I>() -> impl Parser<'src, I, Booking, Extra<'src>>
+/// Matches a [Booking], or else emits an error with any likely correction, and recovers.
+fn booking<'src, I>() -> impl Parser<'src, I, Option<Booking>, Extra<'src>>
 where
     I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
 {
-    string().try_map(|s, span| Booking::try_from(s).map_err(|e| Rich::custom(span, e.to_string())))
+    string().validate(|s, e, emitter| {
+        Booking::try_from(s)
+            .map_err(|_| {
+                let reason = match Booking::closest(s) {
+                    Some(closest) => {
+                        format!(
+                            "unknown booking method \"{}\", did you mean \"{}\"",
+                            s, closest
+                        )
+                    }
+                    None => format!(
+                        "unknown booking method \"{}\", expected one of {}",
+                        s,
+                        Booking::iter()
+                            .map(|booking| booking.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                };
+                emitter.emit(Rich::custom(e.span(), reason))
+            })
+            .ok()
+    })
 }
 
 /// Matches a close, including metadata, over several lines.
@@ -591,6 +778,40 @@ where
     )
 }
 
+/// Matches a custom directive, including metadata, over several lines.
+pub(crate) fn custom<'src, I>() -> impl Parser<'src, I, Directive<'src>, Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    group((
+        date().map_with(spanned_extra),
+        just(Token::Custom),
+        string().map_with(spanned_extra),
+        custom_value()
+            .map_with(spanned_extra)
+            .repeated()
+            .collect::<Vec<_>>(),
+        tags_links(),
+    ))
+    .then_ignore(just(Token::Eol))
+    .then(metadata())
+    .validate(
+        |((date, _, custom_type, values, (tags, links)), mut metadata), _span, emitter| {
+            metadata.merge_tags(&tags, emitter);
+            metadata.merge_links(&links, emitter);
+
+            Directive {
+                date,
+                metadata,
+                variant: DirectiveVariant::Custom(Custom {
+                    custom_type,
+                    values,
+                }),
+            }
+        },
+    )
+}
+
 /// Matches a query, including metadata, over several lines.
 pub(crate) fn query<'src, I>() -> impl Parser<'src, I, Directive<'src>, Extra<'src>>
 where
@@ -639,6 +860,23 @@ where
         just(Token::Asterisk).to(Flag::Asterisk),
         just(Token::Hash).to(Flag::Hash),
     ))
+    .try_map_with(|flag, e| {
+        let span = e.span();
+        let parser_state: &mut ParserState = e.state();
+
+        match flag {
+            Flag::Letter(letter)
+                if parser_state.config.selected_syntax_version() == SyntaxVersion::V2
+                    && !letter.is_python_v2() =>
+            {
+                Err(Rich::custom(
+                    span,
+                    format!("flag {} is invalid in Beancount v2", flag),
+                ))
+            }
+            _ => Ok(flag),
+        }
+    })
 }
 
 /// Matches a [Posting] complete with [Metadata] over several lines.
@@ -684,6 +922,26 @@ where
         .as_context()
 }
 
+/// Matches whatever remains of a broken posting, for recovery from a failed [Posting].
+///
+/// This is the rest of the indented line, and any metadata lines following it,
+/// so that the transaction retains its other postings, and the broken posting results in a single error.
+fn broken_posting<'src, I>() -> impl Parser<'src, I, (), Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    just(Token::Indent)
+        .then(
+            any_ref()
+                .filter(|token| **token != Token::Eol)
+                .repeated()
+                .at_least(1),
+        )
+        .then(just(Token::Eol))
+        .then(metadatum_line().repeated())
+        .ignored()
+}
+
 /// Matches [Metadata], over several lines.
 fn metadata<'src, I>() -> impl Parser<'src, I, Metadata<'src>, Extra<'src>>
 where
@@ -698,45 +956,39 @@ where
             // collate by type of metadatum
             metadata
                 .into_iter()
-                .fold(Metadata::default(), |mut m, item| match item {
+                .fold(MetadataBuilder::default(), |mut m, item| match item {
                     KeyValue(kv) => {
-                        use hash_map::Entry::*;
-
                         let MetaKeyValue { key, value } = kv.item;
 
                         let key_span = key.span;
-                        match m.key_values.entry(key) {
-                            Occupied(entry) => emitter.emit(Rich::custom(
+                        if let Some(existing) = m.get_key(key.item()) {
+                            emitter.emit(Rich::custom(
                                 key_span,
-                                format!("duplicate key {}", entry.key()),
-                            )),
-                            Vacant(entry) => {
-                                entry.insert(value);
-                            }
+                                format!("duplicate key {}", existing),
+                            ))
+                        } else {
+                            m.insert_key_value(key, value);
                         }
 
                         m
                     }
                     Tag(tag) => {
-                        if m.tags.contains(&tag) {
+                        if !m.add_tag(tag) {
                             emitter.emit(Rich::custom(tag.span, format!("duplicate tag {}", tag)))
-                        } else {
-                            m.tags.insert(tag);
                         }
 
                         m
                     }
                     Link(link) => {
-                        if m.links.contains(&link) {
+                        if !m.add_link(link) {
                             emitter
                                 .emit(Rich::custom(link.span, format!("duplicate link {}", link)))
-                        } else {
-                            m.links.insert(link);
                         }
 
                         m
                     }
                 })
+                .build()
         })
 }
 
@@ -810,6 +1062,29 @@ where
     ))
 }
 
+/// Matches a value of a custom directive, which is as for a [MetaValue], except it may not be empty,
+/// and may not be a tag or link, since those are taken as the tags and links of the directive.
+fn custom_value<'src, I>() -> impl Parser<'src, I, MetaValue<'src>, Extra<'src>>
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    use SimpleValue::*;
+
+    choice((
+        amount().map(MetaValue::Amount),
+        choice((
+            string().map(String),
+            currency().map(Currency),
+            account().map(Account),
+            date().map(Date),
+            bool().map(Bool),
+            just(Token::Null).to(None),
+            expr_value().map(Expr),
+        ))
+        .map(MetaValue::Simple),
+    ))
+}
+
 pub(crate) fn amount<'src, I>() -> impl Parser<'src, I, Amount<'src>, Extra<'src>>
 where
     I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
@@ -831,7 +1106,7 @@ where
         group((
             expr_value().map_with(spanned_extra),
             just(Token::Tilde),
-            decimal().map_with(spanned_extra),
+            tolerance().map_with(spanned_extra),
             currency().map_with(spanned_extra),
         ))
         .map_with(|(number, _, tolerance, currency), e| {
@@ -1074,7 +1349,7 @@ where
             .map(|x| Expr::Paren(Box::new(x)));
 
         // Match a bare number
-        let number = select_ref! { Number(x) => Expr::Value(*x) };
+        let number = decimal().map(Expr::Value);
 
         // Match a factor of an expression
         let factor = choice((just(Minus), just(Plus)))
@@ -1156,7 +1431,20 @@ where
     I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
 {
     let currency = select_ref!(Token::Currency(s) => *s);
-    currency.try_map(|s, span| Currency::try_from(s).map_err(|e| Rich::custom(span, e.to_string())))
+    currency.try_map_with(|s, e| {
+        let span = e.span();
+        let parser_state: &mut ParserState = e.state();
+
+        if parser_state.config.selected_syntax_version() == SyntaxVersion::V2 && s.starts_with('/')
+        {
+            Err(Rich::custom(
+                span,
+                format!("currency {} is invalid in Beancount v2", s),
+            ))
+        } else {
+            Currency::try_from(s).map_err(|e| Rich::custom(span, e.to_string()))
+        }
+    })
 }
 
 /// Matches a Date
@@ -1167,12 +1455,52 @@ where
     select_ref!(Token::Date(date) => *date)
 }
 
-/// Matches a Decimal
-fn decimal<'src, I>() -> impl Parser<'src, I, Decimal, Extra<'src>>
+/// Matches a balance tolerance, which must be a plain number,
+/// except in [CompatMode::PythonV2], where any expression is accepted.
+fn tolerance<'src, I>() -> impl Parser<'src, I, Decimal, Extra<'src>>
 where
     I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
 {
-    select_ref!(Token::Number(x) => *x)
+    expr_value().try_map_with(|tolerance, e| {
+        let span = e.span();
+        let parser_state: &mut ParserState = e.state();
+
+        if tolerance.is_literal() || parser_state.config.compat_mode == CompatMode::PythonV2 {
+            Ok(tolerance.value())
+        } else {
+            Err(Rich::custom(span, "tolerance must be a number"))
+        }
+    })
+}
+
+/// Matches a Decimal, or with [ParserConfig::lenient_numbers] one in scientific notation or with underscore separators.
+fn decimal<'src, I>() -> impl Parser<'src, I, Decimal, Extra<'src>> + Clone
+where
+    I: BorrowInput<'src, Token = Token<'src>, Span = Span>,
+{
+    let lenient_number = select_ref!(Token::LenientNumber(x) => *x).validate(|x, e, emitter| {
+        let span = e.span();
+        let parser_state: &mut ParserState = e.state();
+
+        if parser_state.config.lenient_numbers {
+            parser_state.warnings.push(
+                Warning::new(
+                    "lenient number",
+                    "scientific notation and underscore separators are not Beancount syntax",
+                    span,
+                )
+                .with_fix(Fix::new(format!("replace with {}", x)).replace(span, x.to_string())),
+            );
+        } else {
+            emitter.emit(Rich::custom(
+                span,
+                "scientific notation and underscore separators require lenient numbers",
+            ));
+        }
+        x
+    });
+
+    select_ref!(Token::Number(x) => *x).or(lenient_number)
 }
 
 /// Matches a string
@@ -1185,7 +1513,9 @@ where
     string.map_with(|s, e| {
         let span = e.span();
         let parser_state: &mut ParserState = e.state();
-        let ParserState { warnings, options } = parser_state;
+        let ParserState {
+            warnings, options, ..
+        } = parser_state;
         let line_count = s.chars().filter(|c| *c == '\n').count() + 1;
         if line_count > options.long_string_maxlines.item {
             let option_span = options.long_string_maxlines.source.map(|s| s.value);
@@ -1216,49 +1546,57 @@ impl<'a> Metadata<'a> {
     where
         E: Emit<ParserError<'a>>,
     {
-        for tag in tags {
-            match self.tags.get(tag) {
-                None => {
-                    self.tags.insert(*tag);
-                }
-                Some(existing_tag) => {
-                    let mut error =
-                        Rich::custom(existing_tag.span, format!("duplicate tag {}", tag));
-                    LabelError::<ConcreteInput, &str>::in_context(&mut error, "tag", tag.span);
-                    emitter.emit(error);
+        self.build_with(|m| {
+            for tag in tags {
+                match m.get_tag(tag) {
+                    None => {
+                        m.add_tag(*tag);
+                    }
+                    Some(existing_tag) => {
+                        let mut error =
+                            Rich::custom(existing_tag.span, format!("duplicate tag {}", tag));
+                        LabelError::<ConcreteInput, &str>::in_context(&mut error, "tag", tag.span);
+                        emitter.emit(error);
+                    }
                 }
             }
-        }
+        })
     }
 
     // Augment only for tags which are not already present, others silently ignored.
     // This is so that tags attached to directives take precedence over the push stack.
     pub(crate) fn augment_tags(&mut self, tags: &HashMap<Spanned<Tag<'a>>, Vec<Spanned<Tag<'a>>>>) {
-        for (tag, spans) in tags.iter() {
-            if !self.tags.contains(tag) {
-                let most_recently_pushed_tag = spans.last().unwrap_or(tag);
-                self.tags.insert(*most_recently_pushed_tag);
+        self.build_with(|m| {
+            for (tag, spans) in tags.iter() {
+                if m.get_tag(tag).is_none() {
+                    let most_recently_pushed_tag = spans.last().unwrap_or(tag);
+                    m.add_tag(*most_recently_pushed_tag);
+                }
             }
-        }
+        })
     }
 
     pub(crate) fn merge_links<E>(&mut self, links: &HashSet<Spanned<Link<'a>>>, emitter: &mut E)
     where
         E: Emit<ParserError<'a>>,
     {
-        for link in links {
-            match self.links.get(link) {
-                None => {
-                    self.links.insert(*link);
-                }
-                Some(existing_link) => {
-                    let mut error =
-                        Rich::custom(existing_link.span, format!("duplicate link {}", link));
-                    LabelError::<ConcreteInput, &str>::in_context(&mut error, "link", link.span);
-                    emitter.emit(error);
+        self.build_with(|m| {
+            for link in links {
+                match m.get_link(link) {
+                    None => {
+                        m.add_link(*link);
+                    }
+                    Some(existing_link) => {
+                        let mut error =
+                            Rich::custom(existing_link.span, format!("duplicate link {}", link));
+                        LabelError::<ConcreteInput, &str>::in_context(
+                            &mut error, "link", link.span,
+                        );
+                        emitter.emit(error);
+                    }
                 }
             }
-        }
+        })
     }
 
     // Augment only for keys which are not already present, others silently ignored.
@@ -1267,18 +1605,20 @@ impl<'a> Metadata<'a> {
         &mut self,
         key_values: &HashMap<Spanned<Key<'a>>, Vec<(Span, Spanned<MetaValue<'a>>)>>,
     ) {
-        for (key, values) in key_values {
-            if !self.key_values.contains_key(key) {
-                let (key_span, value) = values.last().unwrap();
-                self.key_values.insert(
-                    spanned(*key.item(), *key_span),
-                    // Sadly we do have to clone the value here, so we can
-                    // merge in metadata key/values from the push/pop stack
-                    // without consuming it.
-                    value.clone(),
-                );
+        self.build_with(|m| {
+            for (key, values) in key_values {
+                if m.get_key(key).is_none() {
+                    let (key_span, value) = values.last().unwrap();
+                    m.insert_key_value(
+                        spanned(*key.item(), *key_span),
+                        // Sadly we do have to clone the value here, so we can
+                        // merge in metadata key/values from the push/pop stack
+                        // without consuming it.
+                        value.clone(),
+                    );
+                }
             }
-        }
+        })
     }
 }
 
@@ -1286,6 +1626,25 @@ type ParserError<'a> = Rich<'a, Token<'a>, Span>;
 
 impl<'a> From<ParserError<'a>> for Error {
     fn from(error: ParserError) -> Self {
+        // whatever was expected, the problem is the missing quote
+        if let Some(Token::UnterminatedString(content)) = error.found() {
+            let span = *error.span();
+            let end = span.start() + 1 + content.trim_end().len();
+            let fix = Fix::new("insert closing quote")
+                .insert_after(Span::new(span.context(), span.start()..end), "\"");
+
+            return Error::with_contexts(
+                "unterminated string",
+                "unterminated string starting here",
+                *error.span(),
+                error
+                    .contexts()
+                    .map(|(label, span)| (label.to_string(), *span))
+                    .collect(),
+            )
+            .with_fix(fix);
+        }
+
         let error = error.map_token(|tok| tok.to_string());
 
         Error::with_contexts(
@@ -1305,6 +1664,7 @@ impl<'a> From<ParserError<'a>> for Error {
 pub(crate) struct ParserState<'a> {
     pub(crate) options: ParserOptions<'a>,
     pub(crate) warnings: Vec<Warning>,
+    pub(crate) config: ParserConfig,
 }
 
 // our ParserExtra with our error and state types
@@ -1,5 +1,5 @@
 #![cfg(test)]
-use super::super::{bare_lex, end_of_input, types::*};
+use super::super::{bare_lex, end_of_input, types::*, CompatMode, ParserConfig, SyntaxVersion};
 use super::*;
 use rust_decimal_macros::dec;
 use std::ops::Range;
@@ -14,6 +14,9 @@ fn bare_lex_with_source(source_id: SourceId, s: &str) -> Vec<(Token, Span)> {
 
 #[test_case(r#"2023-07-03 * "New World Gardens North East Va ;"
 "#, ((2023, Month::July, 3), 0..10), (Flag::Asterisk, 11..12), None, Some(("New World Gardens North East Va ;", 13..48)), vec![], vec![])]
+#[test_case(r#"2023-07-03 * "Smith \"Co\"" "first line
+second line"
+"#, ((2023, Month::July, 3), 0..10), (Flag::Asterisk, 11..12), Some(("Smith \"Co\"", 13..27)), Some(("first line\nsecond line", 28..52)), vec![], vec![])]
 fn test_transaction(
     s: &str,
     expected_date: ((i32, Month, u8), Range<usize>),
@@ -55,8 +58,14 @@ fn test_transaction(
     assert!(result.is_ok());
     let result = result.unwrap();
     assert_eq!(&result.date, &expected_date);
-    assert_eq!(&result.metadata.tags, &expected_tags);
-    assert_eq!(&result.metadata.links, &expected_links);
+    assert_eq!(
+        result.metadata.tags().copied().collect::<HashSet<_>>(),
+        expected_tags
+    );
+    assert_eq!(
+        result.metadata.links().copied().collect::<HashSet<_>>(),
+        expected_links
+    );
     assert!(
         matches!(&result.variant, DirectiveVariant::Transaction(x) if
             x.flag == expected_flag &&
@@ -164,3 +173,271 @@ fn expr_test(s: &str, expected: &str) {
 
     assert_eq!(result, Ok(expected.to_owned()))
 }
+
+#[test_case("*", CompatMode::Lima, true)]
+#[test_case("*", CompatMode::PythonV2, true)]
+#[test_case("'P", CompatMode::Lima, true)]
+#[test_case("'P", CompatMode::PythonV2, true)]
+#[test_case("'X", CompatMode::Lima, true)]
+#[test_case("'X", CompatMode::PythonV2, false)]
+fn flag_compat_mode_test(s: &str, compat_mode: CompatMode, expected_ok: bool) {
+    let source_id = SourceId::default();
+    let tokens = bare_lex_with_source(source_id, s);
+    let spanned_tokens = tokens
+        .spanned(end_of_input(source_id, s))
+        .with_context(source_id);
+    let mut parser_state = ParserState {
+        config: ParserConfig::default().compat_mode(compat_mode),
+        ..Default::default()
+    };
+
+    let result = flag()
+        .then_ignore(any().repeated().collect::<Vec<Token>>())
+        .parse_with_state(spanned_tokens, &mut parser_state)
+        .into_result();
+
+    assert_eq!(result.is_ok(), expected_ok);
+}
+
+#[test_case("100.00 ~ 0.01 GBP", CompatMode::Lima, Some(dec!(0.01)))]
+#[test_case("100.00 ~ 0.01 GBP", CompatMode::PythonV2, Some(dec!(0.01)))]
+#[test_case("100.00 ~ 0.02 / 2 GBP", CompatMode::Lima, None)]
+#[test_case("100.00 ~ 0.02 / 2 GBP", CompatMode::PythonV2, Some(dec!(0.01)))]
+fn tolerance_compat_mode_test(s: &str, compat_mode: CompatMode, expected: Option<Decimal>) {
+    let source_id = SourceId::default();
+    let tokens = bare_lex_with_source(source_id, s);
+    let spanned_tokens = tokens
+        .spanned(end_of_input(source_id, s))
+        .with_context(source_id);
+    let mut parser_state = ParserState {
+        config: ParserConfig::default().compat_mode(compat_mode),
+        ..Default::default()
+    };
+
+    let result = amount_with_tolerance()
+        .map(|amount| amount.tolerance().map(|tolerance| *tolerance.item()))
+        .parse_with_state(spanned_tokens, &mut parser_state)
+        .into_result()
+        .ok()
+        .flatten();
+
+    assert_eq!(result, expected);
+}
+
+#[test_case("GBP", SyntaxVersion::V2, true)]
+#[test_case("GBP", SyntaxVersion::V3, true)]
+#[test_case("/ESZ24", SyntaxVersion::V2, false)]
+#[test_case("/ESZ24", SyntaxVersion::V3, true)]
+fn currency_syntax_version_test(s: &str, syntax_version: SyntaxVersion, expected_ok: bool) {
+    let source_id = SourceId::default();
+    let tokens = bare_lex_with_source(source_id, s);
+    let spanned_tokens = tokens
+        .spanned(end_of_input(source_id, s))
+        .with_context(source_id);
+    let mut parser_state = ParserState {
+        config: ParserConfig::default().syntax_version(syntax_version),
+        ..Default::default()
+    };
+
+    let result = currency()
+        .then_ignore(any().repeated().collect::<Vec<Token>>())
+        .parse_with_state(spanned_tokens, &mut parser_state)
+        .into_result();
+
+    assert_eq!(result.is_ok(), expected_ok);
+}
+
+#[test_case(
+    "2024-01-01 open\n2024-01-02 open Assets:Bank\n",
+    1,
+    &[1];
+    "broken single line"
+)]
+#[test_case(
+    "2024-01-01 * \"broken\" 42\n  Assets:Bank  1 USD\n  Equity:Opening\n2024-01-03 open Assets:Cash\n",
+    1,
+    &[1];
+    "broken header swallows its postings only"
+)]
+#[test_case(
+    "2024-01-01 open\n2024-01-02 close\n2024-01-03 open Assets:Cash\n",
+    1,
+    &[1, 2];
+    "consecutive broken entries each have an error"
+)]
+#[test_case(
+    "2024-01-01 * \"broken\" 42 2024-01-02 open Assets:Bank\n2024-01-03 open Assets:Cash\n",
+    1,
+    &[1];
+    "date within a broken line is not an entry"
+)]
+#[test_case(
+    "2024-01-01 open\n  Assets:Bank\ninclude\noption \"title\" \"Test\"\n",
+    1,
+    &[1, 3];
+    "pragma keyword starts an entry"
+)]
+fn broken_entry_recovery_test(
+    s: &str,
+    expected_declarations: usize,
+    expected_error_lines: &[usize],
+) {
+    let source_id = SourceId::default();
+    let tokens = crate::lex_with_source(source_id, s);
+    let spanned_tokens = tokens
+        .spanned(end_of_input(source_id, s))
+        .with_context(source_id);
+    let mut parser_state = ParserState::default();
+
+    let (declarations, errors) = file(None)
+        .parse_with_state(spanned_tokens, &mut parser_state)
+        .into_output_errors();
+
+    let error_lines = errors
+        .iter()
+        .map(|e| s[..e.span().start].lines().count().max(1))
+        .collect::<Vec<_>>();
+
+    assert_eq!(declarations.map(|d| d.len()), Some(expected_declarations));
+    assert_eq!(error_lines, expected_error_lines);
+}
+
+#[test]
+fn broken_posting_recovery_test() {
+    let s = "2024-01-01 * \"Shop\"\n  Expenses:Food  10.00 NZD\n  Expenses:Drink  {{ 5 NZD\n    receipt: \"r1\"\n  Assets:Bank  -10.00 NZD\n  Assets:Cash  ) USD\n2024-01-02 open Assets:Cash\n";
+    let source_id = SourceId::default();
+    let tokens = crate::lex_with_source(source_id, s);
+    let spanned_tokens = tokens
+        .spanned(end_of_input(source_id, s))
+        .with_context(source_id);
+    let mut parser_state = ParserState::default();
+
+    let (declarations, errors) = file(None)
+        .parse_with_state(spanned_tokens, &mut parser_state)
+        .into_output_errors();
+    let declarations = declarations.unwrap();
+
+    let error_lines = errors
+        .iter()
+        .map(|e| s[..e.span().start].lines().count().max(1))
+        .collect::<Vec<_>>();
+    assert_eq!(error_lines, vec![3, 6]);
+    assert_eq!(declarations.len(), 2);
+
+    let Declaration::Directive(directive) = declarations[0].item() else {
+        panic!("expected directive");
+    };
+    let DirectiveVariant::Transaction(transaction) = directive.variant() else {
+        panic!("expected transaction");
+    };
+    assert_eq!(
+        transaction
+            .postings()
+            .map(|posting| posting.account().item().to_string())
+            .collect::<Vec<_>>(),
+        vec!["Expenses:Food", "Assets:Bank"]
+    );
+    assert_eq!(
+        transaction
+            .broken_postings()
+            .map(|broken| s[broken.span().start..broken.span().end].trim())
+            .collect::<Vec<_>>(),
+        vec![
+            "Expenses:Drink  {{ 5 NZD\n    receipt: \"r1\"",
+            "Assets:Cash  ) USD"
+        ]
+    );
+}
+
+#[test]
+fn unterminated_string_test() {
+    let s = "2024-01-01 * \"Coffee\n  Expenses:Coffee\n2024-01-02 * \"Tea\"\n  Expenses:Tea\n";
+    let source_id = SourceId::default();
+    let tokens = crate::lex_with_source(source_id, s);
+    let spanned_tokens = tokens
+        .spanned(end_of_input(source_id, s))
+        .with_context(source_id);
+    let mut parser_state = ParserState::default();
+
+    let (declarations, errors) = file(None)
+        .parse_with_state(spanned_tokens, &mut parser_state)
+        .into_output_errors();
+    let errors = errors.into_iter().map(Error::from).collect::<Vec<_>>();
+
+    assert_eq!(declarations.map(|d| d.len()), Some(1));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(&*errors[0].message, "unterminated string");
+    assert_eq!(&*errors[0].reason, "unterminated string starting here");
+    assert_eq!(&s[errors[0].span.start..errors[0].span.end], "\"Coffee");
+}
+
+#[test]
+fn custom_test() {
+    let s = r#"2024-01-01 custom "budget" Expenses:Food "monthly" 400.00 NZD TRUE 2024-12-31 #plan
+  note: "groceries and dining"
+"#;
+    let sources = crate::BeancountSources::from(s);
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let DirectiveVariant::Custom(custom) = directives[0].variant() else {
+        panic!("expected custom directive, got {}", directives[0]);
+    };
+    assert_eq!(*custom.custom_type().item(), "budget");
+    assert_eq!(
+        custom
+            .values()
+            .iter()
+            .map(|value| value.item().to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "Expenses:Food",
+            r#""monthly""#,
+            "400.00 NZD",
+            "TRUE",
+            "2024-12-31"
+        ]
+    );
+    assert_eq!(directives[0].metadata().tags().count(), 1);
+    assert_eq!(directives[0].to_string(), s.trim_end());
+}
+
+#[test_case(r#""FIFO""#, Ok(Some(Booking::Fifo)))]
+#[test_case("", Ok(None))]
+#[test_case(
+    r#""fifo""#,
+    Err(r#"unknown booking method "fifo", did you mean "FIFO""#)
+)]
+#[test_case(
+    r#""strict-with-size""#,
+    Err(r#"unknown booking method "strict-with-size", did you mean "STRICT_WITH_SIZE""#)
+)]
+#[test_case(
+    r#""AVERGE""#,
+    Err(r#"unknown booking method "AVERGE", did you mean "AVERAGE""#)
+)]
+#[test_case(r#""oldest""#, Err(r#"unknown booking method "oldest", expected one of STRICT, STRICT_WITH_SIZE, NONE, AVERAGE, FIFO, LIFO, HIFO"#))]
+fn open_booking(booking: &str, expected: Result<Option<Booking>, &str>) {
+    let s = format!("2024-01-01 open Assets:Broker {}\n", booking);
+    let sources = crate::BeancountSources::from(s);
+    let parser = crate::BeancountParser::new(&sources);
+
+    match parser.parse() {
+        Ok(success) => {
+            let DirectiveVariant::Open(open) = success.directives[0].variant() else {
+                panic!("expected open directive");
+            };
+            let expected = expected.unwrap();
+            assert_eq!(open.booking().map(|booking| *booking.item()), expected);
+            assert_eq!(open.is_explicit_booking(), expected.is_some());
+            assert_eq!(
+                open.booking_method(&success.options),
+                expected.unwrap_or_default()
+            );
+        }
+        Err(e) => {
+            assert_eq!(e.errors.len(), 1);
+            assert_eq!(Err(&*e.errors[0].reason), expected);
+        }
+    };
+}
@@ -0,0 +1,392 @@
+use crate::{get_includes, path_dir, types::*, BeancountSources, Options};
+use chumsky::span::Span as _;
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    env,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::{self, Path, PathBuf},
+};
+use time::{Date, Month};
+
+/// Resolves the paths of `document` directives and `include` pragmas to the files they refer to,
+/// so that tools can flag broken links.
+///
+/// A relative document path is looked for first alongside the file containing the directive,
+/// and then in each of the folders given by the `documents` option.  A relative include path
+/// is resolved as when reading the sources, see [IncludeResolution](crate::IncludeResolution).
+///
+/// Expansion of `~` and environment variables in document paths is opt-in, see [PathResolver::expand_paths].
+#[derive(Debug)]
+pub struct PathResolver<'s> {
+    sources: &'s BeancountSources,
+    document_folders: Vec<PathBuf>,
+    expand_paths: bool,
+}
+
+/// A path as written in the sources, resolved against the appropriate base, see [PathResolver].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ResolvedPath {
+    span: Span,
+    path: PathBuf,
+    expanded: Option<PathBuf>,
+    exists: bool,
+}
+
+impl ResolvedPath {
+    /// The span of the path as written.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The resolved path, which if the file was not found is that relative to the containing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path as written after expansion of `~` and environment variables, if that changed it.
+    pub fn expanded(&self) -> Option<&Path> {
+        self.expanded.as_deref()
+    }
+
+    /// Whether the file existed when the path was resolved.
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+}
+
+impl<'s> PathResolver<'s> {
+    /// Create a resolver for `sources`, using the `documents` folders from `options`.
+    pub fn new(sources: &'s BeancountSources, options: &Options) -> Self {
+        let mut document_folders = options.documents().cloned().collect::<Vec<_>>();
+        // options are unordered, so sort for a deterministic search order
+        document_folders.sort();
+
+        PathResolver {
+            sources,
+            document_folders,
+            expand_paths: false,
+        }
+    }
+
+    /// Expand a leading `~` to the home directory, and `${VAR}` to the value of the environment variable `VAR`,
+    /// in document paths.  Anything which can't be expanded is left as written.
+    ///
+    /// Expansion in include paths is specified when reading the sources, see [IncludeResolution::expand_paths](crate::IncludeResolution::expand_paths).
+    pub fn expand_paths(mut self, expand_paths: bool) -> Self {
+        self.expand_paths = expand_paths;
+        self
+    }
+
+    /// Resolve the path of the document directive `directive`, or `None` if it is not a document.
+    pub fn document(&self, directive: &Spanned<Directive>) -> Option<ResolvedPath> {
+        match directive.variant() {
+            DirectiveVariant::Document(document) => Some(self.document_path(document.path())),
+            _ => None,
+        }
+    }
+
+    /// Resolve the paths of all the document directives among `directives`.
+    pub fn documents<'d, 'a, I>(&self, directives: I) -> Vec<ResolvedPath>
+    where
+        I: IntoIterator<Item = &'d Spanned<Directive<'a>>>,
+        'a: 'd,
+    {
+        directives
+            .into_iter()
+            .filter_map(|directive| self.document(directive))
+            .collect()
+    }
+
+    /// Resolve the paths of all includes, in all sources.
+    pub fn includes(&self) -> Vec<ResolvedPath> {
+        self.sources
+            .content_iter()
+            .flat_map(|(source_id, source_path, content)| {
+                get_includes(content, source_id)
+                    .into_iter()
+                    .map(move |included_path| {
+                        let include_resolution = &self.sources.include_resolution;
+                        let (path, _searched) = include_resolution.resolve(
+                            self.sources.root_path.as_deref(),
+                            source_path,
+                            included_path.item().as_ref(),
+                        );
+                        let expanded = include_resolution
+                            .expand_paths
+                            .then(|| expanded(included_path.item().as_ref()))
+                            .flatten();
+                        ResolvedPath {
+                            expanded,
+                            ..resolved(*included_path.span(), path)
+                        }
+                    })
+            })
+            .collect()
+    }
+
+    /// Discover the documents filed in the `documents` folders, as Beancount does,
+    /// that is files named `YYYY-MM-DD.*` in a subfolder for each account, such as `Assets/Bank/2024-01-05.statement.pdf`
+    /// for `Assets:Bank`.
+    ///
+    /// Accounts are those opened among `directives`, and files already referred to by `document` directives are skipped.
+    /// Files named for a date but in a folder which is not an opened account are returned as unmatched,
+    /// while other files are ignored, as are `documents` folders which do not exist.
+    pub fn discover_documents<'d, 'a, I>(&self, directives: I) -> io::Result<DiscoveredDocuments>
+    where
+        I: IntoIterator<Item = &'d Spanned<Directive<'a>>>,
+        'a: 'd,
+    {
+        let mut accounts = HashSet::new();
+        let mut known = HashSet::new();
+        for directive in directives {
+            match directive.variant() {
+                DirectiveVariant::Open(open) => {
+                    accounts.insert(open.account().item().to_string());
+                }
+                DirectiveVariant::Document(document) => {
+                    known.insert(self.document_path(document.path()).path);
+                }
+                _ => (),
+            }
+        }
+
+        let mut discovered = DiscoveredDocuments::default();
+        for folder in self
+            .document_folders
+            .iter()
+            .filter(|folder| folder.is_dir())
+        {
+            discover(folder, &mut Vec::new(), &accounts, &known, &mut discovered)?;
+        }
+        Ok(discovered)
+    }
+
+    fn document_path(&self, path: &Spanned<&str>) -> ResolvedPath {
+        let span = *path.span();
+        let expanded = self
+            .expand_paths
+            .then(|| expanded(Path::new(*path.item())))
+            .flatten();
+        let path = expanded.as_deref().unwrap_or(Path::new(*path.item()));
+
+        if path.is_absolute() {
+            return ResolvedPath {
+                expanded: expanded.clone(),
+                ..resolved(span, path.to_path_buf())
+            };
+        }
+
+        let alongside = match self.source_path(span).and_then(path_dir) {
+            Some(dir) => dir.join(path),
+            None => path.to_path_buf(),
+        };
+
+        std::iter::once(alongside.clone())
+            .chain(self.document_folders.iter().map(|folder| folder.join(path)))
+            .find(|candidate| candidate.exists())
+            .map_or_else(
+                || ResolvedPath {
+                    span,
+                    path: alongside,
+                    expanded: expanded.clone(),
+                    exists: false,
+                },
+                |path| ResolvedPath {
+                    span,
+                    path,
+                    expanded: expanded.clone(),
+                    exists: true,
+                },
+            )
+    }
+
+    fn source_path(&self, span: Span) -> Option<&'s Path> {
+        self.sources
+            .content_iter()
+            .find_map(|(source_id, source_path, _)| {
+                (source_id == span.context()).then_some(source_path)
+            })
+            .flatten()
+    }
+}
+
+/// The result of [PathResolver::discover_documents].
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct DiscoveredDocuments {
+    documents: Vec<DiscoveredDocument>,
+    unmatched: Vec<PathBuf>,
+}
+
+impl DiscoveredDocuments {
+    /// The documents found, in order of folder and then file name.
+    pub fn documents(&self) -> &[DiscoveredDocument] {
+        &self.documents
+    }
+
+    /// Files named for a date, but in a folder which is not an opened account.
+    pub fn unmatched(&self) -> &[PathBuf] {
+        &self.unmatched
+    }
+}
+
+/// A document found on disk, which displays as the `document` directive Beancount would synthesize for it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DiscoveredDocument {
+    date: Date,
+    account: String,
+    path: PathBuf,
+}
+
+impl DiscoveredDocument {
+    /// The date from the file name.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// The account from the folder, in the usual colon-separated form.
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    /// Field accessor.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Display for DiscoveredDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} document {} \"{}\"",
+            self.date,
+            self.account,
+            self.path.display()
+        )
+    }
+}
+
+// depth-first, with entries sorted by name for a deterministic order
+fn discover(
+    dir: &Path,
+    components: &mut Vec<String>,
+    accounts: &HashSet<String>,
+    known: &HashSet<PathBuf>,
+    discovered: &mut DiscoveredDocuments,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    let account = components.join(":");
+    for path in entries {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            components.push(name.to_string());
+            discover(&path, components, accounts, known, discovered)?;
+            components.pop();
+        } else if let Some(date) = document_date(name) {
+            if !accounts.contains(&account) {
+                discovered.unmatched.push(path);
+            } else if !known.contains(&path) {
+                discovered.documents.push(DiscoveredDocument {
+                    date,
+                    account: account.clone(),
+                    path,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the date of a file named `YYYY-MM-DD.*`, if any
+fn document_date(name: &str) -> Option<Date> {
+    let (date, _) = name.split_once('.')?;
+    let mut fields = date.split('-');
+    let mut field = |width: usize| {
+        fields
+            .next()
+            .filter(|field| field.len() == width && field.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|field| field.parse::<i32>().ok())
+    };
+    let (year, month, day) = (field(4)?, field(2)?, field(2)?);
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Date::from_calendar_date(year, Month::try_from(month as u8).ok()?, day as u8).ok()
+}
+
+fn resolved(span: Span, path: PathBuf) -> ResolvedPath {
+    let exists = path.exists();
+    ResolvedPath {
+        span,
+        path,
+        expanded: None,
+        exists,
+    }
+}
+
+// the expansion of `path`, if that changed it
+fn expanded(path: &Path) -> Option<PathBuf> {
+    match expand_path(path) {
+        Cow::Owned(path) => Some(path),
+        Cow::Borrowed(_) => None,
+    }
+}
+
+/// Expand a leading `~` to the home directory, and `${VAR}` to the value of the environment variable `VAR`,
+/// leaving as written anything which can't be expanded.
+pub(crate) fn expand_path(path: &Path) -> Cow<'_, Path> {
+    let Some(written) = path.to_str() else {
+        return Cow::Borrowed(path);
+    };
+
+    let mut expanded = String::with_capacity(written.len());
+    let mut rest = written;
+
+    if let Some(after_tilde) = written.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(path::is_separator) {
+            if let Ok(home) = env::var("HOME") {
+                expanded.push_str(&home);
+                rest = after_tilde;
+            }
+        }
+    }
+
+    while let Some(start) = rest.find("${") {
+        let (before, from_start) = rest.split_at(start);
+        expanded.push_str(before);
+        match from_start.find('}') {
+            Some(end) => {
+                let var = &from_start[2..end];
+                match env::var(var) {
+                    Ok(value) if !var.is_empty() => expanded.push_str(&value),
+                    _ => expanded.push_str(&from_start[..=end]),
+                }
+                rest = &from_start[end + 1..];
+            }
+            None => {
+                expanded.push_str(from_start);
+                rest = "";
+            }
+        }
+    }
+    expanded.push_str(rest);
+
+    if expanded == written {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(PathBuf::from(expanded))
+    }
+}
+
+mod tests;
@@ -0,0 +1,262 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+use std::{fs, iter::once};
+
+// a fresh ledger directory, with empty files at the given relative paths
+fn ledger_dir(name: &str, main: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-paths-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (path, content) in once(&("main.beancount", main)).chain(files) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+const MAIN: &str = r#"option "documents" "docs"
+include "sub/ok.beancount"
+2024-01-01 open Assets:Bank
+2024-01-02 document Assets:Bank "alongside.pdf"
+2024-01-03 document Assets:Bank "found.pdf"
+2024-01-04 document Assets:Bank "absent.pdf"
+"#;
+
+const SUB: &str = r#"2024-01-05 document Assets:Bank "local.pdf"
+"#;
+
+#[test]
+fn documents() {
+    let dir = ledger_dir(
+        "documents",
+        MAIN,
+        &[
+            ("sub/ok.beancount", SUB),
+            ("sub/local.pdf", ""),
+            ("alongside.pdf", ""),
+            ("docs/found.pdf", ""),
+        ],
+    );
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let resolver = PathResolver::new(&sources, &success.options);
+
+    let mut documents = resolver
+        .documents(&success.directives)
+        .into_iter()
+        .map(|resolved| (resolved.path().to_path_buf(), resolved.exists()))
+        .collect::<Vec<_>>();
+    documents.sort();
+
+    let mut expected = vec![
+        (dir.join("alongside.pdf"), true),
+        (dir.join("docs/found.pdf"), true),
+        (dir.join("absent.pdf"), false),
+        (dir.join("sub/local.pdf"), true),
+    ];
+    expected.sort();
+
+    assert_eq!(documents, expected);
+    assert!(resolver.document(&success.directives[0]).is_none());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn includes() {
+    let main = format!("{}include \"missing.beancount\"\n", MAIN);
+    let dir = ledger_dir("includes", &main, &[("sub/ok.beancount", SUB)]);
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    // the missing include is a parse error, but includes may still be resolved
+    let options = match parser.parse() {
+        Ok(success) => success.options,
+        Err(_) => Options::new(Default::default()),
+    };
+    let resolver = PathResolver::new(&sources, &options);
+
+    let includes = resolver
+        .includes()
+        .into_iter()
+        .map(|resolved| {
+            (
+                &main[resolved.span().start()..resolved.span().end()],
+                resolved.path().to_path_buf(),
+                resolved.exists(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        includes,
+        vec![
+            ("\"sub/ok.beancount\"", dir.join("sub/ok.beancount"), true),
+            (
+                "\"missing.beancount\"",
+                dir.join("missing.beancount"),
+                false
+            ),
+        ]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn discover_documents() {
+    let main = r#"option "documents" "docs"
+option "documents" "missing"
+2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Bank:Savings
+2024-01-01 open Expenses:Rent
+2024-01-03 document Assets:Bank "docs/Assets/Bank/2024-01-03.known.pdf"
+"#;
+    let dir = ledger_dir(
+        "discover",
+        main,
+        &[
+            ("docs/Assets/Bank/2024-01-31.statement.pdf", ""),
+            ("docs/Assets/Bank/2024-01-03.known.pdf", ""),
+            ("docs/Assets/Bank/notes.txt", ""),
+            ("docs/Assets/Bank/2024-13-01.bad-month.pdf", ""),
+            ("docs/Assets/Bank/Savings/2024-02-29.interest.pdf", ""),
+            ("docs/Expenses/Rent/2024-02-01.lease.pdf", ""),
+            ("docs/Expenses/Food/2024-02-02.receipt.jpg", ""),
+            ("docs/2024-02-03.unfiled.pdf", ""),
+        ],
+    );
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let resolver = PathResolver::new(&sources, &success.options);
+    let discovered = resolver.discover_documents(&success.directives).unwrap();
+    let docs = dir.join("docs");
+
+    assert_eq!(
+        discovered
+            .documents()
+            .iter()
+            .map(|document| document.to_string())
+            .collect::<Vec<_>>(),
+        [
+            (
+                "2024-01-31",
+                "Assets:Bank",
+                "Assets/Bank/2024-01-31.statement.pdf"
+            ),
+            (
+                "2024-02-29",
+                "Assets:Bank:Savings",
+                "Assets/Bank/Savings/2024-02-29.interest.pdf"
+            ),
+            (
+                "2024-02-01",
+                "Expenses:Rent",
+                "Expenses/Rent/2024-02-01.lease.pdf"
+            ),
+        ]
+        .iter()
+        .map(|(date, account, path)| format!(
+            "{} document {} \"{}\"",
+            date,
+            account,
+            docs.join(path).display()
+        ))
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        discovered.unmatched(),
+        &[
+            docs.join("2024-02-03.unfiled.pdf"),
+            docs.join("Expenses/Food/2024-02-02.receipt.jpg")
+        ]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn expand_path_tilde_and_variables() {
+    std::env::set_var("BEANCOUNT_PARSER_LIMA_TEST_DIR", "/srv/ledger");
+    let home = std::env::var("HOME").unwrap();
+
+    assert_eq!(
+        expand_path(Path::new("~/docs/a.pdf")),
+        PathBuf::from(format!("{}/docs/a.pdf", home))
+    );
+    assert_eq!(
+        expand_path(Path::new(
+            "${BEANCOUNT_PARSER_LIMA_TEST_DIR}/2024/${BEANCOUNT_PARSER_LIMA_TEST_DIR}"
+        )),
+        Path::new("/srv/ledger/2024//srv/ledger")
+    );
+    for unexpanded in [
+        "~other/a.pdf",
+        "docs/~/a.pdf",
+        "${BEANCOUNT_PARSER_LIMA_TEST_UNSET}/a.pdf",
+        "${}/a.pdf",
+        "${BEANCOUNT_PARSER_LIMA_TEST_DIR",
+        "$BEANCOUNT_PARSER_LIMA_TEST_DIR/a.pdf",
+    ] {
+        assert!(
+            matches!(expand_path(Path::new(unexpanded)), Cow::Borrowed(_)),
+            "{}",
+            unexpanded
+        );
+    }
+}
+
+#[test]
+fn expanded_documents_and_includes() {
+    let dir = ledger_dir(
+        "expanded",
+        r#"include "${BEANCOUNT_PARSER_LIMA_TEST_EXPANDED}/sub/ok.beancount"
+2024-01-01 open Assets:Bank
+2024-01-02 document Assets:Bank "${BEANCOUNT_PARSER_LIMA_TEST_EXPANDED}/statement.pdf"
+"#,
+        &[("sub/ok.beancount", ""), ("statement.pdf", "")],
+    );
+    std::env::set_var("BEANCOUNT_PARSER_LIMA_TEST_EXPANDED", &dir);
+
+    // without opting in, the include is not found
+    let sources = BeancountSources::try_from(dir.join("main.beancount")).unwrap();
+    let parser = BeancountParser::new(&sources);
+    assert!(parser.parse().is_err());
+
+    let sources = BeancountSources::with_include_resolution(
+        dir.join("main.beancount"),
+        crate::IncludeResolution::default().expand_paths(true),
+    )
+    .unwrap();
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    let unexpanded = PathResolver::new(&sources, &success.options);
+    let documents = unexpanded.documents(&success.directives);
+    assert!(!documents[0].exists());
+    assert_eq!(documents[0].expanded(), None);
+
+    let resolver = PathResolver::new(&sources, &success.options).expand_paths(true);
+    let documents = resolver.documents(&success.directives);
+    assert_eq!(documents[0].path(), dir.join("statement.pdf"));
+    assert_eq!(
+        documents[0].expanded(),
+        Some(dir.join("statement.pdf").as_path())
+    );
+    assert!(documents[0].exists());
+
+    let includes = resolver.includes();
+    assert_eq!(includes[0].path(), dir.join("sub/ok.beancount"));
+    assert_eq!(
+        includes[0].expanded(),
+        Some(dir.join("sub/ok.beancount").as_path())
+    );
+    assert!(includes[0].exists());
+}
@@ -0,0 +1,164 @@
+use crate::{interpolation::interpolate, trial_balance::Units, types::*};
+use std::collections::{BTreeMap, BTreeSet};
+use time::Date;
+
+/// Summarize the transactions with each payee, such as for a view of top merchants, or for suggesting categories on import.
+///
+/// Any directives may be given, such as those of a [LedgerView](crate::LedgerView).
+/// Transactions without a payee are ignored.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Currency, PayeeSummaries};
+///
+/// let sources = BeancountSources::from(r#"2024-01-05 * "Countdown" "Groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Food
+/// 2024-01-12 * "Countdown" "Groceries"
+///   Assets:Bank  -30.00 NZD
+///   Expenses:Food
+/// 2024-01-13 * "Z Energy" "Fuel"
+///   Assets:Bank  -90.00 NZD
+///   Expenses:Fuel
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let summaries = PayeeSummaries::new(&directives).unwrap();
+/// let countdown = summaries.get("Countdown").unwrap();
+///
+/// assert_eq!(countdown.count(), 2);
+/// assert_eq!(countdown.total().to_string(), "72.50 NZD");
+/// assert_eq!(countdown.last_date().to_string(), "2024-01-12");
+///
+/// let nzd = Currency::try_from("NZD").unwrap();
+/// assert_eq!(summaries.top(&nzd).next().unwrap().payee(), "Z Energy");
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PayeeSummaries<'a> {
+    payees: BTreeMap<&'a str, PayeeSummary<'a>>,
+}
+
+impl<'a> PayeeSummaries<'a> {
+    /// Summarize all transactions with a payee, ignoring other directives.
+    ///
+    /// The units of any posting without an amount are interpolated so that its transaction balances.
+    pub fn new<I>(directives: I) -> Result<Self, Vec<Error>>
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut payees = BTreeMap::<&'a str, PayeeSummary<'a>>::new();
+        let mut errors = Vec::new();
+
+        for directive in directives {
+            let DirectiveVariant::Transaction(transaction) = directive.variant() else {
+                continue;
+            };
+            let Some(payee) = transaction.payee().map(|payee| *payee.item()) else {
+                continue;
+            };
+
+            match interpolate(transaction) {
+                Ok(units) => {
+                    let date = *directive.date().item();
+                    let summary = payees.entry(payee).or_insert_with(|| PayeeSummary {
+                        payee,
+                        count: 0,
+                        total: Units::default(),
+                        first_date: date,
+                        last_date: date,
+                        accounts: BTreeSet::new(),
+                    });
+
+                    summary.count += 1;
+                    summary.first_date = summary.first_date.min(date);
+                    summary.last_date = summary.last_date.max(date);
+                    for units in units {
+                        let account = units.posting.account().item();
+                        if matches!(
+                            account.account_type(),
+                            AccountType::Expenses | AccountType::Income
+                        ) {
+                            summary.total.add(units.currency, units.number);
+                        }
+                        summary.accounts.insert(account);
+                    }
+                }
+                Err(e) => errors.push(e.in_context(directive)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(PayeeSummaries { payees })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The summaries for every payee, in order of payee.
+    pub fn payees(&self) -> impl Iterator<Item = &PayeeSummary<'a>> {
+        self.payees.values()
+    }
+
+    /// The summary for the given payee, if there were any transactions with it.
+    pub fn get(&self, payee: &str) -> Option<&PayeeSummary<'a>> {
+        self.payees.get(payee)
+    }
+
+    /// The summaries for every payee with a nonzero total in `currency`, in order of decreasing total,
+    /// so the payees spent with the most come first, and those most received from come last.
+    pub fn top(&self, currency: &Currency<'_>) -> impl Iterator<Item = &PayeeSummary<'a>> {
+        let mut top = self
+            .payees
+            .values()
+            .filter(|summary| !summary.total.get(currency).is_zero())
+            .collect::<Vec<_>>();
+        top.sort_by_key(|summary| std::cmp::Reverse(summary.total.get(currency)));
+        top.into_iter()
+    }
+}
+
+/// The summary of the transactions with a payee, see [PayeeSummaries].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PayeeSummary<'a> {
+    payee: &'a str,
+    count: usize,
+    total: Units<'a>,
+    first_date: Date,
+    last_date: Date,
+    accounts: BTreeSet<&'a Account<'a>>,
+}
+
+impl<'a> PayeeSummary<'a> {
+    /// Field accessor.
+    pub fn payee(&self) -> &'a str {
+        self.payee
+    }
+
+    /// The number of transactions.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The total of the postings to `Expenses` and `Income` accounts,
+    /// which is positive for spending with the payee and negative for income from them.
+    pub fn total(&self) -> &Units<'a> {
+        &self.total
+    }
+
+    /// The date of the earliest transaction.
+    pub fn first_date(&self) -> Date {
+        self.first_date
+    }
+
+    /// The date of the latest transaction.
+    pub fn last_date(&self) -> Date {
+        self.last_date
+    }
+
+    /// All accounts posted to in the transactions, in order of account.
+    pub fn accounts(&self) -> impl Iterator<Item = &'a Account<'a>> + '_ {
+        self.accounts.iter().copied()
+    }
+}
+
+mod tests;
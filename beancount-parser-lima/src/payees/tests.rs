@@ -0,0 +1,91 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources, LedgerView};
+
+#[test]
+fn payee_summaries_over_view() {
+    let s = r#"2024-01-20 * "Countdown" "Groceries"
+  Liabilities:Card  -30.00 NZD
+  Expenses:Food
+2024-01-05 * "Countdown" "Groceries and a refund"
+  Assets:Bank       -40.00 NZD
+  Expenses:Food      42.50 NZD
+  Expenses:Household -2.50 NZD
+2024-01-15 * "Employer" "Salary"
+  Assets:Bank      1000.00 NZD
+  Income:Salary
+2024-01-16 * "Exchange"
+  Assets:Bank       -10.00 NZD
+  Assets:Travel       6.00 USD @ 0.60 NZD
+2024-01-25 * "Amazon" "Books"
+  Liabilities:Card  -20.00 USD
+  Expenses:Books
+2024-02-01 * "Countdown" "Outside the view"
+  Assets:Bank       -99.00 NZD
+  Expenses:Food
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let date = |day| Date::from_calendar_date(2024, time::Month::January, day).unwrap();
+    let view = LedgerView::new(&directives).dates(date(1)..=date(31));
+
+    let summaries = PayeeSummaries::new(&view).unwrap();
+
+    assert_eq!(
+        summaries
+            .payees()
+            .map(|summary| (
+                summary.payee(),
+                summary.count(),
+                summary.total().to_string(),
+                summary.first_date().to_string(),
+                summary.last_date().to_string(),
+                summary
+                    .accounts()
+                    .map(|account| account.to_string())
+                    .collect::<Vec<_>>()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "Amazon",
+                1,
+                "20.00 USD".to_string(),
+                "2024-01-25".to_string(),
+                "2024-01-25".to_string(),
+                vec!["Liabilities:Card".to_string(), "Expenses:Books".to_string()]
+            ),
+            (
+                "Countdown",
+                2,
+                "70.00 NZD".to_string(),
+                "2024-01-05".to_string(),
+                "2024-01-20".to_string(),
+                vec![
+                    "Assets:Bank".to_string(),
+                    "Liabilities:Card".to_string(),
+                    "Expenses:Food".to_string(),
+                    "Expenses:Household".to_string()
+                ]
+            ),
+            (
+                "Employer",
+                1,
+                "-1000.00 NZD".to_string(),
+                "2024-01-15".to_string(),
+                "2024-01-15".to_string(),
+                vec!["Assets:Bank".to_string(), "Income:Salary".to_string()]
+            ),
+        ]
+    );
+
+    let nzd = Currency::try_from("NZD").unwrap();
+    assert_eq!(
+        summaries
+            .top(&nzd)
+            .map(|summary| summary.payee())
+            .collect::<Vec<_>>(),
+        vec!["Countdown", "Employer"]
+    );
+}
@@ -0,0 +1,176 @@
+use crate::types::*;
+
+/// A sequence of transforms applied to directives in turn, as the basis for plugin-style processing.
+///
+/// For example, to keep only the directives in a date range, and tag each one:
+/// ```ignore
+/// let transformed = Pipeline::new()
+///     .filter(|directive| range.contains(directive.date().item()))
+///     .map(|mut directive| {
+///         let tag = directive.date().map(|_| imported);
+///         directive.metadata_mut().add_tag(tag);
+///         directive
+///     })
+///     .run(directives);
+/// ```
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    stages: Vec<Box<dyn Transform<'a> + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// An empty pipeline, which passes all directives through unchanged.
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Add a stage which applies an arbitrary [Transform].
+    pub fn stage<T>(mut self, transform: T) -> Self
+    where
+        T: Transform<'a> + 'a,
+    {
+        self.stages.push(Box::new(transform));
+        self
+    }
+
+    /// Add a stage which keeps only those directives satisfying `predicate`.
+    pub fn filter<F>(self, predicate: F) -> Self
+    where
+        F: FnMut(&Spanned<Directive<'a>>) -> bool + 'a,
+    {
+        self.stage(Filter(predicate))
+    }
+
+    /// Add a stage which replaces each directive with the result of `f`.
+    pub fn map<F>(self, f: F) -> Self
+    where
+        F: FnMut(Spanned<Directive<'a>>) -> Spanned<Directive<'a>> + 'a,
+    {
+        self.stage(Map(f))
+    }
+
+    /// Add a stage which replaces each directive with the result of `f`, or drops it if that is an error.
+    pub fn try_map<F>(self, f: F) -> Self
+    where
+        F: FnMut(Spanned<Directive<'a>>) -> Result<Spanned<Directive<'a>>, Error> + 'a,
+    {
+        self.stage(TryMap(f))
+    }
+
+    /// Add a stage which copies the key/values and tags of each transaction onto its postings,
+    /// as many plugins expect when querying posting metadata.
+    ///
+    /// Metadata already on a posting takes precedence over that of its transaction.
+    pub fn inherit_metadata(self) -> Self {
+        self.stage(InheritMetadata)
+    }
+
+    /// Run the directives through each stage in turn, collecting the errors from all stages.
+    ///
+    /// Later stages run even if earlier ones had errors.
+    pub fn run<I>(&mut self, directives: I) -> Transformed<'a>
+    where
+        I: IntoIterator<Item = Spanned<Directive<'a>>>,
+    {
+        let mut errors = Vec::new();
+        let directives = self
+            .stages
+            .iter_mut()
+            .fold(directives.into_iter().collect(), |directives, stage| {
+                stage.transform(directives, &mut errors)
+            });
+
+        Transformed { directives, errors }
+    }
+}
+
+/// A single stage of a [Pipeline].
+pub trait Transform<'a> {
+    /// Transform the directives, appending any errors to `errors`.
+    fn transform(
+        &mut self,
+        directives: Vec<Spanned<Directive<'a>>>,
+        errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>>;
+}
+
+struct Filter<F>(F);
+
+impl<'a, F> Transform<'a> for Filter<F>
+where
+    F: FnMut(&Spanned<Directive<'a>>) -> bool,
+{
+    fn transform(
+        &mut self,
+        directives: Vec<Spanned<Directive<'a>>>,
+        _errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        directives.into_iter().filter(|d| (self.0)(d)).collect()
+    }
+}
+
+struct Map<F>(F);
+
+impl<'a, F> Transform<'a> for Map<F>
+where
+    F: FnMut(Spanned<Directive<'a>>) -> Spanned<Directive<'a>>,
+{
+    fn transform(
+        &mut self,
+        directives: Vec<Spanned<Directive<'a>>>,
+        _errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        directives.into_iter().map(&mut self.0).collect()
+    }
+}
+
+struct TryMap<F>(F);
+
+impl<'a, F> Transform<'a> for TryMap<F>
+where
+    F: FnMut(Spanned<Directive<'a>>) -> Result<Spanned<Directive<'a>>, Error>,
+{
+    fn transform(
+        &mut self,
+        directives: Vec<Spanned<Directive<'a>>>,
+        errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        directives
+            .into_iter()
+            .filter_map(|d| (self.0)(d).map_err(|e| errors.push(e)).ok())
+            .collect()
+    }
+}
+
+struct InheritMetadata;
+
+impl<'a> Transform<'a> for InheritMetadata {
+    fn transform(
+        &mut self,
+        mut directives: Vec<Spanned<Directive<'a>>>,
+        _errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        for directive in directives.iter_mut() {
+            let Directive {
+                metadata, variant, ..
+            } = &mut directive.item;
+            if let DirectiveVariant::Transaction(transaction) = variant {
+                for posting in transaction.postings.iter_mut() {
+                    posting.item.metadata.inherit(metadata);
+                }
+            }
+        }
+        directives
+    }
+}
+
+/// The result of running a [Pipeline].
+#[derive(Clone, Debug)]
+pub struct Transformed<'a> {
+    /// The directives output by the final stage.
+    pub directives: Vec<Spanned<Directive<'a>>>,
+    /// The errors from all stages, in order of stage.
+    pub errors: Vec<Error>,
+}
+
+mod tests;
@@ -0,0 +1,126 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank
+2024-02-01 note Assets:Bank "February"
+2024-03-01 note Assets:Bank "March"
+2024-04-01 note Assets:Bank "April"
+"#;
+
+#[test]
+fn pipeline_filter_map() {
+    let range = date("2024-02-01")..date("2024-04-01");
+    let imported = Tag::try_from("imported").unwrap();
+
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new()
+        .filter(move |directive| range.contains(directive.date().item()))
+        .map(move |mut directive| {
+            let tag = directive.date().map(|_| imported);
+            directive.metadata_mut().add_tag(tag);
+            directive
+        })
+        .run(directives);
+
+    assert!(transformed.errors.is_empty());
+    assert_eq!(
+        transformed
+            .directives
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "2024-02-01 note Assets:Bank \"February\" #imported",
+            "2024-03-01 note Assets:Bank \"March\" #imported",
+        ]
+    );
+}
+
+#[test]
+fn pipeline_merges_errors() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new()
+        .try_map(|directive| {
+            if *directive.date().item() == date("2024-02-01") {
+                Err(directive.error("no notes in February"))
+            } else {
+                Ok(directive)
+            }
+        })
+        .try_map(|directive| {
+            if *directive.date().item() == date("2024-04-01") {
+                Err(directive.error("no notes in April"))
+            } else {
+                Ok(directive)
+            }
+        })
+        .run(directives);
+
+    assert_eq!(transformed.directives.len(), 2);
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec!["no notes in February", "no notes in April"]
+    );
+}
+
+#[test]
+fn pipeline_inherit_metadata() {
+    let sources = BeancountSources::from(
+        r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-02 * "Shop" #groceries
+  receipt: "r1"
+  trip: "home"
+  Expenses:Food  10.00 NZD
+    trip: "away"
+  Assets:Bank
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new()
+        .inherit_metadata()
+        .run(directives.iter().cloned());
+
+    assert!(transformed.errors.is_empty());
+    let DirectiveVariant::Transaction(transaction) = transformed.directives[2].variant() else {
+        panic!("expected transaction");
+    };
+    let postings = transaction.postings().collect::<Vec<_>>();
+    for posting in postings.iter() {
+        assert_eq!(
+            posting
+                .metadata()
+                .tags()
+                .map(|tag| tag.to_string())
+                .collect::<Vec<_>>(),
+            vec!["#groceries"]
+        );
+        assert_eq!(posting.metadata().get_string("receipt"), Some("r1"));
+    }
+    assert_eq!(postings[0].metadata().get_string("trip"), Some("away"));
+    assert_eq!(postings[1].metadata().get_string("trip"), Some("home"));
+
+    // the source directives are unchanged
+    let DirectiveVariant::Transaction(transaction) = directives[2].variant() else {
+        panic!("expected transaction");
+    };
+    assert!(transaction
+        .postings()
+        .all(|posting| posting.metadata().tags().len() == 0
+            && posting.metadata().get("receipt").is_none()));
+}
@@ -0,0 +1,255 @@
+use crate::{booking::book, types::*, Options};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+};
+use time::Date;
+
+/// The prices to be fetched by an external price fetcher, as for Beancount's `bean-price`,
+/// according to the `price` metadata on `commodity` directives, for example:
+///
+/// ```text
+/// 2024-01-01 commodity AAPL
+///   price: "USD:yahoo/AAPL,google/NASDAQ:AAPL CAD:yahoo/AAPL.TO"
+/// ```
+///
+/// Each space-separated entry gives a quote currency and its sources in order of preference, separated by commas,
+/// where each source is a provider and a ticker, and a ticker prefixed with `^` quotes the inverse price.
+///
+/// By default, only commodities held on the date of the request are included, and prices already in the ledger are omitted.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, PriceRequests};
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 commodity AAPL
+///   price: "USD:yahoo/AAPL"
+/// 2024-01-01 open Assets:Broker
+/// 2024-01-01 open Equity:Opening
+/// 2024-01-02 * "Buy"
+///   Assets:Broker  10 AAPL
+///   Equity:Opening
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let success = parser.parse().unwrap();
+/// let date = Date::from_calendar_date(2024, Month::March, 1).unwrap();
+/// let requests = PriceRequests::new(success.directives.iter())
+///     .requests(&success.options, date)
+///     .unwrap();
+///
+/// assert_eq!(requests[0].to_string(), "2024-03-01 AAPL USD yahoo/AAPL");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PriceRequests<'a> {
+    directives: Vec<&'a Spanned<Directive<'a>>>,
+    inactive: bool,
+}
+
+impl<'a> PriceRequests<'a> {
+    /// Requests from `directives`, of which only `commodity`, `price`, and `transaction` directives are relevant.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        PriceRequests {
+            directives: directives.into_iter().collect(),
+            inactive: false,
+        }
+    }
+
+    /// Include commodities with price sources regardless of whether they are held.
+    pub fn inactive(mut self) -> Self {
+        self.inactive = true;
+        self
+    }
+
+    /// The prices to fetch for `date`, in order of commodity declaration and then quote currency as given.
+    pub fn requests(
+        &self,
+        options: &Options<'_>,
+        date: Date,
+    ) -> Result<Vec<PriceRequest<'a>>, Vec<Error>> {
+        let mut errors = Vec::new();
+
+        let held = if self.inactive {
+            HashSet::new()
+        } else {
+            match book(self.directives.iter().copied(), options, date) {
+                Ok(inventories) => inventories
+                    .values()
+                    .flat_map(|inventory| inventory.positions())
+                    .filter(|position| !position.units().is_zero())
+                    .map(|position| position.currency())
+                    .collect::<HashSet<_>>(),
+                Err(mut booking_errors) => {
+                    errors.append(&mut booking_errors);
+                    HashSet::new()
+                }
+            }
+        };
+
+        let priced = self
+            .directives
+            .iter()
+            .filter(|directive| *directive.date().item() == date)
+            .filter_map(|directive| match directive.variant() {
+                DirectiveVariant::Price(price) => {
+                    Some((*price.currency().item(), *price.amount().currency().item()))
+                }
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let mut requests = Vec::new();
+        for directive in self.directives.iter() {
+            if let DirectiveVariant::Commodity(commodity) = directive.variant() {
+                let base = *commodity.currency().item();
+
+                match commodity.price_sources(directive.metadata()) {
+                    Ok(quotes) => {
+                        if self.inactive || held.contains(&base) {
+                            requests.extend(
+                                quotes
+                                    .into_iter()
+                                    .filter(|(quote, _)| !priced.contains(&(base, *quote)))
+                                    .map(|(quote, sources)| PriceRequest {
+                                        base,
+                                        quote,
+                                        date,
+                                        sources,
+                                    }),
+                            );
+                        }
+                    }
+                    Err(e) => errors.push(e.in_context(directive)),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(requests)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// the quote currencies and their sources, from metadata such as "USD:yahoo/AAPL,google/NASDAQ:AAPL CAD:yahoo/AAPL.TO"
+pub(crate) fn parse_price_metadata<'a>(
+    value: &Spanned<MetaValue<'a>>,
+) -> Result<PriceSources<'a>, Error> {
+    let invalid = |reason: &str| Error::new("invalid price metadata", reason, value.span);
+
+    let MetaValue::Simple(SimpleValue::String(s)) = value.item() else {
+        return Err(invalid("expected string"));
+    };
+
+    let s: &'a str = s;
+
+    s.split_whitespace()
+        .map(|entry| {
+            let (quote, sources) = entry
+                .split_once(':')
+                .ok_or_else(|| invalid("expected quote currency followed by colon"))?;
+            let quote = Currency::try_from(quote).map_err(|_| invalid("invalid quote currency"))?;
+            let sources = sources
+                .split(',')
+                .map(|source| {
+                    let (provider, ticker) = source
+                        .split_once('/')
+                        .filter(|(provider, ticker)| !provider.is_empty() && !ticker.is_empty())
+                        .ok_or_else(|| invalid("expected provider/ticker"))?;
+                    let (ticker, inverted) = match ticker.strip_prefix('^') {
+                        Some(ticker) => (ticker, true),
+                        None => (ticker, false),
+                    };
+                    Ok(PriceSource {
+                        provider,
+                        ticker,
+                        inverted,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            Ok((quote, sources))
+        })
+        .collect()
+}
+
+/// A price to fetch, see [PriceRequests].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PriceRequest<'a> {
+    base: Currency<'a>,
+    quote: Currency<'a>,
+    date: Date,
+    sources: Vec<PriceSource<'a>>,
+}
+
+impl<'a> PriceRequest<'a> {
+    /// The commodity to be priced.
+    pub fn base(&self) -> Currency<'a> {
+        self.base
+    }
+
+    /// The currency in which the price is quoted.
+    pub fn quote(&self) -> Currency<'a> {
+        self.quote
+    }
+
+    /// Field accessor.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// The sources to try, in order of preference.
+    pub fn sources(&self) -> impl ExactSizeIterator<Item = &PriceSource<'a>> {
+        self.sources.iter()
+    }
+}
+
+impl Display for PriceRequest<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} ", self.date, self.base, self.quote)?;
+        crate::format::format(f, self.sources.iter(), |source| source, ",", None)
+    }
+}
+
+/// A source of a price, as a provider and the ticker by which it knows the commodity.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PriceSource<'a> {
+    provider: &'a str,
+    ticker: &'a str,
+    inverted: bool,
+}
+
+impl<'a> PriceSource<'a> {
+    /// The price provider, such as `yahoo`, as understood by the price fetcher.
+    pub fn provider(&self) -> &'a str {
+        self.provider
+    }
+
+    /// The ticker, without any `^` prefix.
+    pub fn ticker(&self) -> &'a str {
+        self.ticker
+    }
+
+    /// Whether the provider quotes the inverse price, which must be inverted after fetching.
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+}
+
+impl Display for PriceSource<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}{}",
+            self.provider,
+            if self.inverted { "^" } else { "" },
+            self.ticker
+        )
+    }
+}
+
+mod tests;
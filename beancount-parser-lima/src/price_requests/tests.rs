@@ -0,0 +1,97 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+use time::Month;
+
+const LEDGER: &str = r#"2024-01-01 commodity AAPL
+  price: "USD:yahoo/AAPL,google/NASDAQ:AAPL CAD:yahoo/AAPL.TO"
+2024-01-01 commodity NZD
+  price: "USD:ecb/^USDNZD"
+2024-01-01 commodity VWRL
+  price: "GBP:yahoo/VWRL.L"
+2024-01-01 open Assets:Broker
+2024-01-01 open Equity:Opening
+
+2024-01-02 * "Buy"
+  Assets:Broker  10 AAPL
+  Assets:Broker  100.00 NZD
+  Equity:Opening
+
+2024-03-01 price AAPL 180.00 CAD
+"#;
+
+fn requests<F>(s: &str, inactive: bool, f: F)
+where
+    F: FnOnce(Result<Vec<PriceRequest<'_>>, Vec<Error>>),
+{
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let date = Date::from_calendar_date(2024, Month::March, 1).unwrap();
+    let price_requests = PriceRequests::new(success.directives.iter());
+    let price_requests = if inactive {
+        price_requests.inactive()
+    } else {
+        price_requests
+    };
+
+    f(price_requests.requests(&success.options, date))
+}
+
+fn rendered(requests: Vec<PriceRequest<'_>>) -> Vec<String> {
+    requests.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn held_and_unpriced() {
+    requests(LEDGER, false, |requests| {
+        assert_eq!(
+            rendered(requests.unwrap()),
+            vec![
+                "2024-03-01 AAPL USD yahoo/AAPL,google/NASDAQ:AAPL",
+                "2024-03-01 NZD USD ecb/^USDNZD",
+            ]
+        );
+    });
+}
+
+#[test]
+fn inactive() {
+    requests(LEDGER, true, |requests| {
+        let requests = requests.unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].to_string(), "2024-03-01 VWRL GBP yahoo/VWRL.L");
+    });
+}
+
+#[test]
+fn sources() {
+    requests(LEDGER, false, |requests| {
+        let requests = requests.unwrap();
+        let nzd = requests[1].sources().next().unwrap();
+        assert_eq!(nzd.provider(), "ecb");
+        assert_eq!(nzd.ticker(), "USDNZD");
+        assert!(nzd.inverted());
+    });
+}
+
+#[test]
+fn invalid_metadata() {
+    requests(
+        r#"2024-01-01 commodity AAPL
+  price: "USD-yahoo/AAPL"
+2024-01-01 commodity VWRL
+  price: "GBP:yahoo"
+"#,
+        true,
+        |requests| {
+            let errors = requests.unwrap_err();
+            assert_eq!(errors.len(), 2);
+            assert_eq!(
+                errors[0].reason.as_ref(),
+                "expected quote currency followed by colon"
+            );
+            assert_eq!(errors[1].reason.as_ref(), "expected provider/ticker");
+        },
+    );
+}
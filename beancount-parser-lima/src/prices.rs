@@ -0,0 +1,77 @@
+use crate::types::*;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use time::Date;
+
+/// Prices from `price` directives, for conversion between currencies.
+#[derive(Clone, Default, Debug)]
+pub struct PriceDb<'a> {
+    // for each (base, quote) pair, prices in date order
+    prices: HashMap<(Currency<'a>, Currency<'a>), Vec<(Date, Decimal)>>,
+}
+
+impl<'a> PriceDb<'a> {
+    /// Collect the prices from all `price` directives, ignoring other directives.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut db = PriceDb::default();
+
+        for directive in directives {
+            if let DirectiveVariant::Price(price) = directive.variant() {
+                db.insert(
+                    *price.currency().item(),
+                    *price.amount().currency().item(),
+                    *directive.date().item(),
+                    price.amount().number().value(),
+                );
+            }
+        }
+
+        db
+    }
+
+    fn insert(&mut self, base: Currency<'a>, quote: Currency<'a>, date: Date, price: Decimal) {
+        let prices = self.prices.entry((base, quote)).or_default();
+        // later prices on the same date take precedence
+        let i = prices.partition_point(|(d, _)| *d <= date);
+        prices.insert(i, (date, price));
+    }
+
+    /// The price of one unit of `base` in `quote` currency, as of `date`, that is, the latest price on or before that date.
+    ///
+    /// Where there is no such price, the inverse of the price of `quote` in `base` is used if possible.
+    pub fn price(&self, base: Currency<'a>, quote: Currency<'a>, date: Date) -> Option<Decimal> {
+        if base == quote {
+            Some(Decimal::ONE)
+        } else {
+            self.latest(base, quote, date).or_else(|| {
+                self.latest(quote, base, date)
+                    .filter(|inverse| !inverse.is_zero())
+                    .map(|inverse| Decimal::ONE / inverse)
+            })
+        }
+    }
+
+    fn latest(&self, base: Currency<'a>, quote: Currency<'a>, date: Date) -> Option<Decimal> {
+        self.prices.get(&(base, quote)).and_then(|prices| {
+            let i = prices.partition_point(|(d, _)| *d <= date);
+            (i > 0).then(|| prices[i - 1].1)
+        })
+    }
+
+    /// Convert `number` of `currency` into `target` currency, as of `date`, if there is a price for that.
+    pub fn convert(
+        &self,
+        currency: Currency<'a>,
+        number: Decimal,
+        target: Currency<'a>,
+        date: Date,
+    ) -> Option<Decimal> {
+        self.price(currency, target, date)
+            .map(|price| number * price)
+    }
+}
+
+mod tests;
@@ -0,0 +1,55 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+
+fn check_prices<F>(source: &str, check: F)
+where
+    F: FnOnce(PriceDb<'_>),
+{
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    check(PriceDb::new(directives.iter()));
+}
+
+#[test]
+fn price_latest_on_or_before_date() {
+    check_prices(
+        r#"
+2024-01-01 price HOOL 100 USD
+2024-02-01 price HOOL 110 USD
+2024-02-01 price HOOL 120 USD
+"#,
+        |prices| {
+            let hool = Currency::try_from("HOOL").unwrap();
+            let usd = Currency::try_from("USD").unwrap();
+
+            assert_eq!(prices.price(hool, usd, date("2023-12-31")), None);
+            assert_eq!(prices.price(hool, usd, date("2024-01-15")), Some(dec!(100)));
+            assert_eq!(prices.price(hool, usd, date("2024-02-01")), Some(dec!(120)));
+            assert_eq!(prices.price(usd, usd, date("2023-12-31")), Some(dec!(1)));
+        },
+    );
+}
+
+#[test]
+fn price_inverse() {
+    check_prices(
+        r#"
+2024-01-01 price GBP 1.25 USD
+"#,
+        |prices| {
+            let gbp = Currency::try_from("GBP").unwrap();
+            let usd = Currency::try_from("USD").unwrap();
+
+            assert_eq!(prices.price(usd, gbp, date("2024-01-01")), Some(dec!(0.8)));
+            assert_eq!(
+                prices.convert(usd, dec!(50), gbp, date("2024-01-01")),
+                Some(dec!(40))
+            );
+        },
+    );
+}
@@ -0,0 +1,193 @@
+use crate::{
+    interpolation::{interpolate, weight},
+    types::*,
+};
+use rust_decimal::Decimal;
+
+/// An exporter of the transactions involving a single account as a QIF file, for import into other accounting software.
+///
+/// Each transaction gives the total of its postings to the account, which must all be in the same currency.
+/// The other postings become the category, or the splits where there are several, with the amount of each being its weight,
+/// that is, at cost or price if any, which must also be in that currency, so that the splits add up to the total.
+/// Income and expense accounts are categories, and other accounts are transfers.
+///
+/// The QIF account type is `Bank` for assets, `CCard` for liabilities, and `Oth A` otherwise.
+/// Dates are month first, as expected by most importers.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveVariant, QifExport};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Expenses:Groceries
+/// 2024-03-02 * "Supermarket" "weekly shop"
+///   Expenses:Groceries  85.20 NZD
+///   Assets:Bank
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let DirectiveVariant::Open(bank) = directives[0].variant() else { unreachable!() };
+/// let qif = QifExport::new(directives.iter(), bank.account()).generate().unwrap();
+///
+/// assert_eq!(
+///     qif,
+///     "!Type:Bank\nD03/02/2024\nT-85.20\nC*\nPSupermarket\nMweekly shop\nLGroceries\n^\n"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct QifExport<'a> {
+    directives: Vec<&'a Spanned<Directive<'a>>>,
+    account: Account<'a>,
+}
+
+impl<'a> QifExport<'a> {
+    /// Export the transactions in `directives` with any postings to `account`.
+    pub fn new<I>(directives: I, account: &Account<'a>) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut directives = directives.into_iter().collect::<Vec<_>>();
+        directives.sort_by_key(|directive| *directive.date().item());
+
+        QifExport {
+            directives,
+            account: account.clone(),
+        }
+    }
+
+    /// Generate the QIF file, or the errors for any transactions which cannot be exported.
+    pub fn generate(&self) -> Result<String, Vec<Error>> {
+        let mut qif = String::new();
+        let mut errors = Vec::new();
+
+        qif.push_str(match self.account.account_type() {
+            AccountType::Assets => "!Type:Bank\n",
+            AccountType::Liabilities => "!Type:CCard\n",
+            _ => "!Type:Oth A\n",
+        });
+
+        for directive in self.directives.iter() {
+            if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+                if transaction
+                    .postings()
+                    .any(|posting| *posting.account().item() == self.account)
+                {
+                    if let Err(e) = self.write_transaction(&mut qif, directive, transaction) {
+                        errors.push(e.in_context(directive));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(qif)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn write_transaction(
+        &self,
+        qif: &mut String,
+        directive: &Spanned<Directive<'a>>,
+        transaction: &'a Transaction<'a>,
+    ) -> Result<(), Error> {
+        let units = interpolate(transaction)?;
+
+        let (own, others): (Vec<_>, Vec<_>) = units
+            .iter()
+            .partition(|units| *units.posting.account().item() == self.account);
+
+        // postings without amount may have nothing to balance
+        let Some(currency) = own.first().map(|units| units.currency) else {
+            return Ok(());
+        };
+        if let Some(other) = own.iter().find(|units| units.currency != currency) {
+            return Err(other
+                .posting
+                .error("multiple currencies in exported account"));
+        }
+        let total = own.iter().map(|units| units.number).sum::<Decimal>();
+
+        // the amounts of the splits are from the perspective of the exported account, so negated
+        let splits = others
+            .iter()
+            .map(|units| {
+                let (weight_currency, weight) = weight(units.posting, units.currency, units.number);
+                if weight_currency == currency {
+                    Ok((category(units.posting.account().item()), -weight))
+                } else {
+                    Err(units
+                        .posting
+                        .error("split in a currency other than that of the exported account"))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let date = directive.date().item();
+        qif.push_str(&format!(
+            "D{:02}/{:02}/{:04}\n",
+            date.month() as u8,
+            date.day(),
+            date.year()
+        ));
+        qif.push_str(&format!("T{}\n", amount(total)));
+        if *transaction.flag().item() == Flag::Asterisk {
+            qif.push_str("C*\n");
+        }
+        if let Some(payee) = transaction.payee() {
+            push_field(qif, 'P', payee.item());
+        }
+        if let Some(narration) = transaction.narration() {
+            push_field(qif, 'M', narration.item());
+        }
+
+        match splits.as_slice() {
+            [] => (),
+            [(category, _)] => push_field(qif, 'L', category),
+            splits => {
+                for (category, number) in splits {
+                    push_field(qif, 'S', category);
+                    qif.push_str(&format!("${}\n", amount(*number)));
+                }
+            }
+        }
+
+        qif.push_str("^\n");
+        Ok(())
+    }
+}
+
+// income and expenses are categories, and everything else a transfer, which in QIF is bracketed
+fn category(account: &Account<'_>) -> String {
+    use AccountType::*;
+
+    let account_type = account.account_type();
+    let account = account.to_string();
+    match account.split_once(':') {
+        Some((_, subaccount)) if matches!(account_type, Income | Expenses) => {
+            subaccount.to_string()
+        }
+        _ => format!("[{}]", account),
+    }
+}
+
+// amounts are conventionally to the cent, but more places are kept where needed
+fn amount(number: Decimal) -> Decimal {
+    let mut cents = number.round_dp(2);
+    if cents == number {
+        cents.rescale(2);
+        cents
+    } else {
+        number.normalize()
+    }
+}
+
+// each field is a single line, so any newlines are replaced
+fn push_field(qif: &mut String, code: char, value: &str) {
+    qif.push(code);
+    qif.push_str(&value.replace(['\r', '\n'], " "));
+    qif.push('\n');
+}
+
+mod tests;
@@ -0,0 +1,142 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+fn export<F>(s: &str, account: &str, f: F)
+where
+    F: FnOnce(Result<String, Vec<Error>>),
+{
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let account = directives
+        .iter()
+        .find_map(|directive| match directive.variant() {
+            DirectiveVariant::Open(open) if open.account().to_string() == account => {
+                Some(open.account().item())
+            }
+            _ => None,
+        })
+        .unwrap();
+
+    let export = QifExport::new(directives.iter(), account);
+    f(export.generate());
+}
+
+#[test]
+fn category_and_transfer() {
+    export(
+        r#"2024-01-01 open Assets:Bank
+2024-01-01 open Liabilities:Visa
+2024-01-01 open Income:Salary
+
+2024-02-15 * "Acme" "salary"
+  Assets:Bank  1000.00 NZD
+  Income:Salary
+
+2024-02-10 ! "pay card"
+  Liabilities:Visa  200.00 NZD
+  Assets:Bank
+"#,
+        "Assets:Bank",
+        |qif| {
+            assert_eq!(
+                qif.unwrap(),
+                r#"!Type:Bank
+D02/10/2024
+T-200.00
+Mpay card
+L[Liabilities:Visa]
+^
+D02/15/2024
+T1000.00
+C*
+PAcme
+Msalary
+LSalary
+^
+"#
+            );
+        },
+    );
+}
+
+#[test]
+fn splits_at_cost() {
+    export(
+        r#"2024-01-01 open Liabilities:Visa
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Travel
+
+2024-03-01 * "trip"
+  Expenses:Food  30.00 NZD
+  Expenses:Travel  12.00 EUR @ 2.00 NZD
+  Liabilities:Visa
+"#,
+        "Liabilities:Visa",
+        |qif| {
+            assert_eq!(
+                qif.unwrap(),
+                r#"!Type:CCard
+D03/01/2024
+T-54.00
+C*
+Mtrip
+SFood
+$-30.00
+STravel
+$-24.00
+^
+"#
+            );
+        },
+    );
+}
+
+#[test]
+fn multiple_currencies() {
+    export(
+        r#"2024-01-01 open Assets:Bank
+2024-01-01 open Equity:Opening
+
+2024-01-02 * "opening"
+  Assets:Bank  10.00 NZD
+  Assets:Bank  5.00 USD
+  Equity:Opening
+"#,
+        "Assets:Bank",
+        |qif| {
+            let errors = qif.unwrap_err();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                errors[0].reason.as_ref(),
+                "multiple currencies in exported account"
+            );
+        },
+    );
+}
+
+#[test]
+fn split_in_other_currency() {
+    export(
+        r#"2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Wallet
+2024-01-01 open Expenses:Food
+
+2024-03-01 * "exchange"
+  Assets:Bank  -50.00 NZD
+  Expenses:Food  20.00 NZD
+  Assets:Wallet  15.00 EUR
+  Assets:Wallet  -15.00 EUR
+  Expenses:Food  30.00 NZD
+"#,
+        "Assets:Bank",
+        |qif| {
+            let errors = qif.unwrap_err();
+            assert_eq!(
+                errors.iter().map(|e| e.reason.as_ref()).collect::<Vec<_>>(),
+                vec!["split in a currency other than that of the exported account"]
+            );
+        },
+    );
+}
@@ -0,0 +1,207 @@
+use crate::{pipeline::Transform, types::*, BudgetInterval};
+use std::collections::HashMap;
+use time::{Date, Duration, Month};
+
+/// A [Transform] which expands recurring transactions, such as monthly rent or salary, into concrete dated transactions.
+///
+/// A template is a transaction with metadata `recurring` naming it, and is itself dropped from the output.
+/// A directive `custom "recurring"` schedules a template, with values for its name,
+/// an interval as for [Budgets](crate::Budgets), such as `"monthly"`, and optionally an end date,
+/// which is otherwise that given by [Recurring::until].
+/// The template is expanded on the date of the `custom` directive, and at every interval after that,
+/// up to and including the end date, and the `custom` directive is dropped.
+///
+/// Monthly and longer intervals from a day which does not exist in every month fall on the last day of shorter months.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Pipeline, Recurring};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 * "Landlord" "Rent"
+///   recurring: "rent"
+///   Expenses:Rent  2000.00 NZD
+///   Assets:Bank
+/// 2024-01-31 custom "recurring" "rent" "monthly" 2024-03-31
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let transformed = Pipeline::new().stage(Recurring::default()).run(directives);
+///
+/// assert!(transformed.errors.is_empty());
+/// assert_eq!(
+///     transformed.directives.iter().map(|d| d.date().to_string()).collect::<Vec<_>>(),
+///     vec!["2024-01-31", "2024-02-29", "2024-03-31"]
+/// );
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Recurring {
+    until: Option<Date>,
+}
+
+impl Recurring {
+    /// The end date for schedules which do not give one, without which such schedules are in error.
+    pub fn until(mut self, until: Date) -> Self {
+        self.until = Some(until);
+        self
+    }
+}
+
+impl<'a> Transform<'a> for Recurring {
+    fn transform(
+        &mut self,
+        directives: Vec<Spanned<Directive<'a>>>,
+        errors: &mut Vec<Error>,
+    ) -> Vec<Spanned<Directive<'a>>> {
+        let (templates, directives): (Vec<_>, Vec<_>) =
+            directives.into_iter().partition(|directive| {
+                matches!(directive.variant(), DirectiveVariant::Transaction(_))
+                    && directive.metadata().get_string("recurring").is_some()
+            });
+        let templates = templates
+            .into_iter()
+            .map(|template| {
+                (
+                    template.metadata().get_string("recurring").unwrap(),
+                    template,
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut expanded = Vec::new();
+        for directive in directives {
+            let schedule = match directive.variant() {
+                DirectiveVariant::Custom(custom) if *custom.custom_type().item() == "recurring" => {
+                    Some(self.schedule(custom, &templates))
+                }
+                _ => None,
+            };
+
+            match schedule {
+                Some(Ok((template, interval, end))) => expanded.extend(recurrences(
+                    template,
+                    interval,
+                    *directive.date().item(),
+                    end,
+                )),
+                Some(Err(e)) => errors.push(e.in_context(&directive)),
+                None => expanded.push(directive),
+            }
+        }
+
+        expanded
+    }
+}
+
+impl Recurring {
+    fn schedule<'t, 'a>(
+        &self,
+        custom: &Custom<'_>,
+        templates: &'t HashMap<&'a str, Spanned<Directive<'a>>>,
+    ) -> Result<(&'t Spanned<Directive<'a>>, BudgetInterval, Date), Error> {
+        use MetaValue::Simple;
+
+        let (name, interval, end) = match custom.values() {
+            [name, interval] => (name, interval, None),
+            [name, interval, end] => (name, interval, Some(end)),
+            _ => {
+                return Err(Error::new(
+                    "invalid recurring",
+                    "expected template name, interval, and optional end date",
+                    *custom.custom_type().span(),
+                ))
+            }
+        };
+
+        let Simple(SimpleValue::String(name_value)) = name.item() else {
+            return Err(Error::new(
+                "invalid recurring",
+                "template name must be a string",
+                *name.span(),
+            ));
+        };
+        let template = templates.get(*name_value).ok_or_else(|| {
+            Error::new(
+                "invalid recurring",
+                "no transaction has this name as its recurring metadata",
+                *name.span(),
+            )
+        })?;
+
+        let interval = match interval.item() {
+            Simple(SimpleValue::String(keyword)) => BudgetInterval::try_from(*keyword).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            Error::new(
+                "invalid recurring",
+                "interval must be one of daily, weekly, monthly, quarterly, yearly",
+                *interval.span(),
+            )
+        })?;
+
+        let end = match end.map(|end| (end.item(), end.span())) {
+            Some((Simple(SimpleValue::Date(end)), _)) => *end,
+            Some((_, span)) => {
+                return Err(Error::new("invalid recurring", "end must be a date", *span))
+            }
+            None => self.until.ok_or_else(|| {
+                Error::new(
+                    "invalid recurring",
+                    "no end date given",
+                    *custom.custom_type().span(),
+                )
+            })?,
+        };
+
+        Ok((template, interval, end))
+    }
+}
+
+/// Expand the `template` transaction into a copy on `start` and every `interval` after that, up to and including `end`.
+///
+/// As for [Recurring], monthly and longer intervals from a day which does not exist in every month
+/// fall on the last day of shorter months.
+/// The copies keep the span of the template.
+pub fn recurrences<'a>(
+    template: &Spanned<Directive<'a>>,
+    interval: BudgetInterval,
+    start: Date,
+    end: Date,
+) -> Vec<Spanned<Directive<'a>>> {
+    (0..)
+        .map(|n| step(start, interval, n))
+        .take_while(|date| date.is_some_and(|date| date <= end))
+        .flatten()
+        .map(|date| {
+            let mut recurrence = template.clone();
+            recurrence.item.date.item = date;
+            recurrence
+        })
+        .collect()
+}
+
+// the date `n` intervals after `start`, if representable
+pub(crate) fn step(start: Date, interval: BudgetInterval, n: u32) -> Option<Date> {
+    use BudgetInterval::*;
+
+    match interval {
+        Daily => start.checked_add(Duration::days(n.into())),
+        Weekly => start.checked_add(Duration::weeks(n.into())),
+        Monthly => add_months(start, n),
+        Quarterly => add_months(start, n * 3),
+        Yearly => add_months(start, n * 12),
+    }
+}
+
+// clamped to the last day of the month if the day doesn't exist there
+pub(crate) fn add_months(date: Date, months: u32) -> Option<Date> {
+    let month0 = date.month() as u32 - 1 + months;
+    let year = date.year().checked_add((month0 / 12).try_into().ok()?)?;
+    let month = Month::try_from((month0 % 12 + 1) as u8).unwrap();
+
+    (1..=date.day())
+        .rev()
+        .find_map(|day| Date::from_calendar_date(year, month, day).ok())
+}
+
+mod tests;
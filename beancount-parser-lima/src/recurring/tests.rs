@@ -0,0 +1,105 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources, Pipeline};
+
+const LEDGER: &str = r#"2024-01-01 open Assets:Bank
+2024-01-01 * "Employer" "Salary"
+  recurring: "salary"
+  Assets:Bank     5000.00 NZD
+  Income:Salary
+2024-01-01 * "Landlord" "Rent"
+  recurring: "rent"
+  Expenses:Rent   2000.00 NZD
+  Assets:Bank
+2024-01-15 custom "recurring" "salary" "quarterly" 2024-12-31
+2024-11-30 custom "recurring" "rent" "monthly"
+2024-12-01 custom "recurring" "bonus" "yearly"
+2024-12-02 custom "recurring" "rent" "fortnightly"
+"#;
+
+#[test]
+fn recurring_expands_templates_until_end() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new()
+        .stage(Recurring::default().until(date("2025-02-28")))
+        .run(directives);
+
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            "no transaction has this name as its recurring metadata",
+            "interval must be one of daily, weekly, monthly, quarterly, yearly",
+        ]
+    );
+    assert_eq!(
+        transformed
+            .directives
+            .iter()
+            .map(|d| match d.variant() {
+                DirectiveVariant::Transaction(transaction) =>
+                    format!("{} {}", d.date(), transaction.narration().unwrap().item()),
+                _ => d.date().to_string(),
+            })
+            .collect::<Vec<_>>(),
+        vec![
+            "2024-01-01",
+            "2024-01-15 Salary",
+            "2024-04-15 Salary",
+            "2024-07-15 Salary",
+            "2024-10-15 Salary",
+            "2024-11-30 Rent",
+            "2024-12-30 Rent",
+            "2025-01-30 Rent",
+            "2025-02-28 Rent",
+        ]
+    );
+}
+
+#[test]
+fn recurring_without_end_date() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let transformed = Pipeline::new().stage(Recurring::default()).run(directives);
+
+    assert_eq!(
+        transformed
+            .errors
+            .iter()
+            .map(|e| &*e.reason)
+            .collect::<Vec<_>>(),
+        vec![
+            "no end date given",
+            "no transaction has this name as its recurring metadata",
+            "interval must be one of daily, weekly, monthly, quarterly, yearly",
+        ]
+    );
+}
+
+#[test]
+fn recurrences_weekly() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let dates = recurrences(
+        &directives[1],
+        BudgetInterval::Weekly,
+        date("2024-02-26"),
+        date("2024-03-12"),
+    )
+    .iter()
+    .map(|d| d.date().to_string())
+    .collect::<Vec<_>>();
+
+    assert_eq!(dates, vec!["2024-02-26", "2024-03-04", "2024-03-11"]);
+}
@@ -0,0 +1,251 @@
+use crate::types::*;
+use std::{collections::HashMap, hash::Hash};
+
+/// Every place where each account, currency, tag, link, and payee appears, for reference listing and rename previews.
+#[derive(Clone, Default, Debug)]
+pub struct References<'a> {
+    accounts: HashMap<&'a Account<'a>, Vec<Reference<'a>>>,
+    currencies: HashMap<Currency<'a>, Vec<Reference<'a>>>,
+    tags: HashMap<Tag<'a>, Vec<Reference<'a>>>,
+    links: HashMap<Link<'a>, Vec<Reference<'a>>>,
+    payees: HashMap<&'a str, Vec<Reference<'a>>>,
+}
+
+/// A single appearance of something within a directive, see [References].
+#[derive(Copy, Clone, Debug)]
+pub struct Reference<'a> {
+    span: Span,
+    directive: &'a Spanned<Directive<'a>>,
+}
+
+impl<'a> Reference<'a> {
+    /// The span of the appearance itself.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The directive containing the appearance.
+    pub fn directive(&self) -> &'a Spanned<Directive<'a>> {
+        self.directive
+    }
+}
+
+impl<'a> References<'a> {
+    /// Index all references in `directives`, which are listed for each referent in the order found.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        use DirectiveVariant::*;
+
+        let mut references = References::default();
+
+        for directive in directives {
+            let mut add = Adder {
+                references: &mut references,
+                directive,
+            };
+
+            match directive.variant() {
+                Transaction(transaction) => {
+                    if let Some(payee) = transaction.payee() {
+                        add.payee(payee);
+                    }
+                    for posting in transaction.postings() {
+                        add.account(posting.account());
+                        if let Some(currency) = posting.currency() {
+                            add.currency(currency);
+                        }
+                        if let Some(currency) = posting
+                            .cost_spec()
+                            .and_then(|cost_spec| cost_spec.currency())
+                        {
+                            add.currency(currency);
+                        }
+                        if let Some(price) = posting.price_annotation() {
+                            add.price_spec(price);
+                        }
+                        add.metadata(posting.metadata());
+                    }
+                }
+                Price(price) => {
+                    add.currency(price.currency());
+                    add.currency(price.amount().currency());
+                }
+                Balance(balance) => {
+                    add.account(balance.account());
+                    add.currency(balance.atol().amount().currency());
+                }
+                Open(open) => {
+                    add.account(open.account());
+                    for currency in open.currencies() {
+                        add.currency(currency);
+                    }
+                }
+                Close(close) => add.account(close.account()),
+                Commodity(commodity) => add.currency(commodity.currency()),
+                Pad(pad) => {
+                    add.account(pad.account());
+                    add.account(pad.source());
+                }
+                Document(document) => add.account(document.account()),
+                Note(note) => add.account(note.account()),
+                Custom(custom) => {
+                    for value in custom.values() {
+                        add.meta_value(value);
+                    }
+                }
+                Event(_) | Query(_) => (),
+            }
+
+            add.metadata(directive.metadata());
+        }
+
+        references
+    }
+
+    /// All references to `account`.
+    pub fn of_account(&self, account: &Account<'a>) -> &[Reference<'a>] {
+        self.accounts.get(account).map_or(&[], Vec::as_slice)
+    }
+
+    /// All references to `currency`.
+    pub fn of_currency(&self, currency: &Currency<'a>) -> &[Reference<'a>] {
+        self.currencies.get(currency).map_or(&[], Vec::as_slice)
+    }
+
+    /// All references to `tag`.
+    pub fn of_tag(&self, tag: &Tag<'a>) -> &[Reference<'a>] {
+        self.tags.get(tag).map_or(&[], Vec::as_slice)
+    }
+
+    /// All references to `link`.
+    pub fn of_link(&self, link: &Link<'a>) -> &[Reference<'a>] {
+        self.links.get(link).map_or(&[], Vec::as_slice)
+    }
+
+    /// All transactions with `payee`.
+    pub fn of_payee(&self, payee: &str) -> &[Reference<'a>] {
+        self.payees.get(payee).map_or(&[], Vec::as_slice)
+    }
+
+    /// All accounts referenced, in no particular order.
+    pub fn accounts(&self) -> impl ExactSizeIterator<Item = &'a Account<'a>> + '_ {
+        self.accounts.keys().copied()
+    }
+
+    /// All currencies referenced, in no particular order.
+    pub fn currencies(&self) -> impl ExactSizeIterator<Item = Currency<'a>> + '_ {
+        self.currencies.keys().copied()
+    }
+
+    /// All payees of transactions, in no particular order.
+    pub fn payees(&self) -> impl ExactSizeIterator<Item = &'a str> + '_ {
+        self.payees.keys().copied()
+    }
+}
+
+struct Adder<'r, 'a> {
+    references: &'r mut References<'a>,
+    directive: &'a Spanned<Directive<'a>>,
+}
+
+impl<'r, 'a> Adder<'r, 'a> {
+    fn account(&mut self, account: &'a Spanned<Account<'a>>) {
+        add(
+            &mut self.references.accounts,
+            account.item(),
+            *account.span(),
+            self.directive,
+        );
+    }
+
+    fn currency(&mut self, currency: &'a Spanned<Currency<'a>>) {
+        add(
+            &mut self.references.currencies,
+            *currency.item(),
+            *currency.span(),
+            self.directive,
+        );
+    }
+
+    fn payee(&mut self, payee: &'a Spanned<&'a str>) {
+        add(
+            &mut self.references.payees,
+            *payee.item(),
+            *payee.span(),
+            self.directive,
+        );
+    }
+
+    /// The currency of a price annotation is not spanned itself, so the span is that of the whole annotation.
+    fn price_spec(&mut self, price: &'a Spanned<PriceSpec<'a>>) {
+        use PriceSpec::*;
+
+        if let BareCurrency(currency) | CurrencyAmount(_, currency) = price.item() {
+            add(
+                &mut self.references.currencies,
+                *currency,
+                *price.span(),
+                self.directive,
+            );
+        }
+    }
+
+    fn metadata(&mut self, metadata: &'a Metadata<'a>) {
+        for tag in metadata.tags() {
+            add(
+                &mut self.references.tags,
+                *tag.item(),
+                *tag.span(),
+                self.directive,
+            );
+        }
+        for link in metadata.links() {
+            add(
+                &mut self.references.links,
+                *link.item(),
+                *link.span(),
+                self.directive,
+            );
+        }
+        for (_, value) in metadata.key_values() {
+            self.meta_value(value);
+        }
+    }
+
+    fn meta_value(&mut self, value: &'a Spanned<MetaValue<'a>>) {
+        match value.item() {
+            MetaValue::Simple(SimpleValue::Account(account)) => add(
+                &mut self.references.accounts,
+                account,
+                *value.span(),
+                self.directive,
+            ),
+            MetaValue::Simple(SimpleValue::Currency(currency)) => add(
+                &mut self.references.currencies,
+                *currency,
+                *value.span(),
+                self.directive,
+            ),
+            MetaValue::Amount(amount) => self.currency(amount.currency()),
+            _ => (),
+        }
+    }
+}
+
+fn add<'a, K>(
+    references: &mut HashMap<K, Vec<Reference<'a>>>,
+    key: K,
+    span: Span,
+    directive: &'a Spanned<Directive<'a>>,
+) where
+    K: Eq + Hash,
+{
+    references
+        .entry(key)
+        .or_default()
+        .push(Reference { span, directive });
+}
+
+mod tests;
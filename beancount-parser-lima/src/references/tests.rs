@@ -0,0 +1,72 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+use chumsky::span::Span as _;
+
+const LEDGER: &str = r#"2024-01-01 open Assets:Bank GBP
+2024-01-01 open Expenses:Coffee
+2024-01-02 * "Cafe" "Coffee" #treat
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-03 * "Cafe" "Cake" ^receipts
+  refund-to: Assets:Bank
+  Assets:Bank  -2.50 GBP
+  Expenses:Coffee
+2024-02-01 balance Assets:Bank -6.00 GBP
+"#;
+
+fn texts(references: &[Reference<'_>]) -> Vec<String> {
+    references
+        .iter()
+        .map(|r| {
+            format!(
+                "{} {}",
+                r.directive().date().item(),
+                &LEDGER[r.span().start()..r.span().end()]
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn references() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let references = References::new(&directives);
+
+    let bank = match directives[0].variant() {
+        DirectiveVariant::Open(open) => open.account().item(),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        texts(references.of_account(bank)),
+        vec![
+            "2024-01-01 Assets:Bank",
+            "2024-01-02 Assets:Bank",
+            "2024-01-03 Assets:Bank",
+            "2024-01-03 Assets:Bank",
+            "2024-02-01 Assets:Bank",
+        ]
+    );
+
+    assert_eq!(
+        references
+            .of_currency(&Currency::try_from("GBP").unwrap())
+            .len(),
+        4
+    );
+    assert_eq!(
+        texts(references.of_tag(&Tag::try_from("treat").unwrap())),
+        vec!["2024-01-02 #treat"]
+    );
+    assert_eq!(
+        texts(references.of_link(&Link::try_from("receipts").unwrap())),
+        vec!["2024-01-03 ^receipts"]
+    );
+    assert_eq!(
+        texts(references.of_payee("Cafe")),
+        vec!["2024-01-02 \"Cafe\"", "2024-01-03 \"Cafe\""]
+    );
+    assert!(references.of_payee("Bakery").is_empty());
+}
@@ -0,0 +1,164 @@
+use crate::{interpolation::interpolate, trial_balance::Units, types::*};
+use regex::Regex;
+use time::Date;
+
+/// Compute the register of all accounts whose full name matches `account_regex`,
+/// being one row for each transaction with postings to any of them, in order of date,
+/// with the change in those accounts and their running balance after it.
+///
+/// The units of any posting without an amount are interpolated so that its transaction balances.
+/// Other directives are ignored, so `pad` directives are not expanded.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{register, BeancountParser, BeancountSources};
+/// use regex::Regex;
+///
+/// let sources = BeancountSources::from(r#"2024-01-05 * "Countdown" "Groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Food
+/// 2024-01-02 * "Employer" "Salary"
+///   Assets:Bank  1000.00 NZD
+///   Income:Salary
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let register = register(&directives, &Regex::new("^Assets:Bank$").unwrap()).unwrap();
+///
+/// assert_eq!(register.len(), 2);
+/// assert_eq!(register.rows()[1].payee(), Some("Countdown"));
+/// assert_eq!(register.rows()[1].change().to_string(), "-42.50 NZD");
+/// assert_eq!(register.rows()[1].balance().to_string(), "957.50 NZD");
+/// ```
+pub fn register<'a, I>(directives: I, account_regex: &Regex) -> Result<Register<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let mut directives = directives.into_iter().collect::<Vec<_>>();
+    directives.sort_by_key(|directive| *directive.date().item());
+
+    let mut rows = Vec::new();
+    let mut balance = Units::default();
+    let mut errors = Vec::new();
+
+    for directive in directives {
+        let DirectiveVariant::Transaction(transaction) = directive.variant() else {
+            continue;
+        };
+        if !transaction
+            .postings()
+            .any(|posting| account_regex.is_match(&posting.account().item().to_string()))
+        {
+            continue;
+        }
+
+        match interpolate(transaction) {
+            Ok(units) => {
+                let mut change = Units::default();
+                for units in units {
+                    if account_regex.is_match(&units.posting.account().item().to_string()) {
+                        change.add(units.currency, units.number);
+                    }
+                }
+                balance.add_all(&change);
+
+                rows.push(RegisterRow {
+                    directive,
+                    date: *directive.date().item(),
+                    payee: transaction.payee().map(|payee| *payee.item()),
+                    narration: transaction.narration().map(|narration| *narration.item()),
+                    change,
+                    balance: balance.clone(),
+                });
+            }
+            Err(e) => errors.push(e.in_context(directive)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Register { rows })
+    } else {
+        Err(errors)
+    }
+}
+
+/// The rows of a register, see [register], with pagination for rendering.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Register<'a> {
+    rows: Vec<RegisterRow<'a>>,
+}
+
+impl<'a> Register<'a> {
+    /// All rows, in order of date.
+    pub fn rows(&self) -> &[RegisterRow<'a>] {
+        &self.rows
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The rows of page number `page`, counting from zero, of `page_size` rows each,
+    /// which is empty beyond the last page.
+    pub fn page(&self, page: usize, page_size: usize) -> &[RegisterRow<'a>] {
+        let start = page.saturating_mul(page_size).min(self.rows.len());
+        let end = start.saturating_add(page_size).min(self.rows.len());
+        &self.rows[start..end]
+    }
+
+    /// The number of pages of `page_size` rows each, which must be nonzero.
+    pub fn page_count(&self, page_size: usize) -> usize {
+        self.rows.len().div_ceil(page_size)
+    }
+}
+
+/// A row of a [Register], for a single transaction.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RegisterRow<'a> {
+    directive: &'a Spanned<Directive<'a>>,
+    date: Date,
+    payee: Option<&'a str>,
+    narration: Option<&'a str>,
+    change: Units<'a>,
+    balance: Units<'a>,
+}
+
+impl<'a> RegisterRow<'a> {
+    /// The transaction, for linking back to its source.
+    pub fn directive(&self) -> &'a Spanned<Directive<'a>> {
+        self.directive
+    }
+
+    /// Field accessor.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// Field accessor.
+    pub fn payee(&self) -> Option<&'a str> {
+        self.payee
+    }
+
+    /// Field accessor.
+    pub fn narration(&self) -> Option<&'a str> {
+        self.narration
+    }
+
+    /// The total of the postings of the transaction to the accounts of the register.
+    pub fn change(&self) -> &Units<'a> {
+        &self.change
+    }
+
+    /// The running balance of the accounts of the register after the transaction.
+    pub fn balance(&self) -> &Units<'a> {
+        &self.balance
+    }
+}
+
+mod tests;
@@ -0,0 +1,76 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+#[test]
+fn register_of_subaccounts_paginated() {
+    let s = r#"2024-01-01 open Assets:Bank:Cheque
+2024-01-01 open Assets:Bank:Savings
+2024-01-02 * "Employer" "Salary"
+  Assets:Bank:Cheque  1000.00 NZD
+  Income:Salary
+2024-01-03 * "Transfer to savings"
+  Assets:Bank:Cheque   -400.00 NZD
+  Assets:Bank:Savings
+2024-01-04 * "Unrelated"
+  Assets:Cash     20.00 NZD
+  Income:Gift
+2024-01-05 * "Exchange"
+  Assets:Bank:Savings  -100.00 NZD @ 0.60 USD
+  Assets:Bank:Cheque     60.00 USD
+2024-01-06 * "Card" "Groceries"
+  Assets:Bank:Cheque   -42.50 NZD
+  Expenses:Food
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let register = register(&directives, &Regex::new("^Assets:Bank").unwrap()).unwrap();
+    let rows = |rows: &[RegisterRow]| {
+        rows.iter()
+            .map(|row| {
+                (
+                    row.date().to_string(),
+                    row.narration().unwrap_or_default().to_string(),
+                    row.change().to_string(),
+                    row.balance().to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        rows(register.rows()),
+        vec![
+            (
+                "2024-01-02".to_string(),
+                "Salary".to_string(),
+                "1000.00 NZD".to_string(),
+                "1000.00 NZD".to_string()
+            ),
+            (
+                "2024-01-03".to_string(),
+                "Transfer to savings".to_string(),
+                "".to_string(),
+                "1000.00 NZD".to_string()
+            ),
+            (
+                "2024-01-05".to_string(),
+                "Exchange".to_string(),
+                "-100.00 NZD, 60.00 USD".to_string(),
+                "900.00 NZD, 60.00 USD".to_string()
+            ),
+            (
+                "2024-01-06".to_string(),
+                "Groceries".to_string(),
+                "-42.50 NZD".to_string(),
+                "857.50 NZD, 60.00 USD".to_string()
+            ),
+        ]
+    );
+
+    assert_eq!(register.page_count(3), 2);
+    assert_eq!(rows(register.page(1, 3)), rows(&register.rows()[3..]));
+    assert!(register.page(2, 3).is_empty());
+}
@@ -0,0 +1,421 @@
+use crate::{types::*, BeancountSources, Fix};
+use ariadne::{Color, Label, Report};
+use chumsky::span::Span as _;
+use lazy_format::lazy_format;
+use std::io::{self, Write};
+
+/// Presentation of errors and warnings against their source locations.
+///
+/// The built-in renderers are [TerminalRenderer], [PlainRenderer], [BeanCheckRenderer], [JsonRenderer], and [HtmlRenderer],
+/// but applications are free to provide their own.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, DiagnosticRenderer, PlainRenderer};
+///
+/// let sources = BeancountSources::from("2024-01-01 open\n");
+/// let parser = BeancountParser::new(&sources);
+/// let errors = parser.parse().unwrap_err().errors;
+///
+/// let mut rendered = Vec::new();
+/// PlainRenderer.render(&sources, &mut rendered, errors).unwrap();
+///
+/// assert!(String::from_utf8(rendered).unwrap().starts_with("error: "));
+/// ```
+pub trait DiagnosticRenderer {
+    fn render<W, K>(
+        &self,
+        sources: &BeancountSources,
+        w: W,
+        errors_or_warnings: Vec<ErrorOrWarning<K>>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        K: ErrorOrWarningKind;
+}
+
+/// Rich terminal output with source excerpts, courtesy of Ariadne.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TerminalRenderer;
+
+impl DiagnosticRenderer for TerminalRenderer {
+    fn render<W, K>(
+        &self,
+        sources: &BeancountSources,
+        mut w: W,
+        errors_or_warnings: Vec<ErrorOrWarning<K>>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        K: ErrorOrWarningKind,
+    {
+        for error_or_warning in errors_or_warnings.into_iter() {
+            let src_id = sources.span_source_id_string(&error_or_warning.span);
+            let color = error_or_warning.color();
+            let report_kind = error_or_warning.report_kind();
+
+            let mut report =
+                Report::build(report_kind, src_id.to_string(), error_or_warning.span.start)
+                    .with_message(error_or_warning.message)
+                    .with_labels(Some(
+                        Label::new((
+                            src_id.to_string(),
+                            error_or_warning.span.start()..error_or_warning.span.end(),
+                        ))
+                        .with_message(error_or_warning.reason)
+                        .with_color(color),
+                    ))
+                    .with_labels(error_or_warning.contexts.into_iter().map(|(label, span)| {
+                        Label::new((
+                            sources.span_source_id_string(&span).to_string(),
+                            span.start()..span.end(),
+                        ))
+                        .with_message(lazy_format!("in this {}", label))
+                        .with_color(Color::Yellow)
+                    }))
+                    .with_labels(error_or_warning.related.into_iter().map(|(label, span)| {
+                        Label::new((
+                            sources.span_source_id_string(&span).to_string(),
+                            span.start()..span.end(),
+                        ))
+                        .with_message(lazy_format!("{}", label))
+                        .with_color(Color::Yellow)
+                    }));
+            let fixes = error_or_warning.fixes;
+            if !fixes.is_empty() {
+                report =
+                    report.with_help(fixes.iter().map(Fix::title).collect::<Vec<_>>().join("; "));
+            }
+            report
+                .finish()
+                .write(ariadne::sources(sources.sources()), &mut w)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plain text output, one line per location, suitable for logs and editors.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PlainRenderer;
+
+impl DiagnosticRenderer for PlainRenderer {
+    fn render<W, K>(
+        &self,
+        sources: &BeancountSources,
+        mut w: W,
+        errors_or_warnings: Vec<ErrorOrWarning<K>>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        K: ErrorOrWarningKind,
+    {
+        for error_or_warning in errors_or_warnings.iter() {
+            writeln!(
+                w,
+                "{}: {}",
+                severity(error_or_warning),
+                &error_or_warning.message
+            )?;
+            writeln!(
+                w,
+                "  --> {}: {}",
+                Location::new(sources, &error_or_warning.span),
+                &error_or_warning.reason
+            )?;
+            for (label, span) in error_or_warning.contexts.iter() {
+                writeln!(w, "  in this {} at {}", label, Location::new(sources, span))?;
+            }
+            for (label, span) in error_or_warning.related.iter() {
+                writeln!(w, "  {} at {}", label, Location::new(sources, span))?;
+            }
+            for fix in error_or_warning.fixes().iter() {
+                writeln!(w, "  fix: {}", fix.title())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Output as from Python `bean-check`, one line per error or warning, for existing scripts and editor configurations.
+///
+/// Each line is the file name, line number padded to width 8 as by `bean-check`, and the message with its reason,
+/// separated by colons, with the contexts and related locations omitted.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BeanCheckRenderer;
+
+impl DiagnosticRenderer for BeanCheckRenderer {
+    fn render<W, K>(
+        &self,
+        sources: &BeancountSources,
+        mut w: W,
+        errors_or_warnings: Vec<ErrorOrWarning<K>>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        K: ErrorOrWarningKind,
+    {
+        for error_or_warning in errors_or_warnings.iter() {
+            let location = Location::new(sources, &error_or_warning.span);
+            writeln!(
+                w,
+                "{}:{:8}: {}: {}",
+                location.file, location.line, &error_or_warning.message, &error_or_warning.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// JSON output, as one object per line, for consumption by other tools.
+///
+/// Each object has fields `severity`, `message`, `reason`, `location`, `contexts`, `related`, and `fixes`,
+/// where locations comprise `file`, `line`, and `column`, counting from 1,
+/// and `start` and `end` byte offsets.
+/// Each fix has a `title` and `edits`, comprising the `location` to replace and its `replacement`.
+/// With the `schemars` feature, `diagnostics_schema` returns the JSON Schema for each line.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct JsonRenderer;
+
+impl DiagnosticRenderer for JsonRenderer {
+    fn render<W, K>(
+        &self,
+        sources: &BeancountSources,
+        mut w: W,
+        errors_or_warnings: Vec<ErrorOrWarning<K>>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        K: ErrorOrWarningKind,
+    {
+        for error_or_warning in errors_or_warnings.iter() {
+            write_json_line(&mut w, sources, error_or_warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Receiver of errors and warnings as they are discovered, see [BeancountParser::parse_reporting](crate::BeancountParser::parse_reporting).
+pub trait DiagnosticSink {
+    fn error(&mut self, error: &Error);
+
+    fn warning(&mut self, warning: &Warning);
+}
+
+/// A [DiagnosticSink] which writes each diagnostic immediately as a line of JSON, in the format of [JsonRenderer],
+/// so that a wrapping tool may show progress through a large ledger.
+///
+/// Since diagnostics are written as discovered, repeated errors are not collapsed, nor are errors limited,
+/// regardless of the [ParserConfig](crate::ParserConfig).
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, JsonLinesWriter};
+///
+/// let sources = BeancountSources::from("2024-01-01 open\n");
+/// let parser = BeancountParser::new(&sources);
+/// let mut writer = JsonLinesWriter::new(&sources, Vec::new());
+///
+/// assert!(parser.parse_reporting(&mut writer).is_err());
+///
+/// let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+/// assert!(written.starts_with(r#"{"severity":"error","#));
+/// ```
+#[derive(Debug)]
+pub struct JsonLinesWriter<'s, W> {
+    sources: &'s BeancountSources,
+    w: W,
+    // the first write error, after which nothing more is written
+    status: io::Result<()>,
+}
+
+impl<'s, W> JsonLinesWriter<'s, W>
+where
+    W: Write,
+{
+    /// Create a writer of diagnostics located in `sources` to `w`.
+    pub fn new(sources: &'s BeancountSources, w: W) -> Self {
+        JsonLinesWriter {
+            sources,
+            w,
+            status: Ok(()),
+        }
+    }
+
+    /// Flush and return the underlying writer, or the first error encountered in writing.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.status?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn write<K>(&mut self, error_or_warning: &ErrorOrWarning<K>)
+    where
+        K: ErrorOrWarningKind,
+    {
+        if self.status.is_ok() {
+            // flushed per line, since the point is for the consumer to see each one as it happens
+            self.status = write_json_line(&mut self.w, self.sources, error_or_warning)
+                .and_then(|()| self.w.flush());
+        }
+    }
+}
+
+impl<W> DiagnosticSink for JsonLinesWriter<'_, W>
+where
+    W: Write,
+{
+    fn error(&mut self, error: &Error) {
+        self.write(error);
+    }
+
+    fn warning(&mut self, warning: &Warning) {
+        self.write(warning);
+    }
+}
+
+fn write_json_line<W, K>(
+    mut w: W,
+    sources: &BeancountSources,
+    error_or_warning: &ErrorOrWarning<K>,
+) -> io::Result<()>
+where
+    W: Write,
+    K: ErrorOrWarningKind,
+{
+    fn write_labelled_locations<W: Write>(
+        mut w: W,
+        sources: &BeancountSources,
+        labelled_spans: &[(String, Span)],
+    ) -> io::Result<()> {
+        write!(w, "[")?;
+        for (i, (label, span)) in labelled_spans.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(
+                w,
+                r#"{{"label":{},"location":{}}}"#,
+                JsonString(label),
+                Location::new(sources, span).json()
+            )?;
+        }
+        write!(w, "]")
+    }
+
+    fn write_fixes<W: Write>(
+        mut w: W,
+        sources: &BeancountSources,
+        fixes: &[Fix],
+    ) -> io::Result<()> {
+        write!(w, "[")?;
+        for (i, fix) in fixes.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(w, r#"{{"title":{},"edits":["#, JsonString(fix.title()))?;
+            for (j, edit) in fix.edits().iter().enumerate() {
+                if j > 0 {
+                    write!(w, ",")?;
+                }
+                write!(
+                    w,
+                    r#"{{"location":{},"replacement":{}}}"#,
+                    Location::new(sources, &edit.span()).json(),
+                    JsonString(edit.replacement())
+                )?;
+            }
+            write!(w, "]}}")?;
+        }
+        write!(w, "]")
+    }
+
+    write!(
+        w,
+        r#"{{"severity":{},"message":{},"reason":{},"location":{},"contexts":"#,
+        JsonString(&severity(error_or_warning)),
+        JsonString(&error_or_warning.message),
+        JsonString(&error_or_warning.reason),
+        Location::new(sources, &error_or_warning.span).json(),
+    )?;
+    write_labelled_locations(&mut w, sources, &error_or_warning.contexts)?;
+    write!(w, r#","related":"#)?;
+    write_labelled_locations(&mut w, sources, &error_or_warning.related)?;
+    write!(w, r#","fixes":"#)?;
+    write_fixes(&mut w, sources, error_or_warning.fixes())?;
+    writeln!(w, "}}")
+}
+
+fn severity<K>(error_or_warning: &ErrorOrWarning<K>) -> String
+where
+    K: ErrorOrWarningKind,
+{
+    error_or_warning.report_kind().to_string().to_lowercase()
+}
+
+/// Human-oriented location of a span, with line and column counting from 1.
+struct Location<'a> {
+    file: &'a str,
+    line: usize,
+    column: usize,
+    span: Span,
+}
+
+impl<'a> Location<'a> {
+    fn new(sources: &'a BeancountSources, span: &Span) -> Self {
+        let content = sources.source_content(span.context());
+        let before = &content[..span.start().min(content.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Location {
+            file: sources.span_source_id_string(span),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span: *span,
+        }
+    }
+
+    fn json(&self) -> impl std::fmt::Display + '_ {
+        lazy_format!(
+            r#"{{"file":{},"line":{},"column":{},"start":{},"end":{}}}"#,
+            JsonString(self.file),
+            self.line,
+            self.column,
+            self.span.start(),
+            self.span.end()
+        )
+    }
+}
+
+impl std::fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// A string formatted as a quoted and escaped JSON string.
+struct JsonString<'a>(&'a str);
+
+impl std::fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str(r#"\""#)?,
+                '\\' => f.write_str(r"\\")?,
+                '\n' => f.write_str(r"\n")?,
+                '\r' => f.write_str(r"\r")?,
+                '\t' => f.write_str(r"\t")?,
+                c if c.is_control() => write!(f, r"\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+pub use html::HtmlRenderer;
+mod html;
+
+mod tests;
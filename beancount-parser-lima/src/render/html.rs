@@ -0,0 +1,311 @@
+use super::{severity, DiagnosticRenderer};
+use crate::{format, lex_with_source, lexer::Token, types::*, BeancountSources};
+use chumsky::span::Span as _;
+use std::io::{self, Write};
+
+/// A standalone HTML page of the source files, with syntax highlighting and each error or warning shown
+/// beneath the line where it starts, for static report sites and for sharing bug reproductions.
+///
+/// Tokens are marked up with classes such as `date`, `account`, `currency`, `number`, `string`, `keyword`,
+/// and `comment`, and lines with diagnostics with the class `error` or `warning`, so the page may be restyled as required.
+/// All sources are included, in order of inclusion, each with its lines numbered and anchored as `{n}-L{line}`
+/// for the `n`th source, counting from 1.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, DiagnosticRenderer, HtmlRenderer};
+///
+/// let sources = BeancountSources::from("2024-01-01 open\n");
+/// let parser = BeancountParser::new(&sources);
+/// let errors = parser.parse().unwrap_err().errors;
+///
+/// let mut rendered = Vec::new();
+/// HtmlRenderer.render(&sources, &mut rendered, errors).unwrap();
+/// let html = String::from_utf8(rendered).unwrap();
+///
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// assert!(html.contains(r#"<span class="date">2024-01-01</span> <span class="keyword">open</span>"#));
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HtmlRenderer;
+
+impl DiagnosticRenderer for HtmlRenderer {
+    fn render<W, K>(
+        &self,
+        sources: &BeancountSources,
+        w: W,
+        errors_or_warnings: Vec<ErrorOrWarning<K>>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        K: ErrorOrWarningKind,
+    {
+        write_page(sources, w, annotations(sources, &errors_or_warnings))
+    }
+}
+
+impl HtmlRenderer {
+    /// Render both errors and warnings in a single page.
+    pub fn render_all<W>(
+        &self,
+        sources: &BeancountSources,
+        w: W,
+        errors: Vec<Error>,
+        warnings: Vec<Warning>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let mut all = annotations(sources, &errors);
+        all.append(&mut annotations(sources, &warnings));
+
+        write_page(sources, w, all)
+    }
+}
+
+fn write_page<W>(
+    sources: &BeancountSources,
+    mut w: W,
+    mut annotations: Vec<Annotation>,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    // stable, so that errors precede warnings on the same line
+    annotations.sort_by_key(|annotation| annotation.line);
+
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, r#"<html><head><meta charset="utf-8">"#)?;
+    writeln!(
+        w,
+        "<title>{}</title>",
+        escape(sources.source_name(sources.root_source_id))
+    )?;
+    writeln!(w, "<style>{}</style>", STYLE)?;
+    writeln!(w, "</head><body>")?;
+
+    for (n, (source_id, _, content)) in sources.content_iter().enumerate() {
+        let source_annotations = annotations
+            .iter()
+            .filter(|annotation| annotation.source_id == source_id)
+            .collect::<Vec<_>>();
+
+        writeln!(w, r#"<section class="source">"#)?;
+        writeln!(w, "<h1>{}</h1>", escape(sources.source_name(source_id)))?;
+        write!(w, "<pre>")?;
+        for (i, line) in highlight(source_id, content).iter().enumerate() {
+            let line_number = i + 1;
+            let line_annotations = source_annotations
+                .iter()
+                .filter(|annotation| annotation.line == line_number)
+                .collect::<Vec<_>>();
+            let line_class = line_annotations
+                .first()
+                .map_or("line".to_string(), |annotation| {
+                    format!("line {}", annotation.severity)
+                });
+
+            writeln!(
+                w,
+                r#"<span class="{}" id="{}-L{}"><span class="lineno">{}</span>{}</span>"#,
+                line_class,
+                n + 1,
+                line_number,
+                line_number,
+                line
+            )?;
+            for annotation in line_annotations {
+                writeln!(
+                    w,
+                    r#"<span class="diagnostic {}">{}: {}: {}</span>"#,
+                    annotation.severity,
+                    annotation.severity,
+                    escape(&annotation.message),
+                    escape(&annotation.reason)
+                )?;
+            }
+        }
+        writeln!(w, "</pre>")?;
+        writeln!(w, "</section>")?;
+    }
+
+    writeln!(w, "</body></html>")
+}
+
+const STYLE: &str = "pre{line-height:1.4}\
+.line{display:block}\
+.lineno{display:inline-block;width:4em;color:#999;user-select:none}\
+.line.error{background:#fdd}\
+.line.warning{background:#ffd}\
+.diagnostic{display:block;margin-left:4em;font-style:italic}\
+.diagnostic.error{color:#b00}\
+.diagnostic.warning{color:#860}\
+.date,.time{color:#05a}\
+.account{color:#707}\
+.currency{color:#077}\
+.number{color:#070}\
+.string{color:#a50}\
+.keyword{font-weight:bold}\
+.tag,.link{color:#55a}\
+.key{color:#555}\
+.flag{color:#a00}\
+.comment{color:#888}";
+
+struct Annotation {
+    source_id: SourceId,
+    line: usize,
+    severity: String,
+    message: String,
+    reason: String,
+}
+
+fn annotations<K>(
+    sources: &BeancountSources,
+    errors_or_warnings: &[ErrorOrWarning<K>],
+) -> Vec<Annotation>
+where
+    K: ErrorOrWarningKind,
+{
+    errors_or_warnings
+        .iter()
+        .map(|error_or_warning| {
+            let span = error_or_warning.span;
+            let content = sources.source_content(span.context());
+            let before = &content[..span.start().min(content.len())];
+
+            Annotation {
+                source_id: span.context(),
+                line: before.matches('\n').count() + 1,
+                severity: severity(error_or_warning),
+                message: error_or_warning.message.to_string(),
+                reason: error_or_warning.reason.to_string(),
+            }
+        })
+        .collect()
+}
+
+// the HTML of each line of `content`, with tokens marked up by class
+fn highlight(source_id: SourceId, content: &str) -> Vec<String> {
+    let mut lines = Lines::default();
+    let mut position = 0;
+
+    for (token, span) in lex_with_source(source_id, content) {
+        let (start, end) = (span.start(), span.end());
+        // ignore any overlapping tokens, as when an indent is split from its end-of-line
+        if start < position {
+            continue;
+        }
+
+        lines.push_between_tokens(&content[position..start]);
+        match class(&token) {
+            Some(Class::Comment) => {
+                // end-of-line tokens include any comments and skipped lines, which are all presented as comments
+                for (i, line) in content[start..end].split('\n').enumerate() {
+                    if i > 0 {
+                        lines.push(None, "\n");
+                    }
+                    let text = line.trim_start();
+                    lines.push(None, &line[..line.len() - text.len()]);
+                    lines.push(Some("comment"), text);
+                }
+            }
+            Some(Class::Named(class)) => lines.push(Some(class), &content[start..end]),
+            None => lines.push(None, &content[start..end]),
+        }
+        position = end;
+    }
+    lines.push_between_tokens(&content[position..]);
+
+    lines.finish()
+}
+
+enum Class {
+    Named(&'static str),
+    Comment,
+}
+
+fn class(token: &Token<'_>) -> Option<Class> {
+    use Class::*;
+    use Token::*;
+
+    match token {
+        True | False | Null => Some(Named("constant")),
+        Currency(_) => Some(Named("currency")),
+        DedicatedFlag(_) | Asterisk | Hash => Some(Named("flag")),
+        Txn | Balance | Open | Close | Commodity | Pad | Event | Query | Custom | Price | Note
+        | Document | Pushtag | Poptag | Pushmeta | Popmeta | Option | Options | Plugin
+        | Include => Some(Named("keyword")),
+        Date(_) => Some(Named("date")),
+        Time(_) => Some(Named("time")),
+        Account(_) => Some(Named("account")),
+        StringLiteral(_) | UnterminatedString(_) => Some(Named("string")),
+        Number(_) | LenientNumber(_) => Some(Named("number")),
+        Tag(_) => Some(Named("tag")),
+        Link(_) => Some(Named("link")),
+        Key(_) => Some(Named("key")),
+        Eol | EolThenIndent => Some(Comment),
+        Error(_) => Some(Named("invalid")),
+        _ => None,
+    }
+}
+
+// accumulates HTML by line, so that no element spans a line break
+#[derive(Default)]
+struct Lines {
+    lines: Vec<String>,
+    current: String,
+}
+
+impl Lines {
+    fn push(&mut self, class: Option<&str>, text: &str) {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.lines.push(std::mem::take(&mut self.current));
+            }
+            if !part.is_empty() {
+                match class {
+                    Some(class) => self.current.push_str(&format!(
+                        r#"<span class="{}">{}</span>"#,
+                        class,
+                        escape(part)
+                    )),
+                    None => self.current.push_str(&escape(part)),
+                }
+            }
+        }
+    }
+
+    // whitespace and comments which the lexer skips
+    fn push_between_tokens(&mut self, text: &str) {
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.push(None, "\n");
+            }
+            match line.find(';') {
+                Some(comment) => {
+                    self.push(None, &line[..comment]);
+                    self.push(Some("comment"), &line[comment..]);
+                }
+                None => self.push(None, line),
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        // content normally ends with a newline, after which there is no further line
+        if !self.current.is_empty() {
+            self.lines.push(self.current);
+        }
+        self.lines
+    }
+}
+
+fn escape(text: &str) -> String {
+    format::escape(text, |c| match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '"' => Some("&quot;"),
+        _ => None,
+    })
+}
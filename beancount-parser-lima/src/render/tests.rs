@@ -0,0 +1,141 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+fn render<R>(renderer: R, s: &str) -> String
+where
+    R: DiagnosticRenderer,
+{
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let errors = parser.parse().unwrap_err().errors;
+
+    let mut rendered = Vec::new();
+    renderer.render(&sources, &mut rendered, errors).unwrap();
+    String::from_utf8(rendered).unwrap()
+}
+
+#[test]
+fn plain_location() {
+    let rendered = render(
+        PlainRenderer,
+        "2024-01-01 open Assets:Bank GBP\n2024-01-02 open\n",
+    );
+
+    let mut lines = rendered.lines();
+    assert!(lines.next().unwrap().starts_with("error: "));
+    assert!(lines.next().unwrap().starts_with("  --> inline:2:"));
+}
+
+#[test]
+fn bean_check_location() {
+    let rendered = render(
+        BeanCheckRenderer,
+        "2024-01-01 open Assets:Bank GBP\n2024-01-02 open\n",
+    );
+
+    let first = rendered.lines().next().unwrap();
+    assert!(first.starts_with("inline:       2: "));
+    assert_eq!(rendered.lines().count(), 1);
+}
+
+#[test]
+fn json_location() {
+    let rendered = render(
+        JsonRenderer,
+        "2024-01-01 open Assets:Bank GBP\n2024-01-02 open\n",
+    );
+
+    let first = rendered.lines().next().unwrap();
+    assert!(first.starts_with(r#"{"severity":"error","message":"#));
+    assert!(first.contains(r#""location":{"file":"inline","line":2,"#));
+    assert!(first.ends_with('}'));
+}
+
+#[test]
+fn json_string_escaped() {
+    assert_eq!(
+        JsonString("say \"hi\"\\\n\u{1}").to_string(),
+        r#""say \"hi\"\\\n\u0001""#
+    );
+}
+
+#[test]
+fn json_fixes() {
+    let rendered = render(JsonRenderer, "2024-01-01 note Assets:Bank \"Hello\n");
+
+    let first = rendered.lines().next().unwrap();
+    assert!(first.ends_with(
+        r#""fixes":[{"title":"insert closing quote","edits":[{"location":{"file":"inline","line":1,"column":35,"start":34,"end":34},"replacement":"\""}]}]}"#
+    ));
+}
+
+#[test]
+fn plain_fixes() {
+    let rendered = render(PlainRenderer, "2024-01-01 note Assets:Bank \"Hello\n");
+
+    assert!(rendered
+        .lines()
+        .any(|line| line == "  fix: insert closing quote"));
+}
+
+#[test]
+fn json_lines_as_rendered() {
+    // errors both in parsing the file and in processing pragmas afterwards
+    let s = "pushtag #trip\n2024-01-01 open Assets:Bank GBP\n2024-01-02 open\n";
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let mut writer = JsonLinesWriter::new(&sources, Vec::new());
+    let errors = parser.parse_reporting(&mut writer).unwrap_err().errors;
+    let streamed = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+    let mut rendered = Vec::new();
+    JsonRenderer
+        .render(&sources, &mut rendered, errors)
+        .unwrap();
+
+    // streamed in order of discovery, whereas the returned errors are in order of position
+    let rendered = String::from_utf8(rendered).unwrap();
+    let mut streamed = streamed.lines().collect::<Vec<_>>();
+    streamed.sort_by_key(|line| rendered.find(line));
+    assert_eq!(streamed.len(), 2);
+    assert_eq!(streamed, rendered.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn html_highlighted_and_annotated() {
+    let rendered = render(
+        HtmlRenderer,
+        "; opening <balances>\n2024-01-01 open Assets:Bank GBP\n2024-01-02 open\n",
+    );
+
+    assert!(rendered.contains(
+        r#"<span class="line" id="1-L1"><span class="lineno">1</span><span class="comment">; opening &lt;balances&gt;</span></span>"#
+    ));
+    assert!(rendered.contains(
+        r#"<span class="date">2024-01-01</span> <span class="keyword">open</span> <span class="account">Assets:Bank</span> <span class="currency">GBP</span>"#
+    ));
+    assert!(rendered.contains(r#"<span class="line error" id="1-L3">"#));
+    assert!(rendered.contains(r#"<span class="diagnostic error">error: "#));
+    assert!(rendered.ends_with("</body></html>\n"));
+}
+
+#[test]
+fn html_errors_and_warnings() {
+    let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n2024-01-02 open\n");
+    let parser = BeancountParser::new(&sources);
+    let errors = parser.parse().unwrap_err().errors;
+    let warning = Warning::new("questionable", "just because", errors[0].span);
+
+    let mut rendered = Vec::new();
+    HtmlRenderer
+        .render_all(&sources, &mut rendered, errors, vec![warning])
+        .unwrap();
+    let rendered = String::from_utf8(rendered).unwrap();
+
+    let error = rendered.find(r#"<span class="diagnostic error">"#).unwrap();
+    let warning = rendered
+        .find(r#"<span class="diagnostic warning">warning: questionable: just because</span>"#)
+        .unwrap();
+    assert!(error < warning);
+}
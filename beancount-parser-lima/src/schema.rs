@@ -0,0 +1,115 @@
+// These types are never constructed, since the JSON is written directly, but only describe it for the schemas,
+// so must be kept in step with the writers.
+#![allow(dead_code)]
+
+use schemars::{schema_for, JsonSchema};
+
+/// The JSON Schema for each line written by [JsonRenderer](crate::JsonRenderer) and [JsonLinesWriter](crate::JsonLinesWriter),
+/// so that consumers in other languages may generate types for diagnostics.
+///
+/// # Examples
+/// ```
+/// let schema = beancount_parser_lima::diagnostics_schema();
+///
+/// assert!(schema.contains(r#""title": "Diagnostic""#));
+/// ```
+pub fn diagnostics_schema() -> String {
+    serde_json::to_string_pretty(&schema_for!(Diagnostic)).unwrap()
+}
+
+/// The JSON Schema for each line written by `beancount-lima dump`, being one directive per line.
+pub fn directives_schema() -> String {
+    serde_json::to_string_pretty(&schema_for!(Directive)).unwrap()
+}
+
+/// An error or warning.
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Diagnostic {
+    severity: Severity,
+    /// A short description of the problem.
+    message: String,
+    /// The reason for the problem at its location.
+    reason: String,
+    location: Location,
+    /// The enclosing elements, such as the directive containing the problem, innermost first.
+    contexts: Vec<LabelledLocation>,
+    /// Other locations involved in the problem.
+    related: Vec<LabelledLocation>,
+    /// Suggested fixes, any one of which may be applied.
+    fixes: Vec<Fix>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A location in a source file.
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Location {
+    /// The path of the source file.
+    file: String,
+    /// The line, counting from 1.
+    #[schemars(range(min = 1))]
+    line: usize,
+    /// The column in characters, counting from 1.
+    #[schemars(range(min = 1))]
+    column: usize,
+    /// The byte offset of the start.
+    start: usize,
+    /// The byte offset of the end, exclusive.
+    end: usize,
+}
+
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct LabelledLocation {
+    /// What is at the location.
+    label: String,
+    location: Location,
+}
+
+/// A fix for a diagnostic.
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Fix {
+    /// A short description of the fix.
+    title: String,
+    /// Replacements of the source text, all of which are applied together.
+    edits: Vec<Edit>,
+}
+
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Edit {
+    /// The source text to replace, which is empty for an insertion.
+    location: Location,
+    /// The new text.
+    replacement: String,
+}
+
+/// A directive as dumped.
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Directive {
+    /// The date, as YYYY-MM-DD.
+    #[schemars(extend("format" = "date"))]
+    date: String,
+    /// The type of directive, such as `transaction` or `open`.
+    #[schemars(rename = "type")]
+    directive_type: String,
+    /// The name of the source file.
+    file: String,
+    /// The byte offset of the start.
+    start: usize,
+    /// The byte offset of the end, exclusive.
+    end: usize,
+    /// The directive, formatted as Beancount source.
+    text: String,
+}
+
+mod tests;
@@ -0,0 +1,96 @@
+#![cfg(test)]
+use super::*;
+use crate::{
+    duplicate_balance_assertions, BeancountParser, BeancountSources, DiagnosticRenderer,
+    JsonRenderer,
+};
+use serde_json::Value;
+
+// the property names of the object schema, or of the one it refers to
+fn properties<'s>(schema: &'s Value, object: &'s Value) -> Vec<&'s str> {
+    let object = match object["$ref"].as_str() {
+        Some(reference) => &schema["$defs"][reference.trim_start_matches("#/$defs/")],
+        None => object,
+    };
+    let mut properties = object["properties"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    properties.sort();
+    properties
+}
+
+fn keys(value: &Value) -> Vec<&str> {
+    let mut keys = value
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn diagnostics_schema_describes_json_renderer() {
+    let sources = BeancountSources::from(
+        r#"2024-01-01 balance Assets:Bank 100.00 NZD
+2024-01-01 balance Assets:Bank 100 NZD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let mut written = Vec::new();
+    JsonRenderer
+        .render(
+            &sources,
+            &mut written,
+            duplicate_balance_assertions(&directives),
+        )
+        .unwrap();
+    let diagnostic = serde_json::from_slice::<Value>(&written).unwrap();
+
+    let schema = serde_json::from_str::<Value>(&diagnostics_schema()).unwrap();
+    assert_eq!(properties(&schema, &schema), keys(&diagnostic));
+    for (object, value) in [
+        (&schema["properties"]["location"], &diagnostic["location"]),
+        (
+            &schema["$defs"]["LabelledLocation"],
+            &diagnostic["related"][0],
+        ),
+        (&schema["$defs"]["Fix"], &diagnostic["fixes"][0]),
+        (
+            &schema["$defs"]["Edit"],
+            &diagnostic["fixes"][0]["edits"][0],
+        ),
+    ] {
+        assert_eq!(properties(&schema, object), keys(value));
+    }
+
+    let sources = BeancountSources::from("2024-01-01 open\n");
+    let parser = BeancountParser::new(&sources);
+    let mut written = Vec::new();
+    JsonRenderer
+        .render(&sources, &mut written, parser.parse().unwrap_err().errors)
+        .unwrap();
+    let diagnostic = serde_json::from_slice::<Value>(&written).unwrap();
+    for severity in ["warning", "error"] {
+        assert!(schema["$defs"]["Severity"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::from(severity)));
+    }
+    assert_eq!(diagnostic["severity"], "error");
+}
+
+#[test]
+fn directives_schema_properties() {
+    let schema = serde_json::from_str::<Value>(&directives_schema()).unwrap();
+
+    assert_eq!(
+        properties(&schema, &schema),
+        vec!["date", "end", "file", "start", "text", "type"]
+    );
+}
@@ -0,0 +1,302 @@
+use crate::{
+    types::*,
+    writeback::{writable_path, write_atomically, write_temp},
+    BeancountSources, Fix, TextEdit, WriteBack,
+};
+use chumsky::span::Span as _;
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// An edit session, accumulating edits from any number of operations, such as [WriteBack]s and [Fix]es,
+/// for interactive tools to preview before applying them all at once.
+///
+/// Edits are combined as by [BeancountSources::apply_fixes].
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, EditSession, WriteBack};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-02 open Assets:Cash
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+///
+/// let mut session = EditSession::new(&sources);
+/// session.record(WriteBack::new(&sources).remove(&directives[1]));
+///
+/// assert_eq!(
+///     session.preview(),
+///     r#"--- inline
+/// +++ inline
+/// @@ -1,2 +1,1 @@
+///  2024-01-01 open Assets:Bank
+/// -2024-01-02 open Assets:Cash
+/// "#
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct EditSession<'s> {
+    sources: &'s BeancountSources,
+    edits: Vec<TextEdit>,
+}
+
+// lines of context around each change in a preview
+const CONTEXT_LINES: usize = 3;
+
+impl<'s> EditSession<'s> {
+    /// A session for editing `sources`, with no edits as yet.
+    pub fn new(sources: &'s BeancountSources) -> Self {
+        EditSession {
+            sources,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Record the edits of `writeback`, which must be for the same sources as the session.
+    pub fn record(&mut self, writeback: WriteBack<'s>) {
+        self.edits.extend(writeback.into_edits());
+    }
+
+    /// Record the edits of `fix`.
+    pub fn record_fix(&mut self, fix: &Fix) {
+        self.edits.extend(fix.edits().iter().cloned());
+    }
+
+    /// The edits recorded so far, in the order recorded.
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+
+    /// Whether no edits have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// The changes the session would make, as a unified diff of each changed source.
+    pub fn preview(&self) -> String {
+        let mut sources = self.sources.applicable_edits(&self.edits);
+        sources.sort_by_key(|(source_id, _, _, _)| Into::<usize>::into(*source_id));
+
+        let mut diff = String::new();
+        for (source_id, _, content, edits) in sources {
+            let changes = changes(content, &edits);
+            if !changes.is_empty() {
+                let name = self.sources.source_id_string(source_id);
+                writeln!(diff, "--- {}\n+++ {}", name, name).unwrap();
+                write_hunks(&mut diff, content, &changes);
+            }
+        }
+        diff
+    }
+
+    /// Apply the edits, writing each changed source file, and returning the paths written.
+    ///
+    /// Either all files are written or, on failure, none are changed.
+    /// Nothing is written if any changed source is inline content, or has been modified since it was read.
+    /// Otherwise, new content is first written to a temporary file alongside each source file,
+    /// and only then are these renamed into place, with any already renamed being restored if one fails.
+    pub fn apply(&self) -> io::Result<Vec<PathBuf>> {
+        let contents = self.sources.apply_edits(&self.edits);
+        let paths = contents
+            .iter()
+            .map(|(source_id, path, _)| writable_path(self.sources, *source_id, *path))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut temp_paths = Vec::new();
+        for (path, (_, _, content)) in paths.iter().zip(contents.iter()) {
+            match write_temp(path, content) {
+                Ok(temp_path) => temp_paths.push(temp_path),
+                Err(e) => {
+                    remove_all(&temp_paths);
+                    return Err(e);
+                }
+            }
+        }
+
+        for (i, (path, temp_path)) in paths.iter().zip(temp_paths.iter()).enumerate() {
+            if let Err(e) = fs::rename(temp_path, path) {
+                self.roll_back(&contents[..i]);
+                remove_all(&temp_paths[i..]);
+                return Err(e);
+            }
+        }
+
+        Ok(paths.into_iter().map(Path::to_path_buf).collect())
+    }
+
+    // restore the original content of sources already written, as far as possible
+    fn roll_back(&self, written: &[(SourceId, Option<&Path>, String)]) {
+        for (source_id, path, _) in written {
+            if let Some(path) = path {
+                let _ = write_atomically(path, self.sources.source_content(*source_id));
+            }
+        }
+    }
+}
+
+fn remove_all(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// a change to a run of lines, with the index of the first line
+#[derive(Debug)]
+struct Change<'c> {
+    line: usize,
+    removed: Vec<&'c str>,
+    added: Vec<String>,
+}
+
+// the line changes made by `edits`, which are in order of position and not overlapping
+fn changes<'c>(content: &'c str, edits: &[&TextEdit]) -> Vec<Change<'c>> {
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+    let line_starts = lines
+        .iter()
+        .scan(0, |start, line| {
+            let line_start = *start;
+            *start += line.len();
+            Some(line_start)
+        })
+        .chain(std::iter::once(content.len()))
+        .collect::<Vec<_>>();
+    let line_of = |offset: usize| {
+        line_starts[..lines.len()]
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1)
+    };
+
+    // group edits on the same lines, as whole lines of old and new text
+    let mut groups: Vec<(usize, usize, String)> = Vec::new();
+    let mut copied_to = 0;
+    for edit in edits {
+        let first = line_of(edit.span().start());
+        let last = (line_of(edit.span().end()) + 1).min(lines.len());
+        match groups.last_mut() {
+            Some((_, group_last, new)) if first < *group_last => {
+                new.push_str(&content[copied_to..edit.span().start()]);
+                *group_last = last.max(*group_last);
+            }
+            _ => {
+                if let Some((_, group_last, new)) = groups.last_mut() {
+                    new.push_str(&content[copied_to..line_starts[*group_last]]);
+                }
+                groups.push((
+                    first,
+                    last.max(first),
+                    content[line_starts[first]..edit.span().start()].to_string(),
+                ));
+            }
+        }
+        let (_, _, new) = groups.last_mut().unwrap();
+        new.push_str(edit.replacement());
+        copied_to = edit.span().end();
+    }
+    if let Some((_, group_last, new)) = groups.last_mut() {
+        new.push_str(&content[copied_to..line_starts[*group_last]]);
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|(first, last, new)| {
+            let mut removed = &lines[first..last];
+            let mut added = new.split_inclusive('\n').collect::<Vec<_>>();
+            let mut line = first;
+
+            // edits may leave whole lines unchanged at either end
+            while !removed.is_empty() && !added.is_empty() && removed[0] == added[0] {
+                removed = &removed[1..];
+                added.remove(0);
+                line += 1;
+            }
+            while !removed.is_empty() && !added.is_empty() && removed.last() == added.last() {
+                removed = &removed[..removed.len() - 1];
+                added.pop();
+            }
+
+            (!removed.is_empty() || !added.is_empty()).then(|| Change {
+                line,
+                removed: removed.to_vec(),
+                added: added.into_iter().map(str::to_string).collect(),
+            })
+        })
+        .collect()
+}
+
+// write hunks for `changes`, merging those whose context overlaps
+fn write_hunks(diff: &mut String, content: &str, changes: &[Change<'_>]) {
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+    let mut offset = 0isize; // the difference in line numbers between new and old
+    let mut i = 0;
+
+    while i < changes.len() {
+        // find the changes in this hunk
+        let mut j = i + 1;
+        while j < changes.len()
+            && changes[j].line
+                <= changes[j - 1].line + changes[j - 1].removed.len() + 2 * CONTEXT_LINES
+        {
+            j += 1;
+        }
+        let hunk = &changes[i..j];
+        let last = &hunk[hunk.len() - 1];
+
+        let old_start = hunk[0].line.saturating_sub(CONTEXT_LINES);
+        let old_end = (last.line + last.removed.len() + CONTEXT_LINES).min(lines.len());
+        let new_len = hunk.iter().fold(old_end - old_start, |len, change| {
+            len + change.added.len() - change.removed.len()
+        });
+        let new_start = (old_start as isize + offset) as usize;
+
+        writeln!(
+            diff,
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_len)
+        )
+        .unwrap();
+
+        let mut line = old_start;
+        for change in hunk {
+            for context in &lines[line..change.line] {
+                write_line(diff, ' ', context);
+            }
+            for removed in &change.removed {
+                write_line(diff, '-', removed);
+            }
+            for added in &change.added {
+                write_line(diff, '+', added);
+            }
+            line = change.line + change.removed.len();
+            offset += change.added.len() as isize - change.removed.len() as isize;
+        }
+        for context in &lines[line..old_end] {
+            write_line(diff, ' ', context);
+        }
+
+        i = j;
+    }
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // an empty range is given by the line before it
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+fn write_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+mod tests;
@@ -0,0 +1,168 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+fn at(start: usize, end: usize) -> Span {
+    Span::new(SourceId::default(), start..end)
+}
+
+// numbered lines, for checking hunk ranges
+fn numbered(n: usize) -> String {
+    (1..=n).map(|i| format!("line {}\n", i)).collect()
+}
+
+#[test]
+fn preview_merges_nearby_changes() {
+    let content = numbered(20);
+    let sources = BeancountSources::from(content.as_str());
+    let line = |i: usize| content.find(&format!("line {}\n", i)).unwrap();
+
+    let mut session = EditSession::new(&sources);
+    session.record_fix(&Fix::new("replace 2").replace(at(line(2), line(3)), "line two\n"));
+    session.record_fix(&Fix::new("remove 8").replace(at(line(8), line(9)), ""));
+    session.record_fix(&Fix::new("insert 18").insert_before(at(line(18), line(18)), "new\n"));
+
+    assert_eq!(
+        session.preview(),
+        r#"--- inline
++++ inline
+@@ -1,11 +1,10 @@
+ line 1
+-line 2
++line two
+ line 3
+ line 4
+ line 5
+ line 6
+ line 7
+-line 8
+ line 9
+ line 10
+ line 11
+@@ -15,6 +14,7 @@
+ line 15
+ line 16
+ line 17
++new
+ line 18
+ line 19
+ line 20
+"#
+    );
+}
+
+#[test]
+fn preview_edits_within_a_line() {
+    let sources = BeancountSources::from("2024-01-01 open Assets:Bank");
+    let mut session = EditSession::new(&sources);
+    session.record_fix(
+        &Fix::new("close")
+            .replace(at(11, 15), "close")
+            .insert_after(at(0, 27), " ; closed"),
+    );
+
+    assert_eq!(
+        session.preview(),
+        r#"--- inline
++++ inline
+@@ -1,1 +1,1 @@
+-2024-01-01 open Assets:Bank
+\ No newline at end of file
++2024-01-01 close Assets:Bank ; closed
+\ No newline at end of file
+"#
+    );
+}
+
+#[test]
+fn preview_empty() {
+    let sources = BeancountSources::from(numbered(3));
+    let mut session = EditSession::new(&sources);
+    assert!(session.is_empty());
+    assert_eq!(session.preview(), "");
+
+    session.record_fix(&Fix::new("no change").replace(at(0, 7), "line 1\n"));
+    assert!(!session.is_empty());
+    assert_eq!(session.preview(), "");
+}
+
+// a fresh ledger directory, with the given files
+fn ledger_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-session-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (path, content) in files {
+        fs::write(dir.join(path), content).unwrap();
+    }
+    dir
+}
+
+const MAIN: &str = "include \"other.beancount\"\n2024-01-01 open Assets:Bank\n";
+const OTHER: &str = "2024-01-02 open Assets:Cash\n";
+
+#[test]
+fn apply_across_files() {
+    let dir = ledger_dir(
+        "apply",
+        &[("main.beancount", MAIN), ("other.beancount", OTHER)],
+    );
+    let main = dir.join("main.beancount");
+    let other = dir.join("other.beancount");
+
+    let sources = BeancountSources::try_from(main.clone()).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let mut session = EditSession::new(&sources);
+    for directive in directives.iter() {
+        session.record(WriteBack::new(&sources).remove(directive));
+    }
+
+    let mut written = session.apply().unwrap();
+    written.sort();
+    assert_eq!(written, vec![main.clone(), other.clone()]);
+    assert_eq!(
+        fs::read_to_string(&main).unwrap(),
+        "include \"other.beancount\"\n"
+    );
+    assert_eq!(fs::read_to_string(&other).unwrap(), "");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn apply_nothing_if_any_modified() {
+    let dir = ledger_dir(
+        "modified",
+        &[("main.beancount", MAIN), ("other.beancount", OTHER)],
+    );
+    let main = dir.join("main.beancount");
+    let other = dir.join("other.beancount");
+
+    let sources = BeancountSources::try_from(main.clone()).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let mut session = EditSession::new(&sources);
+    for directive in directives.iter() {
+        session.record(WriteBack::new(&sources).remove(directive));
+    }
+
+    fs::write(&other, "2024-01-02 open Assets:Wallet\n").unwrap();
+    let error = session.apply().unwrap_err();
+
+    assert!(error
+        .to_string()
+        .ends_with("has been modified since it was read"));
+    assert_eq!(fs::read_to_string(&main).unwrap(), MAIN);
+    assert_eq!(
+        fs::read_to_string(&other).unwrap(),
+        "2024-01-02 open Assets:Wallet\n"
+    );
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -1,7 +1,64 @@
+use crate::types::{Directive, DirectiveKind, Spanned};
+use chumsky::span::Span as _;
 use std::{
     collections::{BTreeMap, VecDeque},
     fmt::{self, Debug},
 };
+use time::Date;
+
+/// Sort directives as Beancount does, for deterministic downstream processing.
+///
+/// Directives are ordered by date, then by kind, then by source order.
+/// On any given date, as in the reference implementation,
+/// opens come first, then balance assertions (which apply at the start of the day),
+/// then all other directives, then documents, and finally closes.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{sort_directives, BeancountParser, BeancountSources, DirectiveKind};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 close Assets:Bank
+/// 2024-01-01 * "Interest"
+///   Assets:Bank  1.00 NZD
+///   Income:Interest
+/// 2024-01-01 balance Assets:Bank 0.00 NZD
+/// 2024-01-01 open Assets:Bank
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let mut directives = parser.parse().unwrap().directives;
+/// sort_directives(&mut directives);
+///
+/// assert_eq!(
+///     directives.iter().map(|d| d.kind()).collect::<Vec<_>>(),
+///     vec![DirectiveKind::Open, DirectiveKind::Balance, DirectiveKind::Transaction, DirectiveKind::Close]
+/// );
+/// ```
+pub fn sort_directives(directives: &mut [Spanned<Directive<'_>>]) {
+    directives.sort_by_key(sort_key)
+}
+
+/// The key by which [sort_directives] orders directives, for sorting collections of references to them.
+pub(crate) fn sort_key(directive: &Spanned<Directive<'_>>) -> (Date, i8, usize, usize) {
+    (
+        *directive.date().item(),
+        kind_priority(directive.kind()),
+        directive.span().context().into(),
+        directive.span().start,
+    )
+}
+
+// as SORT_ORDER in the reference implementation
+fn kind_priority(kind: DirectiveKind) -> i8 {
+    use DirectiveKind::*;
+
+    match kind {
+        Open => -2,
+        Balance => -1,
+        Document => 1,
+        Close => 2,
+        _ => 0,
+    }
+}
 
 /// See [sort].
 pub struct Sort<K, V> {
@@ -36,3 +36,38 @@ proptest! {
         }
     }
 }
+
+#[test]
+fn test_sort_directives_by_date_kind_and_source_order() {
+    let sources = crate::BeancountSources::from(
+        r#"2024-01-02 open Assets:Cash
+2024-01-01 close Assets:Bank
+2024-01-01 document Assets:Bank "statement.pdf"
+2024-01-01 note Assets:Bank "second"
+2024-01-01 balance Assets:Bank 0.00 NZD
+2024-01-01 note Assets:Bank "third"
+2024-01-01 open Assets:Bank
+"#,
+    );
+    let parser = crate::BeancountParser::new(&sources);
+    let mut directives = parser.parse().unwrap().directives;
+    directives.reverse();
+    sort_directives(&mut directives);
+
+    assert_eq!(
+        directives
+            .iter()
+            .map(|d| (d.date().item().day(), d.kind().to_string()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, "open".to_string()),
+            (1, "balance".to_string()),
+            (1, "note".to_string()),
+            (1, "note".to_string()),
+            (1, "document".to_string()),
+            (1, "close".to_string()),
+            (2, "open".to_string()),
+        ]
+    );
+    assert!(directives[2].span().start < directives[3].span().start);
+}
@@ -0,0 +1,167 @@
+use crate::{types::*, Options};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use time::Date;
+
+/// The length of period by which to split a ledger, see [split_by_period].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Period {
+    Year,
+    Month,
+}
+
+impl Period {
+    /// The name of the period containing `date`, which is also the stem of its file name.
+    fn name(&self, date: &Date) -> String {
+        match self {
+            Period::Year => format!("{:04}", date.year()),
+            Period::Month => format!("{:04}-{:02}", date.year(), date.month() as u8),
+        }
+    }
+}
+
+/// Split directives into a file per period, plus a master file which includes them all.
+///
+/// The master file contains the directives which are not about activity within a period, that is
+/// `open`, `close`, `commodity`, and `query`, along with opening balance `pad`s, which are those
+/// padding from the previous balances account (by default `Equity:Opening-Balances`).
+/// All other directives go in the file for their period.
+/// Since Beancount orders directives by date regardless of which file they appear in,
+/// the master file is equivalent to the original ledger.
+///
+/// Options, plugins, and comments are not directives, so these must be copied into the master file separately.
+pub fn split_by_period<'a, I>(directives: I, options: &Options<'_>, period: Period) -> SplitLedger
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let mut master = Vec::new();
+    let mut periods = BTreeMap::<String, Vec<&Spanned<Directive<'a>>>>::new();
+
+    for directive in directives {
+        use DirectiveVariant::*;
+
+        let is_master = match directive.variant() {
+            Open(_) | Close(_) | Commodity(_) | Query(_) => true,
+            Pad(pad) => is_opening_balance(pad.source().item(), options),
+            _ => false,
+        };
+
+        if is_master {
+            master.push(directive);
+        } else {
+            periods
+                .entry(period.name(directive.date().item()))
+                .or_default()
+                .push(directive);
+        }
+    }
+
+    let periods = periods
+        .into_iter()
+        .map(|(name, directives)| PeriodFile {
+            path: format!("{}.beancount", name).into(),
+            content: format_directives(directives),
+            name,
+        })
+        .collect::<Vec<_>>();
+
+    let mut master = format_directives(master);
+    if !periods.is_empty() {
+        master.push('\n');
+    }
+    for period in periods.iter() {
+        master.push_str(&format!("include \"{}\"\n", period.path.display()));
+    }
+
+    SplitLedger { master, periods }
+}
+
+fn is_opening_balance(source: &Account<'_>, options: &Options<'_>) -> bool {
+    source.account_type() == AccountType::Equity
+        && source.names() == options.account_previous_balances()
+}
+
+/// Directives one per line, except that those spanning multiple lines are separated by blank lines.
+fn format_directives<'a, I>(directives: I) -> String
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let mut content = String::new();
+    let mut previous_multiline = false;
+
+    for directive in directives {
+        let formatted = directive.to_string();
+        let multiline = formatted.contains('\n');
+        if !content.is_empty() && (multiline || previous_multiline) {
+            content.push('\n');
+        }
+        content.push_str(&formatted);
+        content.push('\n');
+        previous_multiline = multiline;
+    }
+
+    content
+}
+
+/// The result of [split_by_period].
+#[derive(Clone, Debug)]
+pub struct SplitLedger {
+    master: String,
+    periods: Vec<PeriodFile>,
+}
+
+impl SplitLedger {
+    /// Field accessor.
+    pub fn master(&self) -> &str {
+        &self.master
+    }
+
+    /// Files for each period, in chronological order.
+    pub fn periods(&self) -> impl ExactSizeIterator<Item = &PeriodFile> {
+        self.periods.iter()
+    }
+
+    /// Write the master file to `master_path`, and the period files alongside it.
+    pub fn write<P>(&self, master_path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let master_path = master_path.as_ref();
+        let dir = master_path.parent().unwrap_or(Path::new(""));
+
+        for period in self.periods.iter() {
+            fs::write(dir.join(&period.path), &period.content)?;
+        }
+        fs::write(master_path, &self.master)
+    }
+}
+
+/// The directives for a single period.
+#[derive(Clone, Debug)]
+pub struct PeriodFile {
+    name: String,
+    path: PathBuf,
+    content: String,
+}
+
+impl PeriodFile {
+    /// The name of the period, for example `2024` or `2024-03`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path of the file relative to the master file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Field accessor.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+mod tests;
@@ -0,0 +1,101 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+fn split(source: &str, period: Period) -> SplitLedger {
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    split_by_period(&success.directives, &success.options, period)
+}
+
+const LEDGER: &str = r#"
+2023-01-01 open Assets:Bank GBP
+2023-01-01 open Expenses:Coffee
+2023-01-01 open Equity:Opening-Balances
+2023-01-01 pad Assets:Bank Equity:Opening-Balances
+2023-01-02 balance Assets:Bank 100.00 GBP
+2023-12-31 * "Coffee"
+  Assets:Bank  -3.50 GBP
+  Expenses:Coffee
+2024-01-15 * "Coffee"
+  Assets:Bank  -3.60 GBP
+  Expenses:Coffee
+2024-02-01 balance Assets:Bank 92.90 GBP
+"#;
+
+#[test]
+fn split_by_year() {
+    let split = split(LEDGER, Period::Year);
+
+    assert_eq!(
+        split.master(),
+        r#"2023-01-01 open Assets:Bank GBP
+2023-01-01 open Expenses:Coffee
+2023-01-01 open Equity:Opening-Balances
+2023-01-01 pad Assets:Bank Equity:Opening-Balances
+
+include "2023.beancount"
+include "2024.beancount"
+"#
+    );
+
+    let periods = split.periods().collect::<Vec<_>>();
+    assert_eq!(
+        periods.iter().map(|p| p.name()).collect::<Vec<_>>(),
+        vec!["2023", "2024"]
+    );
+    assert_eq!(
+        periods[0].content(),
+        r#"2023-01-02 balance Assets:Bank 100.00 GBP
+
+2023-12-31 * "Coffee"
+  Assets:Bank -3.50 GBP
+  Expenses:Coffee
+"#
+    );
+}
+
+#[test]
+fn split_by_month() {
+    let split = split(LEDGER, Period::Month);
+
+    assert_eq!(
+        split
+            .periods()
+            .map(|p| p.path().to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        vec![
+            "2023-01.beancount",
+            "2023-12.beancount",
+            "2024-01.beancount",
+            "2024-02.beancount"
+        ]
+    );
+}
+
+#[test]
+fn split_reparses_equivalently() {
+    let split = split(LEDGER, Period::Year);
+    let master = split
+        .master()
+        .lines()
+        .filter(|line| !line.starts_with("include"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let recombined = std::iter::once(master)
+        .chain(split.periods().map(|p| p.content().to_string()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let original_sources = BeancountSources::from(LEDGER);
+    let original_parser = BeancountParser::new(&original_sources);
+    let recombined_sources = BeancountSources::from(recombined);
+    let recombined_parser = BeancountParser::new(&recombined_sources);
+
+    assert_eq!(
+        original_parser.parse().unwrap().directives,
+        recombined_parser.parse().unwrap().directives
+    );
+}
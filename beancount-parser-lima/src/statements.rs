@@ -0,0 +1,150 @@
+use crate::{
+    trial_balance::{trial_balance, TrialBalance},
+    types::*,
+    Options,
+};
+use time::Date;
+
+/// Compute the financial statements for the period from `begin` to `end`, inclusive,
+/// clamping the totals at the period boundaries as Beancount does for its reports.
+///
+/// `Income` and `Expenses` accounts are totalled over the period only, for the income statement,
+/// while `Assets`, `Liabilities`, and `Equity` accounts are totalled to date, for the balance sheets.
+/// At the beginning of the period, `Income` and `Expenses` are closed into the previous earnings account,
+/// as given by the `account_previous_earnings` option, and at the end, those for the period are closed into
+/// the current earnings account, as given by the `account_current_earnings` option,
+/// so that each balance sheet balances, with no `Income` or `Expenses` accounts.
+///
+/// Totals are accumulated as for [trial_balance].
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{statements, BeancountParser, BeancountSources};
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2023-12-20 * "Employer" "Salary"
+///   Assets:Bank  1000.00 NZD
+///   Income:Salary
+/// 2024-01-05 * "Countdown" "Groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Food
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let success = parser.parse().unwrap();
+/// let date = |year, month, day| Date::from_calendar_date(year, month, day).unwrap();
+/// let statements = statements(
+///     &success.directives,
+///     &success.options,
+///     date(2024, Month::January, 1),
+///     date(2024, Month::December, 31),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(statements.income_statement().total().to_string(), "42.50 NZD");
+/// assert!(statements.opening_balance_sheet().total().is_empty());
+/// assert!(statements.balance_sheet().total().is_empty());
+/// ```
+pub fn statements<'a, I>(
+    directives: I,
+    options: &Options<'a>,
+    begin: Date,
+    end: Date,
+) -> Result<Statements<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    use AccountType::*;
+
+    let directives = directives.into_iter().collect::<Vec<_>>();
+    let closing = trial_balance(directives.iter().copied(), end)?;
+    let opening = match begin.previous_day() {
+        Some(day_before) => trial_balance(directives.iter().copied(), day_before)?,
+        None => TrialBalance::empty(begin),
+    };
+
+    let mut income_statement = TrialBalance::empty(end);
+    for account_type in [Income, Expenses] {
+        income_statement.add_root(account_type, &closing, false);
+        income_statement.add_root(account_type, &opening, true);
+    }
+    income_statement.prune();
+
+    // earnings as the total of income and expenses, closed into equity
+    let earnings = |trial_balance: &TrialBalance<'a>| {
+        let mut earnings = TrialBalance::empty(trial_balance.date());
+        for account_type in [Income, Expenses] {
+            earnings.add_root(account_type, trial_balance, false);
+        }
+        earnings.total()
+    };
+    let previous_earnings = earnings(&opening);
+    let current_earnings = income_statement.total();
+
+    let mut opening_balance_sheet = TrialBalance::empty(opening.date());
+    let mut balance_sheet = TrialBalance::empty(end);
+    for account_type in [Assets, Liabilities, Equity] {
+        opening_balance_sheet.add_root(account_type, &opening, false);
+        balance_sheet.add_root(account_type, &closing, false);
+    }
+    for trial_balance in [&mut opening_balance_sheet, &mut balance_sheet] {
+        trial_balance.add_units(
+            Equity,
+            options.account_previous_earnings(),
+            &previous_earnings,
+            false,
+        );
+    }
+    balance_sheet.add_units(
+        Equity,
+        options.account_current_earnings(),
+        &current_earnings,
+        false,
+    );
+
+    Ok(Statements {
+        begin,
+        end,
+        opening_balance_sheet,
+        income_statement,
+        balance_sheet,
+    })
+}
+
+/// The financial statements for a period, see [statements].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Statements<'a> {
+    begin: Date,
+    end: Date,
+    opening_balance_sheet: TrialBalance<'a>,
+    income_statement: TrialBalance<'a>,
+    balance_sheet: TrialBalance<'a>,
+}
+
+impl<'a> Statements<'a> {
+    /// The first day of the period.
+    pub fn begin(&self) -> Date {
+        self.begin
+    }
+
+    /// The last day of the period.
+    pub fn end(&self) -> Date {
+        self.end
+    }
+
+    /// The balance sheet as of the day before the period, with all earnings to then as previous earnings.
+    pub fn opening_balance_sheet(&self) -> &TrialBalance<'a> {
+        &self.opening_balance_sheet
+    }
+
+    /// The `Income` and `Expenses` accounts over the period.
+    pub fn income_statement(&self) -> &TrialBalance<'a> {
+        &self.income_statement
+    }
+
+    /// The balance sheet as of the end of the period, with the earnings over the period as current earnings.
+    pub fn balance_sheet(&self) -> &TrialBalance<'a> {
+        &self.balance_sheet
+    }
+}
+
+mod tests;
@@ -0,0 +1,92 @@
+#![cfg(test)]
+use super::*;
+use crate::{AccountTotals, BeancountParser, BeancountSources};
+use time::Month;
+
+// the units posted to every account with any, by full name
+fn accounts(trial_balance: &TrialBalance<'_>) -> Vec<(String, String)> {
+    fn walk(name: String, totals: &AccountTotals<'_>, accounts: &mut Vec<(String, String)>) {
+        if !totals.units().is_empty() {
+            accounts.push((name.clone(), totals.units().to_string()));
+        }
+        for (child_name, child) in totals.children() {
+            walk(format!("{}:{}", name, child_name), child, accounts);
+        }
+    }
+
+    let mut accounts = Vec::new();
+    for (account_type, totals) in trial_balance.roots() {
+        walk(account_type.as_ref().to_string(), totals, &mut accounts);
+    }
+    accounts
+}
+
+fn pairs(accounts: &[(&str, &str)]) -> Vec<(String, String)> {
+    accounts
+        .iter()
+        .map(|(account, units)| (account.to_string(), units.to_string()))
+        .collect()
+}
+
+#[test]
+fn statements_clamped_to_period() {
+    let s = r#"option "account_current_earnings" "Earnings:ThisYear"
+2023-06-01 * "Employer" "Salary"
+  Assets:Bank      1000.00 NZD
+  Income:Salary
+2023-12-31 * "Dentist"
+  Liabilities:Card  -200.00 NZD
+  Expenses:Health
+2024-01-01 * "Employer" "Salary"
+  Assets:Bank      1000.00 NZD
+  Income:Salary
+2024-03-15 * "Countdown" "Groceries"
+  Liabilities:Card   -50.00 NZD
+  Expenses:Food
+2025-01-01 * "After the period"
+  Assets:Bank       -10.00 NZD
+  Expenses:Food
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let date = |year, month, day| Date::from_calendar_date(year, month, day).unwrap();
+
+    let statements = statements(
+        &success.directives,
+        &success.options,
+        date(2024, Month::January, 1),
+        date(2024, Month::December, 31),
+    )
+    .unwrap();
+
+    assert_eq!(
+        accounts(statements.income_statement()),
+        pairs(&[
+            ("Income:Salary", "-1000.00 NZD"),
+            ("Expenses:Food", "50.00 NZD"),
+        ])
+    );
+    assert_eq!(
+        accounts(statements.opening_balance_sheet()),
+        pairs(&[
+            ("Assets:Bank", "1000.00 NZD"),
+            ("Liabilities:Card", "-200.00 NZD"),
+            ("Equity:Earnings:Previous", "-800.00 NZD"),
+        ])
+    );
+    assert_eq!(
+        accounts(statements.balance_sheet()),
+        pairs(&[
+            ("Assets:Bank", "2000.00 NZD"),
+            ("Liabilities:Card", "-250.00 NZD"),
+            ("Equity:Earnings:Previous", "-800.00 NZD"),
+            ("Equity:Earnings:ThisYear", "-950.00 NZD"),
+        ])
+    );
+    assert_eq!(
+        statements.opening_balance_sheet().date(),
+        date(2023, Month::December, 31)
+    );
+    assert!(statements.balance_sheet().total().is_empty());
+}
@@ -0,0 +1,327 @@
+use crate::{types::*, BeancountSources, ParseError, ParseSuccess, References};
+use chumsky::span::Span as _;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use time::Date;
+
+/// Statistics from parsing, as returned by [BeancountParser::parse_with_stats](crate::BeancountParser::parse_with_stats).
+#[derive(Clone, Debug)]
+pub struct ParseStats {
+    files: Vec<FileStats>,
+    elapsed: Duration,
+}
+
+impl ParseStats {
+    pub(crate) fn new(
+        sources: &BeancountSources,
+        result: &Result<ParseSuccess<'_>, ParseError>,
+        elapsed: Duration,
+    ) -> Self {
+        let mut files = sources
+            .content_iter()
+            .map(|(source_id, path, content)| FileStats {
+                source_id,
+                path: path.map(Path::to_path_buf),
+                bytes: content.len(),
+                directives: BTreeMap::new(),
+                errors: 0,
+                warnings: 0,
+            })
+            .collect::<Vec<_>>();
+        files.sort_by_key(|file| Into::<usize>::into(file.source_id));
+
+        let mut stats = ParseStats { files, elapsed };
+
+        match result {
+            Ok(ParseSuccess {
+                directives,
+                warnings,
+                ..
+            }) => {
+                for directive in directives {
+                    if let Some(file) = stats.file_mut(directive.span()) {
+                        *file
+                            .directives
+                            .entry(directive.item().element_type())
+                            .or_default() += 1;
+                    }
+                }
+                stats.count_warnings(warnings);
+            }
+            Err(ParseError { errors, warnings }) => {
+                for error in errors {
+                    if let Some(file) = stats.file_mut(&error.span) {
+                        file.errors += 1;
+                    }
+                }
+                stats.count_warnings(warnings);
+            }
+        }
+
+        stats
+    }
+
+    fn file_mut(&mut self, span: &Span) -> Option<&mut FileStats> {
+        self.files
+            .iter_mut()
+            .find(|file| file.source_id == span.context())
+    }
+
+    fn count_warnings(&mut self, warnings: &[Warning]) {
+        for warning in warnings {
+            if let Some(file) = self.file_mut(&warning.span) {
+                file.warnings += 1;
+            }
+        }
+    }
+
+    /// Per-file statistics, root file first, then included files in the order they were found.
+    pub fn files(&self) -> &[FileStats] {
+        &self.files
+    }
+
+    /// Time taken to parse, excluding reading and lexing the sources.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Directive counts by kind over all files.
+    pub fn directives(&self) -> BTreeMap<&'static str, usize> {
+        self.files
+            .iter()
+            .flat_map(|file| file.directives.iter())
+            .fold(BTreeMap::new(), |mut total, (kind, count)| {
+                *total.entry(*kind).or_default() += count;
+                total
+            })
+    }
+
+    /// Error count over all files.
+    pub fn errors(&self) -> usize {
+        self.files.iter().map(|file| file.errors).sum()
+    }
+
+    /// Warning count over all files.
+    pub fn warnings(&self) -> usize {
+        self.files.iter().map(|file| file.warnings).sum()
+    }
+}
+
+impl Display for ParseStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for file in self.files.iter() {
+            writeln!(f, "{}", file)?;
+        }
+        write!(
+            f,
+            "total: {} errors, {} warnings, parsed in {:?}",
+            self.errors(),
+            self.warnings(),
+            self.elapsed
+        )
+    }
+}
+
+/// Statistics for a single source file.
+///
+/// Directives are counted only if parsing succeeded, since otherwise there are none.
+#[derive(Clone, Debug)]
+pub struct FileStats {
+    source_id: SourceId,
+    path: Option<PathBuf>,
+    bytes: usize,
+    directives: BTreeMap<&'static str, usize>,
+    errors: usize,
+    warnings: usize,
+}
+
+impl FileStats {
+    /// Field accessor, which is `None` for inline sources.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Field accessor.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Directive counts by kind, for example `transaction`.
+    pub fn directives(&self) -> &BTreeMap<&'static str, usize> {
+        &self.directives
+    }
+
+    /// Field accessor.
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    /// Field accessor.
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+}
+
+impl Display for FileStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}", path.to_string_lossy())?,
+            None => write!(f, "inline")?,
+        }
+        write!(f, ": {} bytes", self.bytes)?;
+        for (kind, count) in self.directives.iter() {
+            write!(f, ", {} {}", count, kind)?;
+        }
+        write!(f, ", {} errors, {} warnings", self.errors, self.warnings)
+    }
+}
+
+/// Summary statistics of a ledger, the backend for a `stats` report, rendered in full by its `Display` implementation.
+///
+/// Apart from the error and warning counts, these are zero or empty if parsing failed, since then there are no directives.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, LedgerStats};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank NZD
+/// 2024-01-02 * "Countdown" "groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let stats = LedgerStats::new(&parser.parse());
+///
+/// assert_eq!(stats.accounts(), 2);
+/// assert_eq!(stats.postings_per_month().get(&(2024, 1)), Some(&2));
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct LedgerStats {
+    dates: Option<(Date, Date)>,
+    directives: BTreeMap<&'static str, usize>,
+    accounts: usize,
+    currencies: usize,
+    payees: usize,
+    postings_per_month: BTreeMap<(i32, u8), usize>,
+    errors: usize,
+    warnings: usize,
+}
+
+impl LedgerStats {
+    /// Compute the statistics from the result of parsing.
+    pub fn new(result: &Result<ParseSuccess<'_>, ParseError>) -> Self {
+        match result {
+            Ok(ParseSuccess {
+                directives,
+                warnings,
+                ..
+            }) => {
+                let mut stats = LedgerStats {
+                    warnings: warnings.len(),
+                    ..Default::default()
+                };
+
+                for directive in directives {
+                    let date = *directive.date().item();
+                    stats.dates = Some(match stats.dates {
+                        Some((first, last)) => (first.min(date), last.max(date)),
+                        None => (date, date),
+                    });
+                    *stats
+                        .directives
+                        .entry(directive.item().element_type())
+                        .or_default() += 1;
+                    if let DirectiveVariant::Transaction(transaction) = directive.variant() {
+                        *stats
+                            .postings_per_month
+                            .entry((date.year(), date.month().into()))
+                            .or_default() += transaction.postings().len();
+                    }
+                }
+
+                let references = References::new(directives.iter());
+                stats.accounts = references.accounts().len();
+                stats.currencies = references.currencies().len();
+                stats.payees = references.payees().len();
+
+                stats
+            }
+            Err(ParseError { errors, warnings }) => LedgerStats {
+                errors: errors.len(),
+                warnings: warnings.len(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The dates of the first and last directives, if any.
+    pub fn dates(&self) -> Option<(Date, Date)> {
+        self.dates
+    }
+
+    /// Directive counts by kind, for example `transaction`.
+    pub fn directives(&self) -> &BTreeMap<&'static str, usize> {
+        &self.directives
+    }
+
+    /// The number of distinct accounts, whether opened or only referenced.
+    pub fn accounts(&self) -> usize {
+        self.accounts
+    }
+
+    /// The number of distinct currencies, whether declared or only referenced.
+    pub fn currencies(&self) -> usize {
+        self.currencies
+    }
+
+    /// The number of distinct payees.
+    pub fn payees(&self) -> usize {
+        self.payees
+    }
+
+    /// The number of postings in each month with any, keyed by year and month number.
+    pub fn postings_per_month(&self) -> &BTreeMap<(i32, u8), usize> {
+        &self.postings_per_month
+    }
+
+    /// Field accessor.
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    /// Field accessor.
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+}
+
+impl Display for LedgerStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.dates {
+            Some((first, last)) => writeln!(f, "dates: {} to {}", first, last)?,
+            None => writeln!(f, "dates: none")?,
+        }
+
+        writeln!(f, "directives:")?;
+        for (kind, count) in self.directives.iter() {
+            writeln!(f, "  {:<12} {:>8}", kind, count)?;
+        }
+
+        writeln!(f, "accounts: {}", self.accounts)?;
+        writeln!(f, "currencies: {}", self.currencies)?;
+        writeln!(f, "payees: {}", self.payees)?;
+
+        writeln!(f, "postings per month:")?;
+        for ((year, month), count) in self.postings_per_month.iter() {
+            writeln!(f, "  {}-{:02} {:>8}", year, month, count)?;
+        }
+
+        write!(f, "{} errors, {} warnings", self.errors, self.warnings)
+    }
+}
+
+mod tests;
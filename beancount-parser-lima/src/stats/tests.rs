@@ -0,0 +1,61 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+#[test]
+fn ledger_stats_rendered() {
+    let sources = BeancountSources::from(
+        r#"
+2024-01-01 open Assets:Bank NZD
+2024-01-01 open Expenses:Groceries
+
+2024-01-02 * "Countdown" "groceries"
+  Assets:Bank  -42.50 NZD
+  Expenses:Groceries
+
+2024-01-20 * "New World" "groceries"
+  Assets:Bank  -10.00 NZD
+  Expenses:Groceries
+
+2024-03-05 * "Countdown" "groceries"
+  Assets:Bank  -5.00 NZD
+  Expenses:Groceries
+  Expenses:Groceries  0.00 USD
+
+2024-03-06 price USD 1.65 NZD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let stats = LedgerStats::new(&parser.parse());
+
+    assert_eq!(
+        stats.to_string(),
+        r#"dates: 2024-01-01 to 2024-03-06
+directives:
+  open                2
+  price               1
+  transaction         3
+accounts: 2
+currencies: 2
+payees: 2
+postings per month:
+  2024-01        4
+  2024-03        3
+0 errors, 0 warnings"#
+    );
+}
+
+#[test]
+fn ledger_stats_of_errors() {
+    let sources = BeancountSources::from("2024-01-01 open\n2024-01-02 open\n");
+    let parser = BeancountParser::new(&sources);
+    let stats = LedgerStats::new(&parser.parse());
+
+    assert_eq!(stats.dates(), None);
+    assert!(stats.directives().is_empty());
+    assert_eq!(stats.errors(), 2);
+    assert_eq!(
+        stats.to_string(),
+        "dates: none\ndirectives:\naccounts: 0\ncurrencies: 0\npayees: 0\npostings per month:\n2 errors, 0 warnings"
+    );
+}
@@ -0,0 +1,68 @@
+use crate::{BeancountParser, BeancountSources, ParseError, ParseSuccess, ParserConfig};
+use std::sync::{Arc, RwLock};
+
+/// The latest snapshot of a ledger, shared between threads, such as web handlers reading the ledger
+/// while a background thread re-parses it.
+///
+/// Since the parse result borrows from the sources, the snapshot is whatever owned value the application
+/// derives from the result, for example the balances of all accounts.
+/// Each reader holds its snapshot for as long as it needs, unaffected by later updates,
+/// and the lock is held only for as long as it takes to clone or replace an `Arc`,
+/// never while parsing.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountSources, LedgerStore, ParserConfig};
+///
+/// let store = LedgerStore::new(0);
+/// let sources = BeancountSources::from("2024-01-01 open Assets:Bank\n");
+/// store.parse(&sources, ParserConfig::default(), |_sources, result| {
+///     result.map_or(0, |success| success.directives.len())
+/// });
+///
+/// assert_eq!(*store.load(), 1);
+/// ```
+#[derive(Default, Debug)]
+pub struct LedgerStore<T> {
+    current: RwLock<Arc<T>>,
+}
+
+impl<T> LedgerStore<T> {
+    /// Create a store holding `initial` until the first update.
+    pub fn new(initial: T) -> Self {
+        LedgerStore {
+            current: RwLock::new(Arc::new(initial)),
+        }
+    }
+
+    /// The current snapshot.
+    pub fn load(&self) -> Arc<T> {
+        // a panic while holding the lock cannot leave the Arc inconsistent, so poisoning is ignored
+        self.current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replace the current snapshot, returning the previous one.
+    pub fn store(&self, snapshot: T) -> Arc<T> {
+        let snapshot = Arc::new(snapshot);
+        let mut current = self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *current, snapshot)
+    }
+
+    /// Parse `sources` and replace the current snapshot with that derived from the result by `snapshot`,
+    /// returning the previous one.
+    pub fn parse<F>(&self, sources: &BeancountSources, config: ParserConfig, snapshot: F) -> Arc<T>
+    where
+        F: FnOnce(&BeancountSources, Result<ParseSuccess<'_>, ParseError>) -> T,
+    {
+        let parser = BeancountParser::with_config(sources, config);
+        self.store(snapshot(sources, parser.parse()))
+    }
+}
+
+mod tests;
@@ -0,0 +1,55 @@
+#![cfg(test)]
+use super::*;
+use std::thread;
+
+#[test]
+fn parse_replaces_snapshot() {
+    let store = LedgerStore::new(Vec::new());
+    let parse = |sources: &BeancountSources| {
+        let store = &store;
+        store.parse(sources, ParserConfig::default(), |_, result| {
+            result
+                .unwrap()
+                .directives
+                .iter()
+                .map(|directive| directive.to_string())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let first = BeancountSources::from("2024-01-01 open Assets:Bank\n");
+    let previous = parse(&first);
+    assert!(previous.is_empty());
+
+    let held = store.load();
+    let second =
+        BeancountSources::from("2024-01-01 open Assets:Bank\n2024-01-01 open Assets:Cash\n");
+    let previous = parse(&second);
+
+    assert_eq!(*previous, vec!["2024-01-01 open Assets:Bank".to_string()]);
+    assert_eq!(held, previous);
+    assert_eq!(store.load().len(), 2);
+}
+
+#[test]
+fn readers_see_consistent_snapshots() {
+    // each snapshot is a run of equal values, so a torn snapshot would be visible
+    let store = LedgerStore::new(vec![0; 100]);
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    let snapshot = store.load();
+                    assert!(snapshot.iter().all(|value| *value == snapshot[0]));
+                }
+            });
+        }
+
+        for i in 1..=1000 {
+            store.store(vec![i; 100]);
+        }
+    });
+
+    assert_eq!(store.load()[0], 1000);
+}
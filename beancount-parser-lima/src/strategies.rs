@@ -0,0 +1,187 @@
+//! [Proptest](https://docs.rs/proptest/latest/proptest/) strategies for generating Beancount source, enabled by the `proptest` feature.
+//!
+//! Since the parsed types borrow from their source, the strategies generate source text rather than the types themselves.
+//! Parse the generated source to obtain [Directive](crate::Directive)s.
+//!
+//! # Examples
+//! ```
+//! use beancount_parser_lima::{strategies::arb_directives, BeancountParser, BeancountSources};
+//! use proptest::{strategy::{Strategy, ValueTree}, test_runner::TestRunner};
+//!
+//! let source = arb_directives(5).new_tree(&mut TestRunner::default()).unwrap().current();
+//! let sources = BeancountSources::from(source);
+//! let parser = BeancountParser::new(&sources);
+//!
+//! assert!(parser.parse().is_ok());
+//! ```
+use proptest::{collection::vec, option, prelude::*};
+
+prop_compose! {
+    /// A date in ISO format.
+    pub fn arb_date()(year in 1900..2100u32, month in 1..=12u32, day in 1..=28u32) -> String {
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+prop_compose! {
+    /// A fully-qualified account name using the default account type names.
+    pub fn arb_account()(
+        account_type in prop_oneof![
+            Just("Assets"),
+            Just("Liabilities"),
+            Just("Equity"),
+            Just("Income"),
+            Just("Expenses"),
+        ],
+        names in vec("[A-Z][A-Za-z0-9]{0,8}", 1..4),
+    ) -> String {
+        format!("{}:{}", account_type, names.join(":"))
+    }
+}
+
+/// A currency.
+pub fn arb_currency() -> impl Strategy<Value = String> {
+    "[A-Z]{3}"
+}
+
+/// A number with at most two decimal places.
+pub fn arb_number() -> impl Strategy<Value = String> {
+    "-?[0-9]{1,5}(\\.[0-9]{1,2})?"
+}
+
+prop_compose! {
+    /// A number and currency.
+    pub fn arb_amount()(number in arb_number(), currency in arb_currency()) -> String {
+        format!("{} {}", number, currency)
+    }
+}
+
+/// A quoted string, free of characters requiring escapes.
+pub fn arb_string() -> impl Strategy<Value = String> {
+    "[A-Za-z0-9 .,]{0,20}".prop_map(|s| format!("\"{}\"", s))
+}
+
+prop_compose! {
+    /// A metadata key/value line, suitably indented.
+    pub fn arb_metadata_line()(key in "[a-z][a-z0-9]{1,8}", value in arb_string()) -> String {
+        format!("  {}: {}\n", key, value)
+    }
+}
+
+prop_compose! {
+    fn arb_posting()(
+        flag in option::of(prop_oneof![Just("* "), Just("! ")]),
+        account in arb_account(),
+        amount in option::of(arb_amount()),
+        cost in option::of(arb_amount()),
+        price in option::of(arb_amount()),
+    ) -> String {
+        match amount {
+            Some(amount) => format!(
+                "  {}{} {}{}{}\n",
+                flag.unwrap_or_default(),
+                account,
+                amount,
+                cost.map(|cost| format!(" {{{}}}", cost)).unwrap_or_default(),
+                price.map(|price| format!(" @ {}", price)).unwrap_or_default()
+            ),
+            None => format!("  {}{}\n", flag.unwrap_or_default(), account),
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_transaction()(
+        date in arb_date(),
+        flag in prop_oneof![Just("*"), Just("!"), Just("txn")],
+        payee in option::of(arb_string()),
+        narration in arb_string(),
+        tags in vec("#[a-z]{1,8}", 0..3),
+        metadata in vec(arb_metadata_line(), 0..3),
+        postings in vec(arb_posting(), 0..4),
+    ) -> String {
+        format!(
+            "{} {} {}{}{}\n{}{}",
+            date,
+            flag,
+            payee.map(|payee| format!("{} ", payee)).unwrap_or_default(),
+            narration,
+            tags.iter().map(|tag| format!(" {}", tag)).collect::<String>(),
+            metadata.concat(),
+            postings.concat()
+        )
+    }
+}
+
+/// The source of a single directive, including its final newline.
+pub fn arb_directive() -> impl Strategy<Value = String> {
+    prop_oneof![
+        arb_transaction(),
+        (
+            arb_date(),
+            arb_account(),
+            vec(arb_currency(), 0..3),
+            option::of(prop_oneof![Just("\"STRICT\""), Just("\"FIFO\"")])
+        )
+            .prop_map(|(date, account, currencies, booking)| {
+                format!(
+                    "{} open {}{}{}\n",
+                    date,
+                    account,
+                    if currencies.is_empty() {
+                        String::default()
+                    } else {
+                        // currencies must be unique
+                        let mut currencies = currencies;
+                        currencies.sort();
+                        currencies.dedup();
+                        format!(" {}", currencies.join(","))
+                    },
+                    booking
+                        .map(|booking| format!(" {}", booking))
+                        .unwrap_or_default()
+                )
+            }),
+        (arb_date(), arb_account())
+            .prop_map(|(date, account)| format!("{} close {}\n", date, account)),
+        (arb_date(), arb_currency())
+            .prop_map(|(date, currency)| format!("{} commodity {}\n", date, currency)),
+        (arb_date(), arb_currency(), arb_amount()).prop_map(|(date, currency, amount)| format!(
+            "{} price {} {}\n",
+            date, currency, amount
+        )),
+        (
+            arb_date(),
+            arb_account(),
+            arb_number(),
+            option::of("[0-9]\\.[0-9]{1,2}"),
+            arb_currency()
+        )
+            .prop_map(|(date, account, number, tolerance, currency)| format!(
+                "{} balance {} {}{} {}\n",
+                date,
+                account,
+                number,
+                tolerance
+                    .map(|tolerance| format!(" ~ {}", tolerance))
+                    .unwrap_or_default(),
+                currency
+            )),
+        (arb_date(), arb_account(), arb_account())
+            .prop_map(|(date, account, source)| format!("{} pad {} {}\n", date, account, source)),
+        (arb_date(), arb_account(), arb_string()).prop_map(|(date, account, comment)| format!(
+            "{} note {} {}\n",
+            date, account, comment
+        )),
+        (arb_date(), arb_string(), arb_string()).prop_map(|(date, event_type, description)| {
+            format!("{} event {} {}\n", date, event_type, description)
+        }),
+    ]
+}
+
+/// The source of up to `max` directives.
+pub fn arb_directives(max: usize) -> impl Strategy<Value = String> {
+    vec(arb_directive(), 0..=max).prop_map(|directives| directives.concat())
+}
+
+mod tests;
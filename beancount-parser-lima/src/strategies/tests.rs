@@ -0,0 +1,33 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+/// Parse the source and format the resulting directives.
+fn parse_and_format(source: String) -> String {
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let formatted = match parser.parse() {
+        Ok(success) => success
+            .directives
+            .iter()
+            .map(|directive| format!("{}\n", directive))
+            .collect::<String>(),
+        Err(e) => {
+            sources.write(&std::io::stderr(), e.errors).unwrap();
+            panic!("failed to parse {:?}", sources);
+        }
+    };
+    formatted
+}
+
+proptest! {
+    // formatting is not quite canonical with respect to the generated source,
+    // so the property is that formatting a second time makes no further change
+    #[test]
+    fn test_format_parse_format_stabilizes(source in arb_directives(10)) {
+        let formatted = parse_and_format(source);
+        let reformatted = parse_and_format(formatted.clone());
+
+        prop_assert_eq!(formatted, reformatted);
+    }
+}
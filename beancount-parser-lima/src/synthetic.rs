@@ -0,0 +1,350 @@
+use rust_decimal::Decimal;
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+use time::{Date, Duration, Month};
+
+/// A generator of realistic synthetic ledgers of configurable size, for benchmarking,
+/// and for sharing reproduction data without revealing real finances.
+///
+/// The ledger has a monthly salary and rent, daily spending across expense accounts
+/// from payees of which a few are much more frequent than the rest, monthly purchases of commodities
+/// with daily prices, and a balance assertion on the checking account at the start of each month.
+///
+/// Generation is deterministic, so the same configuration always produces the same ledger.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, SyntheticLedger};
+///
+/// let ledger = SyntheticLedger::default().days(60).seed(42).generate();
+/// let sources = BeancountSources::from(ledger);
+/// let parser = BeancountParser::new(&sources);
+///
+/// assert!(parser.parse().unwrap().directives.len() > 100);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SyntheticLedger {
+    seed: u64,
+    start: Date,
+    days: u32,
+    transactions_per_day: u32,
+    expense_accounts: usize,
+    payees: usize,
+    commodities: usize,
+}
+
+impl Default for SyntheticLedger {
+    fn default() -> Self {
+        SyntheticLedger {
+            seed: 0,
+            start: Date::from_calendar_date(2024, Month::January, 1).unwrap(),
+            days: 365,
+            transactions_per_day: 3,
+            expense_accounts: 12,
+            payees: 50,
+            commodities: 3,
+        }
+    }
+}
+
+const CURRENCY: &str = "USD";
+const CHECKING: &str = "Assets:Bank:Checking";
+const CREDIT_CARD: &str = "Liabilities:CreditCard";
+const BROKERAGE: &str = "Assets:Brokerage";
+
+const EXPENSE_CATEGORIES: [&str; 12] = [
+    "Groceries",
+    "Dining",
+    "Transport",
+    "Utilities",
+    "Entertainment",
+    "Clothing",
+    "Health",
+    "Travel",
+    "Gifts",
+    "Books",
+    "Home",
+    "Phone",
+];
+
+const PAYEE_WORDS: [&str; 10] = [
+    "Corner", "Market", "Express", "City", "Green", "Golden", "Central", "Harbour", "Union",
+    "Royal",
+];
+
+const PAYEE_KINDS: [&str; 8] = [
+    "Store",
+    "Cafe",
+    "Grocer",
+    "Garage",
+    "Books",
+    "Pharmacy",
+    "Kitchen",
+    "Outfitters",
+];
+
+impl SyntheticLedger {
+    /// Seed for the pseudo-random choices, default 0.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The first date of the ledger, default 2024-01-01.
+    pub fn start(mut self, start: Date) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The number of days covered, default 365.
+    pub fn days(mut self, days: u32) -> Self {
+        self.days = days;
+        self
+    }
+
+    /// The number of everyday spending transactions on each day, default 3.
+    pub fn transactions_per_day(mut self, transactions_per_day: u32) -> Self {
+        self.transactions_per_day = transactions_per_day;
+        self
+    }
+
+    /// The number of expense accounts for everyday spending, default 12, and at least 1.
+    pub fn expense_accounts(mut self, expense_accounts: usize) -> Self {
+        self.expense_accounts = expense_accounts.max(1);
+        self
+    }
+
+    /// The number of distinct payees for everyday spending, default 50, and at least 1.
+    pub fn payees(mut self, payees: usize) -> Self {
+        self.payees = payees.max(1);
+        self
+    }
+
+    /// The number of commodities bought monthly and priced daily, default 3.
+    pub fn commodities(mut self, commodities: usize) -> Self {
+        self.commodities = commodities;
+        self
+    }
+
+    /// Generate the ledger.
+    pub fn generate(&self) -> String {
+        let mut ledger = String::new();
+        let mut rng = Rng::new(self.seed);
+        let commodities = (0..self.commodities)
+            .map(commodity_name)
+            .collect::<Vec<_>>();
+        let mut prices = (0..self.commodities)
+            .map(|_| Decimal::new(rng.between(2_000, 50_000) as i64, 2))
+            .collect::<Vec<_>>();
+        let mut checking = Decimal::ZERO;
+
+        self.write_declarations(&mut ledger, &commodities);
+
+        for date in (0..self.days).map(|day| self.start + Duration::days(day.into())) {
+            if date.day() == 1 {
+                writeln!(
+                    ledger,
+                    "{} balance {}  {} {}\n",
+                    date, CHECKING, checking, CURRENCY
+                )
+                .unwrap();
+
+                let salary = Decimal::new(rng.between(500_000, 520_000) as i64, 2);
+                checking += salary;
+                write_transaction(
+                    &mut ledger,
+                    date,
+                    "Employer",
+                    "salary",
+                    &[(CHECKING, Some(salary)), ("Income:Salary", None)],
+                );
+
+                let rent = Decimal::new(180_000, 2);
+                checking -= rent;
+                write_transaction(
+                    &mut ledger,
+                    date,
+                    "Landlord",
+                    "rent",
+                    &[("Expenses:Rent", Some(rent)), (CHECKING, None)],
+                );
+            }
+
+            for _ in 0..self.transactions_per_day {
+                // squaring skews the choice towards the first few payees
+                let u = rng.fraction();
+                let payee = ((u * u * self.payees as f64) as usize).min(self.payees - 1);
+                let category = payee % self.expense_accounts;
+                let narration =
+                    EXPENSE_CATEGORIES[category % EXPENSE_CATEGORIES.len()].to_lowercase();
+                let amount = Decimal::new(rng.between(150, 20_000) as i64, 2);
+                let source = if rng.between(0, 3) == 0 {
+                    checking -= amount;
+                    CHECKING
+                } else {
+                    CREDIT_CARD
+                };
+                write_transaction(
+                    &mut ledger,
+                    date,
+                    &payee_name(payee),
+                    &narration,
+                    &[(&expense_account(category), Some(amount)), (source, None)],
+                );
+            }
+
+            for (commodity, price) in commodities.iter().zip(prices.iter_mut()) {
+                // a random walk of up to 2% a day
+                let change = Decimal::new(rng.between(0, 401) as i64 - 200, 4);
+                *price = (*price + *price * change).round_dp(2).max(Decimal::ONE);
+                writeln!(
+                    ledger,
+                    "{} price {} {} {}",
+                    date, commodity, price, CURRENCY
+                )
+                .unwrap();
+            }
+            if !commodities.is_empty() {
+                ledger.push('\n');
+            }
+
+            if date.day() == 15 {
+                for (commodity, price) in commodities.iter().zip(prices.iter()) {
+                    let units = Decimal::from(rng.between(1, 10));
+                    let cost = units * *price;
+                    checking -= cost;
+                    writeln!(
+                        ledger,
+                        "{} * \"Broker\" \"buy {}\"\n  {}  {} {} {{{} {}}}\n  {}\n",
+                        date, commodity, BROKERAGE, units, commodity, price, CURRENCY, CHECKING,
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        ledger
+    }
+
+    /// Generate the ledger, writing it to `w`.
+    pub fn write<W>(&self, mut w: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        w.write_all(self.generate().as_bytes())
+    }
+
+    fn write_declarations(&self, ledger: &mut String, commodities: &[String]) {
+        let start = self.start;
+
+        writeln!(
+            ledger,
+            "option \"title\" \"Synthetic ledger, seed {}\"\noption \"operating_currency\" \"{}\"\n",
+            self.seed, CURRENCY
+        )
+        .unwrap();
+
+        for commodity in commodities {
+            writeln!(ledger, "{} commodity {}", start, commodity).unwrap();
+        }
+        if !commodities.is_empty() {
+            ledger.push('\n');
+        }
+
+        for account in [
+            CHECKING,
+            CREDIT_CARD,
+            BROKERAGE,
+            "Income:Salary",
+            "Expenses:Rent",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .chain((0..self.expense_accounts).map(expense_account))
+        {
+            writeln!(ledger, "{} open {}", start, account).unwrap();
+        }
+        ledger.push('\n');
+    }
+}
+
+fn write_transaction(
+    ledger: &mut String,
+    date: Date,
+    payee: &str,
+    narration: &str,
+    postings: &[(&str, Option<Decimal>)],
+) {
+    writeln!(ledger, "{} * \"{}\" \"{}\"", date, payee, narration).unwrap();
+    for (account, amount) in postings {
+        match amount {
+            Some(amount) => writeln!(ledger, "  {}  {} {}", account, amount, CURRENCY).unwrap(),
+            None => writeln!(ledger, "  {}", account).unwrap(),
+        }
+    }
+    ledger.push('\n');
+}
+
+fn expense_account(i: usize) -> String {
+    let category = EXPENSE_CATEGORIES[i % EXPENSE_CATEGORIES.len()];
+    match i / EXPENSE_CATEGORIES.len() {
+        0 => format!("Expenses:{}", category),
+        n => format!("Expenses:{}:Other{}", category, n),
+    }
+}
+
+fn payee_name(i: usize) -> String {
+    let word = PAYEE_WORDS[i % PAYEE_WORDS.len()];
+    let kind = PAYEE_KINDS[(i / PAYEE_WORDS.len()) % PAYEE_KINDS.len()];
+    match i / (PAYEE_WORDS.len() * PAYEE_KINDS.len()) {
+        0 => format!("{} {}", word, kind),
+        n => format!("{} {} {}", word, kind, n + 1),
+    }
+}
+
+// names of three or more letters, as for stock tickers
+fn commodity_name(i: usize) -> String {
+    let mut name = String::new();
+    let mut n = i;
+    for _ in 0..3 {
+        name.insert(0, (b'A' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    while n > 0 {
+        name.insert(0, (b'A' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    name
+}
+
+// splitmix64, which is plenty for synthetic data, and keeps generation reproducible across platforms
+#[derive(Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // in the range `low..high`
+    fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low)
+    }
+
+    // in the range `0.0..1.0`
+    fn fraction(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+mod tests;
@@ -0,0 +1,86 @@
+#![cfg(test)]
+use super::*;
+use crate::{trial_balance, BeancountParser, BeancountSources, DirectiveVariant};
+
+#[test]
+fn generated_ledger_parses() {
+    let ledger = SyntheticLedger::default().days(90).generate();
+    let sources = BeancountSources::from(ledger);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let count = |kind: fn(&DirectiveVariant) -> bool| {
+        directives
+            .iter()
+            .filter(|directive| kind(directive.variant()))
+            .count()
+    };
+
+    // 90 days from 2024-01-01 covers 3 months
+    assert_eq!(count(|d| matches!(d, DirectiveVariant::Open(_))), 17);
+    assert_eq!(count(|d| matches!(d, DirectiveVariant::Commodity(_))), 3);
+    assert_eq!(count(|d| matches!(d, DirectiveVariant::Balance(_))), 3);
+    assert_eq!(count(|d| matches!(d, DirectiveVariant::Price(_))), 90 * 3);
+    assert_eq!(
+        count(|d| matches!(d, DirectiveVariant::Transaction(_))),
+        90 * 3 + 3 * 2 + 3 * 3
+    );
+}
+
+#[test]
+fn balance_assertions_hold() {
+    let ledger = SyntheticLedger::default().days(120).seed(7).generate();
+    let sources = BeancountSources::from(ledger);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    for directive in directives.iter() {
+        if let DirectiveVariant::Balance(balance) = directive.variant() {
+            let date = *directive.date().item();
+            let amount = balance.atol().amount();
+            let totals = trial_balance(directives.iter(), date.previous_day().unwrap()).unwrap();
+            let actual = totals
+                .get(balance.account().item())
+                .map(|totals| totals.total().get(amount.currency().item()))
+                .unwrap_or_default();
+
+            assert_eq!(actual, amount.number().value(), "balance on {}", date);
+        }
+    }
+}
+
+#[test]
+fn deterministic_for_seed() {
+    let ledger = SyntheticLedger::default().days(30);
+
+    assert_eq!(
+        ledger.clone().seed(1).generate(),
+        ledger.clone().seed(1).generate()
+    );
+    assert_ne!(ledger.clone().seed(1).generate(), ledger.seed(2).generate());
+}
+
+#[test]
+fn payees_skewed() {
+    let ledger = SyntheticLedger::default()
+        .days(100)
+        .payees(20)
+        .commodities(0)
+        .generate();
+    let frequency = |payee: &str| ledger.matches(&format!("\"{}\"", payee)).count();
+
+    assert!(frequency(&payee_name(0)) > 4 * frequency(&payee_name(19)));
+    assert_eq!(frequency(&payee_name(20)), 0);
+}
+
+#[test]
+fn names() {
+    assert_eq!(commodity_name(0), "AAA");
+    assert_eq!(commodity_name(27), "ABB");
+    assert_eq!(commodity_name(26 * 26 * 26), "BAAA");
+    assert_eq!(expense_account(1), "Expenses:Dining");
+    assert_eq!(expense_account(13), "Expenses:Dining:Other1");
+    assert_eq!(payee_name(0), "Corner Store");
+    assert_eq!(payee_name(11), "Market Cafe");
+    assert_eq!(payee_name(80), "Corner Store 2");
+}
@@ -0,0 +1,99 @@
+use crate::types::*;
+use std::collections::HashMap;
+
+/// The directives with each tag and link, for filtering by tag or link without scanning every directive.
+///
+/// A transaction is indexed under the tags and links of its postings as well as its own,
+/// and is listed only once under each, however many times it has that tag or link.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, Tag, TagLinkIndex};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-02 * "Countdown" "groceries" #food
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// 2024-01-03 note Assets:Bank "fees refunded" #food
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let index = TagLinkIndex::new(&directives);
+///
+/// let food = Tag::try_from("food").unwrap();
+/// assert_eq!(index.with_tag(&food).len(), 2);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct TagLinkIndex<'a> {
+    tags: HashMap<Tag<'a>, Vec<&'a Spanned<Directive<'a>>>>,
+    links: HashMap<Link<'a>, Vec<&'a Spanned<Directive<'a>>>>,
+}
+
+impl<'a> TagLinkIndex<'a> {
+    /// Index the tags and links of `directives`, which are listed for each tag or link in the order given.
+    pub fn new<I>(directives: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    {
+        let mut index = TagLinkIndex::default();
+
+        for directive in directives {
+            let postings = match directive.variant() {
+                DirectiveVariant::Transaction(transaction) => transaction.postings.as_slice(),
+                _ => &[],
+            };
+            let metadata = std::iter::once(&directive.metadata)
+                .chain(postings.iter().map(|posting| &posting.metadata));
+
+            for metadata in metadata {
+                for tag in metadata.tags() {
+                    add(&mut index.tags, *tag.item(), directive);
+                }
+                for link in metadata.links() {
+                    add(&mut index.links, *link.item(), directive);
+                }
+            }
+        }
+
+        index
+    }
+
+    /// The directives with `tag`.
+    pub fn with_tag(&self, tag: &Tag<'a>) -> &[&'a Spanned<Directive<'a>>] {
+        self.tags.get(tag).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The directives with `link`.
+    pub fn with_link(&self, link: &Link<'a>) -> &[&'a Spanned<Directive<'a>>] {
+        self.links.get(link).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// All the tags, in no particular order.
+    pub fn tags(&self) -> impl ExactSizeIterator<Item = Tag<'a>> + '_ {
+        self.tags.keys().copied()
+    }
+
+    /// All the links, in no particular order.
+    pub fn links(&self) -> impl ExactSizeIterator<Item = Link<'a>> + '_ {
+        self.links.keys().copied()
+    }
+}
+
+// add `directive` under `key`, unless it was the last one added there
+fn add<'a, K>(
+    index: &mut HashMap<K, Vec<&'a Spanned<Directive<'a>>>>,
+    key: K,
+    directive: &'a Spanned<Directive<'a>>,
+) where
+    K: Eq + std::hash::Hash,
+{
+    let directives = index.entry(key).or_default();
+    if !directives
+        .last()
+        .is_some_and(|last| std::ptr::eq(*last, directive))
+    {
+        directives.push(directive);
+    }
+}
+
+mod tests;
@@ -0,0 +1,47 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources};
+
+#[test]
+fn tag_link_index() {
+    let sources = BeancountSources::from(
+        r#"2024-01-01 open Assets:Bank
+2024-01-02 * "Countdown" "groceries" #food ^receipt-1
+  Assets:Bank  -42.50 NZD
+    #food
+  Expenses:Groceries
+    #household
+2024-01-03 document Assets:Bank "receipt.pdf" ^receipt-1
+2024-01-04 note Assets:Bank "no tags"
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let index = TagLinkIndex::new(&directives);
+
+    let dates = |directives: &[&Spanned<Directive>]| {
+        directives
+            .iter()
+            .map(|directive| directive.date().item().day())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        dates(index.with_tag(&Tag::try_from("food").unwrap())),
+        vec![2]
+    );
+    assert_eq!(
+        dates(index.with_tag(&Tag::try_from("household").unwrap())),
+        vec![2]
+    );
+    assert_eq!(
+        dates(index.with_link(&Link::try_from("receipt-1").unwrap())),
+        vec![2, 3]
+    );
+    assert!(index.with_tag(&Tag::try_from("absent").unwrap()).is_empty());
+
+    let mut tags = index.tags().map(|tag| tag.to_string()).collect::<Vec<_>>();
+    tags.sort();
+    assert_eq!(tags, vec!["#food", "#household"]);
+    assert_eq!(index.links().len(), 1);
+}
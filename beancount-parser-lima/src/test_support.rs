@@ -0,0 +1,8 @@
+//! Helpers shared by the unit tests.
+#![cfg(test)]
+use time::{format_description::well_known::Iso8601, Date};
+
+/// The date in ISO 8601 format, such as `2024-03-01`.
+pub(crate) fn date(s: &str) -> Date {
+    Date::parse(s, &Iso8601::DATE).unwrap()
+}
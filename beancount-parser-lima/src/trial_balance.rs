@@ -0,0 +1,409 @@
+use crate::{interpolation::interpolate, prices::PriceDb, sort::sort_key, types::*, Options};
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+use time::Date;
+
+/// Compute the totals for every account as of `date`, inclusive, as a [TrialBalance].
+///
+/// The units of any posting without an amount are interpolated so that its transaction balances,
+/// and `pad` directives are expanded according to the next `balance` for each currency in the padded account.
+/// The result is a tree following the account hierarchy.
+pub fn trial_balance<'a, I>(directives: I, date: Date) -> Result<TrialBalance<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    accumulate(directives, date, |_, _, _| None)
+}
+
+/// Check every `balance` directive against the total of its account and subaccounts, accumulated as for [trial_balance],
+/// with any difference beyond the assertion's [Tolerance] being an error.
+///
+/// Extended assertions restricted to a lot, see [Balance::cost_spec], are not checked.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{check_balances, BeancountParser, BeancountSources};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Equity:Opening
+/// 2024-01-02 * "deposit"
+///   Assets:Bank  100.004 NZD
+///   Equity:Opening
+/// 2024-01-03 balance Assets:Bank 100.00 NZD
+/// 2024-01-03 balance Assets:Bank 100 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let success = parser.parse().unwrap();
+/// let errors = check_balances(success.directives.iter(), &success.options).unwrap_err();
+///
+/// // only the second fails, the first being within the tolerance inferred from its precision
+/// assert_eq!(errors.len(), 1);
+/// ```
+pub fn check_balances<'a, I>(directives: I, options: &Options<'_>) -> Result<(), Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    accumulate(
+        directives,
+        Date::MAX,
+        |directive, balance, trial_balance| {
+            let amount = balance.atol().amount();
+            let (expected, currency) = (amount.number().value(), *amount.currency().item());
+            let accumulated = trial_balance
+                .get(balance.account().item())
+                .map(|totals| totals.total().get(&currency))
+                .unwrap_or_default();
+            let difference = accumulated - expected;
+
+            (!balance.tolerance(options).allows(difference)).then(|| {
+                Error::new(
+                    "balance failed",
+                    format!(
+                        "expected {} {} but accumulated {} {}, {} too {}",
+                        expected,
+                        currency,
+                        accumulated,
+                        currency,
+                        difference.abs(),
+                        if difference.is_sign_negative() {
+                            "little"
+                        } else {
+                            "much"
+                        }
+                    ),
+                    *balance.atol().span(),
+                )
+                .in_context(directive)
+            })
+        },
+    )
+    .map(|_| ())
+}
+
+// the totals as of `date`, calling `check` with each balance directive and the totals to that point, after any padding,
+// for an error to report
+fn accumulate<'a, I, F>(
+    directives: I,
+    date: Date,
+    mut check: F,
+) -> Result<TrialBalance<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+    F: FnMut(&'a Spanned<Directive<'a>>, &'a Balance<'a>, &TrialBalance<'a>) -> Option<Error>,
+{
+    use DirectiveVariant::*;
+
+    // in Beancount's order, so that balance assertions apply at the beginning of the day
+    let mut directives = directives
+        .into_iter()
+        .filter(|directive| *directive.date().item() <= date)
+        .collect::<Vec<_>>();
+    directives.sort_by_key(|directive| sort_key(directive));
+
+    let mut trial_balance = TrialBalance {
+        date,
+        roots: BTreeMap::new(),
+    };
+    let mut pads = Vec::<PendingPad>::new();
+    let mut errors = Vec::new();
+
+    for directive in directives {
+        match directive.variant() {
+            Transaction(transaction) => match interpolate(transaction) {
+                Ok(units) => {
+                    for units in units {
+                        trial_balance.add(units.posting.account(), units.currency, units.number);
+                    }
+                }
+                Err(e) => errors.push(e.in_context(directive)),
+            },
+
+            Pad(pad) => {
+                pads.retain(|pending| pending.account != pad.account().item());
+                pads.push(PendingPad {
+                    account: pad.account().item(),
+                    source: pad.source().item(),
+                    used: Vec::new(),
+                });
+            }
+
+            // totals are not kept by lot, so an assertion restricted to a lot is neither padded nor checked
+            Balance(balance) if balance.cost_spec().is_some() => (),
+
+            Balance(balance) => {
+                let account = balance.account().item();
+                let amount = balance.atol().amount();
+                let currency = *amount.currency().item();
+
+                if let Some(pad) = pads
+                    .iter_mut()
+                    .find(|pending| pending.account == account)
+                    .filter(|pending| !pending.used.contains(&currency))
+                {
+                    let current = trial_balance
+                        .get(account)
+                        .map(|totals| totals.total().get(&currency))
+                        .unwrap_or_default();
+                    let padding = amount.number().value() - current;
+                    if !padding.is_zero() {
+                        trial_balance.add(pad.account, currency, padding);
+                        trial_balance.add(pad.source, currency, -padding);
+                    }
+                    pad.used.push(currency);
+                }
+
+                errors.extend(check(directive, balance, &trial_balance));
+            }
+
+            _ => (),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(trial_balance)
+    } else {
+        Err(errors)
+    }
+}
+
+struct PendingPad<'a> {
+    account: &'a Account<'a>,
+    source: &'a Account<'a>,
+    used: Vec<Currency<'a>>,
+}
+
+/// Totals for every account as of a date, in a tree following the account hierarchy, see [trial_balance].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TrialBalance<'a> {
+    date: Date,
+    roots: BTreeMap<AccountType, AccountTotals<'a>>,
+}
+
+impl<'a> TrialBalance<'a> {
+    /// Field accessor.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// The top-level accounts, in order of account type.
+    pub fn roots(&self) -> impl Iterator<Item = (AccountType, &AccountTotals<'a>)> {
+        self.roots
+            .iter()
+            .map(|(account_type, totals)| (*account_type, totals))
+    }
+
+    /// The totals for the given account, if there were any postings to it or its subaccounts.
+    pub fn get(&self, account: &Account<'_>) -> Option<&AccountTotals<'a>> {
+        account
+            .names()
+            .iter()
+            .try_fold(self.roots.get(&account.account_type())?, |node, name| {
+                node.children.get(name.as_ref())
+            })
+    }
+
+    /// The grand total over all accounts, which is empty if everything balances.
+    pub fn total(&self) -> Units<'a> {
+        let mut total = Units::default();
+        for root in self.roots.values() {
+            total.add_all(&root.total());
+        }
+        total
+    }
+
+    /// Convert all units into `currency` using `prices` as of the date of the trial balance,
+    /// leaving unchanged any units for which there is no price.
+    pub fn convert(&self, prices: &PriceDb<'a>, currency: Currency<'a>) -> TrialBalance<'a> {
+        TrialBalance {
+            date: self.date,
+            roots: self
+                .roots
+                .iter()
+                .map(|(account_type, totals)| {
+                    (*account_type, totals.convert(prices, currency, self.date))
+                })
+                .collect(),
+        }
+    }
+
+    fn add(&mut self, account: &'a Account<'a>, currency: Currency<'a>, number: Decimal) {
+        self.node(account.account_type(), account.names())
+            .units
+            .add(currency, number);
+    }
+
+    pub(crate) fn empty(date: Date) -> Self {
+        TrialBalance {
+            date,
+            roots: BTreeMap::new(),
+        }
+    }
+
+    // the node for the account, created if necessary
+    fn node(
+        &mut self,
+        account_type: AccountType,
+        names: &[AccountName<'a>],
+    ) -> &mut AccountTotals<'a> {
+        names
+            .iter()
+            .fold(self.roots.entry(account_type).or_default(), |node, name| {
+                node.children.entry(*name).or_default()
+            })
+    }
+
+    // add `units` to the account, negated if `negate`
+    pub(crate) fn add_units(
+        &mut self,
+        account_type: AccountType,
+        names: &[AccountName<'a>],
+        units: &Units<'a>,
+        negate: bool,
+    ) {
+        let node = self.node(account_type, names);
+        for (currency, number) in units.iter() {
+            node.units
+                .add(*currency, if negate { -*number } else { *number });
+        }
+    }
+
+    // remove accounts whose total and those of all their subaccounts are zero
+    pub(crate) fn prune(&mut self) {
+        self.roots.retain(|_, totals| !totals.prune());
+    }
+
+    // add the totals for every account of `account_type` in `other`, negated if `negate`
+    pub(crate) fn add_root(
+        &mut self,
+        account_type: AccountType,
+        other: &TrialBalance<'a>,
+        negate: bool,
+    ) {
+        fn add_subtree<'a>(
+            trial_balance: &mut TrialBalance<'a>,
+            account_type: AccountType,
+            names: &mut Vec<AccountName<'a>>,
+            totals: &AccountTotals<'a>,
+            negate: bool,
+        ) {
+            trial_balance.add_units(account_type, names, &totals.units, negate);
+            for (name, child) in totals.children.iter() {
+                names.push(*name);
+                add_subtree(trial_balance, account_type, names, child, negate);
+                names.pop();
+            }
+        }
+
+        if let Some(totals) = other.roots.get(&account_type) {
+            add_subtree(self, account_type, &mut Vec::new(), totals, negate);
+        }
+    }
+}
+
+/// The totals for an account and its subaccounts.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct AccountTotals<'a> {
+    units: Units<'a>,
+    children: BTreeMap<AccountName<'a>, AccountTotals<'a>>,
+}
+
+impl<'a> AccountTotals<'a> {
+    /// The units posted to this account itself, excluding subaccounts.
+    pub fn units(&self) -> &Units<'a> {
+        &self.units
+    }
+
+    /// The units posted to this account and all its subaccounts.
+    pub fn total(&self) -> Units<'a> {
+        let mut total = self.units.clone();
+        for child in self.children.values() {
+            total.add_all(&child.total());
+        }
+        total
+    }
+
+    /// The immediate subaccounts, in order of name.
+    pub fn children(&self) -> impl Iterator<Item = (&AccountName<'a>, &AccountTotals<'a>)> {
+        self.children.iter()
+    }
+
+    // prune subaccounts as for TrialBalance::prune, returning whether this account is now empty
+    fn prune(&mut self) -> bool {
+        self.children.retain(|_, child| !child.prune());
+        self.units.is_empty() && self.children.is_empty()
+    }
+
+    fn convert(&self, prices: &PriceDb<'a>, currency: Currency<'a>, date: Date) -> Self {
+        let mut units = Units::default();
+        for (c, number) in self.units.iter() {
+            match prices.convert(*c, *number, currency, date) {
+                Some(converted) => units.add(currency, converted),
+                None => units.add(*c, *number),
+            }
+        }
+
+        AccountTotals {
+            units,
+            children: self
+                .children
+                .iter()
+                .map(|(name, child)| (*name, child.convert(prices, currency, date)))
+                .collect(),
+        }
+    }
+}
+
+/// Numbers of units in each of several currencies, omitting any which are zero.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct Units<'a>(BTreeMap<Currency<'a>, Decimal>);
+
+impl<'a> Units<'a> {
+    /// The number of units of `currency`, which is zero if there are none.
+    pub fn get(&self, currency: &Currency<'_>) -> Decimal {
+        self.0
+            .iter()
+            .find_map(|(c, number)| (c == currency).then_some(*number))
+            .unwrap_or_default()
+    }
+
+    /// The currencies and numbers of units, in order of currency.
+    pub fn iter(&self) -> impl Iterator<Item = (&Currency<'a>, &Decimal)> {
+        self.0.iter()
+    }
+
+    /// Whether there are no units.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, currency: Currency<'a>, number: Decimal) {
+        let total = self.0.entry(currency).or_default();
+        *total += number;
+        if total.is_zero() {
+            self.0.remove(&currency);
+        }
+    }
+
+    pub(crate) fn add_all(&mut self, other: &Units<'a>) {
+        for (currency, number) in other.iter() {
+            self.add(*currency, *number);
+        }
+    }
+}
+
+impl<'a> Display for Units<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (currency, number)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} {}", number, currency)?;
+        }
+        Ok(())
+    }
+}
+
+mod tests;
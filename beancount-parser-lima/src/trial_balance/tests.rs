@@ -0,0 +1,221 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+
+fn check_trial_balance<F>(source: &str, date: Date, check: F)
+where
+    F: FnOnce(TrialBalance<'_>, PriceDb<'_>),
+{
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    check(
+        trial_balance(directives.iter(), date).unwrap(),
+        PriceDb::new(directives.iter()),
+    );
+}
+
+fn account(s: &str) -> Account<'_> {
+    let mut names = s.split(':');
+    let account_type = names.next().unwrap().parse().unwrap();
+    let names = names.map(|name| AccountName::try_from(name).unwrap());
+    Account::new(account_type, names.collect())
+}
+
+fn currency(s: &str) -> Currency<'_> {
+    Currency::try_from(s).unwrap()
+}
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank:Current
+2024-01-01 open Assets:Bank:Savings
+2024-01-01 open Expenses:Food
+2024-01-01 open Equity:Opening-Balances
+
+2024-01-01 pad Assets:Bank:Current Equity:Opening-Balances
+2024-01-02 balance Assets:Bank:Current 100.00 GBP
+
+2024-01-05 * "Groceries"
+  Expenses:Food  25.00 GBP
+  Assets:Bank:Current
+
+2024-02-01 * "Transfer"
+  Assets:Bank:Savings  50.00 GBP
+  Assets:Bank:Current  -50.00 GBP
+
+2024-02-01 price GBP 1.25 USD
+"#;
+
+#[test]
+fn trial_balance_tree() {
+    check_trial_balance(LEDGER, date("2024-01-31"), |trial_balance, _| {
+        let gbp = currency("GBP");
+
+        let bank = trial_balance.get(&account("Assets:Bank")).unwrap();
+        assert!(bank.units().is_empty());
+        assert_eq!(bank.total().get(&gbp), dec!(75.00));
+        assert_eq!(
+            bank.children()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Current"]
+        );
+        assert_eq!(
+            trial_balance
+                .get(&account("Equity:Opening-Balances"))
+                .unwrap()
+                .units()
+                .get(&gbp),
+            dec!(-100.00)
+        );
+        assert_eq!(
+            trial_balance
+                .get(&account("Expenses:Food"))
+                .unwrap()
+                .units()
+                .to_string(),
+            "25.00 GBP"
+        );
+        assert!(trial_balance.get(&account("Assets:Bank:Savings")).is_none());
+        assert!(trial_balance.total().is_empty());
+    });
+}
+
+#[test]
+fn trial_balance_as_of_date() {
+    check_trial_balance(LEDGER, date("2024-02-01"), |trial_balance, _| {
+        let gbp = currency("GBP");
+
+        assert_eq!(
+            trial_balance
+                .get(&account("Assets:Bank:Current"))
+                .unwrap()
+                .units()
+                .get(&gbp),
+            dec!(25.00)
+        );
+        assert_eq!(
+            trial_balance
+                .get(&account("Assets:Bank:Savings"))
+                .unwrap()
+                .units()
+                .get(&gbp),
+            dec!(50.00)
+        );
+        assert_eq!(
+            trial_balance
+                .roots()
+                .map(|(account_type, _)| account_type)
+                .collect::<Vec<_>>(),
+            vec![
+                AccountType::Assets,
+                AccountType::Equity,
+                AccountType::Expenses
+            ]
+        );
+    });
+}
+
+#[test]
+fn trial_balance_converted() {
+    check_trial_balance(LEDGER, date("2024-02-01"), |trial_balance, prices| {
+        let converted = trial_balance.convert(&prices, currency("USD"));
+
+        assert_eq!(
+            converted
+                .get(&account("Assets:Bank"))
+                .unwrap()
+                .total()
+                .to_string(),
+            "93.7500 USD"
+        );
+    });
+}
+
+fn balance_failures(source: &str) -> Vec<String> {
+    let sources = BeancountSources::from(source);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+
+    match check_balances(success.directives.iter(), &success.options) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.iter().map(|e| e.reason.to_string()).collect(),
+    }
+}
+
+#[test]
+fn check_balances_with_padding() {
+    let source = format!("{}2024-02-02 balance Assets:Bank 125.00 GBP\n", LEDGER);
+
+    // the parent account includes its subaccounts
+    assert_eq!(
+        balance_failures(&source),
+        vec!["expected 125.00 GBP but accumulated 75.00 GBP, 50.00 too little"]
+    );
+    assert!(balance_failures(LEDGER).is_empty());
+}
+
+#[test]
+fn check_balances_tolerance() {
+    let source = r#"
+option "inferred_tolerance_default" "USD:0.05"
+2024-01-01 open Assets:Bank
+2024-01-01 open Equity:Opening
+2024-01-02 * "deposit"
+  Assets:Bank  100.03 USD
+  Assets:Bank  100.03 GBP
+  Equity:Opening
+2024-01-03 balance Assets:Bank 100 USD
+2024-01-03 balance Assets:Bank 100.0 GBP
+2024-01-03 balance Assets:Bank 100.00 GBP
+2024-01-03 balance Assets:Bank 100.00 ~ 0.01 USD
+"#;
+
+    assert_eq!(
+        balance_failures(source),
+        vec![
+            "expected 100.00 GBP but accumulated 100.03 GBP, 0.03 too much",
+            "expected 100.00 USD but accumulated 100.03 USD, 0.03 too much",
+        ]
+    );
+}
+
+#[test]
+fn balance_tolerance() {
+    let sources = BeancountSources::from(
+        r#"
+option "inferred_tolerance_default" "USD:0.05"
+2024-01-03 balance Assets:Bank 100 USD
+2024-01-03 balance Assets:Bank 100.00 GBP
+2024-01-03 balance Assets:Bank 100 GBP
+2024-01-03 balance Assets:Bank 100.00 ~ 0.01 USD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let tolerances = success
+        .directives
+        .iter()
+        .map(|directive| match directive.variant() {
+            DirectiveVariant::Balance(balance) => balance.tolerance(&success.options),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        tolerances,
+        vec![
+            Tolerance::Default(dec!(0.05)),
+            Tolerance::Inferred(dec!(0.01)),
+            Tolerance::Inferred(dec!(0)),
+            Tolerance::Explicit(dec!(0.01)),
+        ]
+    );
+    assert_eq!(
+        success.directives[3].to_string(),
+        "2024-01-03 balance Assets:Bank 100.00 ~ 0.01 USD"
+    );
+}
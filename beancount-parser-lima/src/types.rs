@@ -1,20 +1,29 @@
-use crate::{format::*, options::BeancountOption};
+use crate::{
+    fixes::Fix,
+    format::*,
+    options::BeancountOption,
+    price_requests::{parse_price_metadata, PriceSource},
+    Options,
+};
 use chumsky::{
     extra::ParserExtra,
     input::{Input, MapExtra},
 };
+use lazy_format::lazy_format;
 use rust_decimal::Decimal;
 use smallvec::SmallVec;
 use std::marker::PhantomData;
 use std::{
-    cmp::max,
-    collections::{HashMap, HashSet},
+    borrow::Borrow,
+    cmp::{max, Ordering},
+    collections::{hash_map::DefaultHasher, HashSet},
     fmt::{self, Display, Formatter},
     hash::{Hash, Hasher},
     iter::empty,
     mem::swap,
-    ops::Deref,
+    ops::{Add, Deref, DerefMut, Mul, Neg, Sub},
 };
+use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
 use time::Date;
 
@@ -24,11 +33,13 @@ pub struct ErrorOrWarning<K>
 where
     K: ErrorOrWarningKind,
 {
-    pub(crate) message: String,
-    pub(crate) reason: String,
+    // boxed strings and fixes keep errors small, since they are returned in a `Result` throughout
+    pub(crate) message: Box<str>,
+    pub(crate) reason: Box<str>,
     pub(crate) span: Span,
     pub(crate) contexts: Vec<(String, Span)>,
     pub(crate) related: Vec<(String, Span)>,
+    pub(crate) fixes: Box<[Fix]>,
     kind: PhantomData<K>,
 }
 
@@ -51,11 +62,12 @@ pub type Warning = ErrorOrWarning<WarningKind>;
 impl Error {
     pub(crate) fn new<M: Into<String>, R: Into<String>>(message: M, reason: R, span: Span) -> Self {
         ErrorOrWarning {
-            message: message.into(),
-            reason: reason.into(),
+            message: message.into().into_boxed_str(),
+            reason: reason.into().into_boxed_str(),
             span,
             contexts: Vec::new(),
             related: Vec::new(),
+            fixes: Box::default(),
             kind: PhantomData,
         }
     }
@@ -67,11 +79,12 @@ impl Error {
         contexts: Vec<(String, Span)>,
     ) -> Self {
         ErrorOrWarning {
-            message: message.into(),
-            reason: reason.into(),
+            message: message.into().into_boxed_str(),
+            reason: reason.into().into_boxed_str(),
             span,
             contexts,
             related: Vec::new(),
+            fixes: Box::default(),
             kind: PhantomData,
         }
     }
@@ -80,11 +93,12 @@ impl Error {
 impl Warning {
     pub(crate) fn new<M: Into<String>, R: Into<String>>(message: M, reason: R, span: Span) -> Self {
         ErrorOrWarning {
-            message: message.into(),
-            reason: reason.into(),
+            message: message.into().into_boxed_str(),
+            reason: reason.into().into_boxed_str(),
             span,
             contexts: Vec::new(),
             related: Vec::new(),
+            fixes: Box::default(),
             kind: PhantomData,
         }
     }
@@ -119,8 +133,29 @@ where
         e
     }
 
+    /// Attach a machine-applicable fix.
+    pub fn with_fix(self, fix: Fix) -> Self {
+        let mut e = self;
+        let mut fixes = std::mem::take(&mut e.fixes).into_vec();
+        fixes.push(fix);
+        e.fixes = fixes.into_boxed_slice();
+        e
+    }
+
     pub fn message(&self) -> &str {
-        self.message.as_str()
+        &self.message
+    }
+
+    /// The source in which this occurred.
+    pub fn source_id(&self) -> SourceId {
+        use chumsky::span::Span;
+
+        self.span.context()
+    }
+
+    /// The fixes offered, if any.
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
     }
 
     pub(crate) fn related_to_named_span<S>(self, name: S, span: Span) -> Self
@@ -202,7 +237,9 @@ impl ErrorOrWarningKind for WarningKind {
 }
 
 /// Top-level account type, the prefix of any fully-qualified [Account].
-#[derive(PartialEq, Eq, Hash, Clone, Copy, EnumString, EnumIter, IntoStaticStr, Debug)]
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, EnumString, EnumIter, IntoStaticStr, Debug,
+)]
 pub enum AccountType {
     Assets,
     Liabilities,
@@ -218,7 +255,7 @@ impl AsRef<str> for AccountType {
 }
 
 /// A flag on a [Posting] or [Transaction].
-#[derive(PartialEq, Eq, Default, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Default, Clone, Copy, Debug)]
 pub enum Flag {
     #[default]
     Asterisk,
@@ -257,7 +294,7 @@ impl Display for Flag {
 }
 
 /// A flag other than one of the builtin ones.
-#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 pub struct FlagLetter(char);
 
 impl FlagLetter {
@@ -269,8 +306,16 @@ impl FlagLetter {
     pub(crate) fn is_valid(c: &char) -> bool {
         c.is_ascii_uppercase()
     }
+
+    /// Whether the flag letter is one of those recognised by Python Beancount v2.
+    pub(crate) fn is_python_v2(&self) -> bool {
+        PYTHON_V2_FLAG_LETTERS.contains(&self.0)
+    }
 }
 
+/// The only flag letters recognised by Python Beancount v2.
+const PYTHON_V2_FLAG_LETTERS: [char; 7] = ['P', 'S', 'T', 'C', 'U', 'R', 'M'];
+
 /// Error type for invalid [FlagLetter].
 #[derive(PartialEq, Eq, Debug)]
 pub struct FlagLetterError(char);
@@ -301,7 +346,7 @@ impl TryFrom<char> for FlagLetter {
 
 /// The booking method for an account.
 #[derive(
-    EnumString, EnumIter, IntoStaticStr, PartialEq, Eq, Default, Clone, Copy, Display, Debug,
+    EnumString, EnumIter, IntoStaticStr, PartialEq, Eq, Hash, Default, Clone, Copy, Display, Debug,
 )]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 pub enum Booking {
@@ -327,6 +372,39 @@ impl ElementType for Booking {
     }
 }
 
+impl Booking {
+    /// The booking method most likely intended by `s`, if any is close enough, for suggesting a correction.
+    pub(crate) fn closest(s: &str) -> Option<Booking> {
+        let normalized = s.trim().to_ascii_uppercase().replace(['-', ' '], "_");
+
+        Booking::iter()
+            .map(|booking| (edit_distance(&normalized, booking.as_ref()), booking))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, booking)| booking)
+    }
+}
+
+// the Levenshtein distance between `a` and `b`, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            current.push(
+                (previous[j] + usize::from(ca != *cb))
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
 #[derive(
     EnumString, EnumIter, IntoStaticStr, PartialEq, Eq, Default, Clone, Copy, Display, Debug,
 )]
@@ -379,6 +457,13 @@ impl<T> Deref for Spanned<T> {
     }
 }
 
+/// Spanned item may be modified in place, keeping its span.
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.item
+    }
+}
+
 pub(crate) fn spanned<T>(item: T, span: Span) -> Spanned<T> {
     Spanned { item, span }
 }
@@ -424,6 +509,22 @@ impl<T> Spanned<T> {
             span: self.span,
         }
     }
+
+    /// Converts from `&Spanned<T>` to `Spanned<&T::Target>`, as for `Spanned<String>` to `Spanned<&str>`.
+    pub fn as_deref(&self) -> Spanned<&T::Target>
+    where
+        T: Deref,
+    {
+        Spanned {
+            item: self.item.deref(),
+            span: self.span,
+        }
+    }
+
+    /// Consumes the spanned item, returning the item and its span.
+    pub fn into_item_and_span(self) -> (T, Span) {
+        (self.item, self.span)
+    }
 }
 
 impl<T> Spanned<T>
@@ -483,12 +584,144 @@ where
 /// convenenience trait for dealing with Option<&Spanned>
 pub trait OptionalItem<T> {
     fn item(&self) -> Option<&T>;
+
+    fn span(&self) -> Option<Span>;
+
+    fn item_and_span(&self) -> Option<(&T, Span)>;
 }
 
 impl<T> OptionalItem<T> for Option<&Spanned<T>> {
     fn item(&self) -> Option<&T> {
         self.map(|spanned| spanned.item())
     }
+
+    fn span(&self) -> Option<Span> {
+        self.map(|spanned| spanned.span)
+    }
+
+    fn item_and_span(&self) -> Option<(&T, Span)> {
+        self.map(|spanned| (&spanned.item, spanned.span))
+    }
+}
+
+/// Implemented by anything with tags and links, for generic filtering across all kinds of directive and postings.
+///
+/// Tags and links belong to the metadata of a [Directive] rather than its [DirectiveVariant],
+/// so are available uniformly for every kind of directive.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, HasTagsLinks};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-02-01 note Assets:Bank "call about fees" #todo
+/// 2024-02-02 document Assets:Bank "statement.pdf" #todo ^fees
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+///
+/// fn tagged<'a, T: HasTagsLinks<'a>>(items: &'a [T], tag: &str) -> usize {
+///     items.iter().filter(|item| item.has_tag(tag)).count()
+/// }
+///
+/// assert_eq!(tagged(&directives, "todo"), 2);
+/// assert!(directives[2].has_link("fees"));
+/// ```
+pub trait HasTagsLinks<'a> {
+    /// The metadata comprising the tags and links.
+    fn metadata(&self) -> &Metadata<'a>;
+
+    /// The tags, in no particular order.
+    fn tags(&self) -> std::slice::Iter<'_, Spanned<Tag<'a>>> {
+        self.metadata().tags.iter()
+    }
+
+    /// The links, in no particular order.
+    fn links(&self) -> std::slice::Iter<'_, Spanned<Link<'a>>> {
+        self.metadata().links.iter()
+    }
+
+    /// Whether `tag`, without its `#`, is among the tags.
+    fn has_tag(&self, tag: &str) -> bool {
+        self.tags().any(|t| *t.item() == tag)
+    }
+
+    /// Whether `link`, without its `^`, is among the links.
+    fn has_link(&self, link: &str) -> bool {
+        self.links().any(|l| *l.item() == link)
+    }
+}
+
+impl<'a> HasTagsLinks<'a> for Metadata<'a> {
+    fn metadata(&self) -> &Metadata<'a> {
+        self
+    }
+}
+
+impl<'a> HasTagsLinks<'a> for Directive<'a> {
+    fn metadata(&self) -> &Metadata<'a> {
+        &self.metadata
+    }
+}
+
+impl<'a> HasTagsLinks<'a> for Posting<'a> {
+    fn metadata(&self) -> &Metadata<'a> {
+        &self.metadata
+    }
+}
+
+impl<'a, T> HasTagsLinks<'a> for Spanned<T>
+where
+    T: HasTagsLinks<'a>,
+{
+    fn metadata(&self) -> &Metadata<'a> {
+        self.item.metadata()
+    }
+}
+
+/// Implemented by spanned directives, for generic processing of the fields common to every [DirectiveVariant].
+///
+/// The metadata, tags, and links are available from the supertrait [HasTagsLinks].
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveCommon};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-02-01 note Assets:Bank "call about fees" #todo
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+///
+/// fn summary<'a, D: DirectiveCommon<'a>>(directive: &D) -> String {
+///     format!("{} {} {}", directive.date(), directive.kind(), directive.tags().len())
+/// }
+///
+/// assert_eq!(summary(&directives[1]), "2024-02-01 note 1");
+/// ```
+pub trait DirectiveCommon<'a>: HasTagsLinks<'a> {
+    /// The date of the directive.
+    fn date(&self) -> &Spanned<Date>;
+
+    /// Where the directive was parsed from.
+    fn span(&self) -> &Span;
+
+    /// The kind of directive.
+    fn kind(&self) -> DirectiveKind;
+}
+
+impl<'a> DirectiveCommon<'a> for Spanned<Directive<'a>> {
+    fn date(&self) -> &Spanned<Date> {
+        &self.item.date
+    }
+
+    fn span(&self) -> &Span {
+        &self.span
+    }
+
+    fn kind(&self) -> DirectiveKind {
+        self.item.kind()
+    }
 }
 
 /// Implemented by any element, for access to its kind in error reporting.
@@ -531,7 +764,7 @@ pub(crate) enum Declaration<'a> {
 }
 
 /// A Beancount directive of a particular [DirectiveVariant].
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Directive<'a> {
     pub(crate) date: Spanned<Date>,
     pub(crate) metadata: Metadata<'a>,
@@ -549,29 +782,35 @@ impl<'a> Directive<'a> {
         &self.metadata
     }
 
+    /// Mutable field accessor, for transforming directives.
+    pub fn metadata_mut(&mut self) -> &mut Metadata<'a> {
+        &mut self.metadata
+    }
+
     /// Field accessor.
     pub fn variant(&self) -> &DirectiveVariant {
         &self.variant
     }
+
+    /// The kind of directive, without destructuring its variant.
+    pub fn kind(&self) -> DirectiveKind {
+        self.variant.kind()
+    }
+
+    /// A hash of the content of the directive, ignoring where it was parsed from, for detecting duplicates.
+    ///
+    /// Directives which format identically have the same content hash.
+    /// The hash is not stable across releases, so should not be persisted.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.to_string().hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 impl<'a> ElementType for Directive<'a> {
     fn element_type(&self) -> &'static str {
-        use DirectiveVariant::*;
-
-        match &self.variant {
-            Transaction(_) => "transaction",
-            Price(_) => "price",
-            Balance(_) => "balance",
-            Open(_) => "open",
-            Close(_) => "close",
-            Commodity(_) => "commodity",
-            Pad(_) => "pad",
-            Document(_) => "document",
-            Note(_) => "note",
-            Event(_) => "event",
-            Query(_) => "query",
-        }
+        self.kind().into()
     }
 }
 
@@ -591,12 +830,13 @@ impl<'a> Display for Directive<'a> {
             Note(x) => x.fmt(f, self.date.item, &self.metadata),
             Event(x) => x.fmt(f, self.date.item, &self.metadata),
             Query(x) => x.fmt(f, self.date.item, &self.metadata),
+            Custom(x) => x.fmt(f, self.date.item, &self.metadata),
         }
     }
 }
 
 /// A Beancount directive, without the fields common to all, which belong to [Directive].
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum DirectiveVariant<'a> {
     Transaction(Transaction<'a>),
     Price(Price<'a>),
@@ -609,7 +849,68 @@ pub enum DirectiveVariant<'a> {
     Note(Note<'a>),
     Event(Event<'a>),
     Query(Query<'a>),
-    // TODO Custom
+    Custom(Custom<'a>),
+}
+
+impl<'a> DirectiveVariant<'a> {
+    /// The kind of directive, without destructuring the variant.
+    pub fn kind(&self) -> DirectiveKind {
+        use DirectiveVariant::*;
+
+        match self {
+            Transaction(_) => DirectiveKind::Transaction,
+            Price(_) => DirectiveKind::Price,
+            Balance(_) => DirectiveKind::Balance,
+            Open(_) => DirectiveKind::Open,
+            Close(_) => DirectiveKind::Close,
+            Commodity(_) => DirectiveKind::Commodity,
+            Pad(_) => DirectiveKind::Pad,
+            Document(_) => DirectiveKind::Document,
+            Note(_) => DirectiveKind::Note,
+            Event(_) => DirectiveKind::Event,
+            Query(_) => DirectiveKind::Query,
+            Custom(_) => DirectiveKind::Custom,
+        }
+    }
+}
+
+/// The kind of a [DirectiveVariant], for bucketing and filtering directives, and as a stable key for indexes.
+///
+/// Displays as the keyword used in Beancount source, from which it may also be parsed.
+#[derive(
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Display,
+    Debug,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum DirectiveKind {
+    Transaction,
+    Price,
+    Balance,
+    Open,
+    Close,
+    Commodity,
+    Pad,
+    Document,
+    Note,
+    Event,
+    Query,
+    Custom,
+}
+
+impl AsRef<str> for DirectiveKind {
+    fn as_ref(&self) -> &'static str {
+        self.into()
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -626,20 +927,21 @@ pub(crate) enum Pragma<'a> {
 }
 
 /// A Beancount transaction directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Transaction<'a> {
     pub(crate) flag: Spanned<Flag>,
     pub(crate) payee: Option<Spanned<&'a str>>,
     pub(crate) narration: Option<Spanned<&'a str>>,
     pub(crate) postings: Vec<Spanned<Posting<'a>>>,
+    pub(crate) broken_postings: Vec<Spanned<BrokenPosting>>,
 }
 
 impl<'a> Transaction<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
         write!(f, "{} {}", date, self.flag)?;
 
-        format(f, &self.payee, double_quoted, " ", Some(" "))?;
-        format(f, &self.narration, double_quoted, " ", Some(" "))?;
+        format(f, &self.payee, string_literal, " ", Some(" "))?;
+        format(f, &self.narration, string_literal, " ", Some(" "))?;
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
         metadata.fmt_keys_values(f)?;
@@ -671,10 +973,17 @@ impl<'a> Transaction<'a> {
     pub fn postings(&self) -> impl ExactSizeIterator<Item = &Spanned<Posting>> {
         self.postings.iter()
     }
+
+    /// Postings which failed to parse, whose errors have already been reported.
+    ///
+    /// These are excluded from [Transaction::postings], so that the valid postings may still be analysed.
+    pub fn broken_postings(&self) -> impl ExactSizeIterator<Item = &Spanned<BrokenPosting>> {
+        self.broken_postings.iter()
+    }
 }
 
 /// A Beancount price directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Price<'a> {
     pub(crate) currency: Spanned<Currency<'a>>,
     pub(crate) amount: Spanned<Amount<'a>>,
@@ -709,15 +1018,22 @@ impl<'a> ElementType for Price<'a> {
 }
 
 /// A Beancount balance directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Balance<'a> {
     pub(crate) account: Spanned<Account<'a>>,
     pub(crate) atol: Spanned<AmountWithTolerance<'a>>,
+    // boxed, being rarely present, so as not to enlarge every directive
+    pub(crate) cost_spec: Option<Box<Spanned<CostSpec<'a>>>>,
+    pub(crate) price_annotation: Option<Box<Spanned<PriceSpec<'a>>>>,
 }
 
 impl<'a> Balance<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
         write!(f, "{} balance {} {}", date, &self.account, &self.atol)?;
+        simple_format(f, &self.cost_spec, Some(" "))?;
+        if let Some(price_annotation) = &self.price_annotation {
+            write!(f, " {}", price_annotation.item.annotation())?;
+        }
 
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
@@ -735,6 +1051,40 @@ impl<'a> Balance<'a> {
     pub fn atol(&self) -> &Spanned<AmountWithTolerance> {
         &self.atol
     }
+
+    /// The lot to which the assertion is restricted, as in `balance Assets:Broker 10 AAPL {150.00 USD}`,
+    /// only with [ParserConfig::balance_extensions](crate::ParserConfig::balance_extensions).
+    pub fn cost_spec(&self) -> Option<&Spanned<CostSpec<'_>>> {
+        self.cost_spec.as_deref()
+    }
+
+    /// The price at which the assertion values the units, as in `balance Assets:Broker 10 AAPL @ 160.00 USD`,
+    /// only with [ParserConfig::balance_extensions](crate::ParserConfig::balance_extensions).
+    pub fn price_annotation(&self) -> Option<&Spanned<PriceSpec<'_>>> {
+        self.price_annotation.as_deref()
+    }
+
+    /// The tolerance within which the assertion holds, either as given explicitly,
+    /// or else from the `inferred_tolerance_default` option for the currency,
+    /// or else inferred from the precision of the amount, as in Beancount.
+    pub fn tolerance(&self, options: &Options<'_>) -> Tolerance {
+        let amount = self.atol.amount();
+
+        if let Some(tolerance) = self.atol.tolerance() {
+            Tolerance::Explicit(*tolerance.item())
+        } else if let Some(tolerance) = options.inferred_tolerance_default(amount.currency().item())
+        {
+            Tolerance::Default(tolerance)
+        } else {
+            // Beancount is generous with balance assertions, allowing twice the multiplier of the last digit
+            let scale = amount.number().value().scale();
+            Tolerance::Inferred(if scale > 0 {
+                Decimal::new(1, scale) * options.inferred_tolerance_multiplier() * Decimal::TWO
+            } else {
+                Decimal::ZERO
+            })
+        }
+    }
 }
 
 impl<'a> ElementType for Balance<'a> {
@@ -751,10 +1101,30 @@ pub struct Open<'a> {
     pub(crate) booking: Option<Spanned<Booking>>,
 }
 
+/// Hashing is regardless of the order of currencies, consistent with equality.
+impl<'a> Hash for Open<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.account.hash(state);
+        sorted_by(&self.currencies, |c1, c2| {
+            c1.item().as_ref().cmp(c2.item().as_ref())
+        })
+        .hash(state);
+        self.booking.hash(state);
+    }
+}
+
 impl<'a> Open<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
         write!(f, "{} open {}", date, self.account)?;
-        format(f, &self.currencies, plain, ",", Some(" "))?;
+        format(
+            f,
+            sorted_by(&self.currencies, |c1, c2| {
+                c1.item().as_ref().cmp(c2.item().as_ref())
+            }),
+            plain,
+            ",",
+            Some(" "),
+        )?;
         format(f, &self.booking, double_quoted, " ", Some(" "))?;
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
@@ -771,14 +1141,26 @@ impl<'a> Open<'a> {
         self.currencies.iter()
     }
 
-    /// Field accessor.
+    /// The booking method given explicitly, if any, see [Open::booking_method].
     pub fn booking(&self) -> Option<&Spanned<Booking>> {
         self.booking.as_ref()
     }
+
+    /// Whether the booking method was given explicitly, rather than defaulting to the `booking_method` option.
+    pub fn is_explicit_booking(&self) -> bool {
+        self.booking.is_some()
+    }
+
+    /// The booking method for the account, either as given explicitly, or else the `booking_method` option.
+    pub fn booking_method(&self, options: &Options<'_>) -> Booking {
+        self.booking
+            .as_ref()
+            .map_or(options.booking_method(), |booking| *booking.item())
+    }
 }
 
 /// A Beancount close directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Close<'a> {
     pub(crate) account: Spanned<Account<'a>>,
 }
@@ -798,7 +1180,7 @@ impl<'a> Close<'a> {
 }
 
 /// A Beancount commodity directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Commodity<'a> {
     pub(crate) currency: Spanned<Currency<'a>>,
 }
@@ -815,10 +1197,67 @@ impl<'a> Commodity<'a> {
     pub fn currency(&self) -> &Spanned<Currency> {
         &self.currency
     }
+
+    /// The descriptive name of the commodity, from the `name` key of `metadata`, being that of the commodity directive.
+    pub fn name(&self, metadata: &Metadata<'a>) -> Result<Option<&'a str>, Error> {
+        string_metadata(metadata, "name")
+    }
+
+    /// The asset class of the commodity, such as `stock` or `cash`, from the `asset-class` key of `metadata`,
+    /// being that of the commodity directive.
+    pub fn asset_class(&self, metadata: &Metadata<'a>) -> Result<Option<&'a str>, Error> {
+        string_metadata(metadata, "asset-class")
+    }
+
+    /// The quote currencies of the commodity and their price sources, in order of preference,
+    /// from the `price` key of `metadata`, being that of the commodity directive, see [PriceRequests](crate::PriceRequests).
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveVariant};
+    ///
+    /// let sources = BeancountSources::from(r#"2024-01-01 commodity AAPL
+    ///   name: "Apple Inc"
+    ///   price: "USD:yahoo/AAPL,google/NASDAQ:AAPL"
+    /// "#);
+    /// let parser = BeancountParser::new(&sources);
+    /// let directives = parser.parse().unwrap().directives;
+    /// let DirectiveVariant::Commodity(commodity) = directives[0].variant() else { unreachable!() };
+    /// let metadata = directives[0].metadata();
+    ///
+    /// assert_eq!(commodity.name(metadata).unwrap(), Some("Apple Inc"));
+    /// let price_sources = commodity.price_sources(metadata).unwrap();
+    /// let (quote, sources) = &price_sources[0];
+    /// assert_eq!(quote.to_string(), "USD");
+    /// assert_eq!(sources[1].to_string(), "google/NASDAQ:AAPL");
+    /// ```
+    pub fn price_sources(&self, metadata: &Metadata<'a>) -> Result<PriceSources<'a>, Error> {
+        metadata
+            .get("price")
+            .map_or(Ok(Vec::new()), parse_price_metadata)
+    }
+}
+
+/// The quote currencies of a commodity, each with its price sources, see [Commodity::price_sources].
+pub type PriceSources<'a> = Vec<(Currency<'a>, Vec<PriceSource<'a>>)>;
+
+// the value for `key` if any, which must be a string
+fn string_metadata<'a>(metadata: &Metadata<'a>, key: &str) -> Result<Option<&'a str>, Error> {
+    match metadata.get(key) {
+        Some(value) => match value.item() {
+            MetaValue::Simple(SimpleValue::String(s)) => Ok(Some(*s)),
+            _ => Err(Error::new(
+                format!("invalid {} metadata", key),
+                "expected string",
+                value.span,
+            )),
+        },
+        None => Ok(None),
+    }
 }
 
 /// A Beancount pad directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Pad<'a> {
     pub(crate) account: Spanned<Account<'a>>,
     pub(crate) source: Spanned<Account<'a>>,
@@ -844,7 +1283,7 @@ impl<'a> Pad<'a> {
 }
 
 /// A Beancount document directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Document<'a> {
     pub(crate) account: Spanned<Account<'a>>,
     pub(crate) path: Spanned<&'a str>,
@@ -852,7 +1291,13 @@ pub struct Document<'a> {
 
 impl<'a> Document<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
-        write!(f, "{} document {} \"{}\"", date, self.account, self.path)?;
+        write!(
+            f,
+            "{} document {} {}",
+            date,
+            self.account,
+            string_literal(self.path)
+        )?;
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
         metadata.fmt_keys_values(f)
@@ -864,14 +1309,14 @@ impl<'a> Document<'a> {
     }
 
     /// The path to the document, possibly relative to a directory given by options.
-    /// No check is made as to validity of the path or existence of the file.
+    /// No check is made as to validity of the path or existence of the file, for which see [PathResolver](crate::PathResolver).
     pub fn path(&self) -> &Spanned<&str> {
         &self.path
     }
 }
 
 /// A Beancount note directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Note<'a> {
     pub(crate) account: Spanned<Account<'a>>,
     pub(crate) comment: Spanned<&'a str>,
@@ -879,7 +1324,13 @@ pub struct Note<'a> {
 
 impl<'a> Note<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
-        write!(f, "{} note {} \"{}\"", date, self.account, self.comment)?;
+        write!(
+            f,
+            "{} note {} {}",
+            date,
+            self.account,
+            string_literal(self.comment)
+        )?;
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
         metadata.fmt_keys_values(f)
@@ -897,7 +1348,7 @@ impl<'a> Note<'a> {
 }
 
 /// A Beancount event directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Event<'a> {
     pub(crate) event_type: Spanned<&'a str>,
     pub(crate) description: Spanned<&'a str>,
@@ -907,8 +1358,10 @@ impl<'a> Event<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
         write!(
             f,
-            "{} event \"{}\" \"{}\"",
-            date, self.event_type, self.description
+            "{} event {} {}",
+            date,
+            string_literal(self.event_type),
+            string_literal(self.description)
         )?;
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
@@ -927,7 +1380,7 @@ impl<'a> Event<'a> {
 }
 
 /// A Beancount query directive, without the common [Directive] fields.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Query<'a> {
     pub(crate) name: Spanned<&'a str>,
     pub(crate) content: Spanned<&'a str>,
@@ -935,7 +1388,13 @@ pub struct Query<'a> {
 
 impl<'a> Query<'a> {
     fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
-        write!(f, "{} query \"{}\" \"{}\"", date, self.name, self.content)?;
+        write!(
+            f,
+            "{} query {} {}",
+            date,
+            string_literal(self.name),
+            string_literal(self.content)
+        )?;
         // we prefer to show tags and links inline rather then line by line in metadata
         metadata.fmt_tags_links_inline(f)?;
         metadata.fmt_keys_values(f)
@@ -952,8 +1411,39 @@ impl<'a> Query<'a> {
     }
 }
 
+/// A Beancount custom directive, without the common [Directive] fields.
+///
+/// Custom directives are not interpreted by Beancount itself, but by plugins and tools such as Fava.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct Custom<'a> {
+    pub(crate) custom_type: Spanned<&'a str>,
+    pub(crate) values: Vec<Spanned<MetaValue<'a>>>,
+}
+
+impl<'a> Custom<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>, date: Date, metadata: &Metadata) -> fmt::Result {
+        write!(f, "{} custom {}", date, string_literal(self.custom_type))?;
+        for value in self.values.iter() {
+            write!(f, " {}", value)?;
+        }
+        // we prefer to show tags and links inline rather then line by line in metadata
+        metadata.fmt_tags_links_inline(f)?;
+        metadata.fmt_keys_values(f)
+    }
+
+    /// Field accessor.
+    pub fn custom_type(&self) -> &Spanned<&str> {
+        &self.custom_type
+    }
+
+    /// Field accessor.
+    pub fn values(&self) -> &[Spanned<MetaValue<'a>>] {
+        &self.values
+    }
+}
+
 /// A Beancount plugin pragma.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Plugin<'a> {
     pub(crate) module_name: Spanned<&'a str>,
     pub(crate) config: Option<Spanned<&'a str>>,
@@ -973,16 +1463,16 @@ impl<'a> Plugin<'a> {
 
 impl<'a> Display for Plugin<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "plugin \"{}\"", self.module_name)?;
+        write!(f, "plugin {}", string_literal(self.module_name))?;
         if let Some(config) = &self.config {
-            write!(f, " \"{}\"", config)?;
+            write!(f, " {}", string_literal(config))?;
         }
         writeln!(f)
     }
 }
 
 /// A Beancount account with account type and subaccount names.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Account<'a> {
     pub(crate) account_type: AccountType,
     pub(crate) subaccount: Subaccount<'a>,
@@ -1014,6 +1504,20 @@ impl<'a> ElementType for Account<'a> {
     }
 }
 
+impl<'a> PartialOrd for Account<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for Account<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.account_type
+            .cmp(&other.account_type)
+            .then_with(|| self.subaccount.as_slice().cmp(other.subaccount.as_slice()))
+    }
+}
+
 impl<'a> Display for Account<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.account_type.as_ref())?;
@@ -1127,7 +1631,7 @@ impl Display for AccountTypeNameError {
 impl std::error::Error for AccountTypeNameError {}
 
 /// One component of a colon-separated account.
-#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
 pub struct AccountName<'a>(&'a str);
 
 impl<'a> AccountName<'a> {
@@ -1152,6 +1656,12 @@ impl<'a> AsRef<str> for AccountName<'a> {
     }
 }
 
+impl<'a> Borrow<str> for AccountName<'a> {
+    fn borrow(&self) -> &str {
+        self.0
+    }
+}
+
 impl<'a> PartialEq<&str> for AccountName<'a> {
     fn eq(&self, other: &&str) -> bool {
         self.0 == *other
@@ -1222,7 +1732,7 @@ impl<'a> TryFrom<&'a str> for AccountName<'a> {
 }
 
 /// A Beancount currency.
-#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
 pub struct Currency<'a>(&'a str);
 
 /// The valid intermediate characters for currency, in addition to ASCII uppercase and digits
@@ -1351,7 +1861,7 @@ impl<'a> TryFrom<&'a str> for Currency<'a> {
 }
 
 /// A single posting within a [Transaction].
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Posting<'a> {
     pub(crate) flag: Option<Spanned<Flag>>,
     pub(crate) account: Spanned<Account<'a>>,
@@ -1405,6 +1915,16 @@ impl<'a> ElementType for Posting<'a> {
     }
 }
 
+/// A placeholder for a posting which failed to parse, see [Transaction::broken_postings].
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct BrokenPosting;
+
+impl ElementType for BrokenPosting {
+    fn element_type(&self) -> &'static str {
+        "broken posting"
+    }
+}
+
 impl<'a> Display for Posting<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         simple_format(f, self.flag, None)?;
@@ -1419,9 +1939,12 @@ impl<'a> Display for Posting<'a> {
         simple_format(f, &self.amount, Some(" "))?;
         simple_format(f, self.currency, Some(" "))?;
         simple_format(f, &self.cost_spec, Some(" "))?;
-        simple_format(f, &self.price_annotation, Some(" @ "))?;
+        if let Some(price_annotation) = &self.price_annotation {
+            write!(f, " {}", price_annotation.item.annotation())?;
+        }
 
-        self.metadata.fmt(f)
+        // indented beneath the posting
+        self.metadata.fmt_with_separator(f, NEWLINE_INDENT_INDENT)
     }
 }
 
@@ -1429,19 +1952,142 @@ impl<'a> Display for Posting<'a> {
 ///
 /// Note that tags and links and key/values that may have been specified at the top-level
 /// of a directive are subsumed into the metadata element of the directive.
-#[derive(PartialEq, Eq, Clone, Default, Debug)]
+///
+/// Most directives and postings have no metadata, or only a few items, so each kind is stored
+/// as a boxed slice of exactly the right size, which allocates nothing when empty,
+/// and keys are borrowed from the source rather than owned.
+/// Lookup is by linear search, which for so few items is faster than hashing.
+#[derive(Eq, Clone, Default, Debug)]
 pub struct Metadata<'a> {
-    pub(crate) key_values: HashMap<Spanned<Key<'a>>, Spanned<MetaValue<'a>>>,
-    pub(crate) tags: HashSet<Spanned<Tag<'a>>>,
-    pub(crate) links: HashSet<Spanned<Link<'a>>>,
+    // in source order
+    key_values: Box<[(Spanned<Key<'a>>, Spanned<MetaValue<'a>>)]>,
+    tags: Box<[Spanned<Tag<'a>>]>,
+    links: Box<[Spanned<Link<'a>>]>,
 }
 
 impl<'a> Metadata<'a> {
-    /// Field accessor.
+    /// The key/values in source order.
     pub fn key_values(
         &self,
     ) -> impl ExactSizeIterator<Item = (&Spanned<Key>, &Spanned<MetaValue>)> {
-        self.key_values.iter()
+        self.key_values.iter().map(|(key, value)| (key, value))
+    }
+
+    /// The value for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&Spanned<MetaValue<'a>>> {
+        self.key_values
+            .iter()
+            .find_map(|(k, v)| (k.item().as_ref() == key).then_some(v))
+    }
+
+    /// The existing key equal to `key`, if any.
+    pub(crate) fn get_key(&self, key: &Key<'a>) -> Option<&Spanned<Key<'a>>> {
+        self.key_values
+            .iter()
+            .find_map(|(k, _)| (k.item() == key).then_some(k))
+    }
+
+    /// Insert a key/value in source order, unless the key is already present.
+    pub(crate) fn insert_key_value(
+        &mut self,
+        key: Spanned<Key<'a>>,
+        value: Spanned<MetaValue<'a>>,
+    ) -> bool {
+        self.build_with(|m| m.insert_key_value(key, value))
+    }
+
+    /// Modify the metadata with a [MetadataBuilder], so that many additions are made without resizing each time.
+    pub(crate) fn build_with<T, F>(&mut self, f: F) -> T
+    where
+        F: FnOnce(&mut MetadataBuilder<'a>) -> T,
+    {
+        let mut builder = MetadataBuilder::from(std::mem::take(self));
+        let result = f(&mut builder);
+        *self = builder.build();
+        result
+    }
+
+    /// The existing tag equal to `tag`, if any.
+    pub(crate) fn get_tag(&self, tag: &Tag<'a>) -> Option<&Spanned<Tag<'a>>> {
+        self.tags.iter().find(|t| t.item() == tag)
+    }
+
+    /// The existing link equal to `link`, if any.
+    pub(crate) fn get_link(&self, link: &Link<'a>) -> Option<&Spanned<Link<'a>>> {
+        self.links.iter().find(|l| l.item() == link)
+    }
+
+    /// The value for `key`, if it is a string.
+    pub fn get_string(&self, key: &str) -> Option<&'a str> {
+        self.get_string_spanned(key).map(|value| value.item)
+    }
+
+    /// The value for `key` with its span, if it is a string.
+    pub fn get_string_spanned(&self, key: &str) -> Option<Spanned<&'a str>> {
+        self.get_as(key, |value| match value {
+            MetaValue::Simple(SimpleValue::String(s)) => Some(*s),
+            _ => None,
+        })
+    }
+
+    /// The value for `key`, if it is a date.
+    pub fn get_date(&self, key: &str) -> Option<Date> {
+        self.get_date_spanned(key).map(|value| value.item)
+    }
+
+    /// The value for `key` with its span, if it is a date.
+    pub fn get_date_spanned(&self, key: &str) -> Option<Spanned<Date>> {
+        self.get_as(key, |value| match value {
+            MetaValue::Simple(SimpleValue::Date(date)) => Some(*date),
+            _ => None,
+        })
+    }
+
+    /// The value for `key`, if it is a number, which may have been written as an expression.
+    pub fn get_decimal(&self, key: &str) -> Option<Decimal> {
+        self.get_decimal_spanned(key).map(|value| value.item)
+    }
+
+    /// The value for `key` with its span, if it is a number, which may have been written as an expression.
+    pub fn get_decimal_spanned(&self, key: &str) -> Option<Spanned<Decimal>> {
+        self.get_as(key, |value| match value {
+            MetaValue::Simple(SimpleValue::Expr(expr)) => Some(expr.value()),
+            _ => None,
+        })
+    }
+
+    /// The value for `key`, if it is an amount.
+    pub fn get_amount(&self, key: &str) -> Option<&Amount<'a>> {
+        self.get_amount_spanned(key).map(|value| value.item)
+    }
+
+    /// The value for `key` with its span, if it is an amount.
+    pub fn get_amount_spanned(&self, key: &str) -> Option<Spanned<&Amount<'a>>> {
+        self.get_as(key, |value| match value {
+            MetaValue::Amount(amount) => Some(amount),
+            _ => None,
+        })
+    }
+
+    /// The value for `key`, if it is an account.
+    pub fn get_account(&self, key: &str) -> Option<&Account<'a>> {
+        self.get_account_spanned(key).map(|value| value.item)
+    }
+
+    /// The value for `key` with its span, if it is an account.
+    pub fn get_account_spanned(&self, key: &str) -> Option<Spanned<&Account<'a>>> {
+        self.get_as(key, |value| match value {
+            MetaValue::Simple(SimpleValue::Account(account)) => Some(account),
+            _ => None,
+        })
+    }
+
+    fn get_as<'s, T, F>(&'s self, key: &str, f: F) -> Option<Spanned<T>>
+    where
+        F: FnOnce(&'s MetaValue<'a>) -> Option<T>,
+    {
+        self.get(key)
+            .and_then(|value| f(value.item()).map(|item| spanned(item, value.span)))
     }
 
     /// Field accessor.
@@ -1454,27 +2100,187 @@ impl<'a> Metadata<'a> {
         self.links.iter()
     }
 
+    /// Add a tag, returning whether it was not already present.
+    pub fn add_tag(&mut self, tag: Spanned<Tag<'a>>) -> bool {
+        self.get_tag(tag.item()).is_none() && self.build_with(|m| m.add_tag(tag))
+    }
+
+    /// Add a link, returning whether it was not already present.
+    pub fn add_link(&mut self, link: Spanned<Link<'a>>) -> bool {
+        self.get_link(link.item()).is_none() && self.build_with(|m| m.add_link(link))
+    }
+
+    /// Add the key/values and tags of `parent` which are not already present.
+    pub(crate) fn inherit(&mut self, parent: &Metadata<'a>) {
+        self.build_with(|m| {
+            for (key, value) in parent.key_values.iter() {
+                m.insert_key_value(*key, value.clone());
+            }
+            for tag in parent.tags.iter() {
+                m.add_tag(*tag);
+            }
+        })
+    }
+
     pub(crate) fn fmt_tags_links_inline(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        format(f, &self.tags, plain, SPACE, Some(SPACE))?;
-        format(f, &self.links, plain, SPACE, Some(SPACE))
+        format(f, self.sorted_tags(), plain, SPACE, Some(SPACE))?;
+        format(f, self.sorted_links(), plain, SPACE, Some(SPACE))
+    }
+
+    fn sorted_tags(&self) -> Vec<&Spanned<Tag<'a>>> {
+        sorted_by(&self.tags, |t1, t2| {
+            t1.item().as_ref().cmp(t2.item().as_ref())
+        })
+    }
+
+    fn sorted_links(&self) -> Vec<&Spanned<Link<'a>>> {
+        sorted_by(&self.links, |l1, l2| {
+            l1.item().as_ref().cmp(l2.item().as_ref())
+        })
     }
 
     pub(crate) fn fmt_keys_values(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_keys_values_with_separator(f, NEWLINE_INDENT)
+    }
+
+    fn fmt_keys_values_with_separator(
+        &self,
+        f: &mut Formatter<'_>,
+        separator: &'static str,
+    ) -> fmt::Result {
         format(
             f,
-            &self.key_values,
+            sorted_by(self.key_values(), |(k1, _), (k2, _)| {
+                k1.item().as_ref().cmp(k2.item().as_ref())
+            }),
             key_value,
-            NEWLINE_INDENT,
-            Some(NEWLINE_INDENT),
+            separator,
+            Some(separator),
         )
     }
+
+    // each key/value, tag, and link on its own line, each line starting with `separator`
+    pub(crate) fn fmt_with_separator(
+        &self,
+        f: &mut Formatter<'_>,
+        separator: &'static str,
+    ) -> fmt::Result {
+        self.fmt_keys_values_with_separator(f, separator)?;
+        format(f, self.sorted_tags(), plain, separator, Some(separator))?;
+        format(f, self.sorted_links(), plain, separator, Some(separator))
+    }
+}
+
+/// [Metadata] under construction, whose items are boxed as exactly sized slices only once complete.
+#[derive(Default, Debug)]
+pub(crate) struct MetadataBuilder<'a> {
+    key_values: Vec<(Spanned<Key<'a>>, Spanned<MetaValue<'a>>)>,
+    tags: Vec<Spanned<Tag<'a>>>,
+    links: Vec<Spanned<Link<'a>>>,
+}
+
+impl<'a> MetadataBuilder<'a> {
+    /// The existing key equal to `key`, if any.
+    pub(crate) fn get_key(&self, key: &Key<'a>) -> Option<&Spanned<Key<'a>>> {
+        self.key_values
+            .iter()
+            .find_map(|(k, _)| (k.item() == key).then_some(k))
+    }
+
+    /// The existing tag equal to `tag`, if any.
+    pub(crate) fn get_tag(&self, tag: &Tag<'a>) -> Option<&Spanned<Tag<'a>>> {
+        self.tags.iter().find(|t| t.item() == tag)
+    }
+
+    /// The existing link equal to `link`, if any.
+    pub(crate) fn get_link(&self, link: &Link<'a>) -> Option<&Spanned<Link<'a>>> {
+        self.links.iter().find(|l| l.item() == link)
+    }
+
+    /// Insert a key/value in source order, unless the key is already present.
+    pub(crate) fn insert_key_value(
+        &mut self,
+        key: Spanned<Key<'a>>,
+        value: Spanned<MetaValue<'a>>,
+    ) -> bool {
+        let absent = self.get_key(key.item()).is_none();
+        if absent {
+            let i = self
+                .key_values
+                .partition_point(|(k, _)| k.span.start <= key.span.start);
+            self.key_values.insert(i, (key, value));
+        }
+        absent
+    }
+
+    /// Add a tag, returning whether it was not already present.
+    pub(crate) fn add_tag(&mut self, tag: Spanned<Tag<'a>>) -> bool {
+        let absent = self.get_tag(tag.item()).is_none();
+        if absent {
+            self.tags.push(tag);
+        }
+        absent
+    }
+
+    /// Add a link, returning whether it was not already present.
+    pub(crate) fn add_link(&mut self, link: Spanned<Link<'a>>) -> bool {
+        let absent = self.get_link(link.item()).is_none();
+        if absent {
+            self.links.push(link);
+        }
+        absent
+    }
+
+    pub(crate) fn build(self) -> Metadata<'a> {
+        Metadata {
+            key_values: self.key_values.into_boxed_slice(),
+            tags: self.tags.into_boxed_slice(),
+            links: self.links.into_boxed_slice(),
+        }
+    }
+}
+
+impl<'a> From<Metadata<'a>> for MetadataBuilder<'a> {
+    fn from(metadata: Metadata<'a>) -> Self {
+        MetadataBuilder {
+            key_values: metadata.key_values.into_vec(),
+            tags: metadata.tags.into_vec(),
+            links: metadata.links.into_vec(),
+        }
+    }
+}
+
+/// Equality is regardless of order, since only the order of key/values is significant,
+/// and that only for display.
+impl<'a> PartialEq for Metadata<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key_values.len() == other.key_values.len()
+            && self.tags.len() == other.tags.len()
+            && self.links.len() == other.links.len()
+            && self
+                .key_values
+                .iter()
+                .all(|(key, value)| other.get(key.item().as_ref()) == Some(value))
+            && self.tags.iter().all(|tag| other.get_tag(tag).is_some())
+            && self.links.iter().all(|link| other.get_link(link).is_some())
+    }
+}
+
+/// Hashing is regardless of order, consistent with equality.
+impl<'a> Hash for Metadata<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        sorted_by(self.key_values.iter(), |(k1, _), (k2, _)| {
+            k1.item().as_ref().cmp(k2.item().as_ref())
+        })
+        .hash(state);
+        self.sorted_tags().hash(state);
+        self.sorted_links().hash(state);
+    }
 }
 
 impl<'a> Display for Metadata<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.fmt_keys_values(f)?;
-        format(f, &self.tags, plain, NEWLINE_INDENT, Some(NEWLINE_INDENT))?;
-        format(f, &self.links, plain, NEWLINE_INDENT, Some(NEWLINE_INDENT))
+        self.fmt_with_separator(f, NEWLINE_INDENT)
     }
 }
 
@@ -1486,12 +2292,12 @@ pub(crate) struct MetaKeyValue<'a> {
 
 impl<'a> Display for MetaKeyValue<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: \"{}\"", &self.key, &self.value)
+        write!(f, "{}: {}", &self.key, &self.value)
     }
 }
 
 /// A value of metadata key/value.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum MetaValue<'a> {
     Simple(SimpleValue<'a>),
     Amount(Amount<'a>),
@@ -1509,7 +2315,7 @@ impl<'a> Display for MetaValue<'a> {
 }
 
 /// One possible type of [MetaValue].
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum SimpleValue<'a> {
     String(&'a str),
     Currency(Currency<'a>),
@@ -1533,7 +2339,7 @@ impl<'a> Display for SimpleValue<'a> {
         use SimpleValue::*;
 
         match self {
-            String(x) => write!(f, r#""{}""#, x),
+            String(x) => string_literal(x).fmt(f),
             Currency(x) => x.fmt(f),
             Account(x) => x.fmt(f),
             Tag(x) => x.fmt(f),
@@ -1649,6 +2455,11 @@ pub struct TagOrLinkIdentifierError(Vec<char>);
 
 impl Display for TagOrLinkIdentifierError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // an empty identifier is the only error without any invalid characters
+        if self.0.is_empty() {
+            return f.write_str("empty tag or link identifier");
+        }
+
         format(f, &self.0, single_quoted, ", ", Some("invalid characters "))?;
         format(
             f,
@@ -1670,7 +2481,7 @@ impl<'a> TryFrom<&'a str> for TagOrLinkIdentifier<'a> {
             .chars()
             .filter(|c| (!TagOrLinkIdentifier::is_valid_char(c)))
             .collect::<Vec<char>>();
-        if bad_chars.is_empty() {
+        if bad_chars.is_empty() && !s.is_empty() {
             Ok(TagOrLinkIdentifier(s))
         } else {
             Err(TagOrLinkIdentifierError(bad_chars))
@@ -1791,7 +2602,7 @@ impl<'a> TryFrom<&'a str> for Key<'a> {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 /// An `Expr` which has been evaluated.
 ///
 /// Note that the [decimal scale](https://docs.rs/rust_decimal/latest/rust_decimal/index.html) is set according to the maximum of the scales used within the expression.
@@ -1805,6 +2616,11 @@ impl ExprValue {
     pub fn value(&self) -> Decimal {
         self.value
     }
+
+    /// Whether the expression is simply a number, with no operators.
+    pub(crate) fn is_literal(&self) -> bool {
+        matches!(self.expr, Expr::Value(_))
+    }
 }
 
 impl PartialEq<Decimal> for ExprValue {
@@ -1836,7 +2652,7 @@ impl Display for ExprValue {
 }
 
 /// A numeric expression which respects standard operator precedence.
-#[derive(PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Hash, Clone)]
 pub enum Expr {
     Value(Decimal),
     Add(Box<Expr>, Box<Expr>),
@@ -1914,7 +2730,7 @@ impl fmt::Debug for Expr {
 }
 
 /// An `ExprValue` which quantifies a total or per-unit, or both.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum CompoundExprValue {
     PerUnit(ExprValue),
     Total(ExprValue),
@@ -1934,7 +2750,7 @@ impl Display for CompoundExprValue {
 }
 
 /// An `ExprValue` which quantifies either a total or per-unit, but not both.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum ScopedExprValue {
     PerUnit(ExprValue),
     Total(ExprValue),
@@ -1952,7 +2768,7 @@ impl Display for ScopedExprValue {
 }
 
 /// A `ExprValue` and `Currency`.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Amount<'a> {
     number: Spanned<ExprValue>,
     currency: Spanned<Currency<'a>>,
@@ -1975,6 +2791,28 @@ impl<'a> Amount<'a> {
     pub fn currency(&self) -> &Spanned<Currency> {
         &self.currency
     }
+
+    /// The amount with `number` replaced, keeping the spans of this amount.
+    fn with_number(&self, number: Decimal) -> Self {
+        Amount {
+            number: self.number.map(|_| Expr::Value(number).into()),
+            currency: self.currency,
+        }
+    }
+
+    fn checked<F>(&self, other: &Amount<'a>, op: F) -> Result<Amount<'a>, ArithmeticError<'a>>
+    where
+        F: FnOnce(Decimal, Decimal) -> Decimal,
+    {
+        if self.currency.item == other.currency.item {
+            Ok(self.with_number(op(self.number.value(), other.number.value())))
+        } else {
+            Err(ArithmeticError::CurrencyMismatch(
+                self.currency.item,
+                other.currency.item,
+            ))
+        }
+    }
 }
 
 impl<'a> Display for Amount<'a> {
@@ -1983,8 +2821,96 @@ impl<'a> Display for Amount<'a> {
     }
 }
 
-/// An `Amount` with optional tolerance.
+/// Addition of amounts in the same currency, where the result has the spans of the left operand.
+impl<'a, 'b> Add<&'b Amount<'a>> for &'b Amount<'a> {
+    type Output = Result<Amount<'a>, ArithmeticError<'a>>;
+
+    fn add(self, other: &'b Amount<'a>) -> Self::Output {
+        self.checked(other, |x, y| x + y)
+    }
+}
+
+impl<'a> Add for Amount<'a> {
+    type Output = Result<Amount<'a>, ArithmeticError<'a>>;
+
+    fn add(self, other: Amount<'a>) -> Self::Output {
+        &self + &other
+    }
+}
+
+/// Subtraction of amounts in the same currency, where the result has the spans of the left operand.
+impl<'a, 'b> Sub<&'b Amount<'a>> for &'b Amount<'a> {
+    type Output = Result<Amount<'a>, ArithmeticError<'a>>;
+
+    fn sub(self, other: &'b Amount<'a>) -> Self::Output {
+        self.checked(other, |x, y| x - y)
+    }
+}
+
+impl<'a> Sub for Amount<'a> {
+    type Output = Result<Amount<'a>, ArithmeticError<'a>>;
+
+    fn sub(self, other: Amount<'a>) -> Self::Output {
+        &self - &other
+    }
+}
+
+impl<'a> Neg for &Amount<'a> {
+    type Output = Amount<'a>;
+
+    fn neg(self) -> Self::Output {
+        self.with_number(-self.number.value())
+    }
+}
+
+impl<'a> Neg for Amount<'a> {
+    type Output = Amount<'a>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl<'a> Mul<Decimal> for &Amount<'a> {
+    type Output = Amount<'a>;
+
+    fn mul(self, factor: Decimal) -> Self::Output {
+        self.with_number(self.number.value() * factor)
+    }
+}
+
+impl<'a> Mul<Decimal> for Amount<'a> {
+    type Output = Amount<'a>;
+
+    fn mul(self, factor: Decimal) -> Self::Output {
+        &self * factor
+    }
+}
+
+/// Error type for arithmetic on incompatible amounts or positions.
 #[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ArithmeticError<'a> {
+    /// The currencies of the operands differ.
+    CurrencyMismatch(Currency<'a>, Currency<'a>),
+    /// The operands are positions held at different costs.
+    CostMismatch,
+}
+
+impl<'a> Display for ArithmeticError<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use ArithmeticError::*;
+
+        match self {
+            CurrencyMismatch(c1, c2) => write!(f, "currency mismatch: {} and {}", c1, c2),
+            CostMismatch => f.write_str("cost mismatch"),
+        }
+    }
+}
+
+impl<'a> std::error::Error for ArithmeticError<'a> {}
+
+/// An `Amount` with optional tolerance.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct AmountWithTolerance<'a> {
     amount: Spanned<Amount<'a>>,
     tolerance: Option<Spanned<Decimal>>,
@@ -2011,16 +2937,57 @@ impl<'a> AmountWithTolerance<'a> {
 
 impl<'a> Display for AmountWithTolerance<'a> {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        // only an explicit tolerance is written, so that any other is inferred again on reading
         if let Some(tolerance) = self.tolerance {
-            write!(format, "{} ~ {}", &self.amount, tolerance)
+            write!(
+                format,
+                "{} {} {}",
+                &self.amount.number,
+                Tolerance::Explicit(*tolerance.item()),
+                &self.amount.currency
+            )
         } else {
             write!(format, "{}", &self.amount)
         }
     }
 }
 
+/// The tolerance of a balance assertion, see [Balance::tolerance].
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Tolerance {
+    /// Given with `~` in the assertion.
+    Explicit(Decimal),
+    /// From the `inferred_tolerance_default` option for the currency.
+    Default(Decimal),
+    /// From the precision of the amount, being twice `inferred_tolerance_multiplier` of its last digit,
+    /// or zero for a whole number.
+    Inferred(Decimal),
+}
+
+impl Tolerance {
+    /// The tolerance, however it was determined.
+    pub fn value(&self) -> Decimal {
+        use Tolerance::*;
+
+        match self {
+            Explicit(value) | Default(value) | Inferred(value) => *value,
+        }
+    }
+
+    /// Whether `difference` is within the tolerance, in either direction.
+    pub fn allows(&self, difference: Decimal) -> bool {
+        difference.abs() <= self.value()
+    }
+}
+
+impl Display for Tolerance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "~ {}", self.value())
+    }
+}
+
 /// An amount where each element of `ExprValue` and `Currency` may not actually be specified.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct LooseAmount<'a> {
     number: Option<Spanned<ExprValue>>,
     currency: Option<Spanned<Currency<'a>>>,
@@ -2046,7 +3013,7 @@ impl<'a> LooseAmount<'a> {
 }
 
 /// An amount which specifies a total or per-unit value or both, with or without a currency, or simply just a `Currency`.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum CompoundAmount<'a> {
     BareCurrency(Currency<'a>),
     BareAmount(CompoundExprValue),
@@ -2064,8 +3031,60 @@ impl<'a> Display for CompoundAmount<'a> {
     }
 }
 
+impl<'a> ElementType for PriceSpec<'a> {
+    fn element_type(&self) -> &'static str {
+        "price annotation"
+    }
+}
+
+impl<'a> Spanned<PriceSpec<'a>> {
+    /// The price per unit for a posting of `units`, converting a total price (`@@`) by dividing by the absolute
+    /// number of units, as in Beancount, or `None` if the price lacks either a number or a currency.
+    ///
+    /// A total price for zero units is an error.
+    ///
+    /// # Examples
+    /// ```
+    /// use beancount_parser_lima::{BeancountParser, BeancountSources, DirectiveVariant};
+    /// use rust_decimal_macros::dec;
+    ///
+    /// let sources = BeancountSources::from(r#"2024-01-01 * "Buy"
+    ///   Assets:Broker  -4 AAPL @@ 600.00 USD
+    ///   Assets:Cash
+    /// "#);
+    /// let parser = BeancountParser::new(&sources);
+    /// let directives = parser.parse().unwrap().directives;
+    /// let DirectiveVariant::Transaction(transaction) = directives[0].variant() else { unreachable!() };
+    /// let price = transaction.postings().next().unwrap().price_annotation().unwrap();
+    ///
+    /// assert_eq!(price.normalize(dec!(-4)).unwrap().unwrap().to_string(), "150.00 USD");
+    /// ```
+    pub fn normalize(&self, units: Decimal) -> Result<Option<Amount<'a>>, Error> {
+        use PriceSpec::*;
+        use ScopedExprValue::*;
+
+        let CurrencyAmount(price, currency) = self.item() else {
+            return Ok(None);
+        };
+
+        let per_unit = match price {
+            PerUnit(per_unit) => per_unit.clone(),
+            Total(total) => total
+                .value()
+                .checked_div(units.abs())
+                .map(|per_unit| ExprValue::from(Expr::Value(per_unit)))
+                .ok_or_else(|| self.error("total price for zero units"))?,
+        };
+
+        Ok(Some(Amount::new((
+            spanned(per_unit, self.span),
+            spanned(*currency, self.span),
+        ))))
+    }
+}
+
 /// A cost specification.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct CostSpec<'a> {
     per_unit: Option<Spanned<ExprValue>>,
     total: Option<Spanned<ExprValue>>,
@@ -2117,9 +3136,11 @@ impl<'a> Display for CostSpec<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut prefix = "";
         let space = " ";
+        let comma = ", ";
 
         f.write_str("{")?;
 
+        // the per-unit and total amounts and currency comprise a single component, and the others are comma-separated
         if let Some(per_unit) = &self.per_unit {
             write!(f, "{}{}", prefix, per_unit)?;
             prefix = space;
@@ -2135,14 +3156,18 @@ impl<'a> Display for CostSpec<'a> {
             prefix = space;
         }
 
+        if !prefix.is_empty() {
+            prefix = comma;
+        }
+
         if let Some(date) = &self.date {
             write!(f, "{}{}", prefix, date)?;
-            prefix = space;
+            prefix = comma;
         }
 
         if let Some(label) = &self.label {
-            write!(f, "{}\"{}\"", prefix, label)?;
-            prefix = space;
+            write!(f, "{}{}", prefix, string_literal(label))?;
+            prefix = comma;
         }
 
         if self.merge {
@@ -2327,13 +3352,27 @@ impl std::error::Error for CostSpecErrors {}
 
 /// An amount which specifies a total or per-unit value, with or without a currency, or simply just a `Currency`.
 /// Unlike a `CompoundAmount` it is forbidden to have both total and per-unit.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum PriceSpec<'a> {
     BareCurrency(Currency<'a>),
     BareAmount(ScopedExprValue),
     CurrencyAmount(ScopedExprValue, Currency<'a>),
 }
 
+impl<'a> PriceSpec<'a> {
+    // the price annotation as written after a posting, with `@@` for a total price
+    fn annotation(&self) -> impl Display + '_ {
+        use self::PriceSpec::*;
+        use self::ScopedExprValue::*;
+
+        lazy_format!(match (self) {
+            BareAmount(Total(e)) => ("@@ {}", e),
+            CurrencyAmount(Total(e), cur) => ("@@ {} {}", e, cur),
+            price_spec => ("@ {}", price_spec),
+        })
+    }
+}
+
 impl<'a> Display for PriceSpec<'a> {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
         use self::PriceSpec::*;
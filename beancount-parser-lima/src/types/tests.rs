@@ -79,6 +79,9 @@ fn test_currency_try_from(s: &str, expected: Result<&str, CurrencyErrorKind>) {
 
 #[test_case("a-b-c-d", Ok("a-b-c-d"))]
 #[test_case("a=b?c,d-e", Err(TagOrLinkIdentifierError(vec!['=', '?', ','])))]
+#[test_case("#food", Err(TagOrLinkIdentifierError(vec!['#'])))]
+#[test_case("", Err(TagOrLinkIdentifierError(vec![])))]
+#[test_case("2024/trip.nz_b-1", Ok("2024/trip.nz_b-1"))]
 fn test_tag_or_link_identifier_try_from(
     s: &str,
     expected_raw: Result<&str, TagOrLinkIdentifierError>,
@@ -114,3 +117,473 @@ fn test_flag_letter_try_from(c: char, expected: Result<FlagLetter, FlagLetterErr
     }
     assert_eq!(result, expected);
 }
+
+fn amount(number: Decimal, currency: &str) -> Amount<'_> {
+    let span = chumsky::span::Span::new(SourceId(0), 0..0);
+    Amount::new((
+        spanned(ExprValue::from(Value(number)), span),
+        spanned(Currency::try_from(currency).unwrap(), span),
+    ))
+}
+
+#[test]
+fn test_amount_arithmetic() {
+    let x = amount(dec!(10.50), "USD");
+    let y = amount(dec!(2.25), "USD");
+
+    assert_eq!((&x + &y).unwrap().to_string(), "12.75 USD");
+    assert_eq!((&x - &y).unwrap().to_string(), "8.25 USD");
+    assert_eq!((-&x).to_string(), "-10.50 USD");
+    assert_eq!((x * dec!(2)).number().value(), dec!(21));
+}
+
+#[test]
+fn test_amount_currency_mismatch() {
+    let x = amount(dec!(10), "USD");
+    let y = amount(dec!(2), "GBP");
+
+    let e = (x + y).unwrap_err();
+    assert_eq!(
+        e,
+        ArithmeticError::CurrencyMismatch(
+            Currency::try_from("USD").unwrap(),
+            Currency::try_from("GBP").unwrap()
+        )
+    );
+    assert_eq!(e.to_string(), "currency mismatch: USD and GBP");
+}
+
+#[test]
+fn test_metadata_typed_accessors() {
+    use crate::{BeancountParser, BeancountSources};
+
+    let sources = BeancountSources::from(
+        r#"
+2024-01-01 open Assets:Bank
+  zname: "Current account"
+  opened: 2023-12-25
+  rate: 2.5 + 0.25
+  limit: 500.00 GBP
+  sweep: Assets:Savings
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let metadata = directives[0].metadata();
+
+    assert_eq!(metadata.get_string("zname"), Some("Current account"));
+    assert_eq!(
+        metadata.get_date("opened"),
+        Some(Date::from_calendar_date(2023, time::Month::December, 25).unwrap())
+    );
+    assert_eq!(metadata.get_decimal("rate"), Some(dec!(2.75)));
+    assert_eq!(
+        metadata
+            .get_amount("limit")
+            .map(|amount| amount.to_string()),
+        Some("500.00 GBP".to_string())
+    );
+    assert_eq!(
+        metadata
+            .get_account("sweep")
+            .map(|account| account.to_string()),
+        Some("Assets:Savings".to_string())
+    );
+    assert_eq!(metadata.get_string("opened"), None);
+    assert_eq!(metadata.get_string("missing"), None);
+
+    let name = metadata.get_string_spanned("zname").unwrap();
+    assert_eq!(name.span(), metadata.get("zname").unwrap().span());
+
+    assert_eq!(
+        metadata
+            .key_values()
+            .map(|(key, _)| key.item().as_ref())
+            .collect::<Vec<_>>(),
+        vec!["zname", "opened", "rate", "limit", "sweep"]
+    );
+}
+
+#[test]
+fn test_metadata_eq_regardless_of_order() {
+    use crate::{BeancountParser, BeancountSources};
+
+    let sources = BeancountSources::from(
+        r#"
+2024-01-01 open Assets:Bank #a #b ^x
+  name: "Current account"
+  rate: 2.5
+
+2024-01-01 open Assets:Bank #b #a ^x
+  rate: 2.5
+  name: "Current account"
+
+2024-01-01 open Assets:Bank #b #a ^x
+  rate: 2.5
+
+2024-01-01 open Assets:Bank #b #c ^x
+  rate: 2.5
+  name: "Current account"
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let metadata = directives
+        .iter()
+        .map(|directive| directive.metadata())
+        .collect::<Vec<_>>();
+
+    assert_eq!(metadata[0], metadata[1]);
+    assert_ne!(metadata[0], metadata[2]);
+    assert_ne!(metadata[0], metadata[3]);
+    assert_eq!(metadata[0].to_string(), metadata[1].to_string());
+}
+
+#[test]
+fn test_metadata_compact() {
+    // three boxed slices, and nothing allocated when empty
+    assert_eq!(
+        std::mem::size_of::<Metadata>(),
+        3 * std::mem::size_of::<Box<[u8]>>()
+    );
+    let metadata = Metadata::default();
+    assert_eq!(metadata.key_values().len(), 0);
+    assert_eq!(metadata.tags().len(), 0);
+    assert_eq!(metadata.links().len(), 0);
+}
+
+#[test_case("@ 12.50 USD", dec!(4), Ok(Some("12.50 USD")))]
+#[test_case("@@ 50.00 USD", dec!(4), Ok(Some("12.50 USD")))]
+#[test_case("@@ 50.00 USD", dec!(-4), Ok(Some("12.50 USD")))]
+#[test_case("@@ 50.00 USD", dec!(0), Err("total price for zero units"))]
+#[test_case("@ 12.50", dec!(4), Ok(None))]
+#[test_case("@ USD", dec!(4), Ok(None))]
+fn test_price_spec_normalize(price: &str, units: Decimal, expected: Result<Option<&str>, &str>) {
+    let sources = crate::BeancountSources::from(format!(
+        "2024-01-01 txn\n  Assets:Broker  1 AAPL {}\n  Assets:Cash\n",
+        price
+    ));
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let DirectiveVariant::Transaction(transaction) = directives[0].variant() else {
+        panic!("expected transaction");
+    };
+    let price = transaction
+        .postings()
+        .next()
+        .unwrap()
+        .price_annotation()
+        .unwrap();
+
+    assert_eq!(
+        price
+            .normalize(units)
+            .map(|amount| amount.map(|amount| amount.to_string()))
+            .map_err(|e| e.reason.to_string()),
+        expected
+            .map(|amount| amount.map(String::from))
+            .map_err(String::from)
+    );
+}
+
+#[test]
+fn test_spanned_combinators() {
+    let span = chumsky::span::Span::new(SourceId::default(), 3..8);
+    let s = spanned("hello".to_string(), span);
+
+    assert_eq!(s.as_deref(), spanned("hello", span));
+    assert_eq!(*s.as_deref().span(), span);
+    assert_eq!(s.map(|s| s.len()), spanned(5, span));
+
+    let present = Some(&s);
+    assert_eq!(present.item_and_span(), Some((&"hello".to_string(), span)));
+    assert_eq!(present.span(), Some(span));
+    let absent: Option<&Spanned<String>> = None;
+    assert_eq!(absent.item_and_span(), None);
+    assert_eq!(absent.span(), None);
+
+    assert_eq!(s.into_item_and_span(), ("hello".to_string(), span));
+}
+
+const DISPLAY_LEDGER: &str = r#"2024-01-01 open Assets:Broker AAPL,NZD "FIFO"
+  opened-by: "me"
+2024-01-01 open Assets:Bank
+2024-01-01 open Equity:Opening
+2024-01-01 commodity AAPL
+  name: "Apple \"Inc\" \\ co"
+2024-01-02 * "Broker \"One\"" "buy" #stocks ^trade-1
+  ref: 123.45 NZD
+  flagged: TRUE
+  when: 2024-01-02
+  Assets:Broker  10 AAPL {150.00 # 9.95 NZD, 2024-01-02, "lot \"a\""} @@ 1509.95 NZD
+    lot: "first"
+  ! Assets:Bank  -1509.95 NZD
+2024-01-03 * "Sell"
+  Assets:Broker  -5 AAPL {150.00 NZD} @ 160.00 NZD
+  Assets:Bank  (5 * 160.00) NZD
+2024-01-04 price AAPL 161.00 NZD
+2024-01-05 balance Assets:Bank  -709.95 ~ 0.01 NZD
+2024-01-06 pad Assets:Bank Equity:Opening
+2024-01-07 note Assets:Bank "said \"hello\""
+2024-01-08 document Assets:Bank "docs/statement.pdf"
+2024-01-09 event "location" "Home \\ Away"
+2024-01-10 query "cash" "SELECT \"x\""
+2024-01-11 custom "budget" Assets:Bank "monthly" 100.00 NZD TRUE
+2024-01-12 close Equity:Opening
+"#;
+
+#[test]
+fn test_display_reparses() {
+    let sources = crate::BeancountSources::from(DISPLAY_LEDGER);
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let displayed = directives
+        .iter()
+        .map(|directive| format!("{}\n", directive))
+        .collect::<String>();
+    let redisplayed_sources = crate::BeancountSources::from(displayed.as_str());
+    let reparser = crate::BeancountParser::new(&redisplayed_sources);
+    let reparsed = match reparser.parse() {
+        Ok(success) => success.directives,
+        Err(e) => panic!("failed to reparse:\n{}\n{:?}", displayed, e.errors),
+    };
+
+    assert_eq!(
+        directives.iter().map(|d| d.item()).collect::<Vec<_>>(),
+        reparsed.iter().map(|d| d.item()).collect::<Vec<_>>(),
+        "{}",
+        displayed
+    );
+}
+
+#[test]
+fn test_directive_eq_hash_ignores_spans_and_metadata_order() {
+    let sources = crate::BeancountSources::from(
+        r#"2024-01-02 * "Shop" #food #weekly
+  receipt: "r1"
+  trip: "home"
+  Expenses:Food  10.00 NZD
+  Assets:Bank
+
+2024-01-02 * "Shop" #weekly #food
+  trip: "home"
+  receipt: "r1"
+  Expenses:Food   10.00 NZD
+  Assets:Bank
+
+2024-01-02 * "Shop" #food #weekly
+  receipt: "r2"
+  trip: "home"
+  Expenses:Food  10.00 NZD
+  Assets:Bank
+"#,
+    );
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    fn hash<H>(x: H) -> u64
+    where
+        H: Hash,
+    {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    assert_eq!(directives[0].item(), directives[1].item());
+    assert_eq!(hash(directives[0].item()), hash(directives[1].item()));
+    assert_ne!(directives[0].item(), directives[2].item());
+
+    let distinct = directives
+        .iter()
+        .map(|directive| directive.item())
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(distinct.len(), 2);
+}
+
+#[test]
+fn test_commodity_metadata() {
+    let sources = crate::BeancountSources::from(
+        r#"2024-01-01 commodity VTI
+  name: "Vanguard Total Stock Market"
+  asset-class: "stock"
+  price: "USD:yahoo/VTI CAD:yahoo/^CADUSD=X"
+2024-01-01 commodity NZD
+2024-01-01 commodity GBP
+  asset-class: 2024-01-01
+  price: "GBP"
+"#,
+    );
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let commodity = |i: usize| {
+        let DirectiveVariant::Commodity(commodity) = directives[i].variant() else {
+            panic!("expected commodity");
+        };
+        (commodity, directives[i].metadata())
+    };
+
+    let (vti, metadata) = commodity(0);
+    assert_eq!(
+        vti.name(metadata).unwrap(),
+        Some("Vanguard Total Stock Market")
+    );
+    assert_eq!(vti.asset_class(metadata).unwrap(), Some("stock"));
+    assert_eq!(
+        vti.price_sources(metadata)
+            .unwrap()
+            .iter()
+            .map(|(quote, sources)| (
+                quote.to_string(),
+                sources[0].to_string(),
+                sources[0].inverted()
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("USD".to_string(), "yahoo/VTI".to_string(), false),
+            ("CAD".to_string(), "yahoo/^CADUSD=X".to_string(), true)
+        ]
+    );
+
+    let (nzd, metadata) = commodity(1);
+    assert_eq!(nzd.name(metadata).unwrap(), None);
+    assert_eq!(nzd.asset_class(metadata).unwrap(), None);
+    assert!(nzd.price_sources(metadata).unwrap().is_empty());
+
+    let (gbp, metadata) = commodity(2);
+    assert_eq!(
+        &*gbp.asset_class(metadata).unwrap_err().message,
+        "invalid asset-class metadata"
+    );
+    assert_eq!(
+        &*gbp.price_sources(metadata).unwrap_err().reason,
+        "expected quote currency followed by colon"
+    );
+}
+
+#[test]
+fn test_tags_links_across_directive_kinds() {
+    let sources = crate::BeancountSources::from(
+        r#"2024-01-01 open Assets:Bank NZD #setup
+2024-02-01 note Assets:Bank "called about fees" #fees ^call-1
+2024-02-02 document Assets:Bank "statement.pdf" #fees ^stmt-2
+2024-02-03 * "Bank" "Fees" ^stmt-2
+  Expenses:Fees  5.00 NZD
+    #fees
+  Assets:Bank
+"#,
+    );
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    fn with_tag<'a, T>(items: &'a [T], tag: &str) -> Vec<&'a T>
+    where
+        T: HasTagsLinks<'a>,
+    {
+        items.iter().filter(|item| item.has_tag(tag)).collect()
+    }
+
+    assert_eq!(
+        with_tag(&directives, "fees")
+            .iter()
+            .map(|d| d.item().element_type())
+            .collect::<Vec<_>>(),
+        vec!["note", "document"]
+    );
+    assert_eq!(
+        directives
+            .iter()
+            .filter(|d| d.has_link("stmt-2"))
+            .map(|d| d.date().item().to_string())
+            .collect::<Vec<_>>(),
+        vec!["2024-02-02", "2024-02-03"]
+    );
+    assert_eq!(
+        directives[1]
+            .links()
+            .map(|l| l.item().as_ref().to_string())
+            .collect::<Vec<_>>(),
+        vec!["call-1"]
+    );
+    assert_eq!(directives[0].tags().len(), 1);
+
+    let DirectiveVariant::Transaction(transaction) = directives[3].variant() else {
+        panic!("expected transaction");
+    };
+    let postings = transaction.postings().cloned().collect::<Vec<_>>();
+    assert_eq!(with_tag(&postings, "fees").len(), 1);
+}
+
+#[test]
+fn test_directive_common() {
+    let sources = crate::BeancountSources::from(
+        r#"2024-01-01 open Assets:Bank NZD
+2024-02-01 balance Assets:Bank 0.00 NZD #checked
+2024-03-01 close Assets:Bank
+"#,
+    );
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    fn describe<'a, D>(directive: &D) -> (String, DirectiveKind, usize, usize)
+    where
+        D: DirectiveCommon<'a>,
+    {
+        (
+            directive.date().item().to_string(),
+            directive.kind(),
+            directive.tags().len(),
+            directive.span().start,
+        )
+    }
+
+    assert_eq!(
+        directives.iter().map(describe).collect::<Vec<_>>(),
+        vec![
+            ("2024-01-01".to_string(), DirectiveKind::Open, 0, 0),
+            ("2024-02-01".to_string(), DirectiveKind::Balance, 1, 32),
+            ("2024-03-01".to_string(), DirectiveKind::Close, 0, 81),
+        ]
+    );
+}
+
+#[test]
+fn test_directive_kind() {
+    let sources = crate::BeancountSources::from(
+        r#"2024-01-01 open Assets:Bank NZD
+2024-01-01 open Expenses:Fees
+2024-02-01 * "Bank" "Fees"
+  Expenses:Fees  5.00 NZD
+  Assets:Bank
+2024-02-02 price NZD 0.61 USD
+"#,
+    );
+    let parser = crate::BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+
+    let buckets = directives.iter().fold(
+        std::collections::BTreeMap::<DirectiveKind, usize>::new(),
+        |mut buckets, directive| {
+            *buckets.entry(directive.kind()).or_default() += 1;
+            buckets
+        },
+    );
+    assert_eq!(
+        buckets.into_iter().collect::<Vec<_>>(),
+        vec![
+            (DirectiveKind::Transaction, 1),
+            (DirectiveKind::Price, 1),
+            (DirectiveKind::Open, 2),
+        ]
+    );
+
+    for directive in directives.iter() {
+        assert_eq!(directive.kind().as_ref(), directive.item().element_type());
+    }
+
+    for kind in DirectiveKind::iter() {
+        assert_eq!(kind.to_string().parse::<DirectiveKind>(), Ok(kind));
+    }
+    assert_eq!(DirectiveKind::Commodity.to_string(), "commodity");
+}
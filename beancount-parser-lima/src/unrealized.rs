@@ -0,0 +1,128 @@
+use crate::{holdings::Holdings, prices::PriceDb, types::*};
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+use time::Date;
+
+/// Compute the unrealized gains of all positions held at cost, as of the date of the holdings.
+///
+/// As in Beancount's `unrealized` plugin, lots are aggregated per account, currency, and cost currency,
+/// and valued at the latest price of the currency in its cost currency.  Holdings without a price are omitted.
+pub fn unrealized_gains<'a>(
+    holdings: &Holdings<'a>,
+    prices: &PriceDb<'a>,
+) -> Vec<UnrealizedGain<'a>> {
+    let mut aggregated =
+        BTreeMap::<(&Account<'a>, Currency<'a>, Currency<'a>), (Decimal, Decimal)>::new();
+
+    for (account, position) in holdings.positions() {
+        if let Some(cost) = position.cost() {
+            let (units, book_value) = aggregated
+                .entry((account, position.currency(), cost.currency()))
+                .or_default();
+            *units += position.units();
+            *book_value += position.units() * cost.per_unit();
+        }
+    }
+
+    aggregated
+        .into_iter()
+        .filter_map(
+            |((account, currency, cost_currency), (units, book_value))| {
+                prices
+                    .price(currency, cost_currency, holdings.date())
+                    .map(|price| UnrealizedGain {
+                        account: account.clone(),
+                        date: holdings.date(),
+                        units,
+                        currency,
+                        cost_currency,
+                        book_value,
+                        price,
+                    })
+            },
+        )
+        .collect()
+}
+
+/// The unrealized gain on the units of a currency held at cost in an account, see [unrealized_gains].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct UnrealizedGain<'a> {
+    account: Account<'a>,
+    date: Date,
+    units: Decimal,
+    currency: Currency<'a>,
+    cost_currency: Currency<'a>,
+    book_value: Decimal,
+    price: Decimal,
+}
+
+impl<'a> UnrealizedGain<'a> {
+    /// Field accessor.
+    pub fn account(&self) -> &Account<'a> {
+        &self.account
+    }
+
+    /// The date as of which the gain was computed.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// Field accessor.
+    pub fn units(&self) -> Decimal {
+        self.units
+    }
+
+    /// Field accessor.
+    pub fn currency(&self) -> Currency<'a> {
+        self.currency
+    }
+
+    /// The currency in which the units were acquired, and in which the gain is reckoned.
+    pub fn cost_currency(&self) -> Currency<'a> {
+        self.cost_currency
+    }
+
+    /// The total cost of the units.
+    pub fn book_value(&self) -> Decimal {
+        self.book_value
+    }
+
+    /// The price per unit as of the date.
+    pub fn price(&self) -> Decimal {
+        self.price
+    }
+
+    /// The value of the units at the price.
+    pub fn market_value(&self) -> Decimal {
+        self.units * self.price
+    }
+
+    /// The market value less the book value, negative for a loss.
+    pub fn gain(&self) -> Decimal {
+        self.market_value() - self.book_value
+    }
+}
+
+impl<'a> Display for UnrealizedGain<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} unrealized gain {} {} for {} {} (price: {} {} as of {}, book value: {} {})",
+            self.account,
+            self.gain(),
+            self.cost_currency,
+            self.units,
+            self.currency,
+            self.price,
+            self.cost_currency,
+            self.date,
+            self.book_value,
+            self.cost_currency
+        )
+    }
+}
+
+mod tests;
@@ -0,0 +1,49 @@
+#![cfg(test)]
+use super::*;
+use crate::{holdings, BeancountParser, BeancountSources};
+use rust_decimal_macros::dec;
+
+#[test]
+fn unrealized_gains_per_account_and_currency() {
+    let sources = BeancountSources::from(
+        r#"
+2024-01-01 open Assets:Brokerage
+2024-01-01 open Assets:Pension
+2024-01-01 open Assets:Bank
+
+2024-01-02 * "Buy"
+  Assets:Brokerage  10 HOOL {100 USD}
+  Assets:Brokerage  10 HOOL {120 USD}
+  Assets:Brokerage  5 GOOG {200 USD}
+  Assets:Bank
+
+2024-01-03 * "Contribution"
+  Assets:Pension  4 HOOL {101 USD}
+  Assets:Bank
+
+2024-06-01 price HOOL 150 USD
+"#,
+    );
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let date = Date::from_calendar_date(2024, time::Month::June, 30).unwrap();
+    let holdings = holdings(&success.directives, &success.options, date).unwrap();
+    let prices = PriceDb::new(&success.directives);
+
+    let gains = unrealized_gains(&holdings, &prices);
+
+    assert_eq!(
+        gains
+            .iter()
+            .map(|gain| (gain.account().to_string(), gain.gain()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Assets:Brokerage".to_string(), dec!(800)),
+            ("Assets:Pension".to_string(), dec!(196)),
+        ]
+    );
+    assert_eq!(
+        gains[0].to_string(),
+        "Assets:Brokerage unrealized gain 800 USD for 20 HOOL (price: 150 USD as of 2024-06-30, book value: 2200 USD)"
+    );
+}
@@ -0,0 +1,143 @@
+use crate::{
+    booking::book_snapshots, prices::PriceDb, recurring::step, types::*, BudgetInterval, Options,
+};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use time::Date;
+
+/// Value the positions held in every account in `target` currency at market price,
+/// on `start` and every `interval` after that, up to and including `end`, as for charting net worth over time.
+///
+/// Any directives may be given, such as those of a [LedgerView](crate::LedgerView),
+/// with transactions booked as for [holdings](crate::holdings), once only for all the dates.
+/// As for [unrealized_gains](crate::unrealized_gains), positions without a price as of a date are omitted.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{valuations, BeancountParser, BeancountSources, BudgetInterval, Currency, PriceDb};
+/// use rust_decimal_macros::dec;
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-01 open Assets:Broker
+/// 2024-01-01 open Equity:Opening
+/// 2024-01-05 * "Opening balance"
+///   Assets:Bank  1000.00 NZD
+///   Equity:Opening
+/// 2024-01-10 * "Buy"
+///   Assets:Broker  10 HOOL {50.00 NZD}
+///   Assets:Bank
+/// 2024-02-15 price HOOL 60.00 NZD
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let success = parser.parse().unwrap();
+/// let prices = PriceDb::new(&success.directives);
+/// let nzd = Currency::try_from("NZD").unwrap();
+/// let date = |month, day| Date::from_calendar_date(2024, month, day).unwrap();
+/// let valued = valuations(
+///     &success.directives,
+///     &success.options,
+///     &prices,
+///     nzd,
+///     date(Month::January, 31),
+///     date(Month::March, 31),
+///     BudgetInterval::Monthly,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(valued.dates(), &[date(Month::January, 31), date(Month::February, 29), date(Month::March, 31)]);
+/// assert_eq!(valued.net_worth(), &[dec!(500.00), dec!(1100.00), dec!(1100.00)]);
+/// ```
+pub fn valuations<'a, I>(
+    directives: I,
+    options: &Options<'_>,
+    prices: &PriceDb<'a>,
+    target: Currency<'a>,
+    start: Date,
+    end: Date,
+    interval: BudgetInterval,
+) -> Result<Valuations<'a>, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a Spanned<Directive<'a>>>,
+{
+    let dates = (0..)
+        .map(|n| step(start, interval, n))
+        .take_while(|date| date.is_some_and(|date| date <= end))
+        .flatten()
+        .collect::<Vec<_>>();
+
+    let (snapshots, errors) = book_snapshots(directives, options, &dates);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut accounts = BTreeMap::<Account<'a>, Vec<Decimal>>::new();
+    let mut net_worth = vec![Decimal::ZERO; dates.len()];
+    for (i, (date, inventories)) in dates.iter().zip(snapshots).enumerate() {
+        for (account, inventory) in inventories {
+            let value = inventory
+                .positions()
+                .filter_map(|position| position.market_value(prices, target, *date))
+                .sum::<Decimal>();
+
+            accounts
+                .entry(account.clone())
+                .or_insert_with(|| vec![Decimal::ZERO; dates.len()])[i] = value;
+            if matches!(
+                account.account_type(),
+                AccountType::Assets | AccountType::Liabilities
+            ) {
+                net_worth[i] += value;
+            }
+        }
+    }
+
+    Ok(Valuations {
+        target,
+        dates,
+        accounts,
+        net_worth,
+    })
+}
+
+/// Values over time in a single currency, see [valuations].
+///
+/// Each series of values corresponds to the dates.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Valuations<'a> {
+    target: Currency<'a>,
+    dates: Vec<Date>,
+    accounts: BTreeMap<Account<'a>, Vec<Decimal>>,
+    net_worth: Vec<Decimal>,
+}
+
+impl<'a> Valuations<'a> {
+    /// The currency in which values are given.
+    pub fn target(&self) -> Currency<'a> {
+        self.target
+    }
+
+    /// The dates as of which values are given.
+    pub fn dates(&self) -> &[Date] {
+        &self.dates
+    }
+
+    /// The total value of all `Assets` and `Liabilities` accounts.
+    pub fn net_worth(&self) -> &[Decimal] {
+        &self.net_worth
+    }
+
+    /// The value of each account with positions as of any of the dates, in order of account.
+    pub fn accounts(&self) -> impl Iterator<Item = (&Account<'a>, &[Decimal])> {
+        self.accounts
+            .iter()
+            .map(|(account, values)| (account, values.as_slice()))
+    }
+
+    /// The value of the given account, if it had positions as of any of the dates.
+    pub fn get(&self, account: &Account<'a>) -> Option<&[Decimal]> {
+        self.accounts.get(account).map(Vec::as_slice)
+    }
+}
+
+mod tests;
@@ -0,0 +1,79 @@
+#![cfg(test)]
+use super::*;
+use crate::{BeancountParser, BeancountSources, LedgerView};
+use rust_decimal_macros::dec;
+use time::Month;
+
+#[test]
+fn valuations_weekly_per_account() {
+    let s = r#"2024-01-01 open Assets:Bank
+2024-01-01 open Assets:Broker
+2024-01-01 open Liabilities:Card
+2024-01-01 open Expenses:Food
+2024-01-01 open Equity:Opening
+2024-01-01 * "Opening balance"
+  Assets:Bank     1000.00 NZD
+  Assets:Broker     10 HOOL {50.00 NZD}
+  Equity:Opening
+2024-01-01 price HOOL 50.00 NZD
+2024-01-09 * "Groceries"
+  Liabilities:Card  -80.00 NZD
+  Expenses:Food
+2024-01-15 price HOOL 55.00 NZD
+2024-01-15 * "Groceries outside the view"
+  Liabilities:Card  -20.00 NZD
+  Expenses:Food
+"#;
+    let sources = BeancountSources::from(s);
+    let parser = BeancountParser::new(&sources);
+    let success = parser.parse().unwrap();
+    let prices = PriceDb::new(&success.directives);
+    let nzd = Currency::try_from("NZD").unwrap();
+    let date = |day| Date::from_calendar_date(2024, Month::January, day).unwrap();
+    let view = LedgerView::new(&success.directives).dates(..date(15));
+
+    let valued = valuations(
+        &view,
+        &success.options,
+        &prices,
+        nzd,
+        date(1),
+        date(21),
+        BudgetInterval::Weekly,
+    )
+    .unwrap();
+
+    assert_eq!(valued.dates(), &[date(1), date(8), date(15)]);
+    assert_eq!(
+        valued
+            .accounts()
+            .map(|(account, values)| (account.to_string(), values.to_vec()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "Assets:Bank".to_string(),
+                vec![dec!(1000.00), dec!(1000.00), dec!(1000.00)]
+            ),
+            (
+                "Assets:Broker".to_string(),
+                vec![dec!(500.00), dec!(500.00), dec!(550.00)]
+            ),
+            (
+                "Liabilities:Card".to_string(),
+                vec![dec!(0), dec!(0), dec!(-80.00)]
+            ),
+            (
+                "Equity:Opening".to_string(),
+                vec![dec!(-1500.00), dec!(-1500.00), dec!(-1500.00)]
+            ),
+            (
+                "Expenses:Food".to_string(),
+                vec![dec!(0), dec!(0), dec!(80.00)]
+            ),
+        ]
+    );
+    assert_eq!(
+        valued.net_worth(),
+        &[dec!(1500.00), dec!(1500.00), dec!(1470.00)]
+    );
+}
@@ -0,0 +1,151 @@
+use crate::{index::accounts, types::*};
+use regex::Regex;
+use std::{
+    ops::{Bound, RangeBounds},
+    slice,
+};
+use time::Date;
+
+/// A filtered view of directives, which borrows rather than copies them,
+/// with filters by date range, tag, link, and account, following the semantics of the Fava filter bar.
+///
+/// Filters are composed by chaining, and a directive is in the view only if it passes all of them.
+/// A reference to a view iterates over the directives in it, so may be passed wherever directives are expected,
+/// such as to [trial_balance](crate::trial_balance) or [PriceDb::new](crate::PriceDb::new).
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, LedgerView};
+/// use regex::Regex;
+/// use time::{Date, Month};
+///
+/// let sources = BeancountSources::from(r#"2024-01-01 open Assets:Bank
+/// 2024-01-02 * "Countdown" "groceries" #food
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// 2024-02-02 * "Countdown" "groceries" #food
+///   Assets:Bank  -10.00 NZD
+///   Expenses:Groceries
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let directives = parser.parse().unwrap().directives;
+/// let february = Date::from_calendar_date(2024, Month::February, 1).unwrap();
+///
+/// let view = LedgerView::new(&directives)
+///     .account_matching(Regex::new("^Expenses").unwrap())
+///     .tag("food");
+/// assert_eq!(view.iter().count(), 2);
+///
+/// let view = view.dates(february..);
+/// assert_eq!(view.iter().count(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LedgerView<'a> {
+    directives: &'a [Spanned<Directive<'a>>],
+    dates: (Bound<Date>, Bound<Date>),
+    tags: Vec<String>,
+    links: Vec<String>,
+    accounts: Vec<Regex>,
+}
+
+impl<'a> LedgerView<'a> {
+    /// A view of all of `directives`, as yet unfiltered.
+    pub fn new(directives: &'a [Spanned<Directive<'a>>]) -> Self {
+        LedgerView {
+            directives,
+            dates: (Bound::Unbounded, Bound::Unbounded),
+            tags: Vec::new(),
+            links: Vec::new(),
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Restrict to directives in the date range `dates`, replacing any previous date range.
+    pub fn dates<R: RangeBounds<Date>>(mut self, dates: R) -> Self {
+        self.dates = (dates.start_bound().cloned(), dates.end_bound().cloned());
+        self
+    }
+
+    /// Restrict to directives with `tag`, which is without its leading `#`.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Restrict to directives with `link`, which is without its leading `^`.
+    pub fn link(mut self, link: &str) -> Self {
+        self.links.push(link.to_string());
+        self
+    }
+
+    /// Restrict to directives referring to any account matching `account`, anywhere in the account name,
+    /// so that for example `Assets:Bank` also matches its subaccounts.
+    /// A transaction is kept whole if any of its postings match.
+    pub fn account_matching(mut self, account: Regex) -> Self {
+        self.accounts.push(account);
+        self
+    }
+
+    /// Whether `directive` passes all the filters.
+    pub fn contains(&self, directive: &Directive<'_>) -> bool {
+        let metadata = directive.metadata();
+
+        self.dates.contains(directive.date().item())
+            && self.tags.iter().all(|tag| {
+                metadata
+                    .tags()
+                    .any(|t| AsRef::<str>::as_ref(t.item()) == tag)
+            })
+            && self.links.iter().all(|link| {
+                metadata
+                    .links()
+                    .any(|l| AsRef::<str>::as_ref(l.item()) == link)
+            })
+            && (self.accounts.is_empty() || {
+                let accounts = accounts(directive)
+                    .into_iter()
+                    .map(|account| account.to_string())
+                    .collect::<Vec<_>>();
+                self.accounts
+                    .iter()
+                    .all(|regex| accounts.iter().any(|account| regex.is_match(account)))
+            })
+    }
+
+    /// The directives in the view, in their original order.
+    pub fn iter(&self) -> LedgerViewIter<'_, 'a> {
+        LedgerViewIter {
+            view: self,
+            directives: self.directives.iter(),
+        }
+    }
+}
+
+impl<'v, 'a> IntoIterator for &'v LedgerView<'a> {
+    type Item = &'a Spanned<Directive<'a>>;
+    type IntoIter = LedgerViewIter<'v, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the directives in a [LedgerView].
+#[derive(Clone, Debug)]
+pub struct LedgerViewIter<'v, 'a> {
+    view: &'v LedgerView<'a>,
+    directives: slice::Iter<'a, Spanned<Directive<'a>>>,
+}
+
+impl<'v, 'a> Iterator for LedgerViewIter<'v, 'a> {
+    type Item = &'a Spanned<Directive<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let view = self.view;
+        self.directives
+            .by_ref()
+            .find(|directive| view.contains(directive.item()))
+    }
+}
+
+mod tests;
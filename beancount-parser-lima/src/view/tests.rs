@@ -0,0 +1,116 @@
+#![cfg(test)]
+use super::*;
+use crate::test_support::date;
+use crate::{trial_balance, BeancountParser, BeancountSources};
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Bank:Checking
+2024-01-01 open Assets:Cash
+2024-01-01 open Expenses:Groceries
+2024-01-01 open Expenses:Travel
+
+2024-01-02 * "Countdown" "groceries" #food
+  Assets:Bank:Checking  -42.50 NZD
+  Expenses:Groceries
+
+2024-02-03 * "Air NZ" "flights" #trip ^booking
+  Assets:Bank:Checking  -300.00 NZD
+  Expenses:Travel
+
+2024-02-04 * "Cafe" "lunch" #trip #food
+  Assets:Cash  -20.00 NZD
+  Expenses:Groceries
+
+2024-03-05 note Expenses:Travel "refund due" ^booking
+"#;
+
+// the dates and kinds of directives in the view
+fn listed(view: &LedgerView<'_>) -> Vec<String> {
+    view.iter()
+        .map(|directive| {
+            format!(
+                "{} {}",
+                directive.date().item(),
+                directive.item().element_type()
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn filters_compose() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let view = LedgerView::new(&directives);
+
+    assert_eq!(view.iter().count(), directives.len());
+    assert_eq!(
+        listed(&view.clone().dates(date("2024-02-01")..date("2024-03-05"))),
+        vec!["2024-02-03 transaction", "2024-02-04 transaction"]
+    );
+    assert_eq!(
+        listed(&view.clone().dates(date("2024-02-04")..=date("2024-03-05"))),
+        vec!["2024-02-04 transaction", "2024-03-05 note"]
+    );
+    assert_eq!(
+        listed(&view.clone().tag("food")),
+        vec!["2024-01-02 transaction", "2024-02-04 transaction"]
+    );
+    assert_eq!(
+        listed(&view.clone().tag("food").tag("trip")),
+        vec!["2024-02-04 transaction"]
+    );
+    assert_eq!(
+        listed(&view.clone().link("booking")),
+        vec!["2024-02-03 transaction", "2024-03-05 note"]
+    );
+    assert_eq!(
+        listed(
+            &view
+                .clone()
+                .account_matching(Regex::new("^Assets:Bank").unwrap())
+        ),
+        vec![
+            "2024-01-01 open",
+            "2024-01-02 transaction",
+            "2024-02-03 transaction"
+        ]
+    );
+    assert_eq!(
+        listed(
+            &view
+                .clone()
+                .account_matching(Regex::new("Travel").unwrap())
+                .dates(date("2024-03-01")..)
+        ),
+        vec!["2024-03-05 note"]
+    );
+    assert!(listed(&view.tag("none")).is_empty());
+}
+
+#[test]
+fn view_consumed_by_reports() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let directives = parser.parse().unwrap().directives;
+    let view = LedgerView::new(&directives).tag("trip");
+
+    let totals = trial_balance(&view, date("2024-12-31")).unwrap();
+    let groceries = directives
+        .iter()
+        .find_map(|directive| match directive.variant() {
+            DirectiveVariant::Open(open)
+                if open.account().item().to_string() == "Expenses:Groceries" =>
+            {
+                Some(open.account().item())
+            }
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(
+        totals.get(groceries).unwrap().total().to_string(),
+        "20.00 NZD"
+    );
+}
@@ -0,0 +1,243 @@
+//! Re-parsing of Beancount sources as they change on disk, enabled by the `watch` feature.
+//!
+//! Since the parse result borrows from the sources, each new result is delivered to a handler
+//! rather than retained, so the handler should extract whatever it needs to keep,
+//! for example into a [LedgerStore](crate::LedgerStore).
+//!
+//! # Examples
+//! ```no_run
+//! use beancount_parser_lima::{watch::{LedgerService, Parsed}, ParserConfig};
+//! use std::path::PathBuf;
+//!
+//! let service = LedgerService::new(
+//!     PathBuf::from("main.beancount"),
+//!     ParserConfig::default(),
+//!     |event| {
+//!         if let Ok(Parsed { result: Ok(success), .. }) = event.parsed {
+//!             println!("{} directives", success.directives.len());
+//!         }
+//!     },
+//! )
+//! .unwrap();
+//! ```
+use crate::{path_dir, BeancountParser, BeancountSources, ParseError, ParseSuccess, ParserConfig};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// How long to wait for further changes before re-parsing, so that a burst of writes results in a single parse.
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// A background thread which parses the sources rooted at a file, and parses them again whenever
+/// any of them change, delivering each result as a [LedgerEvent].
+///
+/// The first event is delivered as soon as the thread starts, with no changed paths.
+/// The set of watched files follows the includes as of the latest parse.
+///
+/// Dropping the service stops the thread, waiting for any event in progress to be handled.
+#[derive(Debug)]
+pub struct LedgerService {
+    messages: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The outcome of reading and parsing the sources, see [LedgerService].
+#[derive(Debug)]
+pub struct LedgerEvent<'t> {
+    /// The source files whose change caused this event, which for the first event is empty.
+    pub changed: &'t [PathBuf],
+    /// The parsed sources, or the error if the root file could not be read.
+    pub parsed: Result<Parsed<'t>, io::Error>,
+}
+
+/// Sources which were read, and the result of parsing them.
+#[derive(Debug)]
+pub struct Parsed<'t> {
+    pub sources: &'t BeancountSources,
+    pub result: Result<ParseSuccess<'t>, ParseError>,
+}
+
+#[derive(Debug)]
+enum Message {
+    Changed(Vec<PathBuf>),
+    Stop,
+}
+
+impl LedgerService {
+    /// Start watching the sources rooted at `root_path`, calling `on_change` with each new parse result.
+    ///
+    /// Fails only if the directory containing the root file cannot be watched.
+    pub fn new<F>(root_path: PathBuf, config: ParserConfig, on_change: F) -> notify::Result<Self>
+    where
+        F: FnMut(LedgerEvent<'_>) + Send + 'static,
+    {
+        // event paths are reported relative to the watched directories, so these must be absolute
+        let root_path = std::path::absolute(&root_path)?;
+
+        let (messages, received) = mpsc::channel();
+        let watcher_messages = messages.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            // errors are dropped, as a watch which fails can only result in fewer re-parses
+            if let Ok(event) = event {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    let _ = watcher_messages.send(Message::Changed(event.paths));
+                }
+            }
+        })?;
+
+        let root_dir = watched_dir(&root_path);
+        watcher.watch(&root_dir, RecursiveMode::NonRecursive)?;
+
+        let watching = Watching {
+            watcher,
+            dirs: HashSet::from([root_dir]),
+        };
+        let thread = thread::spawn(move || run(root_path, config, watching, received, on_change));
+
+        Ok(LedgerService {
+            messages,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for LedgerService {
+    fn drop(&mut self) {
+        let _ = self.messages.send(Message::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run<F>(
+    root_path: PathBuf,
+    config: ParserConfig,
+    mut watching: Watching,
+    received: Receiver<Message>,
+    mut on_change: F,
+) where
+    F: FnMut(LedgerEvent<'_>),
+{
+    let mut changed = Vec::new();
+
+    loop {
+        let source_paths = parse(&root_path, &config, &changed, &mut watching, &mut on_change);
+
+        match wait_for_change(&received, &source_paths) {
+            Some(paths) => changed = paths,
+            None => return,
+        }
+    }
+}
+
+// parse and deliver the result, returning the paths of all sources, including those which could not be read
+//
+// the sources are watched before the result is delivered, so that no change made in response is missed
+fn parse<F>(
+    root_path: &Path,
+    config: &ParserConfig,
+    changed: &[PathBuf],
+    watching: &mut Watching,
+    on_change: &mut F,
+) -> HashSet<PathBuf>
+where
+    F: FnMut(LedgerEvent<'_>),
+{
+    match BeancountSources::try_from(root_path) {
+        Ok(sources) => {
+            let source_paths = sources
+                .content_iter()
+                .map(|(_, path, _)| path)
+                .chain(sources.error_path_iter().map(|(path, _)| path))
+                .flatten()
+                .map(Path::to_path_buf)
+                .collect::<HashSet<_>>();
+            watching.watch(&source_paths);
+
+            let parser = BeancountParser::with_config(&sources, config.clone());
+            on_change(LedgerEvent {
+                changed,
+                parsed: Ok(Parsed {
+                    sources: &sources,
+                    result: parser.parse(),
+                }),
+            });
+
+            source_paths
+        }
+        Err(error) => {
+            on_change(LedgerEvent {
+                changed,
+                parsed: Err(error),
+            });
+
+            HashSet::from([root_path.to_path_buf()])
+        }
+    }
+}
+
+// watching directories rather than files means we see files which are replaced rather than written in place
+struct Watching {
+    watcher: RecommendedWatcher,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Watching {
+    fn watch(&mut self, source_paths: &HashSet<PathBuf>) {
+        for source_path in source_paths {
+            let dir = watched_dir(source_path);
+            if !self.dirs.contains(&dir)
+                && self
+                    .watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .is_ok()
+            {
+                self.dirs.insert(dir);
+            }
+        }
+    }
+}
+
+// wait until any source changes and then settles, returning the changed paths, or `None` if stopped
+fn wait_for_change(
+    received: &Receiver<Message>,
+    source_paths: &HashSet<PathBuf>,
+) -> Option<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+
+    while changed.is_empty() {
+        match received.recv().ok()? {
+            Message::Changed(paths) => add_changed(&mut changed, paths, source_paths),
+            Message::Stop => return None,
+        }
+    }
+
+    loop {
+        match received.recv_timeout(SETTLE) {
+            Ok(Message::Changed(paths)) => add_changed(&mut changed, paths, source_paths),
+            Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => return None,
+            Err(RecvTimeoutError::Timeout) => return Some(changed),
+        }
+    }
+}
+
+fn add_changed(changed: &mut Vec<PathBuf>, paths: Vec<PathBuf>, source_paths: &HashSet<PathBuf>) {
+    for path in paths {
+        if source_paths.contains(&path) && !changed.contains(&path) {
+            changed.push(path);
+        }
+    }
+}
+
+fn watched_dir(path: &Path) -> PathBuf {
+    path_dir(path).unwrap_or(Path::new(".")).to_path_buf()
+}
+
+mod tests;
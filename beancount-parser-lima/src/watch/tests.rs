@@ -0,0 +1,76 @@
+#![cfg(test)]
+use super::*;
+use std::{fs, sync::mpsc::Receiver};
+
+// what we keep of each event, since the event itself borrows from the service thread
+#[derive(PartialEq, Eq, Debug)]
+enum Outcome {
+    Parsed(Vec<PathBuf>, Result<usize, usize>),
+    Unreadable(Vec<PathBuf>),
+}
+
+fn outcome(event: LedgerEvent) -> Outcome {
+    let changed = event.changed.to_vec();
+    match event.parsed {
+        Ok(parsed) => Outcome::Parsed(
+            changed,
+            parsed
+                .result
+                .map(|success| success.directives.len())
+                .map_err(|e| e.errors.len()),
+        ),
+        Err(_) => Outcome::Unreadable(changed),
+    }
+}
+
+fn next(outcomes: &Receiver<Outcome>) -> Outcome {
+    outcomes.recv_timeout(Duration::from_secs(10)).unwrap()
+}
+
+#[test]
+fn reparse_on_change() {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-watch-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    let main = dir.join("main.beancount");
+    let included = dir.join("sub/included.beancount");
+    fs::write(
+        &main,
+        "include \"sub/included.beancount\"\n2024-01-01 open Assets:Bank\n",
+    )
+    .unwrap();
+    fs::write(&included, "2024-01-01 open Assets:Cash\n").unwrap();
+
+    let (sender, outcomes) = mpsc::channel();
+    let service = LedgerService::new(main.clone(), ParserConfig::default(), move |event| {
+        sender.send(outcome(event)).unwrap();
+    })
+    .unwrap();
+
+    assert_eq!(next(&outcomes), Outcome::Parsed(Vec::new(), Ok(2)));
+
+    fs::write(
+        &included,
+        "2024-01-01 open Assets:Cash\n2024-01-01 open Assets:Savings\n",
+    )
+    .unwrap();
+    assert_eq!(
+        next(&outcomes),
+        Outcome::Parsed(vec![included.clone()], Ok(3))
+    );
+
+    fs::write(&included, "2024-01-01 open\n").unwrap();
+    assert_eq!(
+        next(&outcomes),
+        Outcome::Parsed(vec![included.clone()], Err(1))
+    );
+
+    fs::remove_file(&main).unwrap();
+    assert_eq!(next(&outcomes), Outcome::Unreadable(vec![main.clone()]));
+
+    drop(service);
+    fs::remove_dir_all(dir).unwrap();
+}
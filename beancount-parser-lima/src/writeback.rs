@@ -0,0 +1,241 @@
+use crate::{types::*, BeancountSources, TextEdit};
+use chumsky::span::Span as _;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Minimal-diff write-back of changed directives to their source files,
+/// so that the formatting of everything else is preserved.
+///
+/// Only the text of directives which are replaced or removed is touched,
+/// and each changed file is written atomically, by writing a temporary file alongside it and renaming.
+///
+/// # Examples
+/// ```
+/// use beancount_parser_lima::{BeancountParser, BeancountSources, WriteBack};
+///
+/// let sources = BeancountSources::from(r#"
+/// 2024-01-01 open   Assets:Bank    ; aligned by hand
+///
+/// 2024-01-05 * "Countdown" "groceries"
+///   Assets:Bank  -42.50 NZD
+///   Expenses:Groceries
+/// "#);
+/// let parser = BeancountParser::new(&sources);
+/// let original = parser.parse().unwrap().directives;
+///
+/// let mut modified = original.clone();
+/// modified.pop();
+///
+/// let contents = WriteBack::new(&sources).diff(&original, &modified).contents();
+/// assert_eq!(contents[0].1, "\n2024-01-01 open   Assets:Bank    ; aligned by hand\n\n");
+/// ```
+#[derive(Clone, Debug)]
+pub struct WriteBack<'s> {
+    sources: &'s BeancountSources,
+    edits: Vec<TextEdit>,
+}
+
+impl<'s> WriteBack<'s> {
+    /// Write-back to `sources`, with no edits as yet.
+    pub fn new(sources: &'s BeancountSources) -> Self {
+        WriteBack {
+            sources,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Add edits for the differences between `original` and `modified` directives, which are matched by span,
+    /// as when `modified` is the result of a [Pipeline](crate::Pipeline) run on `original`.
+    ///
+    /// Original directives not present in `modified` are removed, and those which differ are replaced.
+    /// Modified directives with no original are ignored, since there is no position for them,
+    /// so these should be added using [WriteBack::insert_after].
+    pub fn diff<'a, 'b>(
+        mut self,
+        original: &[Spanned<Directive<'a>>],
+        modified: &[Spanned<Directive<'b>>],
+    ) -> Self {
+        let modified = modified
+            .iter()
+            .map(|directive| (span_key(directive.span()), directive))
+            .collect::<HashMap<_, _>>();
+
+        for directive in original {
+            self = match modified.get(&span_key(directive.span())) {
+                None => self.remove(directive),
+                Some(modified) if modified.item() != directive.item() => {
+                    self.replace(directive, modified.item())
+                }
+                Some(_) => self,
+            };
+        }
+        self
+    }
+
+    /// Add an edit to replace the text of the `original` directive with `directive`,
+    /// leaving any comments and blank lines which follow it.
+    pub fn replace(mut self, original: &Spanned<Directive<'_>>, directive: &Directive<'_>) -> Self {
+        let span = *original.span();
+        let end = self.text_end(span);
+        self.edits.push(TextEdit::new(
+            Span::new(span.context(), span.start()..end),
+            directive.to_string(),
+        ));
+        self
+    }
+
+    /// Add an edit to remove the `original` directive, along with any blank lines which follow it.
+    pub fn remove(mut self, original: &Spanned<Directive<'_>>) -> Self {
+        let span = *original.span();
+        let content = &self.sources.source_content(span.context())[..span.end()];
+
+        // remove whole lines, but not any comment lines which follow the directive
+        let mut end = self.text_end(span);
+        for line in content[end..].split_inclusive('\n') {
+            if line.trim().is_empty() {
+                end += line.len();
+            } else {
+                break;
+            }
+        }
+
+        self.edits.push(TextEdit::new(
+            Span::new(span.context(), span.start()..end),
+            "",
+        ));
+        self
+    }
+
+    /// Add an edit to insert `directive` on the line after the `original` directive.
+    pub fn insert_after(
+        mut self,
+        original: &Spanned<Directive<'_>>,
+        directive: &Directive<'_>,
+    ) -> Self {
+        let span = *original.span();
+        let end = self.text_end(span);
+        self.edits.push(TextEdit::new(
+            Span::new(span.context(), end..end),
+            format!("\n{}", directive),
+        ));
+        self
+    }
+
+    /// The edits so far, in the order added.
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+
+    /// The edited content of each source which was changed, with its path, or `None` for inline content.
+    ///
+    /// Edits are applied as by [BeancountSources::apply_fixes].
+    pub fn contents(&self) -> Vec<(Option<&'s Path>, String)> {
+        self.sources
+            .apply_edits(&self.edits)
+            .into_iter()
+            .map(|(_, path, content)| (path, content))
+            .collect()
+    }
+
+    /// Write each changed source file atomically, returning the paths written.
+    ///
+    /// Nothing is written if any changed source is inline content, or has been modified since it was read,
+    /// and otherwise a failure leaves each file either as it was or fully written.
+    pub fn write(&self) -> io::Result<Vec<PathBuf>> {
+        let contents = self.sources.apply_edits(&self.edits);
+
+        for (source_id, path, _) in contents.iter() {
+            writable_path(self.sources, *source_id, *path)?;
+        }
+
+        contents
+            .into_iter()
+            .filter_map(|(_, path, content)| path.map(|path| (path, content)))
+            .map(|(path, content)| {
+                write_atomically(path, &content)?;
+                Ok(path.to_path_buf())
+            })
+            .collect()
+    }
+
+    pub(crate) fn into_edits(self) -> Vec<TextEdit> {
+        self.edits
+    }
+
+    // the end of the directive's own text within its span, excluding any trailing comment lines and whitespace,
+    // but including a comment on its last line
+    fn text_end(&self, span: Span) -> usize {
+        let content = &self.sources.source_content(span.context())[span.start()..span.end()];
+        let mut text = content.trim_end();
+        while let Some((init, last_line)) = text.rsplit_once('\n') {
+            if last_line.trim_start().starts_with(';') {
+                text = init.trim_end();
+            } else {
+                break;
+            }
+        }
+        span.start() + text.len()
+    }
+}
+
+fn span_key(span: &Span) -> (usize, usize, usize) {
+    (span.context().into(), span.start(), span.end())
+}
+
+// the path of a source which may be written, that is, not inline and not modified since it was read
+pub(crate) fn writable_path<'s>(
+    sources: &BeancountSources,
+    source_id: SourceId,
+    path: Option<&'s Path>,
+) -> io::Result<&'s Path> {
+    let path = path
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "cannot write inline source"))?;
+    if fs::read_to_string(path)? != sources.source_content(source_id) {
+        return Err(io::Error::other(format!(
+            "{} has been modified since it was read",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+// write via a temporary file in the same directory, so the rename is atomic
+pub(crate) fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let temp_path = write_temp(path, content)?;
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+// write content intended for `path` to a temporary file alongside it, with the same permissions if it exists
+pub(crate) fn write_temp(path: &Path, content: &str) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        match fs::metadata(path) {
+            Ok(metadata) => fs::set_permissions(&temp_path, metadata.permissions()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    })();
+
+    match result {
+        Ok(()) => Ok(temp_path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+mod tests;
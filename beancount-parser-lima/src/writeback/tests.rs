@@ -0,0 +1,177 @@
+#![cfg(test)]
+use super::*;
+use crate::BeancountParser;
+
+const LEDGER: &str = r#"; accounts
+2024-01-01 open   Assets:Bank    ; aligned by hand
+2024-01-01 open   Expenses:Groceries
+
+2024-01-05 * "Countdown" "groceries"
+  Assets:Bank          -42.50 NZD
+  Expenses:Groceries
+; reconciled
+
+2024-01-06 note Assets:Bank "statement"
+"#;
+
+fn tagged<'a>(directive: &Spanned<Directive<'a>>, tag: &'a str) -> Spanned<Directive<'a>> {
+    let mut directive = directive.clone();
+    let tag = directive.date().map(|_| Tag::try_from(tag).unwrap());
+    directive.metadata_mut().add_tag(tag);
+    directive
+}
+
+#[test]
+fn diff_touches_only_changed_directives() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let original = parser.parse().unwrap().directives;
+    let modified = vec![
+        original[0].clone(),
+        tagged(&original[2], "food"),
+        original[3].clone(),
+    ];
+
+    let writeback = WriteBack::new(&sources).diff(&original, &modified);
+
+    assert_eq!(writeback.edits().len(), 2);
+    assert_eq!(
+        writeback.contents(),
+        vec![(
+            None,
+            r#"; accounts
+2024-01-01 open   Assets:Bank    ; aligned by hand
+2024-01-05 * "Countdown" "groceries" #food
+  Assets:Bank -42.50 NZD
+  Expenses:Groceries
+; reconciled
+
+2024-01-06 note Assets:Bank "statement"
+"#
+            .to_string()
+        )]
+    );
+}
+
+#[test]
+fn diff_unchanged() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let original = parser.parse().unwrap().directives;
+
+    let writeback = WriteBack::new(&sources).diff(&original, &original);
+
+    assert!(writeback.edits().is_empty());
+    assert!(writeback.contents().is_empty());
+}
+
+#[test]
+fn remove_keeps_following_comments() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let original = parser.parse().unwrap().directives;
+
+    let writeback = WriteBack::new(&sources).remove(&original[2]);
+
+    assert_eq!(
+        writeback.contents()[0].1,
+        r#"; accounts
+2024-01-01 open   Assets:Bank    ; aligned by hand
+2024-01-01 open   Expenses:Groceries
+
+; reconciled
+
+2024-01-06 note Assets:Bank "statement"
+"#
+    );
+}
+
+#[test]
+fn insert_after() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let original = parser.parse().unwrap().directives;
+    let inserted = BeancountSources::from(r#"2024-01-06 * "Z Energy""#);
+    let parser = BeancountParser::new(&inserted);
+    let inserted = parser.parse().unwrap().directives;
+
+    let writeback = WriteBack::new(&sources)
+        .insert_after(&original[2], inserted[0].item())
+        .remove(&original[3]);
+
+    assert_eq!(
+        writeback.contents()[0].1,
+        r#"; accounts
+2024-01-01 open   Assets:Bank    ; aligned by hand
+2024-01-01 open   Expenses:Groceries
+
+2024-01-05 * "Countdown" "groceries"
+  Assets:Bank          -42.50 NZD
+  Expenses:Groceries
+2024-01-06 * "Z Energy"
+; reconciled
+
+"#
+    );
+}
+
+#[test]
+fn write_atomically_to_files() {
+    let dir = std::env::temp_dir().join(format!(
+        "beancount-parser-lima-writeback-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.beancount");
+    let other = dir.join("other.beancount");
+    fs::write(
+        &main,
+        "include \"other.beancount\"\n2024-01-01 open Assets:Bank\n",
+    )
+    .unwrap();
+    fs::write(&other, "2024-01-02 open Assets:Cash  ; cash\n").unwrap();
+
+    let sources = BeancountSources::try_from(main.clone()).unwrap();
+    let parser = BeancountParser::new(&sources);
+    let original = parser.parse().unwrap().directives;
+    let modified = original
+        .iter()
+        .map(|directive| tagged(directive, "checked"))
+        .collect::<Vec<_>>();
+    let writeback = WriteBack::new(&sources).diff(&original, &modified);
+
+    let mut written = writeback.write().unwrap();
+    written.sort();
+    assert_eq!(written, vec![main.clone(), other.clone()]);
+    assert_eq!(
+        fs::read_to_string(&main).unwrap(),
+        "include \"other.beancount\"\n2024-01-01 open Assets:Bank #checked\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&other).unwrap(),
+        "2024-01-02 open Assets:Cash #checked\n"
+    );
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    // the files have now changed since they were read
+    let error = writeback.write().unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("has been modified since it was read"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn write_inline_fails() {
+    let sources = BeancountSources::from(LEDGER);
+    let parser = BeancountParser::new(&sources);
+    let original = parser.parse().unwrap().directives;
+
+    let error = WriteBack::new(&sources)
+        .remove(&original[0])
+        .write()
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
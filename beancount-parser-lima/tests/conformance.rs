@@ -0,0 +1,217 @@
+//! Conformance report against a corpus of Beancount files with reference parse output.
+//!
+//! Each corpus directory contains pairs of `<name>.beancount` and `<name>.txtpb` files,
+//! the latter being the reference parse in Protobuf Text Format as produced by Beancount itself.
+//! Directories are taken from `BEANCOUNT_CONFORMANCE_CORPUS` (a path list), defaulting to
+//! the test cases in this repo, supported and unsupported.
+//!
+//! If `BEANCOUNT_CONFORMANCE_UPSTREAM` is set to a branch or tag of the Beancount repo, or the path of a local clone,
+//! the parser tests of Beancount itself are also extracted as a corpus, see [upstream].
+//! Since error messages differ, only the number of errors is compared for these.
+//!
+//! Unlike the parser tests, mismatches do not fail the test, but are summarised in a report,
+//! which is also written to `BEANCOUNT_CONFORMANCE_REPORT` if set.
+//!
+//! ```Shell
+//! cargo test --test conformance -- --ignored --nocapture
+//! ```
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+#[test]
+#[ignore]
+fn conformance_report() {
+    let mut corpus_dirs = corpus_dirs();
+    let mut skipped = Vec::new();
+    if let Some(revision) = env::var_os("BEANCOUNT_CONFORMANCE_UPSTREAM") {
+        let revision = revision.to_string_lossy();
+        let (upstream_dir, upstream_skipped) =
+            fetch_corpus(&revision, Path::new(env!("CARGO_TARGET_TMPDIR")))
+                .unwrap_or_else(|e| panic!("failed to fetch upstream corpus {}: {}", &revision, e));
+        corpus_dirs.push(upstream_dir);
+        skipped = upstream_skipped;
+    }
+
+    let mut report = Report::new(corpus_dirs.iter().flat_map(|dir| corpus_cases(dir)));
+    report.skipped = skipped;
+
+    print!("{}", &report);
+
+    if let Some(report_path) = env::var_os("BEANCOUNT_CONFORMANCE_REPORT") {
+        fs::write(&report_path, report.to_string())
+            .unwrap_or_else(|e| panic!("failed to write report to {:?}: {}", &report_path, e));
+    }
+}
+
+fn corpus_dirs() -> Vec<PathBuf> {
+    match env::var_os("BEANCOUNT_CONFORMANCE_CORPUS") {
+        Some(corpus) => env::split_paths(&corpus).collect(),
+        None => {
+            let cargo_manifest_dir: PathBuf = env::var("CARGO_MANIFEST_DIR").unwrap().into();
+            // unwrap here is safe because we know the repo structure, so there definitely is a parent
+            let repo_dir = cargo_manifest_dir.parent().unwrap();
+            vec![
+                repo_dir.join("test-cases"),
+                repo_dir.join("test-cases-unsupported"),
+            ]
+        }
+    }
+}
+
+/// Beancount files in `dir` with reference output alongside, ordered by name.
+fn corpus_cases(dir: &Path) -> Vec<Case> {
+    let mut cases = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("failed to read corpus directory {:?}: {}", dir, e))
+        .filter_map(|entry| {
+            let input_path = entry.ok()?.path();
+            let expected_output_path = input_path.with_extension("txtpb");
+
+            (input_path.extension()? == "beancount" && expected_output_path.exists()).then(|| {
+                Case {
+                    name: input_path
+                        .file_stem()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned(),
+                    input_path,
+                    expected_output_path,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+}
+
+struct Case {
+    name: String,
+    input_path: PathBuf,
+    expected_output_path: PathBuf,
+}
+
+impl Case {
+    /// Run the check, returning the mismatch message if any.
+    fn run(&self) -> Result<(), String> {
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            check_parse_file(&self.input_path, &self.expected_output_path)
+        }))
+        .map_err(|payload| {
+            payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown failure".to_string())
+        })
+    }
+}
+
+struct Report {
+    passed: Vec<String>,
+    failed: Vec<(String, String)>,
+    // upstream tests which could not be extracted
+    skipped: Vec<String>,
+}
+
+impl Report {
+    fn new<I>(cases: I) -> Self
+    where
+        I: Iterator<Item = Case>,
+    {
+        let mut report = Report {
+            passed: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        };
+
+        // the default hook would clutter the report with every mismatch
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+
+        for case in cases {
+            match case.run() {
+                Ok(()) => report.passed.push(case.name),
+                Err(message) => report.failed.push((case.name, message)),
+            }
+        }
+
+        panic::set_hook(default_hook);
+
+        report
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let total = self.passed.len() + self.failed.len();
+
+        writeln!(
+            f,
+            "conformance: {} of {} passed, {} failed, {} skipped",
+            self.passed.len(),
+            total,
+            self.failed.len(),
+            self.skipped.len()
+        )?;
+
+        for (name, message) in &self.failed {
+            // keep the report to one line per case
+            let message = message.lines().next().unwrap_or_default();
+            writeln!(f, "FAIL {}: {}", name, message)?;
+        }
+
+        for name in &self.skipped {
+            writeln!(f, "SKIP {}", name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn extract_upstream_tests() {
+    let source = r#"
+TEST(ParserEntryTypes, TransactionOneString) {
+  ExpectParse(R"(
+    2013-05-18 * "Nice dinner at Mermaid Inn"
+      Expenses:Restaurant         100 USD
+      Assets:US:Cash
+  )", R"(
+    directives {
+      date { year: 2013 month: 5 day: 18 }
+    }
+    errors {
+      message: "Invalid token: 'x'"
+    }
+  )");
+}
+
+TEST_F(ParserOptions, Computed) {
+  auto ledger = ExpectParse(absl::StrCat("option ", name));
+}
+"#;
+
+    assert_eq!(
+        extract_tests(source),
+        (
+            vec![UpstreamTest {
+                name: "ParserEntryTypes.TransactionOneString".to_string(),
+                input: "2013-05-18 * \"Nice dinner at Mermaid Inn\"\n  Expenses:Restaurant         100 USD\n  Assets:US:Cash\n".to_string(),
+                expected: "directives {\n  date { year: 2013 month: 5 day: 18 }\n}\nerrors {\n}\n".to_string(),
+            }],
+            vec!["ParserOptions.Computed".to_string()]
+        )
+    );
+}
+
+use helpers::check_parse_file;
+// only checking files is used here, not the parser test cases by name
+#[allow(dead_code)]
+mod helpers;
+use upstream::{extract_tests, fetch_corpus, UpstreamTest};
+mod upstream;
@@ -79,7 +79,12 @@ fn check(sources: &BeancountSources, parser: &BeancountParser, expected_ledger:
                     .zip(expected_errors.into_iter())
                     .enumerate()
                 {
-                    if Some(actual.message()) != expected.message.as_deref() {
+                    // an expected error without a message matches any error, as for an upstream corpus
+                    if expected
+                        .message
+                        .as_deref()
+                        .is_some_and(|message| message != actual.message())
+                    {
                         let actual_message = actual.message().to_string();
                         sources.write(stderr, vec![actual]).unwrap();
                         panic!(
@@ -125,6 +130,17 @@ where
         Into::<PathBuf>::into(format!("{}.txtpb", test_name.as_ref()));
     let expected_output_path = testcase_dir.join(expected_output_file);
 
+    check_parse_file(input_path, expected_output_path);
+}
+
+/// Check the parse of `input_path` against the expected output in Protobuf Text Format from `expected_output_path`,
+/// panicking on any mismatch.
+pub fn check_parse_file<P, Q>(input_path: P, expected_output_path: Q)
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let expected_output_path = expected_output_path.as_ref();
     let expected_output = read_to_string(&expected_output_path).unwrap_or_else(|_| {
         panic!(
             "failed to read expected output from {:?}",
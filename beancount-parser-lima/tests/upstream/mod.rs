@@ -0,0 +1,175 @@
+//! Extraction of a conformance corpus from the parser tests of Beancount itself.
+//!
+//! Each test in `beancount/cparser/parser_test.cc` of the form
+//! `TEST(Suite, Name) { ExpectParse(R"(input)", R"(expected)"); }` becomes a corpus case `Suite.Name`,
+//! as for the test cases in this repo, which were originally extracted in the same way.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const BEANCOUNT_REPO_URL: &str = "https://github.com/beancount/beancount.git";
+const PARSER_TEST_PATH: &str = "beancount/cparser/parser_test.cc";
+
+/// Fetch the Beancount repo at `revision`, being a branch or tag, or else the path of a local clone,
+/// and extract its parser tests as a corpus directory below `work_dir`,
+/// returning the directory and the names of tests which could not be extracted.
+pub fn fetch_corpus(revision: &str, work_dir: &Path) -> io::Result<(PathBuf, Vec<String>)> {
+    let repo_dir = if Path::new(revision).is_dir() {
+        PathBuf::from(revision)
+    } else {
+        let repo_dir = work_dir.join(format!("beancount-{}", revision.replace('/', "-")));
+        if !repo_dir.exists() {
+            clone(revision, &repo_dir)?;
+        }
+        repo_dir
+    };
+
+    let parser_test = fs::read_to_string(repo_dir.join(PARSER_TEST_PATH))?;
+    let (tests, skipped) = extract_tests(&parser_test);
+
+    let corpus_dir = work_dir.join(format!(
+        "conformance-{}",
+        repo_dir
+            .file_name()
+            .map_or("local".into(), |name| name.to_string_lossy())
+    ));
+    let _ = fs::remove_dir_all(&corpus_dir);
+    fs::create_dir_all(&corpus_dir)?;
+    for test in tests {
+        fs::write(
+            corpus_dir.join(format!("{}.beancount", test.name)),
+            &test.input,
+        )?;
+        fs::write(
+            corpus_dir.join(format!("{}.txtpb", test.name)),
+            &test.expected,
+        )?;
+    }
+
+    Ok((corpus_dir, skipped))
+}
+
+fn clone(revision: &str, repo_dir: &Path) -> io::Result<()> {
+    let repo_dir_str = repo_dir.to_string_lossy();
+    let git_args = [
+        "clone",
+        "--depth",
+        "1",
+        "--filter=blob:none",
+        "--branch",
+        revision,
+        BEANCOUNT_REPO_URL,
+        repo_dir_str.as_ref(),
+    ];
+    let status = Command::new("git").args(git_args).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "git {:?} failed: {}",
+            &git_args, status
+        )))
+    }
+}
+
+/// A parser test extracted from the C++ source, normalized as a corpus case.
+#[derive(PartialEq, Eq, Debug)]
+pub struct UpstreamTest {
+    pub name: String,
+    pub input: String,
+    pub expected: String,
+}
+
+/// Extract all the tests which compare the parse of an input with expected output,
+/// returning these and the names of any other tests, which are skipped.
+pub fn extract_tests(source: &str) -> (Vec<UpstreamTest>, Vec<String>) {
+    let mut tests = Vec::new();
+    let mut skipped = Vec::new();
+
+    let mut starts = source
+        .match_indices("TEST(")
+        .chain(source.match_indices("TEST_F("))
+        .map(|(i, prefix)| (i, i + prefix.len()))
+        .filter(|(i, _)| *i == 0 || source[..*i].ends_with('\n'))
+        .collect::<Vec<_>>();
+    starts.sort();
+
+    for (k, &(_, args_start)) in starts.iter().enumerate() {
+        let body_end = starts.get(k + 1).map_or(source.len(), |(i, _)| *i);
+        let Some(args_end) = source[args_start..body_end].find(')') else {
+            continue;
+        };
+        let name = source[args_start..args_start + args_end]
+            .split(',')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(".");
+        let body = &source[args_start + args_end..body_end];
+
+        match expect_parse_args(body) {
+            Some((input, expected)) => tests.push(UpstreamTest {
+                name,
+                input: dedent(input),
+                expected: without_error_messages(expected),
+            }),
+            None => skipped.push(name),
+        }
+    }
+
+    (tests, skipped)
+}
+
+// the two raw string arguments of the first call to ExpectParse, if it has them
+fn expect_parse_args(body: &str) -> Option<(&str, &str)> {
+    let call = body.find("ExpectParse(")? + "ExpectParse(".len();
+    let (input, rest) = raw_string(&body[call..])?;
+    let rest = rest.trim_start().strip_prefix(',')?;
+    let (expected, _) = raw_string(rest)?;
+
+    Some((input, expected))
+}
+
+// a C++ raw string literal `R"delimiter(...)delimiter"` at the start of `s`, after whitespace,
+// returning its content and the remainder of `s`
+fn raw_string(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start().strip_prefix("R\"")?;
+    let open = s.find('(')?;
+    let close = format!("){}\"", &s[..open]);
+    let content = &s[open + 1..];
+    let end = content.find(&close)?;
+
+    Some((&content[..end], &content[end + close.len()..]))
+}
+
+/// Remove the leading blank line and the indentation common to all non-blank lines,
+/// as the C++ tests do for their input.
+pub fn dedent(s: &str) -> String {
+    let s = s.strip_prefix('\n').unwrap_or(s);
+    let indent = s
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    s.lines()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+        .map(|line| format!("{}\n", line.trim_end()))
+        .collect::<String>()
+        .trim_end_matches('\n')
+        .to_string()
+        + "\n"
+}
+
+/// Remove error messages from expected output, since those of this parser differ from Beancount's,
+/// so only the number of errors is compared.
+pub fn without_error_messages(expected: &str) -> String {
+    dedent(expected)
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("message:"))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}